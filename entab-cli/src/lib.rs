@@ -4,6 +4,7 @@ use std::collections::BTreeMap;
 use std::ffi::OsString;
 use std::fs::File;
 use std::io;
+use std::path::Path;
 use std::str;
 
 use clap::error::ErrorKind;
@@ -56,6 +57,65 @@ where
                 .long("metadata")
                 .help("Reports metadata about the file instead of the data itself"),
         )
+        .arg(
+            Arg::new("spectrum")
+                .short('s')
+                .long("spectrum")
+                .help("Groups mass spectrometry data into one row per scan"),
+        )
+        .arg(
+            Arg::new("dataset")
+                .short('d')
+                .long("dataset")
+                .help("Path of the dataset to read from an HDF5 file (or its index in an FCS file)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("regions")
+                .long("regions")
+                .help("Regions of a PNG to summarize as name:x,y,width,height;... (with -p png_regions)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("grid")
+                .long("grid")
+                .help("Summarize a PNG as a grid of ROWSxCOLUMNS regions (with -p png_regions)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("statistic")
+                .long("statistic")
+                .help("How to summarize PNG regions: mean, sum, or max (with -p png_regions)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("compensate")
+                .long("compensate")
+                .help("Compensates FCS data with the spillover matrix in the file"),
+        )
+        .arg(
+            Arg::new("scale")
+                .long("scale")
+                .help("Converts FCS data into linear scale using its gain and amplification"),
+        )
+        .arg(
+            Arg::new("transform")
+                .long("transform")
+                .help("Transforms FCS data: arcsinh:COFACTOR or logicle:T,W,M,A")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("channel_names")
+                .long("channel-names")
+                .help("Which FCS channel names to use as headers: short, long, or both")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("long")
+                .short('l')
+                .long("long")
+                .help("Reports one row per peak instead of one row per spectrum (for MGF files)"),
+        )
         .try_get_matches_from(args);
 
     let matches = match clap_result {
@@ -79,17 +139,42 @@ where
     let mmap: Mmap;
 
     let mut parse_params = BTreeMap::new();
+    if matches.is_present("spectrum") {
+        parse_params.insert("spectrum".to_string(), Value::Boolean(true));
+    }
+    if matches.is_present("long") {
+        parse_params.insert("long".to_string(), Value::Boolean(true));
+    }
+    if let Some(dataset) = matches.value_of("dataset") {
+        parse_params.insert("dataset".to_string(), Value::String(dataset.into()));
+    }
+    for param in ["compensate", "scale"] {
+        if matches.is_present(param) {
+            parse_params.insert(param.to_string(), Value::Boolean(true));
+        }
+    }
+    for param in ["regions", "grid", "statistic", "transform", "channel_names"] {
+        if let Some(value) = matches.value_of(param) {
+            parse_params.insert(param.to_string(), Value::String(value.into()));
+        }
+    }
     let parser = matches.value_of("parser");
     let (mut rec_reader, _) = if let Some(i) = matches.value_of("input") {
         parse_params.insert("filename".to_string(), Value::String(i.into()));
-        let file = File::open(i)?;
-        #[cfg(feature = "mmap")]
-        {
-            mmap = unsafe { Mmap::map(&file)? };
-            get_reader(mmap.as_ref(), parser, Some(parse_params))?
+        if Path::new(i).is_dir() {
+            // directories (e.g. Agilent `.d` runs) are read from their path instead of a stream
+            let parser = parser.unwrap_or("agilent_d");
+            get_reader(&b""[..], Some(parser), Some(parse_params))?
+        } else {
+            let file = File::open(i)?;
+            #[cfg(feature = "mmap")]
+            {
+                mmap = unsafe { Mmap::map(&file)? };
+                get_reader(mmap.as_ref(), parser, Some(parse_params))?
+            }
+            #[cfg(not(feature = "mmap"))]
+            get_reader(file, parser, Some(parse_params))?
         }
-        #[cfg(not(feature = "mmap"))]
-        get_reader(file, parser, Some(parse_params))?
     } else {
         let buffer: Box<dyn io::Read> = Box::new(stdin);
         get_reader(buffer, parser, Some(parse_params))?
@@ -157,6 +242,34 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_agilent_directory() -> Result<(), EtError> {
+        let mut out = Vec::new();
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../entab/tests/data/chemstation_mwd.d"
+        );
+        assert!(run(["entab", "-i", path], &b""[..], io::Cursor::new(&mut out)).is_ok());
+        assert!(out.starts_with(b"file\tdetector\t"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_flow_params() -> Result<(), EtError> {
+        let mut out = Vec::new();
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../entab/tests/data/HTS_BD_LSR_II_Mixed_Specimen_001_D6_D06.fcs"
+        );
+        let args = ["entab", "-i", path, "--channel-names", "both", "--scale"];
+        assert!(run(args, &b""[..], io::Cursor::new(&mut out)).is_ok());
+        assert!(out.starts_with(b"FSC-A\tFSC-H\t"));
+
+        let args = ["entab", "-i", path, "--transform", "sqrt"];
+        assert!(run(args, &b""[..], io::Cursor::new(&mut out)).is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_metadata() -> Result<(), EtError> {
         let mut out = Vec::new();
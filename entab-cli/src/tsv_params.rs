@@ -102,7 +102,18 @@ impl TsvParams {
                 }
                 writer.write_all(&self.list_start_end.1)?;
             }
-            Value::Record(_) => unimplemented!("No writer for records yet"),
+            Value::Record(r) => {
+                writer.write_all(&self.list_start_end.0)?;
+                for (ix, (key, item)) in r.iter().enumerate() {
+                    if ix > 0 {
+                        writer.write_all(&[self.list_delimiter])?;
+                    }
+                    self.write_str(key.as_bytes(), &mut writer)?;
+                    writer.write_all(&[self.record_delimiter])?;
+                    self.write_value(item, writer)?;
+                }
+                writer.write_all(&self.list_start_end.1)?;
+            }
             Value::String(s) => self.write_str(s.as_bytes(), &mut writer)?,
         };
         Ok(())
@@ -174,4 +185,16 @@ mod tests {
         assert_eq!(buffer.get_ref(), &OUT_DATE);
         Ok(())
     }
+
+    #[test]
+    fn test_write_value_record() -> Result<(), EtError> {
+        let p = TsvParams::default();
+        let mut buffer = Cursor::new(Vec::new());
+        let mut record = std::collections::BTreeMap::new();
+        drop(record.insert("a".to_string(), Value::Integer(1)));
+        drop(record.insert("b".to_string(), "x".into()));
+        p.write_value(&Value::Record(record), &mut buffer)?;
+        assert_eq!(buffer.get_ref(), b"a:1,b:x");
+        Ok(())
+    }
 }
@@ -4,6 +4,7 @@ mod raw_io_wrapper;
 use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{Cursor, Read};
+use std::path::Path;
 
 use entab_base::error::EtError;
 use entab_base::readers::{get_reader, RecordReader};
@@ -50,8 +51,12 @@ fn py_from_value(value: Value, py: Python) -> PyResult<PyObject> {
             }
             list.to_object(py)
         }
-        Value::Record(_) => {
-            return Err(EntabError::new_err("record subelements unimplemented"));
+        Value::Record(r) => {
+            let dict = PyDict::new(py);
+            for (key, item) in r {
+                dict.set_item(key, py_from_value(item, py)?)?;
+            }
+            dict.to_object(py)
         }
     })
 }
@@ -95,9 +100,26 @@ impl PyIterProtocol for Reader {
 #[pymethods]
 impl Reader {
     #[new]
-    #[args(data = "None", filename = "None", parser = "None")]
-    fn new(data: Option<&PyAny>, filename: Option<&str>, parser: Option<&str>) -> PyResult<Self> {
-        let mut params = BTreeMap::new();
+    #[args(data = "None", filename = "None", parser = "None", params = "None")]
+    fn new(
+        data: Option<&PyAny>,
+        filename: Option<&str>,
+        mut parser: Option<&str>,
+        params: Option<&PyDict>,
+    ) -> PyResult<Self> {
+        let mut params = match params {
+            Some(d) => d
+                .iter()
+                .map(|(k, v)| {
+                    let value = match v.extract::<bool>() {
+                        Ok(b) => Value::Boolean(b),
+                        Err(_) => Value::String(v.str()?.to_str()?.to_string().into()),
+                    };
+                    Ok((k.extract::<String>()?, value))
+                })
+                .collect::<PyResult<BTreeMap<String, Value>>>()?,
+            None => BTreeMap::new(),
+        };
         let stream: Box<dyn Read> = match (data, filename) {
             (Some(d), None) => {
                 if let Ok(bytes) = d.extract::<Vec<u8>>() {
@@ -114,7 +136,13 @@ impl Reader {
             }
             (None, Some(f)) => {
                 params.insert("filename".to_string(), Value::String(f.into()));
-                Box::new(File::open(f)?)
+                if Path::new(f).is_dir() {
+                    // directories (e.g. Agilent `.d` runs) are read from their path
+                    parser = parser.or(Some("agilent_d"));
+                    Box::new(Cursor::new(Vec::new()))
+                } else {
+                    Box::new(File::open(f)?)
+                }
             }
             _ => {
                 return Err(EntabError::new_err(
@@ -194,11 +222,11 @@ mod tests {
         let py = gil.python();
 
         // a filename or data has to be passed in
-        assert!(Reader::new(None, None, None).is_err());
+        assert!(Reader::new(None, None, None, None).is_err());
 
         // if data's passed in, it works
         let test_data = b">test\nACGT".to_object(py);
-        let reader = Reader::new(Some(test_data.as_ref(py)), None, None)?;
+        let reader = Reader::new(Some(test_data.as_ref(py)), None, None, None)?;
         assert_eq!(&reader.parser, "fasta");
 
         // metadata are available
@@ -228,6 +256,13 @@ reader = entab.Reader(data=">test\nACGT")
 assert reader.metadata == {}
 for record in reader:
     pass
+
+# params are passed through to the parser (and unused ones are an error)
+try:
+    entab.Reader(data=">test\nACGT", params={"scale": True})
+    raise AssertionError("scale is not a FASTA param")
+except Exception as e:
+    assert "Unused params remain: scale" in str(e)
         "#,
             None,
             Some(locals),
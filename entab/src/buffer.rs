@@ -1,6 +1,7 @@
 use alloc::borrow::Cow;
 #[cfg(feature = "std")]
 use alloc::boxed::Box;
+use alloc::vec::Vec;
 #[cfg(feature = "std")]
 use core::convert::TryFrom;
 use core::convert::{AsRef, From};
@@ -302,6 +303,21 @@ impl<'r> From<&'r [u8]> for ReadBuffer<'r> {
     }
 }
 
+impl<'r> From<Vec<u8>> for ReadBuffer<'r> {
+    fn from(buffer: Vec<u8>) -> Self {
+        ReadBuffer {
+            #[cfg(feature = "std")]
+            reader: Box::new(Cursor::new(b"")),
+            buffer: Cow::Owned(buffer),
+            reader_pos: 0,
+            record_pos: 0,
+            consumed: 0,
+            eof: true,
+            end: false,
+        }
+    }
+}
+
 impl<'r> AsRef<[u8]> for ReadBuffer<'r> {
     fn as_ref(&self) -> &[u8] {
         &self.buffer
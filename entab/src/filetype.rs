@@ -1,6 +1,8 @@
 use alloc::format;
 use core::marker::Copy;
 
+use memchr::memmem;
+
 use crate::error::EtError;
 
 /// A file format.
@@ -37,10 +39,14 @@ pub enum FileType {
     AgilentChemstationDad,
     /// Agilent format used for flame ionization trace data
     AgilentChemstationFid,
+    /// Newer (version 179) Agilent format used for flame ionization trace data
+    AgilentChemstationFid2,
     /// Agilent format used for mass spectrometry trace data
     AgilentChemstationMs,
     /// Agilent format used for moving wavelength detector trace data
     AgilentChemstationMwd,
+    /// Agilent format used for instrument telemetry and method parameters
+    AgilentChemstationReg,
     /// Agilent format used for UV-visible detector trace data
     AgilentChemstationUv,
     /// Agilent format used for diode array detector trace data
@@ -63,16 +69,24 @@ pub enum FileType {
     WatersAutospec,
     /// Vendor-independent MS file format based on HDF
     NetCdf,
+    /// Mascot Generic Format peak lists for MS/MS spectra
+    Mgf,
     /// Vendor-independent MS file format based on XML
     MzXml,
+    /// HUPO-PSI standard MS file format based on XML
+    MzMl,
     // geology
     /// "Log ASCII Standard" format for well log information
     Las,
     // catch all
     /// Portable Network Graphics image format
     Png,
+    /// Tagged Image File Format (including OME-TIFF)
+    Tiff,
     /// Generic scientific data format
     Hdf5,
+    /// Microsoft "Compound File Binary" container (also known as OLE or structured storage)
+    MsCfb,
     /// Tab- or comma-seperated value format
     DelimitedText,
     /// Unknown file type
@@ -93,6 +107,7 @@ impl FileType {
                 b"\x04\x03\x02\x01SPAH" => return FileType::InficonHapsite,
                 b"\xAEZTR\x0D\x0A\x1A\x0A" => return FileType::Ztr,
                 b"\x01\xA1F\x00i\x00n\x00" => return FileType::ThermoRaw,
+                b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1" => return FileType::MsCfb,
                 _ => {}
             }
         }
@@ -101,10 +116,13 @@ impl FileType {
                 b"BAM\x01" => return FileType::Bam,
                 b"@HD\t" | b"@SQ\t" => return FileType::Sam,
                 b"\x2Escf" => return FileType::Scf,
+                b"II*\x00" | b"MM\x00*" => return FileType::Tiff,
                 [0x02, 0x33, 0x31, 0x00] => return FileType::AgilentChemstationDad,
                 [0x02, 0x38, 0x31, 0x00] => return FileType::AgilentChemstationFid,
+                [0x03, 0x31, 0x37, 0x39] => return FileType::AgilentChemstationFid2,
                 [0x01, 0x32, 0x00, 0x00] => return FileType::AgilentChemstationMs,
                 [0x02, 0x33, 0x30, 0x00] => return FileType::AgilentChemstationMwd,
+                [0x02, 0x33, 0x32, 0x00] => return FileType::AgilentChemstationReg,
                 [0x03, 0x31, 0x33, 0x31] => return FileType::AgilentChemstationUv,
                 [0x02, 0x02, 0x00, 0x00] => return FileType::AgilentMasshunterDadHeader,
                 [0x03, 0x02, 0x00, 0x00] => return FileType::AgilentMasshunterDad,
@@ -118,6 +136,26 @@ impl FileType {
                 _ => {}
             }
         }
+        if magic.starts_with(b"<?xml") || magic.starts_with(b"\xEF\xBB\xBF<?xml") {
+            // look at the first few tags to tell apart the different XML formats
+            let start = &magic[..magic.len().min(1024)];
+            if memmem::find(start, b"<mzML").is_some()
+                || memmem::find(start, b"<indexedmzML").is_some()
+            {
+                return FileType::MzMl;
+            }
+            if memmem::find(start, b"<mzXML").is_some() {
+                return FileType::MzXml;
+            }
+        }
+        // MGF files start with either a spectrum or one of the global search parameters
+        if magic.starts_with(b"BEGIN IONS")
+            || [&b"CHARGE="[..], b"COM=", b"MASS=", b"SEARCH="]
+                .iter()
+                .any(|p| magic.starts_with(p))
+        {
+            return FileType::Mgf;
+        }
         if magic.len() < 2 {
             return FileType::Unknown;
         }
@@ -147,8 +185,10 @@ impl FileType {
             "bz" | "bz2" | "bzip" => &[FileType::Bzip],
             "cdf" => &[FileType::NetCdf],
             "cf" => &[FileType::ThermoCf],
+            "cfb" => &[FileType::MsCfb],
             "ch" => &[
                 FileType::AgilentChemstationFid,
+                FileType::AgilentChemstationFid2,
                 FileType::AgilentChemstationMwd,
             ],
             "csv" | "tsv" => &[FileType::DelimitedText],
@@ -157,17 +197,21 @@ impl FileType {
             "faq" | "fastq" | "fq" => &[FileType::Fastq],
             "fcs" | "lmd" => &[FileType::Facs],
             "gz" | "gzip" => &[FileType::Gzip],
-            "hdf" => &[FileType::Hdf5],
+            "h5" | "hdf" | "hdf5" => &[FileType::Hdf5],
             "hps" => &[FileType::InficonHapsite],
             "idx" => &[FileType::WatersAutospec],
+            "mgf" => &[FileType::Mgf],
             "ms" => &[FileType::AgilentChemstationMs],
+            "mzml" => &[FileType::MzMl],
             "mzxml" => &[FileType::MzXml],
             "png" => &[FileType::Png],
             "raw" => &[FileType::ThermoRaw],
+            "reg" => &[FileType::AgilentChemstationReg],
             "sam" => &[FileType::Sam],
             "scf" => &[FileType::Scf],
             "sd" => &[FileType::AgilentMasshunterDadHeader],
             "sp" => &[FileType::AgilentMasshunterDad],
+            "tif" | "tiff" => &[FileType::Tiff],
             "uv" => &[
                 FileType::AgilentChemstationDad,
                 FileType::AgilentChemstationUv,
@@ -187,8 +231,10 @@ impl FileType {
         Ok(match (self, hint) {
             (FileType::AgilentChemstationDad, None) => "chemstation_dad",
             (FileType::AgilentChemstationFid, None) => "chemstation_fid",
+            (FileType::AgilentChemstationFid2, None) => "chemstation_fid2",
             (FileType::AgilentChemstationMs, None) => "chemstation_ms",
             (FileType::AgilentChemstationMwd, None) => "chemstation_mwd",
+            (FileType::AgilentChemstationReg, None) => "chemstation_reg",
             (FileType::AgilentChemstationUv, None) => "chemstation_uv",
             (FileType::AgilentMasshunterDad, None) => "masshunter_dad",
             (FileType::AgilentMasshunterDadHeader, None) => return Err("Reading the \".sd\" file is unsupported. Please open the \".sp\" data file instead".into()),
@@ -196,12 +242,19 @@ impl FileType {
             (FileType::Fasta, None) => "fasta",
             (FileType::Fastq, None) => "fastq",
             (FileType::Facs, None) => "flow",
+            (FileType::Hdf5, None) => "hdf5",
             (FileType::InficonHapsite, None) => "inficon_hapsite",
+            (FileType::Mgf, None) => "mgf",
+            (FileType::MsCfb, None) => "ms_cfb",
+            (FileType::MzMl, None) => "mzml",
+            (FileType::MzXml, None) => "mzxml",
+            (FileType::NetCdf, None) => "netcdf",
             (FileType::Png, None) => "png",
             (FileType::Sam, None) => "sam",
             (FileType::ThermoCf, None) => "thermo_cf",
             (FileType::ThermoDxf, None) => "thermo_dxf",
             (FileType::ThermoRaw, None) => "thermo_raw",
+            (FileType::Tiff, None) => "tiff",
             (FileType::DelimitedText, None) => "tsv",
             (_, Some(x)) => x,
             (x, _) => return Err(format!("{:?} doesn't have a parser", x).into())
@@ -217,20 +270,29 @@ mod tests {
     fn test_parser_names() {
         let filetypes = [
             (FileType::AgilentChemstationFid, "chemstation_fid"),
+            (FileType::AgilentChemstationFid2, "chemstation_fid2"),
             (FileType::AgilentChemstationMs, "chemstation_ms"),
             (FileType::AgilentChemstationMwd, "chemstation_mwd"),
+            (FileType::AgilentChemstationReg, "chemstation_reg"),
             (FileType::AgilentChemstationUv, "chemstation_uv"),
             (FileType::AgilentMasshunterDad, "masshunter_dad"),
             (FileType::Bam, "bam"),
             (FileType::Fasta, "fasta"),
             (FileType::Fastq, "fastq"),
             (FileType::Facs, "flow"),
+            (FileType::Hdf5, "hdf5"),
             (FileType::InficonHapsite, "inficon_hapsite"),
+            (FileType::Mgf, "mgf"),
+            (FileType::MsCfb, "ms_cfb"),
+            (FileType::MzMl, "mzml"),
+            (FileType::MzXml, "mzxml"),
+            (FileType::NetCdf, "netcdf"),
             (FileType::Png, "png"),
             (FileType::Sam, "sam"),
             (FileType::ThermoCf, "thermo_cf"),
             (FileType::ThermoDxf, "thermo_dxf"),
             (FileType::ThermoRaw, "thermo_raw"),
+            (FileType::Tiff, "tiff"),
             (FileType::DelimitedText, "tsv"),
         ];
         for (ft, parser) in filetypes {
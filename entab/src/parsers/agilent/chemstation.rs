@@ -18,6 +18,8 @@ const CHEMSTATION_TIME_STEP: f64 = 0.2;
 #[derive(Clone, Debug, Default)]
 /// Metadata consistly found in Chemstation file formats
 pub struct ChemstationMetadata {
+    /// The version of the file format (e.g. "81" for FID files)
+    pub version: String,
     /// Time the run started (minutes)
     pub start_time: f64,
     /// Time the ended started (minutes)
@@ -51,6 +53,7 @@ pub struct ChemstationMetadata {
 impl<'r> From<&ChemstationMetadata> for BTreeMap<String, Value<'r>> {
     fn from(metadata: &ChemstationMetadata) -> Self {
         let mut map = BTreeMap::new();
+        drop(map.insert("version".to_string(), metadata.version.clone().into()));
         drop(map.insert("start_time".to_string(), metadata.start_time.into()));
         drop(map.insert("end_time".to_string(), metadata.end_time.into()));
         drop(map.insert(
@@ -91,8 +94,23 @@ fn get_metadata(header: &[u8], has_signal: bool) -> Result<ChemstationMetadata,
             EtError::from("Chemstation header needs to be at least 512 bytes long").incomplete(),
         );
     }
-    let start_time = f64::from(i32::extract(&header[282..], &Endian::Big)?) / 60000.;
-    let end_time = f64::from(i32::extract(&header[286..], &Endian::Big)?) / 60000.;
+    let version_len = usize::from(header[0]);
+    if version_len > 3 {
+        return Err("Invalid version length".into());
+    }
+    let version = str::from_utf8(&header[1..=version_len])?.to_string();
+    // the run times are stored in milliseconds; FID files store them as floats instead of ints
+    let (start_time, end_time) = if version == "81" {
+        (
+            f64::from(f32::extract(&header[282..], &Endian::Big)?) / 60000.,
+            f64::from(f32::extract(&header[286..], &Endian::Big)?) / 60000.,
+        )
+    } else {
+        (
+            f64::from(i32::extract(&header[282..], &Endian::Big)?) / 60000.,
+            f64::from(i32::extract(&header[286..], &Endian::Big)?) / 60000.,
+        )
+    };
 
     let mut offset_correction = 0.;
     let mut mult_correction = 1.;
@@ -169,6 +187,7 @@ fn get_metadata(header: &[u8], has_signal: bool) -> Result<ChemstationMetadata,
     let replicate = u16::extract(&header[256..], &Endian::Big)?;
 
     Ok(ChemstationMetadata {
+        version,
         start_time,
         end_time,
         signal_name: signal_name.to_string(),
@@ -186,19 +205,54 @@ fn get_metadata(header: &[u8], has_signal: bool) -> Result<ChemstationMetadata,
     })
 }
 
+/// Count the points in the data of a FID trace; most are stored as two byte deltas, but some
+/// are stored as (eight byte) absolute values
+fn count_fid_points(data: &[u8]) -> Result<usize, EtError> {
+    let mut pos = 0;
+    let mut n_points = 0;
+    while pos + 1 < data.len() {
+        pos += if data[pos..pos + 2] == [0x7F, 0xFF] {
+            8
+        } else {
+            2
+        };
+        n_points += 1;
+    }
+    if pos != data.len() {
+        return Err("FID record was incomplete".into());
+    }
+    Ok(n_points)
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+/// Information about a FID trace that has to be found by looking through its data
+pub struct ChemstationFidParams {
+    n_points: usize,
+}
+
 #[derive(Clone, Debug, Default)]
 /// Internal state for the `ChemstationFidRecord` parser
 pub struct ChemstationFidState {
-    cur_time: f64,
+    cur_point: usize,
     cur_delta: f64,
     cur_intensity: f64,
     time_step: f64,
+    units: String,
     metadata: ChemstationMetadata,
 }
 
 impl StateMetadata for ChemstationFidState {
     fn metadata(&self) -> BTreeMap<String, Value> {
-        (&self.metadata).into()
+        let mut metadata: BTreeMap<String, Value> = (&self.metadata).into();
+        let sampling_rate = if self.time_step > 0. {
+            Value::from(1. / (60. * self.time_step))
+        } else {
+            Value::Null
+        };
+        drop(metadata.insert("sampling_rate".to_string(), sampling_rate));
+        drop(metadata.insert("time_units".to_string(), "min".into()));
+        drop(metadata.insert("units".to_string(), self.units.clone().into()));
+        metadata
     }
 
     fn header(&self) -> Vec<&str> {
@@ -207,25 +261,44 @@ impl StateMetadata for ChemstationFidState {
 }
 
 impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationFidState {
-    type State = ();
+    type State = ChemstationFidParams;
 
     fn parse(
         rb: &[u8],
-        _eof: bool,
+        eof: bool,
         consumed: &mut usize,
-        _state: &mut Self::State,
+        state: &mut Self::State,
     ) -> Result<bool, EtError> {
-        *consumed += read_agilent_header(rb, false)?;
+        let header_len = read_agilent_header(rb, false)?;
+        // the number of points isn't stored in the header so we have to look through the rest
+        // of the file to find how far apart the points are
+        if !eof {
+            return Err(EtError::from("Reading FID trace").incomplete());
+        }
+        state.n_points = count_fid_points(&rb[header_len..])?;
+        *consumed += header_len;
         Ok(true)
     }
 
-    fn get(&mut self, rb: &'b [u8], _state: &'s Self::State) -> Result<(), EtError> {
+    fn get(&mut self, rb: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
         let metadata = get_metadata(rb, true)?;
-        // offset the current time back one step so it'll be right after the first time that parse
-        self.cur_time = metadata.start_time - CHEMSTATION_TIME_STEP;
+        let units_len = usize::from(rb[580]);
+        if units_len > 15 {
+            return Err("Invalid units length".into());
+        }
+        self.units = str::from_utf8(&rb[581..581 + units_len])?
+            .trim()
+            .to_string();
+        self.cur_point = 0;
         self.cur_intensity = 0.;
         self.cur_delta = 0.;
-        self.time_step = CHEMSTATION_TIME_STEP;
+        #[allow(clippy::cast_precision_loss)]
+        let time_step = if state.n_points > 1 {
+            (metadata.end_time - metadata.start_time) / (state.n_points - 1) as f64
+        } else {
+            0.
+        };
+        self.time_step = time_step;
         self.metadata = metadata;
         Ok(())
     }
@@ -234,7 +307,7 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationFidState {
 #[derive(Clone, Copy, Debug, Default)]
 /// A point in a FID trace
 pub struct ChemstationFidRecord {
-    /// The time recorded at
+    /// The time recorded at (minutes)
     pub time: f64,
     /// The intensity recorded
     pub intensity: f64,
@@ -271,24 +344,43 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationFidRecord {
             state.cur_intensity += state.cur_delta;
         }
 
-        state.cur_time += state.time_step;
+        state.cur_point += 1;
         *consumed += *con;
         Ok(true)
     }
 
     fn get(&mut self, _buf: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
-        self.time = state.cur_time;
+        #[allow(clippy::cast_precision_loss)]
+        let time = state.metadata.start_time + (state.cur_point - 1) as f64 * state.time_step;
+        self.time = time;
         self.intensity =
             state.cur_intensity * state.metadata.mult_correction + state.metadata.offset_correction;
         Ok(())
     }
 }
 
+#[derive(Clone, Copy, Debug, Default)]
+/// Parameters for the `ChemstationMsReader`
+pub struct ChemstationMsParams {
+    scan_info: bool,
+}
+
+impl ChemstationMsParams {
+    /// Also return the number of the scan with every point
+    #[must_use]
+    pub fn scan_info(mut self, scan_info: bool) -> Self {
+        self.scan_info = scan_info;
+        self
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 /// Internal state for the `ChemstationMsRecord` parser
 pub struct ChemstationMsState {
+    scan_info: bool,
     n_scans_left: usize,
     n_mzs_left: usize,
+    cur_scan: u32,
     cur_time: f64,
     cur_mz: f64,
     cur_intensity: f64,
@@ -301,12 +393,16 @@ impl StateMetadata for ChemstationMsState {
     }
 
     fn header(&self) -> Vec<&str> {
-        vec!["time", "mz", "intensity"]
+        if self.scan_info {
+            vec!["time", "scan", "mz", "intensity"]
+        } else {
+            vec!["time", "mz", "intensity"]
+        }
     }
 }
 
 impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationMsState {
-    type State = ();
+    type State = ChemstationMsParams;
 
     fn parse(
         buffer: &[u8],
@@ -318,11 +414,13 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationMsState {
         Ok(true)
     }
 
-    fn get(&mut self, buffer: &'b [u8], _state: &'s Self::State) -> Result<(), EtError> {
+    fn get(&mut self, buffer: &'b [u8], params: &'s Self::State) -> Result<(), EtError> {
         let metadata = get_metadata(buffer, true)?;
         let n_scans = u32::extract(&buffer[278..], &Endian::Big)? as usize;
 
+        self.scan_info = params.scan_info;
         self.n_scans_left = n_scans;
+        self.cur_scan = 0;
         self.metadata = metadata;
         Ok(())
     }
@@ -333,13 +431,33 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationMsState {
 pub struct ChemstationMsRecord {
     /// The time recorded at
     pub time: f64,
+    /// The (1-based) number of the scan this was recorded in
+    pub scan: u32,
     /// The m/z recorded at
     pub mz: f64,
     /// The intensity recorded
     pub intensity: f64,
+    scan_info: bool,
 }
 
-impl_record!(ChemstationMsRecord: time, mz, intensity);
+impl<'r> From<ChemstationMsRecord> for Vec<Value<'r>> {
+    fn from(record: ChemstationMsRecord) -> Self {
+        if record.scan_info {
+            vec![
+                record.time.into(),
+                record.scan.into(),
+                record.mz.into(),
+                record.intensity.into(),
+            ]
+        } else {
+            vec![
+                record.time.into(),
+                record.mz.into(),
+                record.intensity.into(),
+            ]
+        }
+    }
+}
 
 impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationMsRecord {
     type State = ChemstationMsState;
@@ -364,6 +482,7 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationMsRecord {
                 return Err("Invalid Chemstation MS record header".into());
             }
             n_mzs_left = usize::from((raw_n_mzs_left - 14) / 2);
+            state.cur_scan += 1;
             state.cur_time = f64::from(extract::<u32>(rb, con, &mut Endian::Big)?) / 60000.;
             // eight more bytes of unknown information and then last 4 bytes
             // is a u16/u16 pair for the highest peak?
@@ -397,12 +516,59 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationMsRecord {
 
     fn get(&mut self, _buf: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
         self.time = state.cur_time;
+        self.scan = state.cur_scan;
         self.mz = state.cur_mz;
         self.intensity = state.cur_intensity;
+        self.scan_info = state.scan_info;
         Ok(())
     }
 }
 
+#[derive(Clone, Debug, Default, PartialEq)]
+/// A detector signal as described in a Chemstation header, e.g. "MWD A, Sig=210,5 Ref=360,100"
+pub struct ChemstationSignal {
+    /// The name of the detector channel (e.g. "MWD A")
+    pub name: String,
+    /// The wavelength measured (nm)
+    pub wavelength: Option<f64>,
+    /// The width of the band measured around the wavelength (nm)
+    pub bandwidth: Option<f64>,
+    /// The wavelength of the reference signal, if one was used (nm)
+    pub reference_wavelength: Option<f64>,
+    /// The width of the reference band (nm)
+    pub reference_bandwidth: Option<f64>,
+}
+
+impl ChemstationSignal {
+    /// Parse the signal description from a Chemstation header.
+    #[must_use]
+    pub fn from_description(description: &str) -> Self {
+        // the wavelength and bandwidth are separated by commas (as is the name) so this can't
+        // just be split on commas
+        let pair = |key: &str| -> (Option<f64>, Option<f64>) {
+            let value = match description.split_once(key) {
+                Some((_, rest)) => rest.split_whitespace().next().unwrap_or(""),
+                None => return (None, None),
+            };
+            let (wavelength, bandwidth) = value.split_once(',').unwrap_or((value, ""));
+            (wavelength.parse().ok(), bandwidth.parse().ok())
+        };
+        let name = description
+            .split_once(", Sig=")
+            .or_else(|| description.split_once("Sig="))
+            .map_or(description, |(name, _)| name);
+        let (wavelength, bandwidth) = pair("Sig=");
+        let (reference_wavelength, reference_bandwidth) = pair("Ref=");
+        ChemstationSignal {
+            name: name.trim().trim_end_matches(',').to_string(),
+            wavelength,
+            bandwidth,
+            reference_wavelength,
+            reference_bandwidth,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 /// Internal state for the `ChemstationMwdRecord` parser
 pub struct ChemstationMwdState {
@@ -410,6 +576,7 @@ pub struct ChemstationMwdState {
     cur_time: f64,
     cur_intensity: f64,
     time_step: f64,
+    signal: ChemstationSignal,
     metadata: ChemstationMetadata,
 }
 
@@ -419,7 +586,15 @@ impl StateMetadata for ChemstationMwdState {
     }
 
     fn header(&self) -> Vec<&str> {
-        vec!["time", "signal", "intensity"]
+        vec![
+            "time",
+            "signal",
+            "intensity",
+            "signal_name",
+            "bandwidth",
+            "reference_wavelength",
+            "reference_bandwidth",
+        ]
     }
 }
 
@@ -444,6 +619,7 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationMwdState {
         self.cur_time = metadata.start_time - CHEMSTATION_TIME_STEP;
         self.cur_intensity = 0.;
         self.time_step = CHEMSTATION_TIME_STEP;
+        self.signal = ChemstationSignal::from_description(&metadata.signal_name);
         self.metadata = metadata;
         Ok(())
     }
@@ -452,30 +628,46 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationMwdState {
 #[derive(Clone, Debug, Default)]
 /// A single point from an e.g. moving wavelength detector trace
 pub struct ChemstationMwdRecord<'r> {
-    /// The name of the signal that's being tracked
-    pub signal_name: &'r str,
     /// The time recorded at
     pub time: f64,
+    /// The wavelength of the signal that's being tracked (or 0 if there isn't one)
+    pub signal: f64,
     /// The intensity recorded
     pub intensity: f64,
+    /// The name of the signal that's being tracked (e.g. "MWD A")
+    pub signal_name: &'r str,
+    /// The bandwidth of the signal
+    pub bandwidth: Option<f64>,
+    /// The reference wavelength of the signal
+    pub reference_wavelength: Option<f64>,
+    /// The reference bandwidth of the signal
+    pub reference_bandwidth: Option<f64>,
 }
 
-impl<'r> From<ChemstationMwdRecord<'r>> for Vec<Value<'r>> {
-    fn from(record: ChemstationMwdRecord<'r>) -> Self {
-        // signal name is something like "MWD A, Sig=210,5 Ref=360,100"
-        let signal = record
-            .signal_name
-            .split_once("Sig=")
-            .map(|x| x.1)
-            .and_then(|last_part| {
-                Some(last_part.split_once(',').map_or(last_part, |x| x.0))
-                    .and_then(|sig_name| sig_name.parse::<f64>().ok())
-            })
-            .unwrap_or(0.);
-        vec![record.time.into(), signal.into(), record.intensity.into()]
+impl<'r> ChemstationMwdRecord<'r> {
+    pub(crate) fn new(signal: &'r ChemstationSignal, time: f64, intensity: f64) -> Self {
+        ChemstationMwdRecord {
+            time,
+            signal: signal.wavelength.unwrap_or(0.),
+            intensity,
+            signal_name: &signal.name,
+            bandwidth: signal.bandwidth,
+            reference_wavelength: signal.reference_wavelength,
+            reference_bandwidth: signal.reference_bandwidth,
+        }
     }
 }
 
+impl_record!(
+    ChemstationMwdRecord<'r>: time,
+    signal,
+    intensity,
+    signal_name,
+    bandwidth,
+    reference_wavelength,
+    reference_bandwidth
+);
+
 impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationMwdRecord<'s> {
     type State = ChemstationMwdState;
 
@@ -513,10 +705,9 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationMwdRecord<'s> {
     }
 
     fn get(&mut self, _rb: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
-        self.signal_name = &state.metadata.signal_name;
-        self.time = state.cur_time;
-        self.intensity =
+        let intensity =
             state.cur_intensity * state.metadata.mult_correction + state.metadata.offset_correction;
+        *self = ChemstationMwdRecord::new(&state.signal, state.cur_time, intensity);
         Ok(())
     }
 }
@@ -654,14 +845,14 @@ impl_reader!(
     ChemstationFidRecord,
     ChemstationFidRecord,
     ChemstationFidState,
-    ()
+    ChemstationFidParams
 );
 impl_reader!(
     ChemstationMsReader,
     ChemstationMsRecord,
     ChemstationMsRecord,
     ChemstationMsState,
-    ()
+    ChemstationMsParams
 );
 impl_reader!(
     ChemstationMwdReader,
@@ -680,18 +871,29 @@ mod tests {
     fn test_chemstation_reader_fid() -> Result<(), EtError> {
         let data: &[u8] = include_bytes!("../../../tests/data/test_fid.ch");
         let mut reader = ChemstationFidReader::new(data, None)?;
-        let _ = reader.metadata();
+        let metadata = reader.metadata();
+        assert_eq!(metadata["version"], "81".into());
+        assert_eq!(metadata["units"], "pA".into());
+        if let Value::Float(rate) = metadata["sampling_rate"] {
+            assert!((rate - 4.998).abs() < 0.001);
+        } else {
+            panic!("Sampling rate was not a float");
+        }
         assert_eq!(reader.headers(), ["time", "intensity"]);
         let ChemstationFidRecord { time, intensity } = reader.next()?.unwrap();
-        // TODO: try to confirm this time is correct
-        assert!((time - 20184.8775).abs() < 0.0001);
+        assert!((time - 3.000445).abs() < 0.000001);
         assert!((intensity - 17.500).abs() < 0.001);
+        let ChemstationFidRecord { time, .. } = reader.next()?.unwrap();
+        assert!((time - 3.003779).abs() < 0.000001);
 
-        let mut n_mzs = 1;
-        while reader.next()?.is_some() {
+        let mut n_mzs = 2;
+        let mut last_time = 0.;
+        while let Some(record) = reader.next()? {
+            last_time = record.time;
             n_mzs += 1;
         }
         assert_eq!(n_mzs, 2699);
+        assert!((last_time - 11.997111).abs() < 0.000001);
 
         Ok(())
     }
@@ -706,6 +908,7 @@ mod tests {
             time,
             mz,
             intensity,
+            ..
         } = reader.next()?.unwrap();
         assert!((time - 0.079166).abs() < 0.000001);
         assert!((mz - 915.7).abs() < 0.000001);
@@ -715,6 +918,7 @@ mod tests {
             time,
             mz,
             intensity,
+            ..
         } = reader.next()?.unwrap();
         assert!((time - 0.079166).abs() < 0.000001);
         assert!((mz - 865.4).abs() < 0.000001);
@@ -728,19 +932,58 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_chemstation_reader_ms_scan_info() -> Result<(), EtError> {
+        let data: &[u8] = include_bytes!("../../../tests/data/carotenoid_extract.d/MSD1.MS");
+        let params = ChemstationMsParams::default().scan_info(true);
+        let mut reader = ChemstationMsReader::new(data, Some(params))?;
+        assert_eq!(reader.headers(), ["time", "scan", "mz", "intensity"]);
+        let record: Vec<Value> = reader.next()?.unwrap().into();
+        assert_eq!(record[1], Value::Integer(1));
+
+        let mut last_scan = 1;
+        while let Some(record) = reader.next()? {
+            last_scan = record.scan;
+        }
+        assert_eq!(last_scan, 2534);
+        Ok(())
+    }
+
     #[test]
     fn test_chemstation_reader_mwd() -> Result<(), EtError> {
         let data: &[u8] = include_bytes!("../../../tests/data/chemstation_mwd.d/mwd1A.ch");
         let mut reader = ChemstationMwdReader::new(data, None)?;
-        assert_eq!(reader.headers(), ["time", "signal", "intensity"]);
-        let _ = reader.metadata();
+        assert_eq!(
+            reader.headers(),
+            [
+                "time",
+                "signal",
+                "intensity",
+                "signal_name",
+                "bandwidth",
+                "reference_wavelength",
+                "reference_bandwidth"
+            ]
+        );
+        assert_eq!(
+            reader.metadata()["signal_name"],
+            "MWD A, Sig=210,5 Ref=360,100".into()
+        );
         let ChemstationMwdRecord {
             time,
-            signal_name,
+            signal,
             intensity,
+            signal_name,
+            bandwidth,
+            reference_wavelength,
+            reference_bandwidth,
         } = reader.next()?.unwrap();
         assert!((time - -0.039667).abs() < 0.000001);
-        assert_eq!(signal_name, "MWD A, Sig=210,5 Ref=360,100");
+        assert!((signal - 210.).abs() < f64::EPSILON);
+        assert_eq!(signal_name, "MWD A");
+        assert_eq!(bandwidth, Some(5.));
+        assert_eq!(reference_wavelength, Some(360.));
+        assert_eq!(reference_bandwidth, Some(100.));
         assert!((intensity - -36.34977).abs() < 0.00001);
 
         let mut n_mzs = 1;
@@ -751,6 +994,20 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_chemstation_signal_description() {
+        let signal = ChemstationSignal::from_description("DAD1 B, Sig=254.5,4 Ref=off");
+        assert_eq!(signal.name, "DAD1 B");
+        assert_eq!(signal.wavelength, Some(254.5));
+        assert_eq!(signal.bandwidth, Some(4.));
+        assert_eq!(signal.reference_wavelength, None);
+        assert_eq!(signal.reference_bandwidth, None);
+
+        let signal = ChemstationSignal::from_description("FID1 A");
+        assert_eq!(signal.name, "FID1 A");
+        assert_eq!(signal.wavelength, None);
+    }
+
     #[test]
     fn test_chemstation_reader_bad_fuzzes() -> Result<(), EtError> {
         let test_data = b"\x012>\n\n\n\n\n\n>*\n\x86\n>\n\n\n\n\n\n\n\n\x14\n\n\n\n\n\n\n\n\xaf%\xa8\x00\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\n\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\n\n\n\n\n\n\n\n\n\n\n\n\n>>>\n*\n\n>>\n\xe3\x86\x86\n>>\n\n\n\n>\n\n\n\xaf%\x00\x00\x00\x00\x00\x00\x01\x04\n\n\n\n\n\n\n\n\n\n\n\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\n\n\n\n\n\n\n\n\n\n\n\n\n\n>>>\n*\n\n>>>\n\n\n\n>\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\n\n\n\n\n\n\n\n>\n\n\n\n>";
@@ -17,6 +17,8 @@ use crate::{impl_reader, impl_record};
 #[derive(Clone, Debug, Default)]
 /// Metadata consistly found in new Chemstation file formats
 pub struct ChemstationNewMetadata {
+    /// The version of the file format (e.g. "131" for UV files)
+    pub version: String,
     /// Scaling correction to be applied to all data points
     pub mult_correction: f64,
     /// The name of the sample
@@ -34,6 +36,7 @@ pub struct ChemstationNewMetadata {
 impl<'r> From<&ChemstationNewMetadata> for BTreeMap<String, Value<'r>> {
     fn from(metadata: &ChemstationNewMetadata) -> Self {
         let mut map = BTreeMap::new();
+        drop(map.insert("version".to_string(), metadata.version.clone().into()));
         drop(map.insert(
             "mult_correction".to_string(),
             metadata.mult_correction.into(),
@@ -56,19 +59,29 @@ fn get_utf16_pascal(data: &[u8]) -> String {
         .collect::<String>()
 }
 
-fn get_new_metadata(header: &[u8]) -> Result<ChemstationNewMetadata, EtError> {
-    if header.len() < 4000 {
+/// Read the metadata shared by the newer Chemstation formats; the scaling factor is stored in
+/// different places in different versions so its location has to be passed in.
+fn get_new_metadata(
+    header: &[u8],
+    mult_correction_offset: usize,
+) -> Result<ChemstationNewMetadata, EtError> {
+    if header.len() < 4000 || header.len() < mult_correction_offset + 8 {
         return Err(
             EtError::from("New chemstation header needs to be at least 4000 bytes long")
                 .incomplete(),
         );
     }
+    let version_len = usize::from(header[0]);
+    if version_len > 3 {
+        return Err("Invalid version length".into());
+    }
+    let version = str::from_utf8(&header[1..=version_len])?.to_string();
     //  Also, @ 3093 - Units?
     let sample = get_utf16_pascal(&header[858..]);
     let operator = get_utf16_pascal(&header[1880..]);
     let instrument = get_utf16_pascal(&header[2492..]);
     let method = get_utf16_pascal(&header[2574..]);
-    let mult_correction = f64::extract(&header[3085..3093], &Endian::Big)?;
+    let mult_correction = f64::extract(&header[mult_correction_offset..], &Endian::Big)?;
 
     // We need to detect the date format before we can convert into a
     // NaiveDateTime; not sure the format even maps to the file type
@@ -92,6 +105,7 @@ fn get_new_metadata(header: &[u8]) -> Result<ChemstationNewMetadata, EtError> {
     };
 
     Ok(ChemstationNewMetadata {
+        version,
         mult_correction,
         sample,
         operator,
@@ -139,7 +153,7 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationUvState {
     fn get(&mut self, rb: &'b [u8], _state: &'s Self::State) -> Result<(), EtError> {
         let n_scans = u32::extract(&rb[278..], &Endian::Big)? as usize;
 
-        self.metadata = get_new_metadata(rb)?;
+        self.metadata = get_new_metadata(rb, 3085)?;
         self.n_scans_left = n_scans;
         self.n_wvs_left = 0;
         self.cur_time = 0.;
@@ -224,6 +238,130 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationUvRecord {
     }
 }
 
+#[derive(Clone, Debug, Default)]
+/// Internal state for the `ChemstationFid2Record` parser
+pub struct ChemstationFid2State {
+    metadata: ChemstationNewMetadata,
+    signal_name: String,
+    units: String,
+    n_points: usize,
+    cur_point: usize,
+    start_time: f64,
+    time_step: f64,
+    cur_intensity: f64,
+}
+
+impl StateMetadata for ChemstationFid2State {
+    fn metadata(&self) -> BTreeMap<String, Value> {
+        let mut metadata: BTreeMap<String, Value> = (&self.metadata).into();
+        let sampling_rate = if self.time_step > 0. {
+            Value::from(1. / (60. * self.time_step))
+        } else {
+            Value::Null
+        };
+        drop(metadata.insert("sampling_rate".to_string(), sampling_rate));
+        drop(metadata.insert("signal_name".to_string(), self.signal_name.clone().into()));
+        drop(metadata.insert("time_units".to_string(), "min".into()));
+        drop(metadata.insert("units".to_string(), self.units.clone().into()));
+        metadata
+    }
+
+    fn header(&self) -> Vec<&str> {
+        vec!["time", "intensity"]
+    }
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationFid2State {
+    type State = ();
+
+    fn parse(
+        rb: &[u8],
+        _eof: bool,
+        consumed: &mut usize,
+        _state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        // version 181 files look similar, but we don't have any examples to confirm the layout
+        if rb.len() >= 4 && &rb[..4] != b"\x03179" {
+            return Err("Unsupported version of Chemstation FID file".into());
+        }
+        *consumed += read_agilent_header(rb, false)?;
+        Ok(true)
+    }
+
+    fn get(&mut self, rb: &'b [u8], _state: &'s Self::State) -> Result<(), EtError> {
+        if rb.len() < 4740 {
+            return Err("Chemstation FID header needs to be at least 4740 bytes long".into());
+        }
+        let n_points = u32::extract(&rb[278..], &Endian::Big)? as usize;
+        let start_time = f64::from(i32::extract(&rb[282..], &Endian::Big)?) / 60000.;
+        let end_time = f64::from(i32::extract(&rb[286..], &Endian::Big)?) / 60000.;
+
+        self.metadata = get_new_metadata(rb, 4732)?;
+        self.units = get_utf16_pascal(&rb[4172..]);
+        self.signal_name = get_utf16_pascal(&rb[4213..]);
+        self.n_points = n_points;
+        self.cur_point = 0;
+        self.start_time = start_time;
+        // the times aren't stored so we have to interpolate them from the start and end
+        #[allow(clippy::cast_precision_loss)]
+        let time_step = if n_points > 1 {
+            (end_time - start_time) / (n_points - 1) as f64
+        } else {
+            0.
+        };
+        self.time_step = time_step;
+        self.cur_intensity = 0.;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+/// A point from a newer (version 179) Chemstation FID trace
+pub struct ChemstationFid2Record {
+    /// The time recorded at
+    pub time: f64,
+    /// The intensity recorded
+    pub intensity: f64,
+}
+
+impl_record!(ChemstationFid2Record: time, intensity);
+
+impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationFid2Record {
+    type State = ChemstationFid2State;
+
+    fn parse(
+        rb: &[u8],
+        _eof: bool,
+        consumed: &mut usize,
+        state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        if state.cur_point >= state.n_points {
+            return Ok(false);
+        }
+        let con = &mut 0;
+        // unlike the rest of the file, the data is little-endian
+        state.cur_intensity = extract::<f64>(rb, con, &mut Endian::Little)?;
+        state.cur_point += 1;
+        *consumed += *con;
+        Ok(true)
+    }
+
+    fn get(&mut self, _rb: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
+        #[allow(clippy::cast_precision_loss)]
+        let ix = (state.cur_point - 1) as f64;
+        self.time = state.start_time + ix * state.time_step;
+        self.intensity = state.cur_intensity * state.metadata.mult_correction;
+        Ok(())
+    }
+}
+
+impl_reader!(
+    ChemstationFid2Reader,
+    ChemstationFid2Record,
+    ChemstationFid2Record,
+    ChemstationFid2State,
+    ()
+);
 impl_reader!(
     ChemstationUvReader,
     ChemstationUvRecord,
@@ -237,6 +375,49 @@ mod tests {
     use super::*;
     use crate::readers::RecordReader;
 
+    #[test]
+    fn test_chemstation_reader_fid2() -> Result<(), EtError> {
+        let data: &[u8] = include_bytes!("../../../tests/data/test_fid179.ch");
+        let mut reader = ChemstationFid2Reader::new(data, None)?;
+        assert_eq!(reader.headers(), ["time", "intensity"]);
+        let metadata = reader.metadata();
+        assert_eq!(metadata["version"], "179".into());
+        assert_eq!(metadata["sample"], "FID test".into());
+        assert_eq!(metadata["method"], "TEST.M".into());
+        assert_eq!(metadata["signal_name"], "FID1 A, Front Signal".into());
+        assert_eq!(metadata["units"], "pA".into());
+        if let Value::Float(rate) = metadata["sampling_rate"] {
+            assert!((rate - 2.5).abs() < 0.000001);
+        } else {
+            panic!("Sampling rate was not a float");
+        }
+        assert!(matches!(metadata["run_date"], Value::Datetime(_)));
+
+        let ChemstationFid2Record { time, intensity } = reader.next()?.unwrap();
+        assert!((time - 0.5).abs() < 0.000001);
+        assert!((intensity - 10.).abs() < 0.000001);
+        let ChemstationFid2Record { time, intensity } = reader.next()?.unwrap();
+        assert!((time - 0.506667).abs() < 0.000001);
+        assert!((intensity - 10.245).abs() < 0.000001);
+
+        let mut n_points = 2;
+        let mut last_time = 0.;
+        while let Some(record) = reader.next()? {
+            last_time = record.time;
+            n_points += 1;
+        }
+        assert_eq!(n_points, 50);
+        assert!((last_time - 0.826667).abs() < 0.000001);
+        Ok(())
+    }
+
+    #[test]
+    fn test_chemstation_reader_fid2_unsupported() {
+        let mut data = include_bytes!("../../../tests/data/test_fid179.ch").to_vec();
+        data[1..4].copy_from_slice(b"181");
+        assert!(ChemstationFid2Reader::new(&data[..], None).is_err());
+    }
+
     #[test]
     fn test_chemstation_reader_uv() -> Result<(), EtError> {
         let data: &[u8] = include_bytes!("../../../tests/data/carotenoid_extract.d/dad1.uv");
@@ -1,191 +1,998 @@
+use alloc::boxed::Box;
 use alloc::collections::BTreeMap;
-use core::marker::Copy;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
 
+use chrono::NaiveDateTime;
 use encoding::all::ISO_8859_1;
 use encoding::{DecoderTrap, Encoding};
 
 use crate::parsers::{extract, Endian, FromSlice};
-use crate::record::StateMetadata;
+use crate::record::{StateMetadata, Value};
 use crate::EtError;
 use crate::{impl_reader, impl_record};
-use crate::record::Value;
 
 fn decode_iso_8859(raw: &[u8]) -> Result<String, EtError> {
-    ISO_8859_1.decode(raw, DecoderTrap::Ignore).map_err(|e| e.into_owned().into())
+    let raw = raw.split(|c| *c == 0).next().unwrap_or(raw);
+    ISO_8859_1
+        .decode(raw, DecoderTrap::Ignore)
+        .map_err(|e| e.into_owned().into())
 }
 
-/// State of the Chemstation REG parser
-#[derive(Clone, Copy, Debug, Default)]
-pub struct ChemstationRegState {
+fn decode_utf16(raw: &[u8]) -> String {
+    let chars = raw
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|c| *c != 0)
+        .collect::<Vec<u16>>();
+    String::from_utf16_lossy(&chars)
+}
 
+/// Convert a single numeric cell from a table into a `Value`
+fn read_number(raw: &[u8], value_type: u16) -> Result<Value<'static>, EtError> {
+    Ok(match (value_type, raw.len()) {
+        (4, 4) => f64::from(f32::extract(raw, &Endian::Little)?).into(),
+        (8, 8) => f64::extract(raw, &Endian::Little)?.into(),
+        (12, 2) => i32::from(i16::extract(raw, &Endian::Little)?).into(),
+        (13, 4) => i32::extract(raw, &Endian::Little)?.into(),
+        (_, 2) => u16::extract(raw, &Endian::Little)?.into(),
+        (_, 4) => u32::extract(raw, &Endian::Little)?.into(),
+        _ => Value::Null,
+    })
+}
 
+/// Parse a parameter value, converting the run's timestamp into a date
+fn parse_string_parameter(name: &str, value: String) -> Value<'static> {
+    match NaiveDateTime::parse_from_str(&value, "%d-%b-%y, %H:%M:%S") {
+        Ok(d) if name == "DateTime" => Value::Datetime(d),
+        _ => value.into(),
+    }
+}
 
+/// A single object from a section of a REG file
+#[derive(Clone, Copy, Debug)]
+struct RegObject<'a> {
+    object_type: u16,
+    id: u32,
+    data: &'a [u8],
 }
 
-impl StateMetadata for ChemstationRegState {
-    fn header(&self) -> Vec<&str> {
-        vec!["time", "intensity"]
-    }
+/// The objects in one section of a REG file; objects refer to each other by id so these are
+/// only meaningful in the context of the other objects in the section.
+#[derive(Clone, Debug, Default)]
+struct RegSection<'a> {
+    objects: Vec<RegObject<'a>>,
 }
 
-impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationRegState {
-    type State = ();
+impl<'a> RegSection<'a> {
+    fn read(rb: &'a [u8], con: &mut usize) -> Result<Self, EtError> {
+        let _ = extract::<u8>(rb, con, &mut Endian::Little)?;
+        let n_objects = extract::<u32>(rb, con, &mut Endian::Little)? as usize;
+        let mut headers = Vec::new();
+        for _ in 0..n_objects {
+            let _ = extract::<u16>(rb, con, &mut Endian::Little)?;
+            let object_type = extract::<u16>(rb, con, &mut Endian::Little)?;
+            let length = extract::<u32>(rb, con, &mut Endian::Little)? as usize;
+            let _ = extract::<u32>(rb, con, &mut Endian::Little)?;
+            let id = extract::<u32>(rb, con, &mut Endian::Little)?;
+            headers.push((object_type, length, id));
+        }
+        // skip the index of the objects (sorted by name?)
+        let _ = extract::<&[u8]>(rb, con, &mut (4 * n_objects))?;
+        let mut objects = Vec::with_capacity(n_objects);
+        for (object_type, mut length, id) in headers {
+            let data = extract::<&[u8]>(rb, con, &mut length)?;
+            objects.push(RegObject {
+                object_type,
+                id,
+                data,
+            });
+        }
+        Ok(RegSection { objects })
+    }
 
-    fn parse(
-        buf: &[u8],
-        eof: bool,
-        consumed: &mut usize,
-        _state: &mut Self::State,
-    ) -> Result<bool, EtError> {
-        let con = &mut 0;
-        let header = extract::<&[u8]>(buf, con, &mut 45)?;
-
-        if header[25] != b'A' {
-            return Err(EtError::from("Version of REG file is too new"));
-        }
-        let n_sections = u16::extract(&header[38..], &Endian::Little)?;
-
-        // TODO: parse multiple sections
-
-        let n_records = extract::<u32>(buf, con, &mut Endian::Little)? as usize;
-
-        let mut records = Vec::with_capacity(n_records);
-        for _ in 0..n_records {
-            let _ = extract::<u16>(buf, con, &mut Endian::Little)?;
-            let record_type = extract::<u16>(buf, con, &mut Endian::Little)?;
-            let record_len = extract::<u32>(buf, con, &mut Endian::Little)? as usize;
-            let _ = extract::<u32>(buf, con, &mut Endian::Little)?;
-            let record_id = extract::<u32>(buf, con, &mut Endian::Little)?;
-            records.push((record_type, record_len, record_id))
-        }
-
-        let mut names: BTreeMap<u32, String> = BTreeMap::new();
-        let mut metadata: BTreeMap<u32, Value> = BTreeMap::new();
-        for (record_type, mut record_len, record_id) in records {
-            let record_data = extract::<&[u8]>(buf, con, &mut record_len)?;
-            match record_type {
-                // x-y table
-                1281 | 1283 => { 
-                   	// u16,u16,u8,u32,u32 (n_points),i16,u32,f64
-                   	// H H B I I h I d
-                   	
-                   	// (then repeated twice, first x array and then y array)
-                   	// u32 (units id),u32 (name id?),[12],i16,u32,f64 (multiplicative adjustment),f64,u64,u64,u8,[8]
-                   	// I I 12s h I d d Q Q B 8s
-					// FIXME
-                },
-                // key-value?
+    fn get(&self, id: u32) -> Option<&RegObject<'a>> {
+        if id == 0 {
+            return None;
+        }
+        self.objects.iter().find(|o| o.id == id)
+    }
+
+    /// Look up a string that's stored in a separate object
+    fn get_string(&self, id: u32) -> Result<Option<String>, EtError> {
+        Ok(match self.get(id) {
+            Some(RegObject {
+                object_type: 32769 | 32771,
+                data,
+                ..
+            }) => Some(decode_iso_8859(data)?),
+            Some(RegObject {
+                object_type: 32774,
+                data,
+                ..
+            }) if data.len() >= 2 => Some(decode_iso_8859(&data[2..])?),
+            _ => None,
+        })
+    }
+
+    /// Read a table of values (e.g. a gradient timetable)
+    fn read_table(
+        &self,
+        data: &[u8],
+        parameters: &mut BTreeMap<String, Value<'static>>,
+    ) -> Result<Value<'static>, EtError> {
+        if data.len() < 20 {
+            return Err("REG table was undersized".into());
+        }
+        let row_len = usize::from(u16::extract(&data[2..], &Endian::Little)?);
+        let n_rows = usize::from(u16::extract(&data[4..], &Endian::Little)?);
+        let n_columns = usize::from(u16::extract(&data[16..], &Endian::Little)?);
+        let n_fields = usize::from(u16::extract(&data[18..], &Endian::Little)?);
+        if data.len() < 20 + 30 * (n_columns + n_fields) + row_len * n_rows {
+            return Err("REG table was undersized".into());
+        }
+
+        let mut columns = Vec::with_capacity(n_columns + n_fields);
+        for ix in 0..n_columns + n_fields {
+            let column = &data[20 + 30 * ix..50 + 30 * ix];
+            let name = decode_iso_8859(&column[..16])?;
+            let offset = usize::from(u16::extract(&column[16..], &Endian::Little)?);
+            let size = usize::from(u16::extract(&column[18..], &Endian::Little)?);
+            let value_type = u16::extract(&column[20..], &Endian::Little)?;
+            columns.push((name, offset, size, value_type));
+        }
+
+        // fields after the columns are single values stored in the table header itself
+        for (name, offset, size, value_type) in &columns[n_columns..] {
+            if ["NumberOfRows", "NumberOfCol", "NumberOfHead", "Modified"].contains(&name.as_str())
+            {
+                continue;
+            }
+            if offset + size > data.len() {
+                return Err("REG table field out of bounds".into());
+            }
+            let value = self.read_value(&data[*offset..offset + size], *value_type)?;
+            drop(parameters.insert(name.clone(), value));
+        }
+
+        let rows_start = data.len() - row_len * n_rows;
+        let mut rows = Vec::with_capacity(n_rows);
+        for row_ix in 0..n_rows {
+            let row = &data[rows_start + row_len * row_ix..rows_start + row_len * (row_ix + 1)];
+            let mut record = BTreeMap::new();
+            for (name, offset, size, value_type) in &columns[..n_columns] {
+                if offset + size > row.len() {
+                    return Err("REG table column out of bounds".into());
+                }
+                let value = self.read_value(&row[*offset..offset + size], *value_type)?;
+                drop(record.insert(name.clone(), value));
+            }
+            rows.push(Value::Record(record));
+        }
+        Ok(Value::List(rows))
+    }
+
+    /// Convert a single cell from a table into a `Value`
+    fn read_value(&self, raw: &[u8], value_type: u16) -> Result<Value<'static>, EtError> {
+        Ok(match (value_type, raw.len()) {
+            (2, _) => decode_iso_8859(raw)?.into(),
+            // a reference to a string stored elsewhere
+            (3, 4) => {
+                let id = u32::extract(raw, &Endian::Little)?;
+                self.get_string(id)?.map_or(Value::Null, Value::from)
+            }
+            _ => read_number(raw, value_type)?,
+        })
+    }
+
+    /// Read an array of `n_points` numbers
+    fn read_array(&self, id: u32, n_points: usize) -> Result<Option<Vec<f64>>, EtError> {
+        let data = match self.get(id) {
+            Some(RegObject {
+                object_type: 32770,
+                data,
+                ..
+            }) => data,
+            _ => return Ok(None),
+        };
+        if n_points == 0 || data.len() % n_points != 0 {
+            return Err("REG array was an unexpected size".into());
+        }
+        let mut values = Vec::with_capacity(n_points);
+        for chunk in data.chunks_exact(data.len() / n_points) {
+            values.push(match chunk.len() {
+                4 => f64::from(i32::extract(chunk, &Endian::Little)?),
+                8 => f64::extract(chunk, &Endian::Little)?,
+                _ => return Err("REG array had an unknown data type".into()),
+            });
+        }
+        Ok(Some(values))
+    }
+
+    /// Read the trace referenced by an x-y table object
+    fn read_trace(&self, data: &[u8], name: String) -> Result<Option<RegTrace>, EtError> {
+        if data.len() < 136 {
+            return Err("REG x-y table was undersized".into());
+        }
+        let n_points = u32::extract(&data[9..], &Endian::Little)? as usize;
+        let x_units = self.get_string(u32::extract(&data[27..], &Endian::Little)?)?;
+        let x_id = u32::extract(&data[31..], &Endian::Little)?;
+        let x_mult = f64::extract(&data[61..], &Endian::Little)?;
+        let y_units = self.get_string(u32::extract(&data[94..], &Endian::Little)?)?;
+        let y_id = u32::extract(&data[98..], &Endian::Little)?;
+        let y_mult = f64::extract(&data[128..], &Endian::Little)?;
+
+        // the start/stop condition sections have single point placeholders without units
+        let units = match y_units {
+            Some(units) if !units.is_empty() => units,
+            _ => return Ok(None),
+        };
+        let values = match self.read_array(y_id, n_points)? {
+            Some(values) => values.into_iter().map(|v| v * y_mult).collect(),
+            None => return Ok(None),
+        };
+        // the times are usually only stored as a step size
+        #[allow(clippy::cast_precision_loss)]
+        let times = match self.read_array(x_id, n_points)? {
+            Some(times) => times.into_iter().map(|t| t * x_mult).collect(),
+            None => (0..n_points).map(|ix| ix as f64 * x_mult).collect(),
+        };
+        if x_units.as_deref().is_some_and(|u| u != "min") {
+            return Err("REG trace times were not in minutes".into());
+        }
+        Ok(Some(RegTrace {
+            name,
+            units,
+            times,
+            values,
+        }))
+    }
+
+    /// Read all of the parameters and traces in this section
+    fn read_contents(&self) -> Result<(BTreeMap<String, Value<'static>>, Vec<RegTrace>), EtError> {
+        let mut parameters = BTreeMap::new();
+        let mut xy_tables = Vec::new();
+        for object in &self.objects {
+            let data = object.data;
+            match object.object_type {
+                // a parameter that refers to a string stored elsewhere
                 1537 => {
-                    // the matching data is in a 32770 record so we only get the name
-                    let record_id = u32::extract(&record_data[35..], &Endian::Little)?;
-                    let _ = names.insert(record_id, decode_iso_8859(record_data[14..30].split(|c| *c == 0).next().unwrap_or(&record_data[14..30]))?);
-                },
-                // part of a linked list
+                    if data.len() < 18 {
+                        return Err("REG string parameter was undersized".into());
+                    }
+                    let name = decode_iso_8859(&data[14..data.len() - 4])?;
+                    let id = u32::extract(&data[data.len() - 4..], &Endian::Little)?;
+                    let value = match self.get_string(id)? {
+                        Some(s) => parse_string_parameter(&name, s),
+                        None => Value::Null,
+                    };
+                    drop(parameters.insert(name, value));
+                }
+                // a numeric parameter
                 1538 => {
-                    if record_data.len() != 39 {
-                        return Err(EtError::from("Data type 1538 was an unexpected size"));
+                    if data.len() < 22 {
+                        return Err("REG numeric parameter was undersized".into());
                     }
-                    let _ = names.insert(record_id, decode_iso_8859(&record_data[14..35])?);
-                    let _ = metadata.insert(record_id, u32::extract(&record_data[35..], &Endian::Little)?.into());
-                },
-                // another part of a linked list with a table reference
+                    let name = decode_iso_8859(&data[14..data.len() - 8])?;
+                    let value = f64::extract(&data[data.len() - 8..], &Endian::Little)?;
+                    drop(parameters.insert(name, value.into()));
+                }
+                // a parameter that refers to a table
                 1539 => {
-                    if record_data.len() != 39 {
-                        return Err(EtError::from("Data type 1539 was an unexpected size"));
+                    if data.len() < 18 {
+                        return Err("REG table parameter was undersized".into());
                     }
-                    let id = u32::extract(&record_data[35..], &Endian::Little)?;
-                    let _ = names.insert(id, decode_iso_8859(&record_data[14..35])?);
-                    // no data?
-                },
-                // table of values
-                1793 => {
-					let n_rows = u16::extract(&record_data[4..], &Endian::Little)?;
-					let n_columns = u16::extract(&record_data[16..], &Endian::Little)?;
-					if n_columns == 0 {
-						continue;
-					}
-					// FIXME
-                },
-                // names (these have data elsewhere?)
-                32769 | 32771 => {
-                    let _ = names.insert(record_id, decode_iso_8859(&record_data[..record_len-1])?);
-                },
-                32774 => {
-                    let _ = names.insert(record_id, decode_iso_8859(&record_data[2..record_len-1])?);
-                },
-                // flattened numeric array; contains the raw data for 1281/1283 records
-                32770 => {
-                    if record_data.len() < 4 {
-                        return Err(EtError::from("Array was undersized"));
+                    let name = decode_iso_8859(&data[14..data.len() - 4])?;
+                    let id = u32::extract(&data[data.len() - 4..], &Endian::Little)?;
+                    if let Some(table) = self.get(id).filter(|o| o.object_type == 1793) {
+                        let value = self.read_table(table.data, &mut parameters)?;
+                        drop(parameters.insert(name, value));
                     }
-                    let n_points = record_data.len() / 4 - 1;
-                    let mut data: Vec<Value> = Vec::with_capacity(n_points);
-                    for ix in 0..n_points {
-                        data.push(u32::extract(&record_data[4 * ix + 4..], &Endian::Little)?.into());
+                }
+                // x-y tables; we need the title of the section to read these
+                1281 | 1283 => xy_tables.push(data),
+                _ => {}
+            }
+        }
+
+        let title = match parameters.get("Title") {
+            Some(Value::String(s)) => s.to_string(),
+            _ => String::new(),
+        };
+        let mut traces = Vec::new();
+        for data in xy_tables {
+            if let Some(trace) = self.read_trace(data, title.clone())? {
+                traces.push(trace);
+            }
+        }
+        Ok((parameters, traces))
+    }
+}
+
+/// A single object from a section of a newer REG file
+#[derive(Clone, Debug)]
+enum RegItem {
+    Null,
+    /// A parameter; unnamed ones describe the layout of the table they belong to
+    Parameter(Option<String>, Value<'static>),
+    /// A table of values and the single-value fields stored alongside it
+    Table(
+        Option<String>,
+        Value<'static>,
+        BTreeMap<String, Value<'static>>,
+    ),
+    List(Vec<RegItem>),
+    /// The parameters and the data (if any) of one section of the file
+    Section(Box<RegItem>, Box<RegItem>),
+    /// One axis of a trace with its units
+    Axis(String, Vec<f64>),
+    /// The axes of a trace (the values followed by the times)
+    Data(Vec<RegItem>),
+    Annotation,
+}
+
+/// A section of a newer REG file.
+///
+/// These are written out as an MFC archive: every object is preceded by a tag that either
+/// introduces a new class (by name) or refers back to one seen earlier in the section. The names of
+/// parameters and columns can also refer back to names seen earlier in the section.
+#[derive(Clone, Debug)]
+struct RegArchive<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// The classes seen so far, indexed by tag (objects are assigned tags too so those are `None`)
+    classes: Vec<Option<&'a [u8]>>,
+    names: Vec<String>,
+}
+
+impl<'a> RegArchive<'a> {
+    /// Read the parameters and traces from the section starting at `con`
+    fn read_section(
+        data: &'a [u8],
+        con: &mut usize,
+    ) -> Result<(BTreeMap<String, Value<'static>>, Vec<RegTrace>), EtError> {
+        let mut archive = RegArchive {
+            data,
+            // skip the section header
+            pos: *con + 12,
+            classes: vec![None],
+            names: Vec::new(),
+        };
+        let (items, data) = match archive.read_object()? {
+            RegItem::Section(items, data) => (*items, *data),
+            _ => return Err("REG section did not start with a section object".into()),
+        };
+        *con = archive.pos;
+
+        let mut parameters = BTreeMap::new();
+        if let RegItem::List(items) = items {
+            for item in items {
+                match item {
+                    RegItem::Parameter(Some(name), value) => drop(parameters.insert(name, value)),
+                    RegItem::Table(name, rows, fields) => {
+                        parameters.extend(fields);
+                        if let Some(name) = name {
+                            drop(parameters.insert(name, rows));
+                        }
                     }
-                    let _ = metadata.insert(record_id, data.into());
-                },
-                _ => { },
+                    _ => {}
+                }
             }
         }
 
+        let mut traces = Vec::new();
+        if let RegItem::Data(axes) = data {
+            let (units, values, time_units, times) = match &axes[..] {
+                [RegItem::Axis(units, values), RegItem::Axis(time_units, times)] => {
+                    (units, values, time_units, times)
+                }
+                _ => return Err("REG trace did not have two axes".into()),
+            };
+            // the start/stop condition sections have single point placeholders without units
+            if !units.is_empty() {
+                if time_units != "min" {
+                    return Err("REG trace times were not in minutes".into());
+                }
+                if times.len() != values.len() {
+                    return Err("REG trace times and values were different lengths".into());
+                }
+                let name = match parameters.get("Title") {
+                    Some(Value::String(s)) => s.to_string(),
+                    _ => String::new(),
+                };
+                traces.push(RegTrace {
+                    name,
+                    units: units.clone(),
+                    times: times.clone(),
+                    values: values.clone(),
+                });
+            }
+        }
+        Ok((parameters, traces))
+    }
+
+    fn bytes(&mut self, mut len: usize) -> Result<&'a [u8], EtError> {
+        extract::<&[u8]>(self.data, &mut self.pos, &mut len)
+    }
+
+    fn u8(&mut self) -> Result<u8, EtError> {
+        extract::<u8>(self.data, &mut self.pos, &mut Endian::Little)
+    }
+
+    fn u16(&mut self) -> Result<u16, EtError> {
+        extract::<u16>(self.data, &mut self.pos, &mut Endian::Little)
+    }
+
+    fn u32(&mut self) -> Result<u32, EtError> {
+        extract::<u32>(self.data, &mut self.pos, &mut Endian::Little)
+    }
+
+    fn f64(&mut self) -> Result<f64, EtError> {
+        extract::<f64>(self.data, &mut self.pos, &mut Endian::Little)
+    }
+
+    /// Read a UTF-16 string; inside of tables the lengths include a (not stored) terminator
+    fn read_string(&mut self, in_table: bool) -> Result<String, EtError> {
+        let mut len = usize::from(self.u16()?);
+        if in_table {
+            len = len.saturating_sub(1);
+        }
+        Ok(decode_utf16(self.bytes(2 * len)?))
+    }
+
+    /// Read the name of a parameter or column. Names can be stored directly, refer to a name
+    /// earlier in the section, or be one of a set of predefined names.
+    fn read_name(&mut self, in_table: bool) -> Result<Option<String>, EtError> {
+        let len = self.u16()?;
+        if len & 0xC000 == 0xC000 {
+            // the other predefined names are the bookkeeping fields of tables
+            return Ok(match self.u16()? {
+                0 => Some("ObjClass".to_string()),
+                1 => Some("Title".to_string()),
+                _ => None,
+            });
+        } else if len & 0x8000 == 0x8000 {
+            let ix = usize::from(self.u16()?);
+            let name = self
+                .names
+                .get(ix)
+                .ok_or("REG name reference out of range")?;
+            return Ok(Some(name.clone()));
+        }
+        self.pos -= 2;
+        let name = self.read_string(in_table)?;
+        if !name.is_empty() {
+            self.names.push(name.clone());
+        }
+        Ok(Some(name))
+    }
+
+    fn read_object(&mut self) -> Result<RegItem, EtError> {
+        let class = match self.u16()? {
+            0 => return Ok(RegItem::Null),
+            0xFFFF => {
+                let _ = self.u16()?;
+                let len = usize::from(self.u16()?);
+                let name = self.bytes(len)?;
+                self.classes.push(Some(name));
+                name
+            }
+            tag if tag & 0x8000 == 0x8000 => self
+                .classes
+                .get(usize::from(tag & 0x7FFF))
+                .copied()
+                .flatten()
+                .ok_or("REG object had an unknown class")?,
+            _ => return Err("REG object references are not supported".into()),
+        };
+        self.classes.push(None);
+
+        Ok(match class {
+            b"CHPUserObject" | b"CHPLCObject" => {
+                // the annotations (i.e. labels for the traces) aren't needed
+                let _ = self.read_object()?;
+                let parameters = self.read_object()?;
+                let data = if self.u8()? != 0 {
+                    self.read_object()?
+                } else {
+                    RegItem::Null
+                };
+                RegItem::Section(Box::new(parameters), Box::new(data))
+            }
+            b"CHPList" => self.read_object()?,
+            b"CObArray" => {
+                let n_items = self.u16()?;
+                let mut items = Vec::new();
+                for _ in 0..n_items {
+                    items.push(self.read_object()?);
+                }
+                RegItem::List(items)
+            }
+            b"CHPNdrDouble" | b"CHPNdrString" | b"CHPNdrObject" => {
+                let name = self.read_name(false)?;
+                let _ = self.u16()?;
+                let flags = self.u32()?;
+                match class {
+                    b"CHPNdrDouble" => {
+                        let value = self.f64()?;
+                        if flags & 0x0010_0000 != 0 {
+                            // the allowed range of the value
+                            let _ = self.bytes(16)?;
+                        }
+                        RegItem::Parameter(name, value.into())
+                    }
+                    b"CHPNdrString" => {
+                        let value = self.read_string(false)?;
+                        let value = match &name {
+                            Some(name) => parse_string_parameter(name, value),
+                            None => value.into(),
+                        };
+                        RegItem::Parameter(name, value)
+                    }
+                    _ => {
+                        let object = if self.u8()? != 0 {
+                            self.read_object()?
+                        } else {
+                            RegItem::Null
+                        };
+                        match object {
+                            RegItem::Table(_, rows, fields) => RegItem::Table(name, rows, fields),
+                            _ => RegItem::Null,
+                        }
+                    }
+                }
+            }
+            b"CHPTable" => self.read_table()?,
+            b"CHPAnnText" => {
+                let _ = self.bytes(34)?;
+                let _ = self.read_string(false)?;
+                // the name of the font
+                let len = usize::from(self.u8()?);
+                let _ = self.bytes(2 * len + 3)?;
+                RegItem::Annotation
+            }
+            b"CHPDatDoubleSliced" | b"CHPDatLongSliced" => {
+                let _ = self.u16()?;
+                let n_axes = usize::from(self.u16()?) + usize::from(self.u16()?);
+                let mut axes = Vec::new();
+                for _ in 0..n_axes {
+                    axes.push(self.read_object()?);
+                }
+                RegItem::Data(axes)
+            }
+            b"CHPDatDoubleRow" => self.read_axis(false)?,
+            b"CHPDatLongRow" => self.read_axis(true)?,
+            _ => {
+                return Err(format!(
+                    "Unknown object type {} in REG file",
+                    String::from_utf8_lossy(class)
+                )
+                .into())
+            }
+        })
+    }
+
+    /// Read a table of values (e.g. a gradient timetable)
+    fn read_table(&mut self) -> Result<RegItem, EtError> {
+        let start = self.pos;
+        let header = self.bytes(24)?;
+        let row_len = usize::from(u16::extract(&header[2..], &Endian::Little)?);
+        let n_rows = u32::extract(&header[4..], &Endian::Little)? as usize;
+        let data_len = usize::from(u16::extract(&header[8..], &Endian::Little)?);
+        let n_extra_rows = usize::from(u16::extract(&header[10..], &Endian::Little)?);
+        let n_columns = usize::from(u16::extract(&header[20..], &Endian::Little)?);
+        let n_fields = usize::from(u16::extract(&header[22..], &Endian::Little)?);
+
+        let mut columns = Vec::with_capacity(n_columns + n_fields);
+        for _ in 0..n_columns + n_fields {
+            let column = self.bytes(18)?;
+            let offset = usize::from(u16::extract(&column[4..], &Endian::Little)?);
+            let size = usize::from(u16::extract(&column[6..], &Endian::Little)?);
+            let value_type = u16::extract(&column[8..], &Endian::Little)?;
+            columns.push((offset, size, value_type));
+        }
+        // the fields are stored after the column descriptions and then the rows come after those
+        // (starting with a row of defaults)
+        if start + data_len < self.pos {
+            return Err("REG table was undersized".into());
+        }
+        let fields_data = self
+            .data
+            .get(start..start + data_len)
+            .ok_or("REG table was undersized")?;
+        self.pos = start + data_len;
+        let n_stored_rows = n_rows + n_extra_rows + 1;
+        let rows_len = row_len
+            .checked_mul(n_stored_rows)
+            .ok_or("REG table was oversized")?;
+        let rows_data = self.bytes(rows_len)?;
+
+        // the names come last with the text of any string cells after each name
+        let mut names = Vec::with_capacity(columns.len());
+        let mut strings = Vec::with_capacity(columns.len());
+        for (ix, (_, _, value_type)) in columns.iter().enumerate() {
+            names.push(self.read_name(true)?);
+            let n_strings = match (value_type, ix < n_columns) {
+                (3, true) => n_stored_rows,
+                (3, false) => 1,
+                _ => 0,
+            };
+            let mut column_strings = Vec::with_capacity(n_strings);
+            for _ in 0..n_strings {
+                column_strings.push(self.read_string(true)?);
+            }
+            strings.push(column_strings);
+        }
+
+        let read_cell = |ix: usize, raw: &[u8], row_ix: usize| -> Result<Value<'static>, EtError> {
+            let (offset, size, value_type) = columns[ix];
+            if value_type == 3 {
+                return Ok(strings[ix][row_ix].clone().into());
+            }
+            let raw = raw
+                .get(offset..offset + size)
+                .ok_or("REG table cell out of bounds")?;
+            Ok(match value_type {
+                2 => decode_utf16(raw).into(),
+                _ => read_number(raw, value_type)?,
+            })
+        };
+
+        let mut fields = BTreeMap::new();
+        for (ix, name) in names.iter().enumerate().skip(n_columns) {
+            if let Some(name) = name {
+                drop(fields.insert(name.clone(), read_cell(ix, fields_data, 0)?));
+            }
+        }
+        let mut rows = Vec::with_capacity(n_rows);
+        for row_ix in 1..=n_rows {
+            let row = &rows_data[row_len * row_ix..row_len * (row_ix + 1)];
+            let mut record = BTreeMap::new();
+            for (ix, name) in names[..n_columns].iter().enumerate() {
+                if let Some(name) = name {
+                    drop(record.insert(name.clone(), read_cell(ix, row, row_ix)?));
+                }
+            }
+            rows.push(Value::Record(record));
+        }
+        Ok(RegItem::Table(None, Value::List(rows), fields))
+    }
+
+    /// Read one axis of a trace
+    fn read_axis(&mut self, long: bool) -> Result<RegItem, EtError> {
+        let _ = self.bytes(14)?;
+        let units = self.read_string(false)?;
+        let _ = self.u16()?;
+        let flags = self.u16()?;
+        let n_points = self.u32()? as usize;
+        // the times are usually only stored as a step size
+        let stored = flags == 0xFFFF || self.data.get(self.pos) != Some(&1);
+        let mut values = Vec::new();
+        if stored {
+            let width = if long { 4 } else { 8 };
+            let len = n_points
+                .checked_mul(width)
+                .ok_or("REG trace was oversized")?;
+            for chunk in self.bytes(len)?.chunks_exact(width) {
+                values.push(if long {
+                    // long values are fixed point with an (always empty) fractional byte
+                    f64::from(i32::extract(chunk, &Endian::Little)?) / 256.
+                } else {
+                    f64::extract(chunk, &Endian::Little)?
+                });
+            }
+        }
+        let _ = self.bytes(14)?;
+        let scaled = self.u32()? != 0;
+        let mult = self.f64()?;
+        let offset = self.f64()?;
+        #[allow(clippy::cast_precision_loss)]
+        let values = if !stored {
+            (0..n_points).map(|ix| offset + ix as f64 * mult).collect()
+        } else if scaled {
+            values.into_iter().map(|v| v * mult + offset).collect()
+        } else {
+            values
+        };
+        Ok(RegItem::Axis(units, values))
+    }
+}
+
+/// A single telemetry trace (e.g. the pump pressure over the course of a run)
+#[derive(Clone, Debug, Default)]
+struct RegTrace {
+    name: String,
+    units: String,
+    times: Vec<f64>,
+    values: Vec<f64>,
+}
+
+/// State of the Chemstation REG parser
+///
+/// Both the older (revision A) layout and the newer MFC archive layout written by later versions
+/// of Chemstation (e.g. B.04) are supported.
+#[derive(Clone, Debug, Default)]
+pub struct ChemstationRegState {
+    metadata: BTreeMap<String, Value<'static>>,
+    traces: Vec<RegTrace>,
+    cur_trace: usize,
+    cur_point: usize,
+}
+
+impl StateMetadata for ChemstationRegState {
+    fn metadata(&self) -> BTreeMap<String, Value> {
+        self.metadata.clone()
+    }
+
+    fn header(&self) -> Vec<&str> {
+        vec!["signal", "time", "value", "units"]
+    }
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationRegState {
+    type State = ();
+
+    fn parse(
+        rb: &[u8],
+        _eof: bool,
+        consumed: &mut usize,
+        _state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        let header = extract::<&[u8]>(rb, &mut 0, &mut 44)?;
+        if &header[..18] != b"\x0232\x00\x0dREGISTER FILE" {
+            return Err("Invalid Chemstation REG file".into());
+        }
+        // older files have a revision number here and newer ones have a placeholder
+        if header[25] != b'A' && &header[25..32] != b"notused" {
+            return Err("Unsupported version of REG file".into());
+        }
+        let data_len = u32::extract(&header[34..], &Endian::Little)? as usize;
+        if rb.len() < data_len {
+            return Err(EtError::from("REG files must be read in full").incomplete());
+        }
+        *consumed += data_len;
         Ok(true)
     }
 
-    fn get(
-        &mut self,
-        buf: &'b [u8],
-        state: &'s Self::State,
-    ) -> Result<(), EtError> {
+    fn get(&mut self, rb: &'b [u8], _state: &'s Self::State) -> Result<(), EtError> {
+        let n_sections = u16::extract(&rb[38..], &Endian::Little)?;
+        let con = &mut 44;
+        let mut contents = Vec::with_capacity(usize::from(n_sections));
+        if rb[25] == b'A' {
+            let mut sections = Vec::with_capacity(usize::from(n_sections));
+            for _ in 0..n_sections {
+                sections.push(RegSection::read(rb, con)?);
+            }
+            for section in &sections {
+                contents.push(section.read_contents()?);
+            }
+        } else {
+            for _ in 0..n_sections {
+                contents.push(RegArchive::read_section(rb, con)?);
+            }
+        }
+
+        let mut metadata = BTreeMap::new();
+        let mut traces = Vec::new();
+        for (parameters, section_traces) in contents {
+            traces.extend(section_traces);
+            if n_sections == 1 {
+                metadata = parameters;
+                continue;
+            }
+            // files with multiple sections (e.g. LCDIAG.REG) repeat parameter names in each
+            // one so we nest them under the section's title
+            let title = match parameters.get("Title") {
+                Some(Value::String(s)) => s.to_string(),
+                _ => format!("Section {}", metadata.len() + 1),
+            };
+            drop(metadata.insert(title, Value::Record(parameters)));
+        }
+
+        self.metadata = metadata;
+        self.traces = traces;
+        self.cur_trace = 0;
+        self.cur_point = 0;
         Ok(())
     }
 }
 
-/// Record
-#[derive(Clone, Copy, Debug, Default)]
-pub struct ChemstationRegRecord {
-    point: f64
+/// A single point from a telemetry trace in a Chemstation REG file
+#[derive(Clone, Debug, Default)]
+pub struct ChemstationRegRecord<'r> {
+    /// The name of the trace (e.g. "PMP1, Pressure")
+    pub signal: &'r str,
+    /// The time recorded at (in minutes)
+    pub time: f64,
+    /// The value recorded
+    pub value: f64,
+    /// The units of the value (e.g. "bar")
+    pub units: &'r str,
 }
 
-impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationRegRecord {
+impl_record!(ChemstationRegRecord<'r>: signal, time, value, units);
+
+impl<'b: 's, 's> FromSlice<'b, 's> for ChemstationRegRecord<'s> {
     type State = ChemstationRegState;
 
     fn parse(
-        buf: &[u8],
-        eof: bool,
-        consumed: &mut usize,
-        _state: &mut Self::State,
+        _rb: &[u8],
+        _eof: bool,
+        _consumed: &mut usize,
+        state: &mut Self::State,
     ) -> Result<bool, EtError> {
+        while let Some(trace) = state.traces.get(state.cur_trace) {
+            if state.cur_point < trace.values.len() {
+                state.cur_point += 1;
+                return Ok(true);
+            }
+            state.cur_trace += 1;
+            state.cur_point = 0;
+        }
         Ok(false)
     }
 
-    fn get(
-        &mut self,
-        buf: &'b [u8],
-        state: &'s Self::State,
-    ) -> Result<(), EtError> {
+    fn get(&mut self, _rb: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
+        let trace = &state.traces[state.cur_trace];
+        let ix = state.cur_point - 1;
+        self.signal = &trace.name;
+        self.time = trace.times[ix];
+        self.value = trace.values[ix];
+        self.units = &trace.units;
         Ok(())
     }
 }
 
-impl_record!(ChemstationRegRecord: point);
-
-impl_reader!(ChemstationRegReader, ChemstationRegRecord, ChemstationRegRecord, ChemstationRegState, ());
+impl_reader!(
+    ChemstationRegReader,
+    ChemstationRegRecord,
+    ChemstationRegRecord<'r>,
+    ChemstationRegState,
+    ()
+);
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::readers::RecordReader;
 
     #[test]
-    fn test_chemstation_reg_reader() -> Result<(), EtError> {
+    fn test_chemstation_reg_telemetry() -> Result<(), EtError> {
         let rb: &[u8] = include_bytes!("../../../tests/data/chemstation_mwd.d/LCDIAG.REG");
         let mut reader = ChemstationRegReader::new(rb, None)?;
+        assert_eq!(reader.headers(), ["signal", "time", "value", "units"]);
+
+        let metadata = reader.metadata();
+        let pump = match &metadata["PMP1, Start/Stop Conditions"] {
+            Value::Record(r) => r,
+            _ => panic!("pump conditions should be a record"),
+        };
+        assert!(matches!(pump["StartPressure"], Value::Float(p) if (p - 47.08).abs() < 0.01));
+        assert!(matches!(pump["DateTime"], Value::Datetime(_)));
+
+        let ChemstationRegRecord {
+            signal,
+            time,
+            value,
+            units,
+        } = reader.next()?.unwrap();
+        assert_eq!(signal, "PMP1, Pressure");
+        assert_eq!(units, "bar");
+        assert!(time.abs() < 0.0001);
+        assert!((value - 46.92).abs() < 0.0001);
+
+        let mut n_points = 1;
+        let mut last_time = 0.;
+        let mut signals = vec![signal.to_string()];
+        while let Some(record) = reader.next()? {
+            if record.signal != signals[signals.len() - 1] {
+                signals.push(record.signal.to_string());
+            }
+            last_time = record.time;
+            n_points += 1;
+        }
+        assert_eq!(n_points, 6 * 2400);
+        assert!((last_time - 11.995).abs() < 0.0001);
+        assert_eq!(
+            signals,
+            [
+                "PMP1, Pressure",
+                "PMP1, Flow",
+                "PMP1, Solvent A",
+                "PMP1, Solvent B",
+                "PMP1, Solvent C",
+                "PMP1, Solvent D"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_chemstation_reg_method() -> Result<(), EtError> {
+        let rb: &[u8] = include_bytes!("../../../tests/data/chemstation_mwd.d/RUN.M/LPMP1.REG");
+        let mut reader = ChemstationRegReader::new(rb, None)?;
+        assert!(reader.next()?.is_none());
+
+        let metadata = reader.metadata();
+        assert_eq!(metadata["STOPTIME"], Value::Float(12.));
+        assert_eq!(metadata["SOLV_NAME_C"], "EtOAc".into());
+        let timetable = match &metadata["TIMETABLE"] {
+            Value::List(rows) => rows,
+            _ => panic!("timetable should be a list"),
+        };
+        assert_eq!(timetable.len(), 2);
+        match &timetable[1] {
+            Value::Record(row) => {
+                assert_eq!(row["time"], Value::Float(10.));
+                assert_eq!(row["solv_D"], Value::Float(100.));
+            }
+            _ => panic!("timetable rows should be records"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_chemstation_reg_new_telemetry() -> Result<(), EtError> {
+        let rb: &[u8] = include_bytes!("../../../tests/data/carotenoid_extract.d/LCDIAG.REG");
+        let mut reader = ChemstationRegReader::new(rb, None)?;
+        let metadata = reader.metadata();
+        let pump = match &metadata["PMP1 , Pressure"] {
+            Value::Record(r) => r,
+            _ => panic!("pump pressure should be a record"),
+        };
+        assert_eq!(
+            pump["DateTime"],
+            Value::Datetime(
+                NaiveDateTime::parse_from_str("28-Jun-13, 10:59:23", "%d-%b-%y, %H:%M:%S").unwrap()
+            )
+        );
 
-        let mut n_recs = 1;
-        while reader.next()?.is_some() {
-            n_recs += 1;
+        let ChemstationRegRecord {
+            signal,
+            time,
+            value,
+            units,
+        } = reader.next()?.unwrap();
+        assert_eq!(signal, "THM1 , Temperature (Left)");
+        assert_eq!(time, 0.);
+        assert_eq!(units, "\u{b0}C");
+        assert!((value - 29.98).abs() < 0.0001);
+
+        let mut n_points = 1;
+        let mut last_time = 0.;
+        while let Some(record) = reader.next()? {
+            if record.signal == "PMP1 , Pressure" && n_points == 2700 {
+                assert_eq!(record.units, "bar");
+                assert!((record.value - 38.04).abs() < 0.0001);
+            }
+            last_time = record.time;
+            n_points += 1;
         }
-        assert_eq!(n_recs, 5);
+        assert_eq!(n_points, 2700 + 6 * 9000);
+        assert!((last_time - 44.995).abs() < 0.0001);
+        Ok(())
+    }
+
+    #[test]
+    fn test_chemstation_reg_new_method() -> Result<(), EtError> {
+        let rb: &[u8] = include_bytes!("../../../tests/data/carotenoid_extract.d/RUN.M/LPMP1.REG");
+        let mut reader = ChemstationRegReader::new(rb, None)?;
+        assert!(reader.next()?.is_none());
+        let metadata = reader.metadata();
+        assert_eq!(metadata["STOPTIME"], Value::Float(45.));
+        assert!(matches!(metadata["FLOW"], Value::Float(f) if (f - 0.4).abs() < 0.0001));
+        let timetable = match &metadata["TIMETABLE"] {
+            Value::List(l) => l,
+            _ => panic!("Timetable not a list"),
+        };
+        assert_eq!(timetable.len(), 3);
+
+        let rb: &[u8] = include_bytes!("../../../tests/data/carotenoid_extract.d/ACQRES.REG");
+        let reader = ChemstationRegReader::new(rb, None)?;
+        let metadata = reader.metadata();
+        assert_eq!(metadata["Title"], "Acquisition Results".into());
         Ok(())
     }
 }
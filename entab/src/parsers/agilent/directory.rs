@@ -0,0 +1,481 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use std::fs::{read_dir, File};
+use std::path::{Path, PathBuf};
+
+use crate::buffer::ReadBuffer;
+use crate::filetype::FileType;
+use crate::readers::{get_reader, RecordReader};
+use crate::record::Value;
+use crate::EtError;
+
+/// Extensions of the files in a `.d` directory that may hold signal, spectral, or telemetry data
+const DATA_EXTENSIONS: [&str; 5] = ["ch", "ms", "reg", "sp", "uv"];
+
+/// Run-level metadata keys that are merged across all of the files in a `.d` directory
+const RUN_KEYS: [&str; 9] = [
+    "sample",
+    "description",
+    "operator",
+    "run_date",
+    "method",
+    "instrument",
+    "sequence",
+    "vial",
+    "replicate",
+];
+
+/// Recursively list the data files in the directory `path`, sorted by name. Method (`*.M`)
+/// directories are skipped because they only hold instrument settings.
+fn data_files(path: &Path) -> Result<Vec<PathBuf>, EtError> {
+    let mut entries = Vec::new();
+    for entry in read_dir(path)? {
+        entries.push(entry?.path());
+    }
+    entries.sort();
+    let mut paths = Vec::new();
+    for entry_path in entries {
+        let ext = entry_path.extension().and_then(|e| e.to_str());
+        if entry_path.is_dir() {
+            if !ext.is_some_and(|e| e.eq_ignore_ascii_case("m")) {
+                paths.extend(data_files(&entry_path)?);
+            }
+        } else if ext.is_some_and(|e| DATA_EXTENSIONS.iter().any(|d| e.eq_ignore_ascii_case(d))) {
+            paths.push(entry_path);
+        }
+    }
+    Ok(paths)
+}
+
+/// Find the `acqmeth.txt` description of the acquisition method anywhere in the directory `path`
+/// (including in the method directories).
+fn find_acq_method(path: &Path) -> Option<PathBuf> {
+    let mut entries = read_dir(path)
+        .ok()?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .collect::<Vec<_>>();
+    entries.sort();
+    for entry_path in &entries {
+        let is_acq_method = entry_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.eq_ignore_ascii_case("acqmeth.txt"));
+        if is_acq_method && entry_path.is_file() {
+            return Some(entry_path.clone());
+        }
+    }
+    entries
+        .iter()
+        .filter(|p| p.is_dir())
+        .find_map(|p| find_acq_method(p))
+}
+
+/// Decode a text file written by Chemstation; these are usually UTF-16 with a byte order mark.
+fn decode_text(data: &[u8]) -> String {
+    if let Some(rest) = data.strip_prefix(b"\xFF\xFE") {
+        let chars = rest
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]));
+        return char::decode_utf16(chars)
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect();
+    }
+    String::from_utf8_lossy(data).into_owned()
+}
+
+/// The detector that produced a given type of Agilent file (REG files hold instrument telemetry
+/// and method settings instead of detector data)
+fn detector_name(file_type: &FileType) -> Option<&'static str> {
+    Some(match file_type {
+        FileType::AgilentChemstationDad | FileType::AgilentMasshunterDad => "DAD",
+        FileType::AgilentChemstationFid | FileType::AgilentChemstationFid2 => "FID",
+        FileType::AgilentChemstationMs => "MS",
+        FileType::AgilentChemstationMwd => "MWD",
+        FileType::AgilentChemstationReg => "telemetry",
+        FileType::AgilentChemstationUv => "UV",
+        _ => return None,
+    })
+}
+
+/// True if a metadata value doesn't carry any information
+fn is_empty_value(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(s) => s.is_empty(),
+        _ => false,
+    }
+}
+
+/// Open a single data file with the matching Chemstation or MassHunter parser, returning the
+/// detector it came from and its reader.
+fn open_data_file(path: &Path) -> Result<(&'static str, Box<dyn RecordReader>), EtError> {
+    let mut rb = ReadBuffer::try_from(File::open(path)?)?;
+    let detector =
+        detector_name(&rb.sniff_filetype()?).ok_or("Not a supported Agilent data file")?;
+    // MassHunter readers need the path to find the matching header file
+    let mut params = BTreeMap::new();
+    let filename = path.to_string_lossy().into_owned();
+    drop(params.insert("filename".to_string(), filename.into()));
+    let (reader, _) = get_reader(rb, None, Some(params))?;
+    Ok((detector, reader))
+}
+
+/// A single data file inside of a `.d` directory
+struct AgilentDirFile {
+    /// The path of the file relative to the `.d` directory
+    name: String,
+    detector: &'static str,
+    reader: Box<dyn RecordReader>,
+    /// For each of the reader's columns, its position in the combined headers
+    columns: Vec<usize>,
+}
+
+impl core::fmt::Debug for AgilentDirFile {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AgilentDirFile")
+            .field("name", &self.name)
+            .field("detector", &self.detector)
+            .finish()
+    }
+}
+
+/// Read every supported data file in an Agilent `.d` directory as one table.
+///
+/// Each file (signal traces, spectra, and REG telemetry) is opened with the matching Chemstation
+/// or MassHunter parser and read in turn. The records are tagged with the file they came from and
+/// the detector that produced them; the remaining columns are the union of the columns of all the
+/// files with nulls where a file doesn't have a given column (e.g. MWD traces add the signal name
+/// and wavelengths of each trace).
+///
+/// Files that can't be opened are skipped and listed with the reason in the `skipped` metadata
+/// instead of failing the whole directory; errors partway through a file are still returned.
+#[derive(Debug)]
+pub struct AgilentDirReader {
+    files: Vec<AgilentDirFile>,
+    headers: Vec<String>,
+    acq_method: Option<String>,
+    skipped: Vec<(String, String)>,
+    cur_file: usize,
+}
+
+impl AgilentDirReader {
+    /// Create a new reader from the path to a `.d` directory
+    ///
+    /// # Errors
+    /// If the directory can't be read or doesn't contain any readable files, an error is returned.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, EtError> {
+        let root = path.as_ref();
+        let relative_name = |file_path: &Path| {
+            file_path
+                .strip_prefix(root)
+                .unwrap_or(file_path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        };
+        let mut files = Vec::new();
+        let mut skipped = Vec::new();
+        let mut headers = vec!["file".to_string(), "detector".to_string()];
+        for file_path in data_files(root)? {
+            let name = relative_name(&file_path);
+            let (detector, reader) = match open_data_file(&file_path) {
+                Ok(file) => file,
+                Err(e) => {
+                    skipped.push((name, e.msg.into_owned()));
+                    continue;
+                }
+            };
+
+            let mut columns = Vec::new();
+            for header in reader.headers() {
+                let pos = match headers.iter().position(|h| *h == header) {
+                    Some(pos) => pos,
+                    None => {
+                        headers.push(header);
+                        headers.len() - 1
+                    }
+                };
+                columns.push(pos);
+            }
+            files.push(AgilentDirFile {
+                name,
+                detector,
+                reader,
+                columns,
+            });
+        }
+        if files.is_empty() {
+            return Err("No readable Agilent data files found in directory".into());
+        }
+        let acq_method = find_acq_method(root)
+            .and_then(|p| std::fs::read(p).ok())
+            .map(|data| decode_text(&data));
+        Ok(AgilentDirReader {
+            files,
+            headers,
+            acq_method,
+            skipped,
+            cur_file: 0,
+        })
+    }
+}
+
+impl RecordReader for AgilentDirReader {
+    /// The next record from any of the files in the directory.
+    fn next_record(&mut self) -> Result<Option<Vec<Value>>, EtError> {
+        while let Some(file) = self.files.get_mut(self.cur_file) {
+            // copy the values out so the borrow on the file's reader ends here
+            let values = match file.reader.next_record() {
+                Ok(values) => {
+                    values.map(|r| r.into_iter().map(Value::into_owned).collect::<Vec<_>>())
+                }
+                Err(mut e) => {
+                    // a file that opened fine but breaks partway through is an error rather
+                    // than something to skip or we'd silently return a truncated table
+                    e.msg = format!("{} (in {})", e.msg, file.name).into();
+                    return Err(e);
+                }
+            };
+            if let Some(values) = values {
+                let mut record = vec![Value::Null; self.headers.len()];
+                record[0] = file.name.clone().into();
+                record[1] = file.detector.into();
+                for (value, pos) in values.into_iter().zip(&file.columns) {
+                    record[*pos] = value;
+                }
+                return Ok(Some(record));
+            }
+            self.cur_file += 1;
+        }
+        Ok(None)
+    }
+
+    /// The headers for this Reader.
+    fn headers(&self) -> Vec<String> {
+        self.headers.clone()
+    }
+
+    /// The run metadata merged across all of the files, the acquisition method, a list of the
+    /// files read, and a list of the files that couldn't be read.
+    fn metadata(&self) -> BTreeMap<String, Value> {
+        let mut metadata = BTreeMap::new();
+        for file in &self.files {
+            for (key, value) in file.reader.metadata() {
+                if !RUN_KEYS.contains(&key.as_str()) || is_empty_value(&value) {
+                    continue;
+                }
+                let _ = metadata.entry(key).or_insert_with(|| value.into_owned());
+            }
+        }
+        if let Some(acq_method) = &self.acq_method {
+            drop(metadata.insert("acquisition_method".to_string(), acq_method.as_str().into()));
+        }
+        let files = self
+            .files
+            .iter()
+            .map(|f| {
+                let mut file = BTreeMap::new();
+                drop(file.insert("file".to_string(), f.name.clone().into()));
+                drop(file.insert("detector".to_string(), f.detector.into()));
+                match f.reader.metadata().remove("signal_name") {
+                    Some(signal) if !is_empty_value(&signal) => {
+                        drop(file.insert("signal_name".to_string(), signal.into_owned()));
+                    }
+                    _ => {}
+                }
+                Value::Record(file)
+            })
+            .collect::<Vec<Value>>();
+        drop(metadata.insert("files".to_string(), files.into()));
+        if !self.skipped.is_empty() {
+            let skipped = self
+                .skipped
+                .iter()
+                .map(|(name, error)| {
+                    let mut file = BTreeMap::new();
+                    drop(file.insert("file".to_string(), name.as_str().into()));
+                    drop(file.insert("error".to_string(), error.as_str().into()));
+                    Value::Record(file)
+                })
+                .collect::<Vec<Value>>();
+            drop(metadata.insert("skipped".to_string(), skipped.into()));
+        }
+        metadata
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mwd_directory() -> Result<(), EtError> {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("tests/data/chemstation_mwd.d");
+        let mut reader = AgilentDirReader::new(&path)?;
+        let headers = reader.headers();
+        assert_eq!(&headers[..4], ["file", "detector", "signal", "time"]);
+        let signal_col = headers.iter().position(|h| h == "signal_name").unwrap();
+        let metadata = reader.metadata();
+        assert!(metadata.contains_key("sample"));
+        assert!(metadata.contains_key("run_date"));
+        if let Value::List(files) = &metadata["files"] {
+            let n_signals = files
+                .iter()
+                .filter(|f| matches!(f, Value::Record(f) if f.contains_key("signal_name")))
+                .count();
+            assert_eq!(n_signals, 5);
+        } else {
+            panic!("Files should be a list");
+        }
+
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        let mut wavelengths = Vec::new();
+        while let Some(record) = reader.next_record()? {
+            if record[1] != "MWD".into() {
+                continue;
+            }
+            let count = counts
+                .entry(record[signal_col].clone().into_string()?)
+                .or_default();
+            if *count == 0 {
+                wavelengths.push(record[2].clone().into_owned());
+            }
+            *count += 1;
+        }
+        assert_eq!(counts.len(), 5);
+        assert_eq!(counts["MWD A"], 1801);
+        assert_eq!(wavelengths, [210., 230., 260., 280., 320.].map(Value::from));
+        Ok(())
+    }
+
+    #[test]
+    fn test_agilent_directory() -> Result<(), EtError> {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("tests/data/chemstation_mwd.d");
+        let mut reader = AgilentDirReader::new(&path)?;
+        let metadata = reader.metadata();
+        let file_names = |files: &Value| match files {
+            Value::List(files) => files
+                .iter()
+                .filter_map(|f| match f {
+                    Value::Record(f) => f["file"].clone().into_string().ok(),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+            _ => Vec::new(),
+        };
+        // the REG telemetry files are read along with the signal traces
+        assert!(!metadata.contains_key("skipped"));
+        let mut names = file_names(&metadata["files"]);
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "ACQRES.REG",
+                "LAFC1FD.REG",
+                "LCDIAG.REG",
+                "MSACQINF.REG",
+                "mwd1A.ch",
+                "mwd1B.ch",
+                "mwd1C.ch",
+                "mwd1D.ch",
+                "mwd1E.ch"
+            ]
+        );
+        assert!(!metadata.contains_key("signal_name"));
+
+        let mut n_mwd_records = 0;
+        let mut n_reg_records = 0;
+        while let Some(record) = reader.next_record()? {
+            if record[1] == "MWD".into() {
+                n_mwd_records += 1;
+            } else if record[1] == "telemetry".into() {
+                n_reg_records += 1;
+            }
+        }
+        assert_eq!(n_mwd_records, 5 * 1801);
+        assert!(n_reg_records > 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_agilent_directory_masshunter() -> Result<(), EtError> {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("tests/data/masshunter_example");
+        let mut reader = AgilentDirReader::new(&path)?;
+        let record = reader.next_record()?.unwrap();
+        assert_eq!(record[0], "AcqData/DAD1.sp".into());
+        assert_eq!(record[1], "DAD".into());
+        assert!(AgilentDirReader::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/sequence.fasta"
+        ))
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_agilent_directory_skips_unreadable() -> Result<(), EtError> {
+        let mut path = std::env::temp_dir();
+        path.push(format!("entab_test_{}.d", std::process::id()));
+        std::fs::create_dir_all(&path)?;
+        let _ = std::fs::copy(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/data/chemstation_mwd.d/mwd1A.ch"
+            ),
+            path.join("mwd1A.ch"),
+        )?;
+        std::fs::write(path.join("dad1.uv"), b"")?;
+        std::fs::write(path.join("bad.REG"), b"not a reg file")?;
+        let reader = AgilentDirReader::new(&path);
+        std::fs::remove_dir_all(&path)?;
+
+        let reader = reader?;
+        let metadata = reader.metadata();
+        if let Value::List(skipped) = &metadata["skipped"] {
+            let names = skipped
+                .iter()
+                .map(|f| match f {
+                    Value::Record(f) => f["file"].clone(),
+                    _ => Value::Null,
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(names, ["bad.REG".into(), "dad1.uv".into()]);
+        } else {
+            panic!("Skipped should be a list");
+        }
+        if let Value::List(files) = &metadata["files"] {
+            assert_eq!(files.len(), 1);
+        } else {
+            panic!("Files should be a list");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_agilent_directory_parser() -> Result<(), EtError> {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("tests/data/chemstation_mwd.d");
+        let mut params = BTreeMap::new();
+        drop(params.insert(
+            "filename".to_string(),
+            path.to_string_lossy().into_owned().into(),
+        ));
+        let (mut reader, _) = get_reader(&b""[..], Some("agilent_d"), Some(params))?;
+        assert_eq!(reader.headers()[0], "file");
+        assert!(reader.next_record()?.is_some());
+        assert!(get_reader(&b""[..], Some("agilent_d"), None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_text() {
+        assert_eq!(decode_text(b"\xFF\xFEA\x00b\x00"), "Ab");
+        assert_eq!(decode_text(b"Ab"), "Ab");
+    }
+}
@@ -2,9 +2,11 @@
 pub mod chemstation;
 /// Readers for newer formats generated by the GC/LC control software Chemstation
 pub mod chemstation_new;
-// TODO: finish and reenable this
-// /// Readers for instrument telemetry data generated by Chemstation
-// pub mod chemstation_reg;
+/// Readers for instrument telemetry data generated by Chemstation
+pub mod chemstation_reg;
+/// Readers for Agilent `.d` run directories
+#[cfg(feature = "std")]
+pub mod directory;
 /// Readers for formats generated by the GC/LC control software Masshunter
 #[cfg(feature = "std")]
 pub mod masshunter;
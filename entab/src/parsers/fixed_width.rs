@@ -0,0 +1,382 @@
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::impl_reader;
+use crate::parsers::common::NewLine;
+use crate::parsers::tsv_inference::TsvFieldType;
+use crate::parsers::{extract, extract_opt, FromSlice};
+use crate::record::{StateMetadata, Value};
+use crate::EtError;
+
+/// Parameters for parsing fixed-width text tables
+#[derive(Clone, Debug)]
+pub struct FixedWidthParams {
+    /// The width of each of the columns (in characters); if not provided, these will be inferred
+    /// from the whitespace alignment of the beginning of the file. The last column always extends
+    /// to the end of the line.
+    pub widths: Option<Vec<usize>>,
+    /// Automatically determine the column widths.
+    pub sniff_widths: bool,
+    /// The number of lines to skip before the column titles and data start.
+    pub skip_lines: usize,
+    /// Automatically determine the types of each of the fields in the table.
+    pub infer_types: bool,
+    /// The data types of each of the fields in the table
+    pub types: Vec<TsvFieldType>,
+}
+
+impl Default for FixedWidthParams {
+    fn default() -> Self {
+        FixedWidthParams {
+            widths: None,
+            sniff_widths: true,
+            skip_lines: 0,
+            infer_types: true,
+            types: vec![],
+        }
+    }
+}
+
+impl FixedWidthParams {
+    /// Set the column widths
+    #[must_use]
+    pub fn widths(mut self, widths: &[usize]) -> Self {
+        self.widths = Some(widths.to_vec());
+        self.sniff_widths = false;
+        self
+    }
+
+    /// Set the number of lines to skip before the column titles
+    #[must_use]
+    pub fn skip_lines(mut self, n: usize) -> Self {
+        self.skip_lines = n;
+        self
+    }
+}
+
+/// Split a line into fields at the character positions in `starts`; the last field runs to the
+/// end of the line. Fields are trimmed of surrounding whitespace.
+fn split_fixed<'a>(
+    buffer: &mut Vec<&'a str>,
+    line: &'a [u8],
+    starts: &[usize],
+) -> Result<(), EtError> {
+    buffer.clear();
+    let line = alloc::str::from_utf8(line)?;
+    // byte offsets of each character so multibyte characters are never split
+    let offsets: Option<Vec<usize>> = if line.is_ascii() {
+        None
+    } else {
+        Some(line.char_indices().map(|(ix, _)| ix).collect())
+    };
+    let byte_pos = |pos: usize| match &offsets {
+        Some(offsets) => offsets.get(pos).copied().unwrap_or(line.len()),
+        None => pos.min(line.len()),
+    };
+    for (ix, start) in starts.iter().enumerate() {
+        let start = byte_pos(*start);
+        let end = starts.get(ix + 1).map_or(line.len(), |e| byte_pos(*e));
+        buffer.push(line[start..end.max(start)].trim());
+    }
+    Ok(())
+}
+
+/// Determine the column widths from the whitespace alignment of the lines in `data`.
+///
+/// Any run of character positions that is whitespace in every line separates two columns.
+pub fn sniff_widths_from_data(params: &mut FixedWidthParams, data: &[u8]) {
+    let mut occupied: Vec<bool> = Vec::new();
+    let con = &mut 0;
+    let mut line_ix = 0;
+    while let Ok(NewLine(line)) = extract(data, con, &mut 0) {
+        line_ix += 1;
+        if line_ix <= params.skip_lines {
+            continue;
+        }
+        let line = match alloc::str::from_utf8(line) {
+            Ok(line) => line,
+            Err(_) => continue,
+        };
+        for (pos, c) in line.chars().enumerate() {
+            if pos >= occupied.len() {
+                occupied.resize(pos + 1, false);
+            }
+            if !c.is_whitespace() {
+                occupied[pos] = true;
+            }
+        }
+    }
+
+    let mut widths = Vec::new();
+    let mut last_start = 0;
+    for pos in 1..occupied.len() {
+        if occupied[pos] && !occupied[pos - 1] {
+            widths.push(pos - last_start);
+            last_start = pos;
+        }
+    }
+    params.widths = Some(widths);
+}
+
+/// Determine the types of the fields in the data.
+pub fn sniff_fixed_types_from_data(params: &mut FixedWidthParams, data: &[u8]) {
+    let starts = column_starts(params.widths.as_deref().unwrap_or(&[]));
+    let mut fields = Vec::new();
+    let mut types: Vec<TsvFieldType> = vec![TsvFieldType::default(); starts.len()];
+    let con = &mut 0;
+    let mut line_ix = 0;
+    while let Ok(NewLine(line)) = extract(data, con, &mut 0) {
+        line_ix += 1;
+        // + 1 for the headers line
+        if line_ix <= params.skip_lines + 1 {
+            continue;
+        }
+        if split_fixed(&mut fields, line, &starts).is_err() {
+            continue;
+        }
+        for (field, ty) in fields.iter().zip(types.iter_mut()) {
+            // empty fields are treated as nulls so they shouldn't affect the type
+            if !field.is_empty() {
+                ty.infer(field);
+            }
+        }
+    }
+    params.types = types;
+}
+
+fn column_starts(widths: &[usize]) -> Vec<usize> {
+    let mut starts = Vec::with_capacity(widths.len() + 1);
+    let mut pos = 0;
+    starts.push(pos);
+    for width in widths {
+        pos += width;
+        starts.push(pos);
+    }
+    starts
+}
+
+/// Track the current state of the fixed-width parser
+#[derive(Clone, Debug, Default)]
+pub struct FixedWidthState {
+    headers: Vec<String>,
+    starts: Vec<usize>,
+    types: Option<Vec<TsvFieldType>>,
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for FixedWidthState {
+    type State = FixedWidthParams;
+
+    fn parse(
+        buffer: &[u8],
+        eof: bool,
+        consumed: &mut usize,
+        state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        if state.sniff_widths || state.widths.is_none() {
+            // only look at complete lines so a line cut off at the end of the buffer can't
+            // change the widths; this is redone every time more of the file is read
+            let complete = if eof {
+                buffer
+            } else {
+                let end = buffer
+                    .iter()
+                    .rposition(|b| *b == b'\n')
+                    .map_or(0, |ix| ix + 1);
+                &buffer[..end]
+            };
+            sniff_widths_from_data(state, complete);
+        }
+        if state.infer_types {
+            sniff_fixed_types_from_data(state, buffer);
+        }
+        let con = &mut 0;
+        for _ in 0..state.skip_lines {
+            if extract_opt::<NewLine>(buffer, eof, con, &mut 0)?.is_none() {
+                return Err("could not skip header lines".into());
+            }
+        }
+        if !NewLine::parse(&buffer[*con..], eof, con, &mut 0)? {
+            return Ok(false);
+        }
+        *consumed += *con;
+        Ok(true)
+    }
+
+    fn get(&mut self, buffer: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
+        let con = &mut 0;
+        for _ in 0..state.skip_lines {
+            let _ = extract::<NewLine>(buffer, con, &mut 0)?;
+        }
+        let NewLine(header) = extract::<NewLine>(buffer, con, &mut 0)?;
+
+        self.starts = column_starts(state.widths.as_deref().unwrap_or(&[]));
+        let mut fields = Vec::new();
+        split_fixed(&mut fields, header, &self.starts)?;
+        self.headers = fields.iter().map(ToString::to_string).collect();
+        if state.infer_types {
+            self.types = Some(state.types.clone());
+        } else if !state.types.is_empty() {
+            if state.types.len() != self.starts.len() {
+                return Err("Number of types does not match the number of columns".into());
+            }
+            self.types = Some(state.types.clone());
+        }
+        Ok(())
+    }
+}
+
+impl StateMetadata for FixedWidthState {
+    fn header(&self) -> Vec<&str> {
+        let mut headers = Vec::new();
+        for header in &self.headers {
+            headers.push(header.as_ref());
+        }
+        headers
+    }
+}
+
+/// Values from the current line of the fixed-width table
+#[derive(Debug, Default, PartialEq)]
+pub struct FixedWidthRecord<'r> {
+    values: Vec<Value<'r>>,
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for FixedWidthRecord<'s> {
+    type State = FixedWidthState;
+
+    fn parse(
+        buffer: &[u8],
+        eof: bool,
+        consumed: &mut usize,
+        _state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        NewLine::parse(buffer, eof, consumed, &mut 0)
+    }
+
+    fn get(&mut self, buffer: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
+        let mut buffer = buffer;
+        if buffer.last() == Some(&b'\n') {
+            buffer = &buffer[..buffer.len() - 1];
+        }
+        if buffer.last() == Some(&b'\r') {
+            buffer = &buffer[..buffer.len() - 1];
+        }
+        let mut fields = Vec::with_capacity(state.starts.len());
+        split_fixed(&mut fields, buffer, &state.starts)?;
+        self.values = if let Some(types) = &state.types {
+            fields
+                .into_iter()
+                .zip(types)
+                .map(|(f, ty)| {
+                    if f.is_empty() {
+                        Value::Null
+                    } else {
+                        ty.coerce(Cow::Borrowed(f))
+                    }
+                })
+                .collect()
+        } else {
+            fields
+                .into_iter()
+                .map(|f| if f.is_empty() { Value::Null } else { f.into() })
+                .collect()
+        };
+        Ok(())
+    }
+}
+
+impl<'r> From<FixedWidthRecord<'r>> for Vec<Value<'r>> {
+    fn from(record: FixedWidthRecord<'r>) -> Self {
+        record.values
+    }
+}
+
+impl_reader!(
+    FixedWidthReader,
+    FixedWidthRecord,
+    FixedWidthRecord<'r>,
+    FixedWidthState,
+    FixedWidthParams
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::buffer::ReadBuffer;
+    use crate::readers::RecordReader;
+
+    #[test]
+    fn test_sniffed_reader() -> Result<(), EtError> {
+        const TEST_TEXT: &[u8] =
+            b"name    count  value\nfirst       1   1.50\nsecond     20  12.25\nthird          -3.00";
+        let mut reader = FixedWidthReader::new(TEST_TEXT, None)?;
+        assert_eq!(&reader.headers(), &["name", "count", "value"]);
+
+        let FixedWidthRecord { values } = reader.next()?.expect("first record");
+        assert_eq!(values, vec!["first".into(), 1i64.into(), 1.5.into()]);
+        let FixedWidthRecord { values } = reader.next()?.expect("second record");
+        assert_eq!(values, vec!["second".into(), 20i64.into(), 12.25.into()]);
+        let FixedWidthRecord { values } = reader.next()?.expect("third record");
+        assert_eq!(values, vec!["third".into(), Value::Null, (-3.).into()]);
+        assert!(reader.next()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_explicit_widths() -> Result<(), EtError> {
+        const TEST_TEXT: &[u8] = b"# comment\nIDVAL NOTE\nA1  7extra words\nB2 10\n";
+        let params = FixedWidthParams::default().widths(&[2, 3]).skip_lines(1);
+        let mut reader = FixedWidthReader::new(TEST_TEXT, Some(params))?;
+        assert_eq!(&reader.headers(), &["ID", "VAL", "NOTE"]);
+
+        let FixedWidthRecord { values } = reader.next()?.expect("first record");
+        assert_eq!(values, vec!["A1".into(), 7i64.into(), "extra words".into()]);
+        let FixedWidthRecord { values } = reader.next()?.expect("second record");
+        assert_eq!(values, vec!["B2".into(), 10i64.into(), Value::Null]);
+        assert!(reader.next()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_small_buffer() -> Result<(), EtError> {
+        // the widths shouldn't be locked in from the first few bytes of the file
+        const TEST_TEXT: &[u8] =
+            b"name    count  value\nfirst       1   1.50\nsecond     20  12.25\n";
+        let rb = ReadBuffer::from_reader(Box::new(std::io::Cursor::new(TEST_TEXT)), Some(8))?;
+        let params = FixedWidthParams {
+            infer_types: false,
+            ..FixedWidthParams::default()
+        };
+        let mut reader = FixedWidthReader::new(rb, Some(params))?;
+        assert_eq!(&reader.headers(), &["name", "count", "value"]);
+        let FixedWidthRecord { values } = reader.next()?.expect("first record");
+        assert_eq!(values, vec!["first".into(), "1".into(), "1.50".into()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_multibyte_characters() -> Result<(), EtError> {
+        const TEST_TEXT: &[u8] = "name  city\n\u{c5}sa   Malm\u{f6}\nBo    Ume\u{e5}\n".as_bytes();
+        let mut reader = FixedWidthReader::new(TEST_TEXT, None)?;
+        assert_eq!(&reader.headers(), &["name", "city"]);
+        let FixedWidthRecord { values } = reader.next()?.expect("first record");
+        assert_eq!(values, vec!["\u{c5}sa".into(), "Malm\u{f6}".into()]);
+        let FixedWidthRecord { values } = reader.next()?.expect("second record");
+        assert_eq!(values, vec!["Bo".into(), "Ume\u{e5}".into()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bad_fuzzes() -> Result<(), EtError> {
+        const TEST_TEXT: &[u8] = b" \n\n \xFF";
+        let mut reader = FixedWidthReader::new(TEST_TEXT, None)?;
+        while let Ok(Some(_)) = reader.next() {}
+
+        const TEST_TEXT_2: &[u8] = b"";
+        assert!(FixedWidthReader::new(TEST_TEXT_2, None).is_err());
+        Ok(())
+    }
+}
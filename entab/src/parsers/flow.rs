@@ -1,9 +1,10 @@
-use alloc::borrow::ToOwned;
 use alloc::collections::BTreeMap;
 use alloc::string::{String, ToString};
+use alloc::vec;
 use alloc::vec::Vec;
 use alloc::{format, str};
 use core::default::Default;
+use core::str::FromStr;
 
 use chrono::{NaiveDate, NaiveTime};
 
@@ -20,20 +21,467 @@ struct FcsColumn {
     range: u64,
     short_name: String,
     long_name: String,
+    log_decades: f64,
+    log_offset: f64,
+    gain: f64,
+    voltage: Option<f64>,
+}
+
+impl FcsColumn {
+    /// The name of this channel in the chosen style.
+    fn name(&self, style: FcsChannelNames) -> String {
+        match style {
+            FcsChannelNames::Long if !self.long_name.is_empty() => self.long_name.clone(),
+            FcsChannelNames::Both if !self.long_name.is_empty() => {
+                format!("{} ({})", self.short_name, self.long_name)
+            }
+            _ => self.short_name.clone(),
+        }
+    }
+
+    /// Everything we know about this channel from the TEXT segment.
+    fn metadata(&self) -> Value<'static> {
+        let mut record = BTreeMap::new();
+        drop(record.insert("name".into(), self.short_name.clone().into()));
+        if !self.long_name.is_empty() {
+            drop(record.insert("long_name".into(), self.long_name.clone().into()));
+        }
+        let bits = if self.delimited {
+            Value::Null
+        } else {
+            i64::from(self.size).into()
+        };
+        drop(record.insert("bits".into(), bits));
+        #[allow(clippy::cast_possible_wrap)]
+        let range = Value::Integer(self.range as i64);
+        drop(record.insert("range".into(), range));
+        drop(record.insert("log_decades".into(), self.log_decades.into()));
+        drop(record.insert("log_offset".into(), self.log_offset.into()));
+        if self.gain > 0. {
+            drop(record.insert("gain".into(), self.gain.into()));
+        }
+        if let Some(voltage) = self.voltage {
+            drop(record.insert("voltage".into(), voltage.into()));
+        }
+        Value::Record(record)
+    }
+
+    /// Convert a raw channel value into linear scale using the `$PnE` and `$PnG` keywords.
+    fn scale(&self, value: f64) -> f64 {
+        if self.log_decades > 0. {
+            #[allow(clippy::cast_precision_loss)]
+            let range = self.range as f64;
+            let offset = if self.log_offset > 0. {
+                self.log_offset
+            } else {
+                1.
+            };
+            offset * 10f64.powf(self.log_decades * value / range)
+        } else if self.gain > 0. {
+            value / self.gain
+        } else {
+            value
+        }
+    }
+}
+
+/// A transformation to apply to the (compensated) fluorescence values in an FCS file.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FcsTransform {
+    /// Leave the values as is
+    #[default]
+    None,
+    /// Inverse hyperbolic sine scaled by a cofactor (commonly 150 for flow cytometry and 5 for
+    /// mass cytometry)
+    Arcsinh {
+        /// The value to divide by before taking the arcsinh
+        cofactor: f64,
+    },
+    /// The logicle transform of Parks, Roederer & Moore (2006)
+    Logicle {
+        /// The top of the scale (e.g. 262144)
+        t: f64,
+        /// The width of the linearization region in decades (e.g. 0.5)
+        w: f64,
+        /// The total number of decades of the display (e.g. 4.5)
+        m: f64,
+        /// The number of additional negative decades (e.g. 0)
+        a: f64,
+    },
+}
+
+impl FromStr for FcsTransform {
+    type Err = EtError;
+
+    /// Parse a transform written as `none`, `arcsinh:cofactor`, or `logicle:t,w,m,a`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, args) = s.split_once(':').unwrap_or((s, ""));
+        let args = args
+            .split(',')
+            .filter(|a| !a.trim().is_empty())
+            .map(|a| a.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()?;
+        let transform = match (name.trim().to_ascii_lowercase().as_str(), &args[..]) {
+            ("none", []) => FcsTransform::None,
+            ("arcsinh", [cofactor]) => FcsTransform::Arcsinh {
+                cofactor: *cofactor,
+            },
+            ("logicle", [t, w, m, a]) => FcsTransform::Logicle {
+                t: *t,
+                w: *w,
+                m: *m,
+                a: *a,
+            },
+            _ => return Err(format!("Unknown FCS transform {}", s).into()),
+        };
+        Ok(transform)
+    }
+}
+
+/// Precomputed constants for the logicle transform.
+///
+/// Adapted from the reference implementation in Moore & Parks (2012), "Update for the logicle
+/// data scale including operational code implementations".
+#[derive(Clone, Copy, Debug, Default)]
+struct Logicle {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    f: f64,
+    x1: f64,
+}
+
+impl Logicle {
+    fn new(t: f64, w: f64, m: f64, a: f64) -> Result<Self, EtError> {
+        if t <= 0. || m <= 0. || w < 0. || 2. * w > m || -a > w || a + w > m - w {
+            return Err("Invalid logicle parameters".into());
+        }
+        let w = w / (m + a);
+        let x2 = a / (m + a);
+        let x1 = x2 + w;
+        let x0 = x2 + 2. * w;
+        let b = (m + a) * core::f64::consts::LN_10;
+        let d = Self::solve(b, w);
+        let c_a = (x0 * (b + d)).exp();
+        let mf_a = (b * x1).exp() - c_a / (d * x1).exp();
+        let a = t / ((b.exp() - mf_a) - c_a / d.exp());
+        Ok(Logicle {
+            a,
+            b,
+            c: c_a * a,
+            d,
+            f: -mf_a * a,
+            x1,
+        })
+    }
+
+    /// Find d such that `2 * ln(d) + w * d = 2 * ln(b) - w * b`.
+    fn solve(b: f64, w: f64) -> f64 {
+        if w == 0. {
+            return b;
+        }
+        // the function is monotonic in d so bisection is guaranteed to converge
+        let f_b = -2. * b.ln() + w * b;
+        let (mut lo, mut hi) = (0., b);
+        for _ in 0..200 {
+            let d = (lo + hi) / 2.;
+            let f = 2. * d.ln() + w * d + f_b;
+            if f < 0. {
+                lo = d;
+            } else {
+                hi = d;
+            }
+        }
+        (lo + hi) / 2.
+    }
+
+    /// Transform a value into logicle scale (0 to 1 over the display range)
+    fn scale(&self, value: f64) -> f64 {
+        if value == 0. {
+            return self.x1;
+        }
+        let negative = value < 0.;
+        let value = value.abs();
+
+        let mut x = if value < self.f {
+            let slope = self.a * self.b * (self.b * self.x1).exp()
+                + self.c * self.d / (self.d * self.x1).exp();
+            self.x1 + value / slope
+        } else {
+            (value / self.a).ln() / self.b
+        };
+        let tolerance = 3. * f64::EPSILON * x.abs().max(1.);
+        // Halley's method
+        for _ in 0..20 {
+            let ae2bx = self.a * (self.b * x).exp();
+            let ce2mdx = self.c / (self.d * x).exp();
+            let y = (ae2bx + self.f) - (ce2mdx + value);
+            let abe2bx = self.b * ae2bx;
+            let cde2mdx = self.d * ce2mdx;
+            let dy = abe2bx + cde2mdx;
+            let ddy = self.b * abe2bx - self.d * cde2mdx;
+            let delta = y / (dy * (1. - y * ddy / (2. * dy * dy)));
+            x -= delta;
+            if delta.abs() < tolerance {
+                break;
+            }
+        }
+        if negative {
+            2. * self.x1 - x
+        } else {
+            x
+        }
+    }
+}
+
+/// Invert a square matrix using Gauss-Jordan elimination with partial pivoting.
+fn invert_matrix(matrix: &[Vec<f64>]) -> Result<Vec<Vec<f64>>, EtError> {
+    let n = matrix.len();
+    let mut left: Vec<Vec<f64>> = matrix.to_vec();
+    let mut right: Vec<Vec<f64>> = (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1. } else { 0. }).collect())
+        .collect();
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|i, j| left[*i][col].abs().total_cmp(&left[*j][col].abs()))
+            .ok_or("Empty spillover matrix")?;
+        if left[pivot][col].abs() < f64::EPSILON {
+            return Err("Spillover matrix is singular".into());
+        }
+        left.swap(col, pivot);
+        right.swap(col, pivot);
+        let scale = left[col][col];
+        for j in 0..n {
+            left[col][j] /= scale;
+            right[col][j] /= scale;
+        }
+        for row in 0..n {
+            if row == col {
+                continue;
+            }
+            let factor = left[row][col];
+            if factor == 0. {
+                continue;
+            }
+            for j in 0..n {
+                left[row][j] -= factor * left[col][j];
+                right[row][j] -= factor * right[col][j];
+            }
+        }
+    }
+    Ok(right)
+}
+
+/// Parse a `$SPILLOVER`-style keyword: the number of channels, their names, and then the
+/// matrix in row-major order.
+fn parse_spillover(value: &str) -> Result<(Vec<String>, Vec<Vec<f64>>), EtError> {
+    let mut items = value.split(',').map(str::trim);
+    let n: usize = items.next().ok_or("Empty spillover keyword")?.parse()?;
+    let names: Vec<String> = items.by_ref().take(n).map(String::from).collect();
+    let values = items
+        .map(str::parse::<f64>)
+        .collect::<Result<Vec<f64>, _>>()?;
+    if names.len() != n || values.len() != n * n {
+        return Err("Spillover keyword has the wrong number of values".into());
+    }
+    Ok((names, values.chunks(n).map(<[f64]>::to_vec).collect()))
+}
+
+/// Parse a `$BTIM`/`$ETIM` value; these are `hh:mm:ss` with an optional fractional part that's
+/// either `:tt` in 60ths of a second (FCS3.0) or `.cc` in hundredths of a second (FCS3.1).
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    let mut parts = value.trim().split(':');
+    let hours = parts.next()?.trim().parse().ok()?;
+    let minutes = parts.next()?.trim().parse().ok()?;
+    let seconds: f64 = parts.next().unwrap_or("0").trim().parse().ok()?;
+    let sixtieths: f64 = match parts.next() {
+        Some(p) => p.trim().parse().ok()?,
+        None => 0.,
+    };
+    if seconds < 0. || !(0. ..60.).contains(&sixtieths) {
+        return None;
+    }
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let (whole, nanos) = (
+        seconds.trunc() as u32,
+        (((seconds.fract() + sixtieths / 60.) * 1e9).round() as u32).min(999_999_999),
+    );
+    NaiveTime::from_hms_nano_opt(hours, minutes, whole, nanos)
+}
+
+/// How to name the channels in an FCS file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FcsChannelNames {
+    /// The short names from `$PnN`, e.g. "FL1-A"
+    #[default]
+    Short,
+    /// The long names from `$PnS` (e.g. "CD4"), or the short name if there isn't one
+    Long,
+    /// Both names, e.g. "FL1-A (CD4)"
+    Both,
+}
+
+impl FromStr for FcsChannelNames {
+    type Err = EtError;
+
+    /// Parse a naming style from its name (`short`, `long`, or `both`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.trim().to_ascii_lowercase().as_str() {
+            "short" => FcsChannelNames::Short,
+            "long" => FcsChannelNames::Long,
+            "both" => FcsChannelNames::Both,
+            _ => return Err(format!("Unknown FCS channel naming {}", s).into()),
+        })
+    }
+}
+
+/// Parameters for reading FCS files
+#[derive(Clone, Debug, Default)]
+pub struct FcsParams {
+    /// Compensate the data using the spillover matrix in the file (`$SPILLOVER` or `SPILL`) or
+    /// the one provided in `spillover`.
+    pub compensate: bool,
+    /// A spillover matrix to use instead of the one in the file, as the names of the channels
+    /// it applies to and then one row per channel.
+    pub spillover: Option<(Vec<String>, Vec<Vec<f64>>)>,
+    /// Convert values to linear scale using the `$PnE` (log amplification) and `$PnG` (gain)
+    /// keywords.
+    pub scale: bool,
+    /// A transform to apply to the compensated channels (or every channel if the data isn't
+    /// being compensated).
+    pub transform: FcsTransform,
+    /// Only read this dataset (numbered from 0) from files with multiple datasets. If not set,
+    /// all of the datasets are read with a "dataset" column to distinguish them.
+    pub dataset: Option<usize>,
+    /// Which of the channel names to use for the headers
+    pub channel_names: FcsChannelNames,
+    keywords: BTreeMap<String, String>,
+    analysis: BTreeMap<String, String>,
+    analysis_range: Option<(usize, usize)>,
+}
+
+impl FcsParams {
+    /// Set if the data should be compensated
+    #[must_use]
+    pub fn compensate(mut self, compensate: bool) -> Self {
+        self.compensate = compensate;
+        self
+    }
+
+    /// Compensate the data with a user-supplied spillover matrix
+    #[must_use]
+    pub fn spillover(mut self, channels: &[&str], matrix: &[Vec<f64>]) -> Self {
+        self.compensate = true;
+        self.spillover = Some((
+            channels.iter().map(|c| String::from(*c)).collect(),
+            matrix.to_vec(),
+        ));
+        self
+    }
+
+    /// Set if values should be converted into linear scale
+    #[must_use]
+    pub fn scale(mut self, scale: bool) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Set the transform to apply to the values
+    #[must_use]
+    pub fn transform(mut self, transform: FcsTransform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Set how the channels are named
+    #[must_use]
+    pub fn channel_names(mut self, channel_names: FcsChannelNames) -> Self {
+        self.channel_names = channel_names;
+        self
+    }
+
+    /// Only read a single dataset from the file
+    #[must_use]
+    pub fn dataset(mut self, dataset: usize) -> Self {
+        self.dataset = Some(dataset);
+        self
+    }
+}
+
+/// Split a TEXT segment into its keyword/value pairs.
+///
+/// The spec says repeated delimiters should be parsed as an escaped delimiter, but some files
+/// (e.g. from Applied Biosystems instruments) instead use them to denote empty values. If a
+/// spec-compliant parse yields keywords containing the delimiter, or an unpaired keyword, we
+/// assume it's one of the latter and reparse treating repeated delimiters as empty values.
+fn parse_keywords(text: &[u8], delim: u8) -> Result<Vec<(String, String)>, EtError> {
+    let mut items: Vec<Vec<u8>> = Vec::new();
+    let mut item = Vec::new();
+    let mut pos = 0;
+    while pos < text.len() {
+        if text[pos] == delim {
+            if text.get(pos + 1) == Some(&delim) {
+                item.push(delim);
+                pos += 2;
+                continue;
+            }
+            items.push(core::mem::take(&mut item));
+        } else {
+            item.push(text[pos]);
+        }
+        pos += 1;
+    }
+    items.push(item);
+    // ignore a trailing delimiter if the TEXT segment end included it
+    if items.chunks_exact(2).remainder() == [Vec::new()] {
+        let _ = items.pop();
+    }
+
+    let spec_compliant = items.chunks_exact(2).remainder().is_empty()
+        && items
+            .iter()
+            .step_by(2)
+            .all(|k| !k.is_empty() && !k.contains(&delim));
+    if !spec_compliant {
+        items = text.split(|b| b == &delim).map(<[u8]>::to_vec).collect();
+    }
+
+    let mut keywords = Vec::with_capacity(items.len() / 2);
+    for pair in items.chunks_exact(2) {
+        let key = str::from_utf8(&pair[0])?.to_ascii_uppercase();
+        keywords.push((key, String::from_utf8_lossy(&pair[1]).into_owned()));
+    }
+    Ok(keywords)
+}
+
+/// Is this byte one of the separators between values in delimited-ASCII data?
+fn is_ascii_separator(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b',' | b'\r' | b'\n')
 }
 
 /// State of an `FcsReader`.
 ///
-/// Note that the state is primarily derived from the TEXT segment of the file.
+/// Note that the state is primarily derived from the TEXT segment of the file. When reading
+/// through several datasets, the headers and metadata are those of the dataset that the last
+/// record came from.
 #[derive(Clone, Debug, Default)]
 pub struct FcsState {
     params: Vec<FcsColumn>,
     endian: Endian,
     data_type: char,
     next_data: Option<usize>,
+    dataset_index: Option<usize>,
+    record_start: usize,
+    names: Vec<String>,
     n_events_left: usize,
     bytes_data_left: usize,
+    analysis: Option<(usize, usize)>,
     metadata: BTreeMap<String, Value<'static>>,
+    options: FcsParams,
+    compensation: Option<(Vec<usize>, Vec<Vec<f64>>)>,
+    transformed: Vec<bool>,
+    logicle: Logicle,
 }
 
 impl StateMetadata for FcsState {
@@ -44,90 +492,190 @@ impl StateMetadata for FcsState {
     /// The fields in the associated struct
     fn header(&self) -> Vec<&str> {
         let mut headers = Vec::new();
-        for param in &self.params {
-            headers.push(param.short_name.as_ref());
+        if self.dataset_index.is_some() {
+            headers.push("dataset");
+        }
+        for name in &self.names {
+            headers.push(name.as_ref());
         }
         headers
     }
 }
 
-impl<'b: 's, 's> FromSlice<'b, 's> for FcsState {
-    type State = BTreeMap<String, String>;
-
-    fn parse(
+impl FcsState {
+    /// Read the ANALYSIS segment (if there is one) from `buf`, which starts `bytes_data_left`
+    /// bytes before the end of the DATA segment.
+    fn read_analysis(
+        &self,
         buf: &[u8],
-        _eof: bool,
-        consumed: &mut usize,
-        map: &mut Self::State,
-    ) -> Result<bool, EtError> {
-        let con = &mut 0;
-
-        let magic = extract::<&[u8]>(buf, con, &mut 10)?;
-        if &magic[..3] != b"FCS" {
-            return Err("FCS file has invalid header".into());
+        bytes_data_left: usize,
+    ) -> Result<Option<Value<'static>>, EtError> {
+        let (offset, len) = match self.analysis {
+            Some(analysis) => analysis,
+            None => return Ok(None),
+        };
+        let start = bytes_data_left + offset;
+        if buf.len() <= start + len {
+            return Err(EtError::from("Analysis segment shorter than specified").incomplete());
         }
+        let analysis = parse_keywords(&buf[start + 1..start + len], buf[start])?
+            .into_iter()
+            .map(|(k, v)| (k, v.into()))
+            .collect();
+        Ok(Some(Value::Record(analysis)))
+    }
 
-        // get the offsets to the different data
-        let text_start: usize = extract::<&str>(buf, con, &mut 8)?.trim().parse()?;
-        let text_end: usize = extract::<&str>(buf, con, &mut 8)?.trim().parse()?;
-        if text_end < text_start {
-            return Err("Invalid end from text segment".into());
-        }
-        if text_start < 58 {
-            return Err("Bad FCS text start offset".into());
+    /// Scale, compensate, and transform the raw values from a record.
+    fn process(&self, values: &mut [Value]) {
+        let mut floats: Vec<f64> = values
+            .iter()
+            .map(|v| match v {
+                #[allow(clippy::cast_precision_loss)]
+                Value::Integer(i) => *i as f64,
+                Value::Float(f) => *f,
+                _ => f64::NAN,
+            })
+            .collect();
+        if self.options.scale {
+            for (value, param) in floats.iter_mut().zip(&self.params) {
+                *value = param.scale(*value);
+            }
         }
-        if buf.len() < text_end {
-            return Err(EtError::from("Text segment shorter than specified").incomplete());
+        if let Some((indices, inverse)) = &self.compensation {
+            let raw: Vec<f64> = indices.iter().map(|ix| floats[*ix]).collect();
+            for (j, ix) in indices.iter().enumerate() {
+                floats[*ix] = raw.iter().zip(inverse).map(|(r, row)| r * row[j]).sum();
+            }
         }
-        drop(map.insert(
-            "$BEGINDATA".to_string(),
-            extract::<&str>(buf, con, &mut 8)?.trim().to_string(),
-        ));
-        drop(map.insert(
-            "$ENDDATA".to_string(),
-            extract::<&str>(buf, con, &mut 8)?.trim().to_string(),
-        ));
-        drop(map.insert(
-            "$BEGINANALYSIS".to_string(),
-            extract::<&str>(buf, con, &mut 8)?.trim().to_string(),
-        ));
-        drop(map.insert(
-            "$ENDANALYSIS".to_string(),
-            extract::<&str>(buf, con, &mut 8)?.trim().to_string(),
-        ));
-        let _ = extract::<Skip>(buf, con, &mut (text_start - 58))?;
-        let delim: u8 = extract(buf, con, &mut Endian::Little)?;
-        // The spec says repeated delimiters should be parsed as an escaped delimiter, but I've
-        // never seen that so we parse them as empty values (which I have seen in Applied
-        // Biosystems files) which allows us to simplify the parsing logic a lot.
-        let params = extract::<&[u8]>(buf, con, &mut (text_end.saturating_sub(*con)))?;
-        let mut key: Option<String> = None;
-        for item in params.split(|b| b == &delim) {
-            if let Some(k) = key {
-                let value = String::from_utf8_lossy(item);
-                if &k == "$BEGINDATA" || &k == "$ENDDATA" {
-                    if map[&k] == "0" {
-                        drop(map.insert(k.to_string(), value.trim().into()));
-                    }
-                } else {
-                    drop(map.insert(k.to_string(), value.into()));
-                }
-                key = None;
-            } else {
-                key = Some(str::from_utf8(item)?.to_ascii_uppercase());
+        for (value, transformed) in floats.iter_mut().zip(&self.transformed) {
+            if !transformed {
+                continue;
+            }
+            match self.options.transform {
+                FcsTransform::None => {}
+                FcsTransform::Arcsinh { cofactor } => *value = (*value / cofactor).asinh(),
+                FcsTransform::Logicle { .. } => *value = self.logicle.scale(*value),
             }
         }
-        let data_start: usize = map["$BEGINDATA"].parse()?;
-        let data_end: usize = map["$ENDDATA"].parse()?;
-        if data_end < data_start {
-            return Err("Invalid end from data segment".into());
+        for (value, float) in values.iter_mut().zip(floats) {
+            *value = float.into();
         }
-        // get anything between the end of the text segment and the start of the data segment
-        if data_start > text_end {
-            let _ = extract::<Skip>(buf, con, &mut (data_start - *con))?;
+    }
+}
+
+/// Read the TEXT and ANALYSIS segments of the dataset at the start of `buf` into `map` and
+/// return the offset of its DATA segment.
+fn parse_dataset_header(buf: &[u8], map: &mut FcsParams) -> Result<usize, EtError> {
+    map.keywords.clear();
+    map.analysis.clear();
+    map.analysis_range = None;
+    let con = &mut 0;
+
+    let magic = extract::<&[u8]>(buf, con, &mut 10)?;
+    if &magic[..3] != b"FCS" {
+        return Err("FCS file has invalid header".into());
+    }
+
+    // get the offsets to the different data
+    let text_start: usize = extract::<&str>(buf, con, &mut 8)?.trim().parse()?;
+    let text_end: usize = extract::<&str>(buf, con, &mut 8)?.trim().parse()?;
+    if text_end < text_start {
+        return Err("Invalid end from text segment".into());
+    }
+    if text_start < 58 {
+        return Err("Bad FCS text start offset".into());
+    }
+    if buf.len() < text_end {
+        return Err(EtError::from("Text segment shorter than specified").incomplete());
+    }
+    drop(map.keywords.insert(
+        "$BEGINDATA".to_string(),
+        extract::<&str>(buf, con, &mut 8)?.trim().to_string(),
+    ));
+    drop(map.keywords.insert(
+        "$ENDDATA".to_string(),
+        extract::<&str>(buf, con, &mut 8)?.trim().to_string(),
+    ));
+    drop(map.keywords.insert(
+        "$BEGINANALYSIS".to_string(),
+        extract::<&str>(buf, con, &mut 8)?.trim().to_string(),
+    ));
+    drop(map.keywords.insert(
+        "$ENDANALYSIS".to_string(),
+        extract::<&str>(buf, con, &mut 8)?.trim().to_string(),
+    ));
+    let _ = extract::<Skip>(buf, con, &mut (text_start - 58))?;
+    let delim: u8 = extract(buf, con, &mut Endian::Little)?;
+    let params = extract::<&[u8]>(buf, con, &mut (text_end.saturating_sub(*con)))?;
+    for (k, v) in parse_keywords(params, delim)? {
+        if matches!(
+            k.as_ref(),
+            "$BEGINDATA" | "$ENDDATA" | "$BEGINANALYSIS" | "$ENDANALYSIS"
+        ) {
+            // the TEXT values are only used if the ones in the header overflowed
+            if map.keywords[&k] == "0" {
+                drop(map.keywords.insert(k, v.trim().into()));
+            }
+        } else {
+            drop(map.keywords.insert(k, v));
+        }
+    }
+    let data_start: usize = map.keywords["$BEGINDATA"].parse()?;
+    let data_end: usize = map.keywords["$ENDDATA"].parse()?;
+    if data_end < data_start {
+        return Err("Invalid end from data segment".into());
+    }
+    // get anything between the end of the text segment and the start of the data segment
+    if data_start > text_end {
+        let _ = extract::<Skip>(buf, con, &mut (data_start - *con))?;
+    }
+
+    // and the ANALYSIS segment if there is one; this is usually after the DATA segment so
+    // in that case we leave it to be read along with the last record
+    let analysis_start: usize = map.keywords["$BEGINANALYSIS"].parse().unwrap_or(0);
+    let analysis_end: usize = map.keywords["$ENDANALYSIS"].parse().unwrap_or(0);
+    if analysis_start > data_end && analysis_end > analysis_start {
+        map.analysis_range = Some((analysis_start, analysis_end));
+    } else if analysis_start > 0 && analysis_end > analysis_start {
+        if buf.len() <= analysis_end {
+            return Err(EtError::from("Analysis segment shorter than specified").incomplete());
         }
+        let delim = buf[analysis_start];
+        map.analysis = parse_keywords(&buf[analysis_start + 1..analysis_end], delim)?
+            .into_iter()
+            .collect();
+    }
+
+    Ok(data_start)
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for FcsState {
+    type State = FcsParams;
 
-        *consumed += data_start;
+    fn parse(
+        buf: &[u8],
+        _eof: bool,
+        consumed: &mut usize,
+        map: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        // if a specific dataset was requested, follow the $NEXTDATA offsets until we find it
+        let mut start = 0;
+        for _ in 0..map.dataset.unwrap_or(0) {
+            let _ = parse_dataset_header(&buf[start..], map)?;
+            let next: usize = match map.keywords.get("$NEXTDATA") {
+                Some(n) => n.trim().parse()?,
+                None => 0,
+            };
+            if next == 0 {
+                return Err("FCS file does not have the requested dataset".into());
+            }
+            start += next;
+            if start > buf.len() {
+                return Err(EtError::from("Next FCS dataset past end of buffer").incomplete());
+            }
+        }
+        let data_start = parse_dataset_header(&buf[start..], map)?;
+        *consumed += start + data_start;
         Ok(true)
     }
 
@@ -140,9 +688,11 @@ impl<'b: 's, 's> FromSlice<'b, 's> for FcsState {
         let mut n_events_left = 0;
         let mut metadata = BTreeMap::new();
 
-        let mut date = NaiveDate::from_yo(2000, 1);
-        let mut time = NaiveTime::from_num_seconds_from_midnight(0, 0);
-        for (key, value) in map.iter() {
+        let mut spillover = None;
+        let mut date = NaiveDate::from_yo_opt(2000, 1).unwrap_or_default();
+        let mut time = NaiveTime::default();
+        let mut end_time = None;
+        for (key, value) in map.keywords.iter() {
             match (key.as_ref(), value.as_ref()) {
                 ("$NEXTDATA", v) => {
                     let next_value: usize = v.trim().parse()?;
@@ -161,21 +711,21 @@ impl<'b: 's, 's> FromSlice<'b, 's> for FcsState {
                     return Err("FCS histograms not yet supported ($MODE=C/U)".into())
                 }
                 ("$MODE", v) => return Err(format!("Unknown FCS $MODE {}", v).into()),
-                ("$TOT", v) => n_events_left = v.trim().parse()?,
+                ("$TOT", v) => {
+                    n_events_left = v.trim().parse()?;
+                    #[allow(clippy::cast_possible_wrap)]
+                    let events = Value::Integer(n_events_left as i64);
+                    drop(metadata.insert("events".into(), events));
+                }
                 ("$BTIM", v) => {
-                    // TODO: sometimes there's a fractional (/60) part after the last colon
-                    // that we should include in the time too
-                    let hms = v
-                        .trim()
-                        .split(':')
-                        .take(3)
-                        .map(ToOwned::to_owned)
-                        .collect::<Vec<String>>()
-                        .join(":");
-                    if let Ok(t) = NaiveTime::parse_from_str(&hms, "%H:%M:%S") {
+                    if let Some(t) = parse_time(v) {
                         time = t;
                     }
                 }
+                ("$ETIM", v) => end_time = parse_time(v),
+                ("$CYT", v) => {
+                    drop(metadata.insert("cytometer".into(), v.trim().to_string().into()));
+                }
                 ("$CELLS", v) => {
                     drop(metadata.insert("specimen".into(), v.to_string().into()));
                 }
@@ -218,7 +768,15 @@ impl<'b: 's, 's> FromSlice<'b, 's> for FcsState {
                     }
                     params.resize_with(n_params, FcsColumn::default);
                 }
-                (k, v) if k.starts_with("$P") && k.ends_with(&['B', 'N', 'R', 'S'][..]) => {
+                ("$SPILLOVER" | "SPILL" | "SPILLOVER", v) => {
+                    // only parsed if we need it so a bad matrix doesn't stop the file being read
+                    spillover = Some(v);
+                }
+                (k, v)
+                    if k.starts_with("$P")
+                        && k.ends_with(&['B', 'E', 'G', 'N', 'R', 'S', 'V'][..])
+                        && k[2..k.len() - 1].parse::<usize>().is_ok() =>
+                {
                     let mut i: usize = k[2..k.len() - 1].parse()?;
                     i -= 1; // params are numbered from 1
                     if i >= params.len() {
@@ -233,6 +791,26 @@ impl<'b: 's, 's> FromSlice<'b, 's> for FcsState {
                             params[i].size = v.trim().parse()?;
                             params[i].delimited = false;
                         }
+                    } else if k.ends_with('E') {
+                        // "decades,offset" for log amplification or "0,0" for linear
+                        let mut parts = v.split(',').map(|p| p.trim().parse::<f64>());
+                        let decades = parts.next().transpose();
+                        let offset = parts.next().transpose();
+                        match (decades, offset) {
+                            (Ok(d), Ok(o)) => {
+                                params[i].log_decades = d.unwrap_or(0.);
+                                params[i].log_offset = o.unwrap_or(0.);
+                            }
+                            (Err(e), _) | (_, Err(e)) if map.scale => return Err(e.into()),
+                            // we only need this for scaling so ignore bad values otherwise
+                            _ => {}
+                        }
+                    } else if k.ends_with('G') {
+                        match v.trim().parse() {
+                            Ok(gain) => params[i].gain = gain,
+                            Err(e) if map.scale => return Err(e.into()),
+                            Err(_) => {}
+                        }
                     } else if k.ends_with('N') {
                         params[i].short_name = v.to_string();
                     } else if k.ends_with('R') {
@@ -246,20 +824,56 @@ impl<'b: 's, 's> FromSlice<'b, 's> for FcsState {
                         }
                     } else if k.ends_with('S') {
                         params[i].long_name = v.to_string();
+                    } else if k.ends_with('V') {
+                        params[i].voltage = v.trim().parse().ok();
                     }
                 }
                 _ => {}
             }
         }
         drop(metadata.insert("date".into(), date.and_time(time).into()));
+        if let Some(end) = end_time {
+            // acquisitions that run past midnight end on the next day
+            let end_date = if end < time {
+                date.succ_opt().unwrap_or(date)
+            } else {
+                date
+            };
+            drop(metadata.insert("end_date".into(), end_date.and_time(end).into()));
+        }
+        drop(
+            metadata.insert(
+                "channels".into(),
+                params
+                    .iter()
+                    .map(FcsColumn::metadata)
+                    .collect::<Vec<Value>>()
+                    .into(),
+            ),
+        );
+        drop(
+            metadata.insert(
+                "keywords".into(),
+                Value::Record(
+                    map.keywords
+                        .iter()
+                        .map(|(k, v)| (k.clone(), v.clone().into()))
+                        .collect(),
+                ),
+            ),
+        );
 
         // make the next_data offset relative
         if let Some(n) = next_data {
-            next_data = Some(n.saturating_sub(map["$ENDDATA"].parse::<usize>()?));
+            next_data = Some(n.saturating_sub(map.keywords["$ENDDATA"].parse::<usize>()?));
+        }
+        if map.dataset.is_some() {
+            // we only want the selected dataset
+            next_data = None;
         }
 
-        let data_start: usize = map["$BEGINDATA"].parse()?;
-        let data_end: usize = map["$ENDDATA"].parse()?;
+        let data_start: usize = map.keywords["$BEGINDATA"].parse()?;
+        let data_end: usize = map.keywords["$ENDDATA"].parse()?;
 
         // check that the datatypes and params match up
         for p in &params {
@@ -278,13 +892,90 @@ impl<'b: 's, 's> FromSlice<'b, 's> for FcsState {
             }
         }
 
+        // set up the compensation and transforms
+        let mut compensation = None;
+        let mut transformed = vec![true; params.len()];
+        if map.compensate {
+            let (names, matrix) = match (&map.spillover, spillover) {
+                (Some(s), _) => s.clone(),
+                (None, Some(v)) => parse_spillover(v)?,
+                (None, None) => {
+                    return Err("FCS file has no spillover matrix to compensate with".into())
+                }
+            };
+            if matrix.len() != names.len() || matrix.iter().any(|r| r.len() != names.len()) {
+                return Err("Spillover matrix must be square and match its channels".into());
+            }
+            let mut indices = Vec::with_capacity(names.len());
+            for name in &names {
+                let ix = params
+                    .iter()
+                    .position(|p| &p.short_name == name)
+                    .ok_or_else(|| format!("Spillover channel {} not in FCS file", name))?;
+                indices.push(ix);
+            }
+            transformed.iter_mut().for_each(|t| *t = false);
+            for ix in &indices {
+                transformed[*ix] = true;
+            }
+            drop(metadata.insert("spillover_channels".into(), names.into()));
+            drop(
+                metadata.insert(
+                    "spillover".into(),
+                    matrix
+                        .iter()
+                        .map(|r| r.iter().map(|v| Value::from(*v)).collect::<Vec<_>>().into())
+                        .collect::<Vec<Value>>()
+                        .into(),
+                ),
+            );
+            compensation = Some((indices, invert_matrix(&matrix)?));
+        }
+        let logicle = match map.transform {
+            FcsTransform::None => Logicle::default(),
+            FcsTransform::Arcsinh { cofactor } => {
+                if cofactor <= 0. {
+                    return Err("Arcsinh cofactor must be positive".into());
+                }
+                drop(metadata.insert("transform".into(), "arcsinh".into()));
+                Logicle::default()
+            }
+            FcsTransform::Logicle { t, w, m, a } => {
+                drop(metadata.insert("transform".into(), "logicle".into()));
+                Logicle::new(t, w, m, a)?
+            }
+        };
+        if !map.analysis.is_empty() {
+            let analysis = map
+                .analysis
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone().into()))
+                .collect();
+            drop(metadata.insert("analysis".into(), Value::Record(analysis)));
+        }
+        let mut options = map.clone();
+        options.keywords.clear();
+        options.analysis.clear();
+        options.analysis_range = None;
+
+        self.names = params.iter().map(|p| p.name(map.channel_names)).collect();
         self.params = params;
         self.endian = endian;
         self.data_type = data_type;
+        // only label the datasets if we're reading through more than one of them
+        self.dataset_index = next_data.map(|_| 0);
         self.next_data = next_data;
         self.n_events_left = n_events_left;
         self.bytes_data_left = data_end - data_start + 1;
+        // store where the ANALYSIS segment is relative to the end of the DATA segment
+        self.analysis = map
+            .analysis_range
+            .map(|(start, end)| (start - data_end - 1, end - start));
         self.metadata = metadata;
+        self.options = options;
+        self.compensation = compensation;
+        self.transformed = transformed;
+        self.logicle = logicle;
         Ok(())
     }
 }
@@ -298,6 +989,8 @@ impl<'b: 's, 's> FromSlice<'b, 's> for FcsState {
 /// <https://www.bioconductor.org/packages/release/bioc/vignettes/flowCore/inst/doc/fcs3.html>
 #[derive(Debug, Default)]
 pub struct FcsRecord<'r> {
+    /// The index of the dataset this record is from, if the file has several.
+    pub dataset: Option<usize>,
     /// A list of the values for the current FCS scan. See the associated state for their names.
     pub values: Vec<Value<'r>>,
 }
@@ -313,24 +1006,51 @@ impl<'b: 's, 's> FromSlice<'b, 's> for FcsRecord<'s> {
     ) -> Result<bool, EtError> {
         let con = &mut 0;
         if state.n_events_left == 0 {
+            if let Some(analysis) = state.read_analysis(buf, state.bytes_data_left)? {
+                drop(state.metadata.insert("analysis".into(), analysis));
+                state.analysis = None;
+            }
             if let Some(next_data) = state.next_data {
                 let _ = extract::<Skip>(buf, con, &mut (next_data + state.bytes_data_left - 1))?;
-                let mut headers = BTreeMap::new();
+                let mut headers = state.options.clone();
                 let start = *con;
                 if !FcsState::parse(&buf[*con..], eof, con, &mut headers)? {
                     return Ok(false);
                 }
+                let dataset_index = state.dataset_index.unwrap_or(0) + 1;
+                // each dataset has its own parameters so the headers and metadata change to
+                // match the dataset of the current record
                 FcsState::get(state, &buf[start..*con], &headers)?;
+                // the last dataset won't have a $NEXTDATA so we have to set this manually
+                state.dataset_index = Some(dataset_index);
             } else {
                 return Ok(false);
             }
         }
 
+        let record_start = *con;
         for param in &state.params {
             *con += match state.data_type {
                 'A' if !param.delimited => param.size as usize,
                 'A' if param.delimited => {
-                    return Err("Delimited-ASCII number datatypes are not yet supported".into());
+                    // values are separated by any amount of whitespace/commas so we have to
+                    // scan for the end of each one
+                    let segment_end = record_start + state.bytes_data_left;
+                    let limit = segment_end.min(buf.len());
+                    while *con < limit && is_ascii_separator(buf[*con]) {
+                        *con += 1;
+                    }
+                    let start = *con;
+                    while *con < limit && !is_ascii_separator(buf[*con]) {
+                        *con += 1;
+                    }
+                    if *con == buf.len() && buf.len() < segment_end && !eof {
+                        return Err(EtError::from("Record was incomplete").incomplete());
+                    }
+                    if *con == start {
+                        return Err("Delimited-ASCII data ended before all values read".into());
+                    }
+                    0
                 }
                 'D' => 8,
                 'F' => 4,
@@ -346,8 +1066,19 @@ impl<'b: 's, 's> FromSlice<'b, 's> for FcsRecord<'s> {
         if *con > buf.len() {
             return Err(EtError::from("Record was incomplete").incomplete());
         }
+        let bytes_data_left = state.bytes_data_left.saturating_sub(*con - record_start);
+        if state.n_events_left == 1 {
+            // read the ANALYSIS segment with the last record so it's in the metadata once all
+            // of the dataset has been read
+            if let Some(analysis) = state.read_analysis(&buf[*con..], bytes_data_left)? {
+                drop(state.metadata.insert("analysis".into(), analysis));
+                state.analysis = None;
+            }
+        }
         state.n_events_left -= 1;
-        state.bytes_data_left = state.bytes_data_left.saturating_sub(*con);
+        state.bytes_data_left = bytes_data_left;
+        // if we skipped into a new dataset, the record itself starts after its headers
+        state.record_start = record_start;
         *consumed += *con;
         Ok(true)
     }
@@ -356,8 +1087,9 @@ impl<'b: 's, 's> FromSlice<'b, 's> for FcsRecord<'s> {
         if self.values.len() != state.params.len() {
             self.values.resize(state.params.len(), Value::Null);
         }
+        self.dataset = state.dataset_index;
         // TODO: need to handle incompletes here
-        let con = &mut 0;
+        let con = &mut state.record_start.clone();
         for (ix, param) in state.params.iter().enumerate() {
             self.values[ix] = match state.data_type {
                 'A' if !param.delimited => {
@@ -365,7 +1097,14 @@ impl<'b: 's, 's> FromSlice<'b, 's> for FcsRecord<'s> {
                     str::from_utf8(n)?.trim().parse::<f64>()?.into()
                 }
                 'A' if param.delimited => {
-                    return Err("Delimited-ASCII number datatypes are not yet supported".into());
+                    while *con < buf.len() && is_ascii_separator(buf[*con]) {
+                        *con += 1;
+                    }
+                    let start = *con;
+                    while *con < buf.len() && !is_ascii_separator(buf[*con]) {
+                        *con += 1;
+                    }
+                    str::from_utf8(&buf[start..*con])?.parse::<f64>()?.into()
                 }
                 'D' => extract::<f64>(buf, con, &mut state.endian.clone())?.into(),
                 'F' => extract::<f32>(buf, con, &mut state.endian.clone())?.into(),
@@ -397,17 +1136,28 @@ impl<'b: 's, 's> FromSlice<'b, 's> for FcsRecord<'s> {
                 _ => panic!("Data type is in an unknown state"),
             };
         }
+        if state.options.scale
+            || state.compensation.is_some()
+            || state.options.transform != FcsTransform::None
+        {
+            state.process(&mut self.values);
+        }
         Ok(())
     }
 }
 
 impl<'r> From<FcsRecord<'r>> for Vec<Value<'r>> {
     fn from(record: FcsRecord<'r>) -> Self {
-        record.values
+        let mut values = record.values;
+        if let Some(dataset) = record.dataset {
+            #[allow(clippy::cast_possible_wrap)]
+            values.insert(0, Value::Integer(dataset as i64));
+        }
+        values
     }
 }
 
-impl_reader!(FcsReader, FcsRecord, FcsRecord<'r>, FcsState, BTreeMap<String, String>);
+impl_reader!(FcsReader, FcsRecord, FcsRecord<'r>, FcsState, FcsParams);
 
 #[cfg(test)]
 mod tests {
@@ -458,6 +1208,335 @@ mod tests {
             metadata["date"],
             NaiveDate::from_ymd(2012, 10, 26).and_hms(18, 8, 10).into()
         );
+        assert_eq!(
+            metadata["end_date"],
+            NaiveDate::from_ymd_opt(2012, 10, 26)
+                .and_then(|d| d.and_hms_opt(18, 8, 21))
+                .expect("valid date")
+                .into()
+        );
+        assert_eq!(metadata["events"], 14945i64.into());
+        assert_eq!(metadata["cytometer"], "LSRII".into());
+        if let Value::List(channels) = &metadata["channels"] {
+            assert_eq!(channels.len(), 11);
+            if let Value::Record(channel) = &channels[0] {
+                assert_eq!(channel["name"], "FSC-A".into());
+                assert_eq!(channel["range"], 262_144i64.into());
+                assert_eq!(channel["voltage"], 611.0.into());
+            } else {
+                panic!("Channel metadata should be a record");
+            }
+        } else {
+            panic!("Channels should be a list");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_fcs_reader_delimited_ascii_file() -> Result<(), EtError> {
+        let buf: &[u8] = include_bytes!("../../tests/data/HTS_BD_delimited_ascii.fcs");
+        let mut reader = FcsReader::new(buf, None)?;
+        let binary: &[u8] =
+            include_bytes!("../../tests/data/HTS_BD_LSR_II_Mixed_Specimen_001_D6_D06.fcs");
+        let mut binary_reader = FcsReader::new(binary, None)?;
+        assert_eq!(reader.headers(), binary_reader.headers());
+
+        let metadata = reader.metadata();
+        assert_eq!(metadata["events"], 20i64.into());
+        if let Value::Record(keywords) = &metadata["keywords"] {
+            assert_eq!(keywords["THRESHOLD"], "SSC,400".into());
+            assert_eq!(keywords["$P1E"], "0,0".into());
+            assert_eq!(keywords["P1BS"], "-1".into());
+        } else {
+            panic!("Keywords should be a record");
+        }
+
+        let mut n_recs = 0;
+        while let Some(record) = reader.next()? {
+            let expected = binary_reader.next()?.expect("binary file has more events");
+            assert_eq!(record.values, expected.values);
+            n_recs += 1;
+        }
+        assert_eq!(n_recs, 20);
+        Ok(())
+    }
+
+    /// Assemble a FCS3.0 dataset from its TEXT, DATA, and ANALYSIS segments.
+    fn build_fcs(text: &[u8], data: &[u8], analysis: &[u8]) -> Vec<u8> {
+        let text_end = 58 + text.len() - 1;
+        let data_end = text_end + data.len();
+        let (analysis_start, analysis_end) = if analysis.is_empty() {
+            (0, 0)
+        } else {
+            (data_end + 1, data_end + analysis.len())
+        };
+        let mut buf = format!(
+            "FCS3.0    {:>8}{:>8}{:>8}{:>8}{:>8}{:>8}",
+            58,
+            text_end,
+            text_end + 1,
+            data_end,
+            analysis_start,
+            analysis_end
+        )
+        .into_bytes();
+        buf.extend_from_slice(text);
+        buf.extend_from_slice(data);
+        buf.extend_from_slice(analysis);
+        buf
+    }
+
+    #[test]
+    fn test_fcs_delimited_ascii() -> Result<(), EtError> {
+        let buf = build_fcs(
+            b"/$DATATYPE/A/$MODE/L/$NEXTDATA/0/$PAR/2/$TOT/3/$P1B/*/$P1N/FSC/$P1R/1024/$P2B/*/$P2N/SSC/$P2R/1024/",
+            b"1,2 3\r\n4.5\t 5\n6\n", b""
+        );
+        let mut reader = FcsReader::new(&buf[..], None)?;
+        assert_eq!(reader.headers(), ["FSC", "SSC"]);
+        let record = reader.next()?.expect("first record");
+        assert_eq!(record.values, vec![1.0.into(), 2.0.into()]);
+        let record = reader.next()?.expect("second record");
+        assert_eq!(record.values, vec![3.0.into(), 4.5.into()]);
+        let record = reader.next()?.expect("third record");
+        assert_eq!(record.values, vec![5.0.into(), 6.0.into()]);
+        assert!(reader.next()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fcs_escaped_delimiters() -> Result<(), EtError> {
+        let buf = build_fcs(
+            b"/$CELLS/CD4//CD8/$DATATYPE/F/$MODE/L/$PAR/1/$TOT/1/$P1B/32/$P1N/FSC/",
+            b"\0\0\0\0",
+            b"",
+        );
+        let reader = FcsReader::new(&buf[..], None)?;
+        assert_eq!(reader.metadata()["specimen"], "CD4/CD8".into());
+
+        // Applied Biosystems-style files use repeated delimiters for empty values instead
+        let buf = build_fcs(
+            b"/$CELLS//$DATATYPE/F/$MODE/L/$PAR/1/$SRC/Blood/$TOT/1/$P1B/32/$P1N/FSC/",
+            b"\0\0\0\0",
+            b"",
+        );
+        let reader = FcsReader::new(&buf[..], None)?;
+        let metadata = reader.metadata();
+        assert_eq!(metadata["specimen"], "".into());
+        assert_eq!(metadata["specimen_source"], "Blood".into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fcs_channel_names() -> Result<(), EtError> {
+        let buf = build_fcs(
+            b"/$BTIM/10:20:30:15/$DATATYPE/F/$DATE/01-JAN-2020/$ETIM/10:21:05.50/$MODE/L/$PAR/2/$TOT/1/$P1B/32/$P1N/FL1-A/$P1S/CD4/$P2B/32/$P2N/FSC-A/",
+            b"\0\0\0\0\0\0\0\0",
+            b"",
+        );
+        let reader = FcsReader::new(&buf[..], None)?;
+        assert_eq!(reader.headers(), ["FL1-A", "FSC-A"]);
+        let metadata = reader.metadata();
+        assert_eq!(
+            metadata["date"],
+            NaiveDate::from_ymd_opt(2020, 1, 1)
+                .and_then(|d| d.and_hms_milli_opt(10, 20, 30, 250))
+                .expect("valid date")
+                .into()
+        );
+        assert_eq!(
+            metadata["end_date"],
+            NaiveDate::from_ymd_opt(2020, 1, 1)
+                .and_then(|d| d.and_hms_milli_opt(10, 21, 5, 500))
+                .expect("valid date")
+                .into()
+        );
+
+        let params = FcsParams::default().channel_names(FcsChannelNames::Long);
+        let reader = FcsReader::new(&buf[..], Some(params))?;
+        assert_eq!(reader.headers(), ["CD4", "FSC-A"]);
+        let params = FcsParams::default().channel_names(FcsChannelNames::Both);
+        let reader = FcsReader::new(&buf[..], Some(params))?;
+        assert_eq!(reader.headers(), ["FL1-A (CD4)", "FSC-A"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fcs_compensation() -> Result<(), EtError> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&110f32.to_le_bytes());
+        data.extend_from_slice(&50f32.to_le_bytes());
+        let buf = build_fcs(
+            b"/$BYTEORD/1,2,3,4/$DATATYPE/F/$MODE/L/$PAR/2/$TOT/1/$P1B/32/$P1N/FL1/$P2B/32/$P2N/FL2/$SPILLOVER/2,FL1,FL2,1,0.1,0,1/",
+            &data, b""
+        );
+        let mut reader = FcsReader::new(&buf[..], None)?;
+        let record = reader.next()?.expect("first record");
+        assert_eq!(record.values, vec![110f32.into(), 50f32.into()]);
+
+        let params = FcsParams::default().compensate(true);
+        let mut reader = FcsReader::new(&buf[..], Some(params))?;
+        assert_eq!(
+            reader.metadata()["spillover_channels"],
+            vec!["FL1".to_string(), "FL2".to_string()].into()
+        );
+        let record = reader.next()?.expect("first record");
+        if let [Value::Float(a), Value::Float(b)] = record.values[..] {
+            assert!((a - 110.).abs() < 1e-9);
+            assert!((b - 39.).abs() < 1e-9);
+        } else {
+            panic!("Compensated values should be floats");
+        }
+
+        // a user-supplied matrix overrides the one from the file
+        let params = FcsParams::default().spillover(&["FL1", "FL2"], &[vec![1., 0.], vec![0., 2.]]);
+        let mut reader = FcsReader::new(&buf[..], Some(params))?;
+        let record = reader.next()?.expect("first record");
+        assert_eq!(record.values, vec![110f64.into(), 25f64.into()]);
+
+        let params = FcsParams::default().transform(FcsTransform::Arcsinh { cofactor: 5. });
+        let mut reader = FcsReader::new(&buf[..], Some(params))?;
+        let record = reader.next()?.expect("first record");
+        assert_eq!(
+            record.values,
+            vec![22f64.asinh().into(), 10f64.asinh().into()]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fcs_generic_params() -> Result<(), EtError> {
+        use crate::readers::get_reader;
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&110f32.to_le_bytes());
+        data.extend_from_slice(&50f32.to_le_bytes());
+        let buf = build_fcs(
+            b"/$BYTEORD/1,2,3,4/$DATATYPE/F/$MODE/L/$PAR/2/$TOT/1/$P1B/32/$P1N/FL1/$P1S/CD4/$P2B/32/$P2N/FL2/$SPILLOVER/2,FL1,FL2,1,0.1,0,1/",
+            &data, b""
+        );
+        let mut params = BTreeMap::new();
+        drop(params.insert("compensate".to_string(), true.into()));
+        drop(params.insert("transform".to_string(), "arcsinh:5".into()));
+        drop(params.insert("channel_names".to_string(), "long".into()));
+        let (mut reader, _) = get_reader(&buf[..], Some("flow"), Some(params))?;
+        assert_eq!(reader.headers(), ["CD4", "FL2"]);
+        let record = reader.next_record()?.expect("first record");
+        if let [Value::Float(a), Value::Float(b)] = record[..] {
+            assert!((a - 22f64.asinh()).abs() < 1e-9);
+            assert!((b - 7.8f64.asinh()).abs() < 1e-9);
+        } else {
+            panic!("Transformed values should be floats");
+        }
+
+        let mut params = BTreeMap::new();
+        drop(params.insert("transform".to_string(), "logicle:1".into()));
+        assert!(get_reader(&buf[..], Some("flow"), Some(params)).is_err());
+        let mut params = BTreeMap::new();
+        drop(params.insert("scale".to_string(), "yes".into()));
+        assert!(get_reader(&buf[..], Some("flow"), Some(params)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fcs_scaling() -> Result<(), EtError> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&512u16.to_le_bytes());
+        data.extend_from_slice(&10u16.to_le_bytes());
+        let buf = build_fcs(
+            b"/$BYTEORD/1,2/$DATATYPE/I/$MODE/L/$PAR/2/$TOT/1/$P1B/16/$P1E/4,1/$P1N/FL1/$P1R/1024/$P2B/16/$P2E/0,0/$P2G/2/$P2N/FSC/$P2R/1024/",
+            &data, b""
+        );
+        let params = FcsParams::default().scale(true);
+        let mut reader = FcsReader::new(&buf[..], Some(params))?;
+        let record = reader.next()?.expect("first record");
+        assert_eq!(record.values, vec![100f64.into(), 5f64.into()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fcs_bad_optional_keywords() -> Result<(), EtError> {
+        let buf = build_fcs(
+            b"/$BYTEORD/1,2/$DATATYPE/I/$MODE/L/$PAR/1/$TOT/1/$P1B/16/$P1E/x,y/$P1G/z/$P1N/FL1/$P1R/1024/$SPILLOVER/2,FL1/",
+            &512u16.to_le_bytes(), b""
+        );
+        // these keywords are only needed for scaling and compensation
+        let mut reader = FcsReader::new(&buf[..], None)?;
+        let record = reader.next()?.expect("first record");
+        assert_eq!(record.values, vec![512u64.into()]);
+
+        let params = FcsParams::default().scale(true);
+        assert!(FcsReader::new(&buf[..], Some(params)).is_err());
+        let params = FcsParams::default().compensate(true);
+        assert!(FcsReader::new(&buf[..], Some(params)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_logicle() -> Result<(), EtError> {
+        let logicle = Logicle::new(262_144., 0.5, 4.5, 0.)?;
+        assert!((logicle.scale(262_144.) - 1.).abs() < 1e-9);
+        assert!((logicle.scale(0.) - 0.5 / 4.5).abs() < 1e-9);
+        assert!(logicle.scale(-100.) < logicle.scale(0.));
+        assert!(logicle.scale(100.) < logicle.scale(1000.));
+        assert!(Logicle::new(262_144., 3., 4.5, 0.).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fcs_multiple_datasets() -> Result<(), EtError> {
+        let text = |next: usize, tot: usize| {
+            format!(
+                "/$BYTEORD/1,2,3,4/$DATATYPE/F/$MODE/L/$NEXTDATA/{:08}/$PAR/1/$TOT/{}/$P1B/32/$P1N/FSC/",
+                next, tot
+            )
+        };
+        let first_data = [1f32.to_le_bytes(), 2f32.to_le_bytes()].concat();
+        let analysis = b"|GATE|CD4+|COUNT|2|";
+        let first_len = build_fcs(text(0, 2).as_bytes(), &first_data, analysis).len();
+        let mut buf = build_fcs(text(first_len, 2).as_bytes(), &first_data, analysis);
+        buf.extend(build_fcs(text(0, 1).as_bytes(), &3f32.to_le_bytes(), b""));
+
+        let mut reader = FcsReader::new(&buf[..], None)?;
+        assert_eq!(reader.headers(), ["dataset", "FSC"]);
+        // the ANALYSIS segment comes after the data so it's read with the last record
+        assert!(!reader.metadata().contains_key("analysis"));
+        for value in [1f32, 2.] {
+            let record = reader.next_record()?.expect("record");
+            assert_eq!(record, vec![0.into(), value.into()]);
+        }
+        if let Value::Record(analysis) = &reader.metadata()["analysis"] {
+            assert_eq!(analysis["GATE"], "CD4+".into());
+            assert_eq!(analysis["COUNT"], "2".into());
+        } else {
+            panic!("Analysis segment should be in metadata");
+        }
+        let record = reader.next_record()?.expect("record");
+        assert_eq!(record, vec![1.into(), 3f32.into()]);
+        assert!(!reader.metadata().contains_key("analysis"));
+        assert!(reader.next()?.is_none());
+
+        let mut reader = FcsReader::new(&buf[..], Some(FcsParams::default().dataset(1)))?;
+        assert_eq!(reader.headers(), ["FSC"]);
+        assert!(!reader.metadata().contains_key("analysis"));
+        let record = reader.next()?.expect("first record");
+        assert_eq!(record.values, vec![3f32.into()]);
+        assert!(reader.next()?.is_none());
+
+        assert!(FcsReader::new(&buf[..], Some(FcsParams::default().dataset(2))).is_err());
+
+        // later datasets can have different parameters from the first
+        let second_text = "/$BYTEORD/1,2,3,4/$DATATYPE/F/$MODE/L/$PAR/2/$TOT/1/$P1B/32/$P1N/FSC/$P2B/32/$P2N/SSC/";
+        let second_data = [3f32.to_le_bytes(), 4f32.to_le_bytes()].concat();
+        let mut buf = build_fcs(text(first_len, 2).as_bytes(), &first_data, analysis);
+        buf.extend(build_fcs(second_text.as_bytes(), &second_data, b""));
+        let mut reader = FcsReader::new(&buf[..], None)?;
+        let _ = reader.next()?.expect("first record");
+        let _ = reader.next()?.expect("second record");
+        let record = reader.next_record()?.expect("third record");
+        assert_eq!(record, vec![1.into(), 3f32.into(), 4f32.into()]);
+        assert_eq!(reader.headers(), ["dataset", "FSC", "SSC"]);
+        assert!(reader.next()?.is_none());
         Ok(())
     }
 
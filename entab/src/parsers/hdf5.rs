@@ -0,0 +1,1645 @@
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::{TryFrom, TryInto};
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+
+use crate::buffer::ReadBuffer;
+use crate::error::EtError;
+use crate::parsers::{Endian, FromSlice};
+use crate::readers::RecordReader;
+use crate::record::Value;
+
+/// The signature at the start of the HDF5 superblock
+const HDF5_MAGIC: &[u8] = b"\x89HDF\r\n\x1A\n";
+
+/// Header message types that we need to read datasets and groups
+const MSG_DATASPACE: u16 = 0x01;
+const MSG_LINK_INFO: u16 = 0x02;
+const MSG_DATATYPE: u16 = 0x03;
+const MSG_LINK: u16 = 0x06;
+const MSG_LAYOUT: u16 = 0x08;
+const MSG_FILTERS: u16 = 0x0B;
+const MSG_ATTRIBUTE: u16 = 0x0C;
+const MSG_CONTINUATION: u16 = 0x10;
+const MSG_SYMBOL_TABLE: u16 = 0x11;
+
+/// Round `len` up to the next multiple of eight (many older HDF5 structures are aligned to this)
+fn pad8(len: usize) -> usize {
+    (len + 7) & !7
+}
+
+/// Reads the little-endian numbers and addresses that make up HDF5's internal structures
+#[derive(Clone, Copy, Debug)]
+struct Hdf5Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+    offset_size: usize,
+    length_size: usize,
+    base: usize,
+}
+
+impl<'a> Hdf5Cursor<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], EtError> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| EtError::from("HDF5 structure extends past the end of the file"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, n: usize) -> Result<(), EtError> {
+        let _ = self.bytes(n)?;
+        Ok(())
+    }
+
+    fn uint(&mut self, n: usize) -> Result<u64, EtError> {
+        let bytes = self.bytes(n)?;
+        Ok(bytes
+            .iter()
+            .rev()
+            .fold(0, |acc, b| (acc << 8) | u64::from(*b)))
+    }
+
+    fn u8(&mut self) -> Result<u8, EtError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, EtError> {
+        u16::extract(self.bytes(2)?, &Endian::Little)
+    }
+
+    fn u32(&mut self) -> Result<usize, EtError> {
+        Ok(usize::try_from(u32::extract(
+            self.bytes(4)?,
+            &Endian::Little,
+        )?)?)
+    }
+
+    /// Read an address in the file; returns `None` if it's the "undefined address"
+    fn address(&mut self) -> Result<Option<usize>, EtError> {
+        let raw = self.uint(self.offset_size)?;
+        if raw.count_ones() as usize == 8 * self.offset_size {
+            return Ok(None);
+        }
+        Ok(Some(self.base + usize::try_from(raw)?))
+    }
+
+    fn length(&mut self) -> Result<usize, EtError> {
+        Ok(usize::try_from(self.uint(self.length_size)?)?)
+    }
+
+    fn signature(&mut self, signature: &[u8]) -> Result<(), EtError> {
+        if self.bytes(signature.len())? != signature {
+            return Err(
+                format!("Bad HDF5 {} signature", String::from_utf8_lossy(signature)).into(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Read a null-terminated string
+    fn cstr(&mut self) -> Result<String, EtError> {
+        let len = self.data[self.pos..]
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or_else(|| EtError::from("Unterminated HDF5 string"))?;
+        let text = String::from_utf8_lossy(self.bytes(len)?).into_owned();
+        self.skip(1)?;
+        Ok(text)
+    }
+}
+
+/// The type of the values in a dataset or attribute
+#[derive(Clone, Debug, PartialEq)]
+pub enum Hdf5Datatype {
+    /// An integer (also used for bitfields and enumerations)
+    Integer {
+        /// The number of bytes in the integer
+        size: usize,
+        /// If the integer is signed
+        signed: bool,
+        /// If the integer is stored in big-endian order
+        big_endian: bool,
+    },
+    /// An IEEE floating point number
+    Float {
+        /// The number of bytes in the float
+        size: usize,
+        /// If the float is stored in big-endian order
+        big_endian: bool,
+    },
+    /// A fixed-length string
+    String {
+        /// The number of bytes in the string
+        size: usize,
+    },
+    /// A variable-length string stored in the global heap
+    VarString {
+        /// The number of bytes in the reference to the heap
+        size: usize,
+    },
+    /// A structure with named members
+    Compound {
+        /// The number of bytes in the entire structure
+        size: usize,
+        /// The name, byte offset, and type of each member
+        members: Vec<(String, usize, Hdf5Datatype)>,
+    },
+    /// A fixed-size array of another type
+    Array {
+        /// The dimensions of the array
+        dims: Vec<usize>,
+        /// The type of each element in the array
+        base: Box<Hdf5Datatype>,
+    },
+    /// A type we can't decode (e.g. references or opaque data)
+    Other {
+        /// The number of bytes in each value
+        size: usize,
+    },
+}
+
+impl Hdf5Datatype {
+    fn parse(c: &mut Hdf5Cursor) -> Result<Self, EtError> {
+        let class_version = c.u8()?;
+        let (class, version) = (class_version & 0x0F, class_version >> 4);
+        let bits = c.bytes(3)?;
+        let size = c.u32()?;
+        Ok(match class {
+            // fixed-point numbers and bitfields
+            0 | 4 => {
+                c.skip(4)?;
+                Hdf5Datatype::Integer {
+                    size,
+                    signed: class == 0 && bits[0] & 0x08 != 0,
+                    big_endian: bits[0] & 0x01 != 0,
+                }
+            }
+            1 => {
+                c.skip(12)?;
+                Hdf5Datatype::Float {
+                    size,
+                    big_endian: bits[0] & 0x01 != 0,
+                }
+            }
+            // time
+            2 => {
+                c.skip(2)?;
+                Hdf5Datatype::Other { size }
+            }
+            3 => Hdf5Datatype::String { size },
+            // opaque; the first bits are the length of the (padded) tag
+            5 => {
+                c.skip(usize::from(bits[0]))?;
+                Hdf5Datatype::Other { size }
+            }
+            6 => {
+                let n_members = usize::from(u16::from_le_bytes([bits[0], bits[1]]));
+                let mut members = Vec::with_capacity(n_members);
+                for _ in 0..n_members {
+                    let name_start = c.pos;
+                    let name = c.cstr()?;
+                    if version < 3 {
+                        c.pos = name_start + pad8(c.pos - name_start);
+                    }
+                    let (offset, member) = match version {
+                        1 => {
+                            let offset = c.u32()?;
+                            let n_dims = usize::from(c.u8()?);
+                            c.skip(11)?;
+                            let mut dims = Vec::new();
+                            for _ in 0..4 {
+                                dims.push(c.u32()?);
+                            }
+                            dims.truncate(n_dims);
+                            let member = Hdf5Datatype::parse(c)?;
+                            if dims.is_empty() {
+                                (offset, member)
+                            } else {
+                                let base = Box::new(member);
+                                (offset, Hdf5Datatype::Array { dims, base })
+                            }
+                        }
+                        2 => (c.u32()?, Hdf5Datatype::parse(c)?),
+                        _ => {
+                            // the offset only takes up as many bytes as needed for the size
+                            let offset_len = match size {
+                                0..=0xFF => 1,
+                                0x100..=0xFFFF => 2,
+                                0x1_0000..=0xFF_FFFF => 3,
+                                _ => 4,
+                            };
+                            let offset = usize::try_from(c.uint(offset_len)?)?;
+                            (offset, Hdf5Datatype::parse(c)?)
+                        }
+                    };
+                    if offset + member.size() > size {
+                        return Err("HDF5 compound member extends past its parent".into());
+                    }
+                    members.push((name, offset, member));
+                }
+                Hdf5Datatype::Compound { size, members }
+            }
+            // references
+            7 => Hdf5Datatype::Other { size },
+            // enumerations are read as their underlying integers
+            8 => {
+                let n_members = usize::from(u16::from_le_bytes([bits[0], bits[1]]));
+                let base = Hdf5Datatype::parse(c)?;
+                for _ in 0..n_members {
+                    let name_start = c.pos;
+                    let _ = c.cstr()?;
+                    if version < 3 {
+                        c.pos = name_start + pad8(c.pos - name_start);
+                    }
+                }
+                c.skip(n_members * base.size())?;
+                base
+            }
+            9 => {
+                let _ = Hdf5Datatype::parse(c)?;
+                if bits[0] & 0x0F == 1 {
+                    Hdf5Datatype::VarString { size }
+                } else {
+                    Hdf5Datatype::Other { size }
+                }
+            }
+            10 => {
+                let n_dims = usize::from(c.u8()?);
+                if version < 3 {
+                    c.skip(3)?;
+                }
+                let mut dims = Vec::with_capacity(n_dims);
+                for _ in 0..n_dims {
+                    dims.push(c.u32()?);
+                }
+                if version < 3 {
+                    c.skip(4 * n_dims)?;
+                }
+                let base = Box::new(Hdf5Datatype::parse(c)?);
+                Hdf5Datatype::Array { dims, base }
+            }
+            _ => return Err("Unknown HDF5 datatype class".into()),
+        })
+    }
+
+    /// The number of bytes in each value of this type
+    #[must_use]
+    pub fn size(&self) -> usize {
+        match self {
+            Hdf5Datatype::Integer { size, .. }
+            | Hdf5Datatype::Float { size, .. }
+            | Hdf5Datatype::String { size }
+            | Hdf5Datatype::VarString { size }
+            | Hdf5Datatype::Compound { size, .. }
+            | Hdf5Datatype::Other { size } => *size,
+            Hdf5Datatype::Array { dims, base } => dims.iter().product::<usize>() * base.size(),
+        }
+    }
+
+    /// A short description of the type (e.g. `int32` or `compound`)
+    #[must_use]
+    pub fn name(&self) -> String {
+        match self {
+            Hdf5Datatype::Integer { size, signed, .. } => {
+                format!("{}{}", if *signed { "int" } else { "uint" }, size * 8)
+            }
+            Hdf5Datatype::Float { size, .. } => format!("float{}", size * 8),
+            Hdf5Datatype::String { .. } | Hdf5Datatype::VarString { .. } => "string".to_string(),
+            Hdf5Datatype::Compound { .. } => "compound".to_string(),
+            Hdf5Datatype::Array { .. } => "array".to_string(),
+            Hdf5Datatype::Other { .. } => "other".to_string(),
+        }
+    }
+}
+
+/// Parse a dataspace message into the dimensions of the data (empty for scalars); returns `None`
+/// for "null" dataspaces that don't have any data
+fn parse_dataspace(c: &mut Hdf5Cursor) -> Result<Option<Vec<usize>>, EtError> {
+    let version = c.u8()?;
+    let rank = usize::from(c.u8()?);
+    let _flags = c.u8()?;
+    if version == 1 {
+        c.skip(5)?;
+    } else if c.u8()? == 2 {
+        return Ok(None);
+    }
+    let mut dims = Vec::with_capacity(rank);
+    for _ in 0..rank {
+        dims.push(c.length()?);
+    }
+    Ok(Some(dims))
+}
+
+/// How the chunks of a chunked dataset are found
+#[derive(Clone, Debug)]
+enum Hdf5ChunkIndex {
+    /// A version 1 B-tree of chunks
+    BTree,
+    /// The dataset is one chunk (that might be filtered)
+    Single {
+        size: Option<usize>,
+        filter_mask: usize,
+    },
+    /// All the chunks are stored in order one after another
+    Implicit,
+}
+
+/// Where the data for a dataset is stored
+#[derive(Clone, Debug)]
+enum Hdf5Layout {
+    Compact(usize, usize),
+    Contiguous(Option<usize>),
+    Chunked {
+        address: Option<usize>,
+        dims: Vec<usize>,
+        index: Hdf5ChunkIndex,
+    },
+}
+
+impl Hdf5Layout {
+    fn parse(c: &mut Hdf5Cursor) -> Result<Self, EtError> {
+        let version = c.u8()?;
+        if version < 3 {
+            let n_dims = usize::from(c.u8()?);
+            let class = c.u8()?;
+            c.skip(5)?;
+            let address = if class == 0 { None } else { c.address()? };
+            let mut dims = Vec::with_capacity(n_dims);
+            for _ in 0..n_dims {
+                dims.push(c.u32()?);
+            }
+            return Ok(match class {
+                0 => {
+                    let size = c.u32()?;
+                    Hdf5Layout::Compact(c.pos, size)
+                }
+                1 => Hdf5Layout::Contiguous(address),
+                _ => {
+                    // the last "dimension" is the size of each element
+                    let _ = dims.pop();
+                    Hdf5Layout::Chunked {
+                        address,
+                        dims,
+                        index: Hdf5ChunkIndex::BTree,
+                    }
+                }
+            });
+        }
+        let class = c.u8()?;
+        Ok(match class {
+            0 => {
+                let size = usize::from(c.u16()?);
+                Hdf5Layout::Compact(c.pos, size)
+            }
+            1 => Hdf5Layout::Contiguous(c.address()?),
+            2 if version == 3 => {
+                let n_dims = usize::from(c.u8()?);
+                let address = c.address()?;
+                let mut dims = Vec::with_capacity(n_dims);
+                for _ in 0..n_dims {
+                    dims.push(c.u32()?);
+                }
+                let _ = dims.pop();
+                Hdf5Layout::Chunked {
+                    address,
+                    dims,
+                    index: Hdf5ChunkIndex::BTree,
+                }
+            }
+            2 => {
+                let flags = c.u8()?;
+                let n_dims = usize::from(c.u8()?);
+                let dim_len = usize::from(c.u8()?);
+                let mut dims = Vec::with_capacity(n_dims);
+                for _ in 0..n_dims {
+                    dims.push(usize::try_from(c.uint(dim_len)?)?);
+                }
+                let _ = dims.pop();
+                let index = match c.u8()? {
+                    1 if flags & 0x02 != 0 => Hdf5ChunkIndex::Single {
+                        size: Some(c.length()?),
+                        filter_mask: c.u32()?,
+                    },
+                    1 => Hdf5ChunkIndex::Single {
+                        size: None,
+                        filter_mask: 0,
+                    },
+                    2 => Hdf5ChunkIndex::Implicit,
+                    _ => {
+                        return Err(
+                            "Only B-tree, single and implicit HDF5 chunk indices are supported"
+                                .into(),
+                        )
+                    }
+                };
+                Hdf5Layout::Chunked {
+                    address: c.address()?,
+                    dims,
+                    index,
+                }
+            }
+            _ => return Err("Virtual HDF5 datasets are not supported".into()),
+        })
+    }
+}
+
+/// A filter (e.g. compression) applied to the chunks of a dataset
+#[derive(Clone, Debug)]
+struct Hdf5Filter {
+    id: u16,
+    client_data: Vec<usize>,
+}
+
+fn parse_filters(c: &mut Hdf5Cursor) -> Result<Vec<Hdf5Filter>, EtError> {
+    let version = c.u8()?;
+    let n_filters = c.u8()?;
+    if version == 1 {
+        c.skip(6)?;
+    }
+    let mut filters = Vec::new();
+    for _ in 0..n_filters {
+        let id = c.u16()?;
+        let name_len = if version == 1 || id >= 256 {
+            usize::from(c.u16()?)
+        } else {
+            0
+        };
+        let _flags = c.u16()?;
+        let n_values = usize::from(c.u16()?);
+        c.skip(name_len)?;
+        let mut client_data = Vec::with_capacity(n_values);
+        for _ in 0..n_values {
+            client_data.push(c.u32()?);
+        }
+        if version == 1 && n_values % 2 == 1 {
+            c.skip(4)?;
+        }
+        filters.push(Hdf5Filter { id, client_data });
+    }
+    Ok(filters)
+}
+
+/// Undo the `filters` (skipping any that are set in `mask`) applied to a chunk
+fn unfilter(
+    mut data: Vec<u8>,
+    filters: &[Hdf5Filter],
+    mask: usize,
+    element_size: usize,
+) -> Result<Vec<u8>, EtError> {
+    for (ix, filter) in filters.iter().enumerate().rev() {
+        if ix < usize::BITS as usize && mask & (1 << ix) != 0 {
+            continue;
+        }
+        match filter.id {
+            1 => {
+                let mut decompressed = Vec::new();
+                let _ = ZlibDecoder::new(&data[..]).read_to_end(&mut decompressed)?;
+                data = decompressed;
+            }
+            2 => {
+                let size = filter
+                    .client_data
+                    .first()
+                    .copied()
+                    .unwrap_or(element_size)
+                    .max(1);
+                let n = data.len() / size;
+                let mut unshuffled = data.clone();
+                for (i, b) in data[..n * size].iter().enumerate() {
+                    unshuffled[(i % n) * size + i / n] = *b;
+                }
+                data = unshuffled;
+            }
+            // the Fletcher-32 checksum is stored at the end
+            3 => data.truncate(data.len().saturating_sub(4)),
+            id => return Err(format!("HDF5 filter {} is not supported", id).into()),
+        }
+    }
+    Ok(data)
+}
+
+/// The number of bytes needed to store an array with dimensions `dims` of `element_size` elements
+fn n_bytes(dims: &[usize], element_size: usize) -> Result<usize, EtError> {
+    dims.iter()
+        .try_fold(element_size, |acc, d| acc.checked_mul(*d))
+        .ok_or_else(|| "HDF5 dataset is too large".into())
+}
+
+/// Copy the data in a chunk (with dimensions `chunk_dims` starting at `offset`) into the right
+/// places in the data for the entire dataset
+fn copy_chunk(
+    out: &mut [u8],
+    shape: &[usize],
+    chunk: &[u8],
+    chunk_dims: &[usize],
+    offset: &[usize],
+    element_size: usize,
+) -> Result<(), EtError> {
+    let rank = shape.len();
+    if rank == 0 || chunk_dims.len() != rank || offset.len() < rank {
+        return Err("HDF5 chunk has the wrong number of dimensions".into());
+    }
+    let row_len = chunk_dims[rank - 1];
+    if offset[rank - 1] >= shape[rank - 1] {
+        return Ok(());
+    }
+    let copy_len = row_len.min(shape[rank - 1] - offset[rank - 1]) * element_size;
+    let n_rows: usize = chunk_dims[..rank - 1].iter().product();
+    let mut coords = vec![0; rank - 1];
+    'rows: for row in 0..n_rows {
+        let mut rem = row;
+        for d in (0..rank - 1).rev() {
+            coords[d] = rem % chunk_dims[d];
+            rem /= chunk_dims[d];
+        }
+        let mut out_ix = 0;
+        for d in 0..rank - 1 {
+            let pos = offset[d] + coords[d];
+            if pos >= shape[d] {
+                continue 'rows;
+            }
+            out_ix = out_ix * shape[d] + pos;
+        }
+        let out_start = (out_ix * shape[rank - 1] + offset[rank - 1]) * element_size;
+        let src_start = row * row_len * element_size;
+        if chunk.len() < src_start + copy_len || out.len() < out_start + copy_len {
+            return Err("HDF5 chunk is too short".into());
+        }
+        out[out_start..out_start + copy_len]
+            .copy_from_slice(&chunk[src_start..src_start + copy_len]);
+    }
+    Ok(())
+}
+
+/// A message in an object header
+#[derive(Clone, Copy, Debug)]
+struct Hdf5Message {
+    kind: u16,
+    shared: bool,
+    start: usize,
+    end: usize,
+}
+
+/// A group or dataset in an HDF5 file
+#[derive(Clone, Debug)]
+pub struct Hdf5Entry {
+    /// The path to the group or dataset (e.g. `group/dataset`)
+    pub path: String,
+    /// If this is a dataset (otherwise it's a group)
+    pub is_dataset: bool,
+    /// The dimensions of the dataset
+    pub shape: Vec<usize>,
+    /// The type of the values in the dataset
+    pub datatype: Option<Hdf5Datatype>,
+}
+
+/// The data read out of a dataset
+#[derive(Clone, Debug)]
+pub struct Hdf5Dataset {
+    /// The dimensions of the dataset (empty for scalar datasets)
+    pub shape: Vec<usize>,
+    /// The type of each value in the dataset
+    pub datatype: Hdf5Datatype,
+    /// The raw bytes of the values in the dataset
+    pub data: Vec<u8>,
+}
+
+/// An HDF5 file that's been completely read in
+#[derive(Clone, Debug)]
+pub struct Hdf5File {
+    data: Vec<u8>,
+    offset_size: usize,
+    length_size: usize,
+    base: usize,
+    root: usize,
+    entries: Vec<Hdf5Entry>,
+}
+
+impl Hdf5File {
+    /// Read an HDF5 file and list all of the groups and datasets in it
+    ///
+    /// # Errors
+    /// If the file can't be read or the superblock or group structure are invalid, returns an
+    /// `EtError`.
+    pub fn new<'r, B>(data: B) -> Result<Self, EtError>
+    where
+        B: TryInto<ReadBuffer<'r>>,
+        EtError: From<<B as TryInto<ReadBuffer<'r>>>::Error>,
+    {
+        let rb: ReadBuffer<'r> = data.try_into()?;
+        let mut data = Vec::new();
+        let _ = rb.into_box_read().read_to_end(&mut data)?;
+        Self::from_vec(data)
+    }
+
+    fn from_vec(data: Vec<u8>) -> Result<Self, EtError> {
+        // the superblock can be at 0 or any power of two after 512 bytes (e.g. if there's a user
+        // block in front of it)
+        let mut start = 0;
+        while !data[start..].starts_with(HDF5_MAGIC) {
+            start = if start == 0 { 512 } else { start * 2 };
+            if start >= data.len() {
+                return Err("Could not find the HDF5 superblock".into());
+            }
+        }
+        let mut c = Hdf5Cursor {
+            data: &data,
+            pos: start + 8,
+            offset_size: 8,
+            length_size: 8,
+            base: 0,
+        };
+        let version = c.u8()?;
+        match version {
+            0 | 1 => c.skip(4)?,
+            2 | 3 => {}
+            _ => {
+                return Err(format!("HDF5 superblock version {} is not supported", version).into())
+            }
+        }
+        c.offset_size = usize::from(c.u8()?);
+        c.length_size = usize::from(c.u8()?);
+        if ![2, 4, 8].contains(&c.offset_size) || ![2, 4, 8].contains(&c.length_size) {
+            return Err("Bad HDF5 offset or length sizes".into());
+        }
+        if version < 2 {
+            // group B-tree sizes and flags (and B-tree sizes for chunks in version 1)
+            c.skip(if version == 1 { 13 } else { 9 })?;
+            c.base = usize::try_from(c.uint(c.offset_size)?)?;
+            // skip the free space, end of file, and driver addresses and then the link name
+            // offset at the start of the root group's symbol table entry
+            c.skip(4 * c.offset_size)?;
+        } else {
+            c.skip(1)?;
+            c.base = usize::try_from(c.uint(c.offset_size)?)?;
+            c.skip(2 * c.offset_size)?;
+        }
+        let root = c
+            .address()?
+            .ok_or_else(|| EtError::from("HDF5 file has no root group"))?;
+        let (offset_size, length_size, base) = (c.offset_size, c.length_size, c.base);
+        let mut file = Hdf5File {
+            data,
+            offset_size,
+            length_size,
+            base,
+            root,
+            entries: Vec::new(),
+        };
+
+        let mut entries = Vec::new();
+        let mut visited = BTreeSet::new();
+        let _ = visited.insert(file.root);
+        file.list_group(file.root, "", &mut entries, &mut visited)?;
+        file.entries = entries;
+        Ok(file)
+    }
+
+    fn cursor(&self, pos: usize) -> Hdf5Cursor<'_> {
+        Hdf5Cursor {
+            data: &self.data,
+            pos,
+            offset_size: self.offset_size,
+            length_size: self.length_size,
+            base: self.base,
+        }
+    }
+
+    /// Read all of the messages in the header of the object at `address`
+    fn messages(&self, address: usize) -> Result<Vec<Hdf5Message>, EtError> {
+        let mut c = self.cursor(address);
+        let mut messages = Vec::new();
+        let mut chunks = Vec::new();
+        let version = if self.data.get(address..address + 4) == Some(b"OHDR") {
+            c.skip(4)?;
+            let version = c.u8()?;
+            let flags = c.u8()?;
+            if flags & 0x20 != 0 {
+                c.skip(16)?;
+            }
+            if flags & 0x10 != 0 {
+                c.skip(4)?;
+            }
+            let size = usize::try_from(c.uint(1 << (flags & 0x03))?)?;
+            chunks.push((c.pos, size));
+            (version, flags & 0x04 != 0)
+        } else {
+            let version = c.u8()?;
+            if version != 1 {
+                return Err("Bad HDF5 object header version".into());
+            }
+            c.skip(7)?;
+            let size = c.u32()?;
+            chunks.push((address + 16, size));
+            (version, false)
+        };
+        let (version, has_order) = version;
+        let mut ix = 0;
+        while ix < chunks.len() {
+            if ix > 10_000 {
+                return Err("Too many HDF5 object header continuations".into());
+            }
+            let (start, size) = chunks[ix];
+            ix += 1;
+            let end = start
+                .checked_add(size)
+                .ok_or_else(|| EtError::from("HDF5 object header is too large"))?;
+            let mut c = self.cursor(start);
+            let header_size = match (version, has_order) {
+                (1, _) => 8,
+                (_, true) => 6,
+                (_, false) => 4,
+            };
+            while c.pos + header_size <= end {
+                let (kind, size, flags) = if version == 1 {
+                    let kind = c.u16()?;
+                    let size = usize::from(c.u16()?);
+                    let flags = c.u8()?;
+                    c.skip(3)?;
+                    (kind, size, flags)
+                } else {
+                    let kind = u16::from(c.u8()?);
+                    let size = usize::from(c.u16()?);
+                    let flags = c.u8()?;
+                    if has_order {
+                        c.skip(2)?;
+                    }
+                    (kind, size, flags)
+                };
+                let msg_start = c.pos;
+                c.skip(size)?;
+                if kind == MSG_CONTINUATION {
+                    let mut m = self.cursor(msg_start);
+                    let cont = m
+                        .address()?
+                        .ok_or_else(|| EtError::from("Bad HDF5 continuation address"))?;
+                    let cont_size = m.length()?;
+                    if version == 1 {
+                        chunks.push((cont, cont_size));
+                    } else {
+                        // strip off the signature and checksum
+                        let mut s = self.cursor(cont);
+                        s.signature(b"OCHK")?;
+                        chunks.push((cont + 4, cont_size.saturating_sub(8)));
+                    }
+                } else if kind != 0 {
+                    messages.push(Hdf5Message {
+                        kind,
+                        shared: flags & 0x02 != 0,
+                        start: msg_start,
+                        end: c.pos,
+                    });
+                }
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Find the first message of `kind` in `messages` and return a cursor to the start of it.
+    ///
+    /// Shared messages (e.g. committed datatypes) are followed to the object they're stored in.
+    fn find_message(
+        &self,
+        messages: &[Hdf5Message],
+        kind: u16,
+    ) -> Result<Option<Hdf5Cursor<'_>>, EtError> {
+        let msg = match messages.iter().find(|m| m.kind == kind) {
+            Some(m) => m,
+            None => return Ok(None),
+        };
+        if !msg.shared {
+            return Ok(Some(self.cursor(msg.start)));
+        }
+        let mut c = self.cursor(msg.start);
+        let version = c.u8()?;
+        let shared_type = c.u8()?;
+        if version == 1 {
+            c.skip(6)?;
+        } else if version == 3 && shared_type != 2 {
+            return Err("HDF5 shared message tables are not supported".into());
+        }
+        let address = c
+            .address()?
+            .ok_or_else(|| EtError::from("Bad HDF5 shared message address"))?;
+        let shared_messages = self.messages(address)?;
+        match shared_messages.iter().find(|m| m.kind == kind && !m.shared) {
+            Some(m) => Ok(Some(self.cursor(m.start))),
+            None => Err("HDF5 shared message not found".into()),
+        }
+    }
+
+    /// Read the names and addresses of the objects in a group
+    fn links(&self, messages: &[Hdf5Message]) -> Result<Vec<(String, usize)>, EtError> {
+        let mut links = Vec::new();
+        if let Some(mut c) = self.find_message(messages, MSG_SYMBOL_TABLE)? {
+            let btree = c.address()?;
+            let heap = c
+                .address()?
+                .ok_or_else(|| EtError::from("HDF5 group has no local heap"))?;
+            let mut h = self.cursor(heap);
+            h.signature(b"HEAP")?;
+            h.skip(4 + 2 * self.length_size)?;
+            let heap_data = h
+                .address()?
+                .ok_or_else(|| EtError::from("HDF5 local heap has no data"))?;
+            if let Some(btree) = btree {
+                self.read_group_btree(btree, heap_data, &mut links, 0)?;
+            }
+        }
+        for msg in messages.iter().filter(|m| m.kind == MSG_LINK) {
+            let mut c = self.cursor(msg.start);
+            let _version = c.u8()?;
+            let flags = c.u8()?;
+            let link_type = if flags & 0x08 != 0 { c.u8()? } else { 0 };
+            if flags & 0x04 != 0 {
+                c.skip(8)?;
+            }
+            if flags & 0x10 != 0 {
+                c.skip(1)?;
+            }
+            let name_len = usize::try_from(c.uint(1 << (flags & 0x03))?)?;
+            let name = String::from_utf8_lossy(c.bytes(name_len)?).into_owned();
+            // soft and external links aren't followed
+            if link_type == 0 {
+                if let Some(address) = c.address()? {
+                    links.push((name, address));
+                }
+            }
+        }
+        if let Some(mut c) = self.find_message(messages, MSG_LINK_INFO)? {
+            let _version = c.u8()?;
+            if c.u8()? & 0x01 != 0 {
+                c.skip(8)?;
+            }
+            if c.address()?.is_some() {
+                return Err("HDF5 groups with dense link storage are not supported".into());
+            }
+        }
+        links.sort();
+        Ok(links)
+    }
+
+    /// Walk a version 1 "group" B-tree to find all of the symbols in it
+    fn read_group_btree(
+        &self,
+        address: usize,
+        heap_data: usize,
+        links: &mut Vec<(String, usize)>,
+        depth: usize,
+    ) -> Result<(), EtError> {
+        if depth > 64 {
+            return Err("HDF5 group B-tree is too deep".into());
+        }
+        let mut c = self.cursor(address);
+        c.signature(b"TREE")?;
+        if c.u8()? != 0 {
+            return Err("HDF5 group B-tree has the wrong node type".into());
+        }
+        let level = c.u8()?;
+        let n_entries = c.u16()?;
+        c.skip(2 * self.offset_size)?;
+        for _ in 0..n_entries {
+            c.skip(self.length_size)?;
+            let child = c
+                .address()?
+                .ok_or_else(|| EtError::from("Bad HDF5 B-tree child address"))?;
+            if level > 0 {
+                self.read_group_btree(child, heap_data, links, depth + 1)?;
+                continue;
+            }
+            let mut s = self.cursor(child);
+            s.signature(b"SNOD")?;
+            s.skip(2)?;
+            let n_symbols = s.u16()?;
+            for _ in 0..n_symbols {
+                let name_offset = usize::try_from(s.uint(self.offset_size)?)?;
+                let object = s.address()?;
+                s.skip(24)?;
+                let name = self.cursor(heap_data + name_offset).cstr()?;
+                if let Some(object) = object {
+                    links.push((name, object));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn list_group(
+        &self,
+        address: usize,
+        prefix: &str,
+        entries: &mut Vec<Hdf5Entry>,
+        visited: &mut BTreeSet<usize>,
+    ) -> Result<(), EtError> {
+        let messages = self.messages(address)?;
+        for (name, child) in self.links(&messages)? {
+            let path = if prefix.is_empty() {
+                name
+            } else {
+                format!("{}/{}", prefix, name)
+            };
+            let child_messages = self.messages(child)?;
+            if child_messages.iter().any(|m| m.kind == MSG_LAYOUT) {
+                let shape = match self.find_message(&child_messages, MSG_DATASPACE)? {
+                    Some(mut c) => parse_dataspace(&mut c)?.unwrap_or_default(),
+                    None => Vec::new(),
+                };
+                let datatype = match self.find_message(&child_messages, MSG_DATATYPE)? {
+                    Some(mut c) => Some(Hdf5Datatype::parse(&mut c)?),
+                    None => None,
+                };
+                entries.push(Hdf5Entry {
+                    path,
+                    is_dataset: true,
+                    shape,
+                    datatype,
+                });
+            } else if child_messages
+                .iter()
+                .any(|m| [MSG_SYMBOL_TABLE, MSG_LINK, MSG_LINK_INFO].contains(&m.kind))
+            {
+                entries.push(Hdf5Entry {
+                    path: path.clone(),
+                    is_dataset: false,
+                    shape: Vec::new(),
+                    datatype: None,
+                });
+                // hard links can make the same group show up more than once
+                if visited.insert(child) {
+                    self.list_group(child, &path, entries, visited)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// All of the groups and datasets in the file
+    #[must_use]
+    pub fn entries(&self) -> &[Hdf5Entry] {
+        &self.entries
+    }
+
+    /// The paths to all of the datasets in the file
+    #[must_use]
+    pub fn datasets(&self) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter(|e| e.is_dataset)
+            .map(|e| e.path.as_str())
+            .collect()
+    }
+
+    /// Find the address of the object at `path` (the root group for an empty path)
+    fn find_object(&self, path: &str) -> Result<usize, EtError> {
+        let mut address = self.root;
+        for part in path.split('/').filter(|p| !p.is_empty()) {
+            let messages = self.messages(address)?;
+            address = self
+                .links(&messages)?
+                .into_iter()
+                .find(|(name, _)| name == part)
+                .map(|(_, a)| a)
+                .ok_or_else(|| EtError::from(format!("{} not found in HDF5 file", path)))?;
+        }
+        Ok(address)
+    }
+
+    /// The attributes on the group or dataset at `path` (use an empty path for the root group)
+    ///
+    /// # Errors
+    /// If the object can't be found or its attributes are malformed, returns an `EtError`.
+    pub fn attributes(&self, path: &str) -> Result<BTreeMap<String, Value<'static>>, EtError> {
+        let messages = self.messages(self.find_object(path)?)?;
+        let mut attributes = BTreeMap::new();
+        for msg in messages.iter().filter(|m| m.kind == MSG_ATTRIBUTE) {
+            let mut c = self.cursor(msg.start);
+            let version = c.u8()?;
+            let flags = c.u8()?;
+            let name_size = usize::from(c.u16()?);
+            let datatype_size = usize::from(c.u16()?);
+            let dataspace_size = usize::from(c.u16()?);
+            if version >= 3 {
+                c.skip(1)?;
+            }
+            let padded = |size| if version == 1 { pad8(size) } else { size };
+            let name = self.cursor(c.pos).cstr()?;
+            c.skip(padded(name_size))?;
+            let mut t = c;
+            c.skip(padded(datatype_size))?;
+            let mut s = c;
+            c.skip(padded(dataspace_size))?;
+            // attributes with shared types or spaces are rare so we skip them
+            if version > 1 && flags & 0x03 != 0 {
+                continue;
+            }
+            let datatype = Hdf5Datatype::parse(&mut t)?;
+            let value = match parse_dataspace(&mut s)? {
+                None => Value::Null,
+                Some(dims) => {
+                    let n = dims.iter().product::<usize>();
+                    let size = datatype.size();
+                    let raw = self
+                        .data
+                        .get(c.pos..c.pos + n * size)
+                        .filter(|_| c.pos + n * size <= msg.end)
+                        .ok_or_else(|| EtError::from("HDF5 attribute data is too short"))?;
+                    if n == 1 {
+                        self.decode(&datatype, raw)?
+                    } else {
+                        Value::List(
+                            raw.chunks_exact(size.max(1))
+                                .map(|v| self.decode(&datatype, v))
+                                .collect::<Result<_, _>>()?,
+                        )
+                    }
+                }
+            };
+            drop(attributes.insert(name, value));
+        }
+        Ok(attributes)
+    }
+
+    /// Read an object out of the global heap
+    fn global_heap_object(&self, address: usize, index: usize) -> Result<&[u8], EtError> {
+        let mut c = self.cursor(address);
+        c.signature(b"GCOL")?;
+        c.skip(4)?;
+        let end = address + c.length()?;
+        while c.pos + 8 + self.length_size <= end {
+            let object_index = usize::from(c.u16()?);
+            c.skip(6)?;
+            let size = c.length()?;
+            if object_index == 0 {
+                break;
+            }
+            let data = c.bytes(pad8(size))?;
+            if object_index == index {
+                return Ok(&data[..size]);
+            }
+        }
+        Err("HDF5 global heap object not found".into())
+    }
+
+    /// Convert one value of `datatype` from `bytes` into a `Value`
+    ///
+    /// # Errors
+    /// If a variable-length string can't be found in the global heap, returns an `EtError`.
+    pub fn decode(&self, datatype: &Hdf5Datatype, bytes: &[u8]) -> Result<Value<'static>, EtError> {
+        Ok(match datatype {
+            Hdf5Datatype::Integer {
+                size: size @ 1..=8,
+                signed,
+                big_endian,
+            } => {
+                let bytes = &bytes[..*size];
+                let mut raw: u64 = 0;
+                for i in 0..*size {
+                    let b = if *big_endian {
+                        bytes[i]
+                    } else {
+                        bytes[size - 1 - i]
+                    };
+                    raw = (raw << 8) | u64::from(b);
+                }
+                if *signed && *size < 8 && raw >> (size * 8 - 1) & 1 == 1 {
+                    // sign-extend
+                    raw |= u64::MAX << (size * 8);
+                }
+                match (*signed, i64::try_from(raw)) {
+                    #[allow(clippy::cast_possible_wrap)]
+                    (true, _) => Value::Integer(raw as i64),
+                    (false, Ok(i)) => Value::Integer(i),
+                    #[allow(clippy::cast_precision_loss)]
+                    (false, Err(_)) => Value::Float(raw as f64),
+                }
+            }
+            Hdf5Datatype::Float {
+                size: 4,
+                big_endian,
+            } => {
+                let endian = if *big_endian {
+                    Endian::Big
+                } else {
+                    Endian::Little
+                };
+                Value::Float(f64::from(f32::extract(bytes, &endian)?))
+            }
+            Hdf5Datatype::Float {
+                size: 8,
+                big_endian,
+            } => {
+                let endian = if *big_endian {
+                    Endian::Big
+                } else {
+                    Endian::Little
+                };
+                Value::Float(f64::extract(bytes, &endian)?)
+            }
+            Hdf5Datatype::String { size } => {
+                let bytes = &bytes[..*size];
+                let len = bytes.iter().position(|b| *b == 0).unwrap_or(*size);
+                let text = String::from_utf8_lossy(&bytes[..len]);
+                Value::String(text.trim_end().to_string().into())
+            }
+            Hdf5Datatype::VarString { .. } => {
+                let mut c = Hdf5Cursor {
+                    data: bytes,
+                    pos: 0,
+                    offset_size: self.offset_size,
+                    length_size: self.length_size,
+                    base: self.base,
+                };
+                let len = c.u32()?;
+                let text = match c.address()? {
+                    Some(address) if len > 0 => {
+                        let object = self.global_heap_object(address, c.u32()?)?;
+                        let text = object.get(..len).unwrap_or(object);
+                        String::from_utf8_lossy(text).into_owned()
+                    }
+                    _ => String::new(),
+                };
+                Value::String(text.into())
+            }
+            Hdf5Datatype::Compound { members, .. } => {
+                let mut record = BTreeMap::new();
+                for (name, offset, member) in members {
+                    drop(record.insert(name.clone(), self.decode(member, &bytes[*offset..])?));
+                }
+                Value::Record(record)
+            }
+            Hdf5Datatype::Array { base, .. } => {
+                let size = base.size().max(1);
+                Value::List(
+                    bytes[..datatype.size()]
+                        .chunks_exact(size)
+                        .map(|v| self.decode(base, v))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            Hdf5Datatype::Integer { .. }
+            | Hdf5Datatype::Float { .. }
+            | Hdf5Datatype::Other { .. } => Value::Null,
+        })
+    }
+
+    /// Read all of the data in the dataset at `path`
+    ///
+    /// # Errors
+    /// If the dataset can't be found, is malformed, or uses an unsupported filter, returns an
+    /// `EtError`.
+    pub fn read_dataset(&self, path: &str) -> Result<Hdf5Dataset, EtError> {
+        let messages = self.messages(self.find_object(path)?)?;
+        let datatype = match self.find_message(&messages, MSG_DATATYPE)? {
+            Some(mut c) => Hdf5Datatype::parse(&mut c)?,
+            None => return Err(format!("{} is not an HDF5 dataset", path).into()),
+        };
+        let shape = match self.find_message(&messages, MSG_DATASPACE)? {
+            Some(mut c) => parse_dataspace(&mut c)?.unwrap_or_default(),
+            None => return Err(format!("{} is not an HDF5 dataset", path).into()),
+        };
+        let layout = match self.find_message(&messages, MSG_LAYOUT)? {
+            Some(mut c) => Hdf5Layout::parse(&mut c)?,
+            None => return Err(format!("{} is not an HDF5 dataset", path).into()),
+        };
+        let filters = match self.find_message(&messages, MSG_FILTERS)? {
+            Some(mut c) => parse_filters(&mut c)?,
+            None => Vec::new(),
+        };
+        let element_size = datatype.size();
+        let total = n_bytes(&shape, element_size)?;
+
+        let data = match layout {
+            Hdf5Layout::Compact(start, size) => {
+                if total > self.data.len() {
+                    return Err("HDF5 compact dataset is larger than the file".into());
+                }
+                let raw = start
+                    .checked_add(size)
+                    .and_then(|end| self.data.get(start..end))
+                    .ok_or_else(|| EtError::from("HDF5 compact data is too short"))?;
+                let mut data = raw.to_vec();
+                data.resize(total, 0);
+                data
+            }
+            // space was never allocated for the data so it's all the fill value
+            Hdf5Layout::Contiguous(None) | Hdf5Layout::Chunked { address: None, .. } => {
+                if total > self.data.len() {
+                    return Err("HDF5 dataset is larger than the file".into());
+                }
+                vec![0; total]
+            }
+            Hdf5Layout::Contiguous(Some(address)) => address
+                .checked_add(total)
+                .and_then(|end| self.data.get(address..end))
+                .ok_or_else(|| EtError::from("HDF5 dataset extends past the end of the file"))?
+                .to_vec(),
+            Hdf5Layout::Chunked {
+                address: Some(address),
+                dims,
+                index,
+            } => {
+                let chunk_size = n_bytes(&dims, element_size)?;
+                let mut chunks = Vec::new();
+                match index {
+                    Hdf5ChunkIndex::BTree => {
+                        self.read_chunk_btree(address, dims.len(), &mut chunks, 0)?;
+                    }
+                    Hdf5ChunkIndex::Single { size, filter_mask } => chunks.push((
+                        vec![0; dims.len()],
+                        address,
+                        size.unwrap_or(chunk_size),
+                        filter_mask,
+                    )),
+                    Hdf5ChunkIndex::Implicit => {
+                        let grid: Vec<usize> = shape
+                            .iter()
+                            .zip(&dims)
+                            .map(|(s, c)| s.div_ceil((*c).max(1)))
+                            .collect();
+                        // every chunk is stored one after another so they all have to fit in the file
+                        let n_chunks = grid
+                            .iter()
+                            .try_fold(1usize, |acc, n| acc.checked_mul(*n))
+                            .filter(|n| {
+                                n.checked_mul(chunk_size)
+                                    .and_then(|size| size.checked_add(address))
+                                    .is_some_and(|end| end <= self.data.len())
+                            })
+                            .ok_or_else(|| {
+                                EtError::from("HDF5 chunks extend past the end of the file")
+                            })?;
+                        for ix in 0..n_chunks {
+                            let mut rem = ix;
+                            let mut offset = vec![0; dims.len()];
+                            for d in (0..dims.len()).rev() {
+                                offset[d] = (rem % grid[d]) * dims[d];
+                                rem /= grid[d];
+                            }
+                            let chunk_address = ix
+                                .checked_mul(chunk_size)
+                                .and_then(|o| o.checked_add(address))
+                                .ok_or_else(|| EtError::from("HDF5 chunk address is too large"))?;
+                            chunks.push((offset, chunk_address, chunk_size, 0));
+                        }
+                    }
+                }
+                // the chunks have to cover the dataset so it can't be bigger than all of them
+                if chunks
+                    .len()
+                    .checked_mul(chunk_size)
+                    .is_none_or(|max| total > max)
+                {
+                    return Err("HDF5 dataset is larger than all of its chunks".into());
+                }
+                let mut data = vec![0; total];
+                for (offset, chunk_address, size, mask) in chunks {
+                    let raw = chunk_address
+                        .checked_add(size)
+                        .and_then(|end| self.data.get(chunk_address..end))
+                        .ok_or_else(|| {
+                            EtError::from("HDF5 chunk extends past the end of the file")
+                        })?;
+                    let chunk = unfilter(raw.to_vec(), &filters, mask, element_size)?;
+                    copy_chunk(&mut data, &shape, &chunk, &dims, &offset, element_size)?;
+                }
+                data
+            }
+        };
+        Ok(Hdf5Dataset {
+            shape,
+            datatype,
+            data,
+        })
+    }
+
+    /// Walk a version 1 "raw data chunk" B-tree to find the offset, address, size and filter mask
+    /// of every chunk in a dataset
+    fn read_chunk_btree(
+        &self,
+        address: usize,
+        rank: usize,
+        chunks: &mut Vec<(Vec<usize>, usize, usize, usize)>,
+        depth: usize,
+    ) -> Result<(), EtError> {
+        if depth > 64 {
+            return Err("HDF5 chunk B-tree is too deep".into());
+        }
+        let mut c = self.cursor(address);
+        c.signature(b"TREE")?;
+        if c.u8()? != 1 {
+            return Err("HDF5 chunk B-tree has the wrong node type".into());
+        }
+        let level = c.u8()?;
+        let n_entries = c.u16()?;
+        c.skip(2 * self.offset_size)?;
+        for _ in 0..n_entries {
+            let size = c.u32()?;
+            let filter_mask = c.u32()?;
+            let mut offset = Vec::with_capacity(rank);
+            for _ in 0..rank {
+                offset.push(usize::try_from(c.uint(8)?)?);
+            }
+            // skip the offset into the "element size" dimension
+            c.skip(8)?;
+            let child = c
+                .address()?
+                .ok_or_else(|| EtError::from("Bad HDF5 B-tree child address"))?;
+            if level > 0 {
+                self.read_chunk_btree(child, rank, chunks, depth + 1)?;
+            } else {
+                chunks.push((offset, child, size, filter_mask));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A reader for HDF5 files.
+///
+/// Without a dataset, it lists all the groups and datasets in the file; with one (e.g.
+/// `group/dataset`), it returns one record for each row of that dataset.
+#[derive(Debug)]
+pub struct Hdf5Reader {
+    file: Hdf5File,
+    dataset: Option<Hdf5Dataset>,
+    metadata: BTreeMap<String, Value<'static>>,
+    cur_row: usize,
+}
+
+impl Hdf5Reader {
+    /// Create a new instance of the reader
+    ///
+    /// # Errors
+    /// If the file can't be read, isn't a valid HDF5 file, or the dataset can't be read, returns
+    /// an `EtError`.
+    pub fn new<'r, B>(data: B, dataset: Option<String>) -> Result<Self, EtError>
+    where
+        B: TryInto<ReadBuffer<'r>>,
+        EtError: From<<B as TryInto<ReadBuffer<'r>>>::Error>,
+    {
+        let file = Hdf5File::new(data)?;
+        let mut metadata = file.attributes("")?;
+        let dataset = match dataset {
+            Some(path) => {
+                metadata.extend(file.attributes(&path)?);
+                Some(file.read_dataset(&path)?)
+            }
+            None => None,
+        };
+        Ok(Hdf5Reader {
+            file,
+            dataset,
+            metadata,
+            cur_row: 0,
+        })
+    }
+
+    /// The underlying file (e.g. to read other datasets in it)
+    #[must_use]
+    pub fn file(&self) -> &Hdf5File {
+        &self.file
+    }
+}
+
+impl RecordReader for Hdf5Reader {
+    /// The next group or dataset in the file or the next row in the dataset.
+    fn next_record(&mut self) -> Result<Option<Vec<Value>>, EtError> {
+        let dataset = match &self.dataset {
+            Some(d) => d,
+            None => {
+                let entry = match self.file.entries.get(self.cur_row) {
+                    Some(e) => e,
+                    None => return Ok(None),
+                };
+                self.cur_row += 1;
+                let shape: Vec<Value> = entry
+                    .shape
+                    .iter()
+                    .map(|d| u64::try_from(*d).unwrap_or(0).into())
+                    .collect();
+                return Ok(Some(vec![
+                    entry.path.as_str().into(),
+                    if entry.is_dataset { "dataset" } else { "group" }.into(),
+                    if entry.is_dataset {
+                        Value::List(shape)
+                    } else {
+                        Value::Null
+                    },
+                    entry.datatype.as_ref().map(Hdf5Datatype::name).into(),
+                ]));
+            }
+        };
+
+        // scalar datasets have a single row
+        let n_rows = dataset.shape.first().copied().unwrap_or(1);
+        if self.cur_row >= n_rows {
+            return Ok(None);
+        }
+        let size = dataset.datatype.size();
+        let row_len = dataset.shape.iter().skip(1).product::<usize>() * size;
+        let row = &dataset.data[self.cur_row * row_len..(self.cur_row + 1) * row_len];
+        self.cur_row += 1;
+        Ok(Some(match (&dataset.datatype, dataset.shape.len()) {
+            (Hdf5Datatype::Compound { members, .. }, 0 | 1) => members
+                .iter()
+                .map(|(_, offset, member)| self.file.decode(member, &row[*offset..]))
+                .collect::<Result<_, _>>()?,
+            (_, 0 | 1) => vec![self.file.decode(&dataset.datatype, row)?],
+            (datatype, 2) => row
+                .chunks_exact(size.max(1))
+                .map(|v| self.file.decode(datatype, v))
+                .collect::<Result<_, _>>()?,
+            (datatype, _) => vec![Value::List(
+                row.chunks_exact(size.max(1))
+                    .map(|v| self.file.decode(datatype, v))
+                    .collect::<Result<_, _>>()?,
+            )],
+        }))
+    }
+
+    /// The headers for this Reader.
+    fn headers(&self) -> Vec<String> {
+        let dataset = match &self.dataset {
+            Some(d) => d,
+            None => {
+                return ["path", "type", "shape", "dtype"]
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect()
+            }
+        };
+        match (&dataset.datatype, dataset.shape.len()) {
+            (Hdf5Datatype::Compound { members, .. }, 0 | 1) => {
+                members.iter().map(|(name, _, _)| name.clone()).collect()
+            }
+            (_, 2) => (0..dataset.shape[1]).map(|i| i.to_string()).collect(),
+            _ => vec!["value".to_string()],
+        }
+    }
+
+    /// The metadata for this Reader.
+    fn metadata(&self) -> BTreeMap<String, Value> {
+        self.metadata.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hdf5_listing() -> Result<(), EtError> {
+        let data: &[u8] = include_bytes!("../../tests/data/test_v0.h5");
+        let mut reader = Hdf5Reader::new(data, None)?;
+        assert_eq!(reader.headers(), ["path", "type", "shape", "dtype"]);
+        let metadata = reader.metadata();
+        assert_eq!(metadata["title"], "entab test file".into());
+        // stored in a continuation of the root group's header
+        assert_eq!(metadata["version"], 3.into());
+        assert_eq!(
+            metadata["ratios"],
+            Value::List(vec![0.5.into(), 0.25.into()])
+        );
+        let record = reader.next_record()?.unwrap();
+        assert_eq!(record[0], "matrix".into());
+        assert_eq!(record[1], "dataset".into());
+        assert_eq!(record[2], Value::List(vec![3u64.into(), 4u64.into()]));
+        assert_eq!(record[3], "int16".into());
+        assert_eq!(
+            reader.file().datasets(),
+            ["matrix", "peaks", "spectra/intensity", "spectra/mz"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hdf5_datasets() -> Result<(), EtError> {
+        let data: &[u8] = include_bytes!("../../tests/data/test_v0.h5");
+        let file = Hdf5File::new(data)?;
+
+        // contiguous
+        let mz = file.read_dataset("/spectra/mz")?;
+        assert_eq!(mz.shape, [10]);
+        assert_eq!(file.decode(&mz.datatype, &mz.data[72..])?, 212.5.into());
+
+        // chunked with shuffling and compression and a partial last chunk
+        let mut reader = Hdf5Reader::new(data, Some("spectra/intensity".to_string()))?;
+        assert_eq!(reader.headers(), ["value"]);
+        assert_eq!(reader.metadata()["units"], "counts".into());
+        let mut values = Vec::new();
+        while let Some(record) = reader.next_record()? {
+            values.push(record[0].clone().into_owned());
+        }
+        let expected: Vec<Value> = (0..10).map(|i| f64::from(i * i).into()).collect();
+        assert_eq!(values, expected);
+
+        // compact and big-endian
+        let mut reader = Hdf5Reader::new(data, Some("matrix".to_string()))?;
+        assert_eq!(reader.headers(), ["0", "1", "2", "3"]);
+        let _ = reader.next_record()?;
+        assert_eq!(
+            reader.next_record()?.unwrap(),
+            [5.into(), 6.into(), 7.into(), 8.into()]
+        );
+
+        let mut reader = Hdf5Reader::new(data, Some("peaks".to_string()))?;
+        assert_eq!(reader.headers(), ["mz", "intensity", "label", "counts"]);
+        let _ = reader.next_record()?;
+        let _ = reader.next_record()?;
+        assert_eq!(
+            reader.next_record()?.unwrap(),
+            [
+                300.75.into(),
+                7.5.into(),
+                "gamma!".into(),
+                Value::List(vec![5.into(), 250.into()]),
+            ]
+        );
+        assert!(reader.next_record()?.is_none());
+
+        assert!(file.read_dataset("spectra").is_err());
+        assert!(file.read_dataset("missing").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hdf5_new_style() -> Result<(), EtError> {
+        let data: &[u8] = include_bytes!("../../tests/data/test_v3.h5");
+        let file = Hdf5File::new(data)?;
+        // the soft link isn't followed
+        let paths: Vec<&str> = file.entries().iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["data", "empty", "nested", "nested/data", "table"]);
+        assert_eq!(file.attributes("data")?["scale"], 0.001.into());
+
+        let mut reader = Hdf5Reader::new(data, Some("nested/data".to_string()))?;
+        assert_eq!(reader.next_record()?.unwrap(), [(-(1i64 << 40)).into()]);
+
+        let mut reader = Hdf5Reader::new(data, Some("table".to_string()))?;
+        assert_eq!(reader.headers(), ["time", "ok", "values"]);
+        let _ = reader.next_record()?;
+        assert_eq!(
+            reader.next_record()?.unwrap(),
+            [
+                1.0.into(),
+                0.into(),
+                Value::List(vec![4.0.into(), 5.0.into(), 6.0.into()]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hdf5_bad_data() {
+        let data: &[u8] = b"\x89HDF\r\n\x1A\n too short";
+        assert!(Hdf5File::new(data).is_err());
+        let data: &[u8] = include_bytes!("../../tests/data/test.cfb");
+        assert!(Hdf5File::new(data).is_err());
+        // shapes that would overflow when working out how much space the data needs
+        assert_eq!(n_bytes(&[3, 4], 8).unwrap(), 96);
+        assert!(n_bytes(&[usize::MAX, 2], 1).is_err());
+    }
+
+    #[test]
+    fn test_unshuffle() -> Result<(), EtError> {
+        let filters = [Hdf5Filter {
+            id: 2,
+            client_data: vec![2],
+        }];
+        let data = unfilter(vec![1, 3, 5, 2, 4, 6, 7], &filters, 0, 2)?;
+        assert_eq!(data, [1, 2, 3, 4, 5, 6, 7]);
+        // the filter is skipped if it's masked out
+        let data = unfilter(vec![1, 3, 5, 2, 4, 6], &filters, 1, 2)?;
+        assert_eq!(data, [1, 3, 5, 2, 4, 6]);
+        // and a long pipeline doesn't overflow the mask
+        let filters = vec![
+            Hdf5Filter {
+                id: 2,
+                client_data: vec![1],
+            };
+            70
+        ];
+        let data = unfilter(vec![1, 2, 3], &filters, usize::MAX, 1)?;
+        assert_eq!(data, [1, 2, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_chunk() -> Result<(), EtError> {
+        // a 3x3 array with 2x2 chunks; the chunk in the bottom right is mostly outside of it
+        let mut out = vec![0; 9];
+        copy_chunk(&mut out, &[3, 3], &[1, 2, 3, 4], &[2, 2], &[0, 0], 1)?;
+        copy_chunk(&mut out, &[3, 3], &[5, 6, 7, 8], &[2, 2], &[2, 2], 1)?;
+        assert_eq!(out, [1, 2, 0, 3, 4, 0, 0, 0, 5]);
+        Ok(())
+    }
+}
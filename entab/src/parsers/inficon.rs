@@ -3,17 +3,36 @@ use alloc::{format, vec};
 use core::convert::TryFrom;
 use core::marker::Copy;
 
+use crate::impl_reader;
 use crate::parsers::common::SeekPattern;
 use crate::parsers::{extract, extract_opt, Endian, FromSlice};
-use crate::record::StateMetadata;
+use crate::record::{StateMetadata, Value};
 use crate::EtError;
-use crate::{impl_reader, impl_record};
+
+/// Parameters for the `InficonReader`
+#[derive(Clone, Debug, Default)]
+pub struct InficonParams {
+    scan_info: bool,
+    mz_segments: Vec<Vec<f64>>,
+    data_left: usize,
+}
+
+impl InficonParams {
+    /// Also return the number of the scan with every point
+    #[must_use]
+    pub fn scan_info(mut self, scan_info: bool) -> Self {
+        self.scan_info = scan_info;
+        self
+    }
+}
 
 /// The current state of the Inficon reader
 #[derive(Clone, Debug, Default)]
 pub struct InficonState {
+    scan_info: bool,
     mz_segments: Vec<Vec<f64>>,
     data_left: usize,
+    cur_scan: u32,
     cur_time: f64,
     cur_mz: f64,
     cur_intensity: f64,
@@ -23,19 +42,28 @@ pub struct InficonState {
 
 impl StateMetadata for InficonState {
     fn header(&self) -> Vec<&str> {
-        vec!["time", "mz", "intensity"]
+        if self.scan_info {
+            vec!["time", "scan", "mz", "intensity"]
+        } else {
+            vec!["time", "mz", "intensity"]
+        }
     }
 }
 
 impl<'b: 's, 's> FromSlice<'b, 's> for InficonState {
-    type State = (Vec<Vec<f64>>, usize);
+    type State = InficonParams;
 
     fn parse(
         rb: &[u8],
         eof: bool,
         consumed: &mut usize,
-        (mz_segments, data_left): &mut Self::State,
+        params: &mut Self::State,
     ) -> Result<bool, EtError> {
+        let InficonParams {
+            mz_segments,
+            data_left,
+            ..
+        } = params;
         let con = &mut 0;
 
         if extract::<&[u8]>(rb, con, &mut 4)? != [4, 3, 2, 1] {
@@ -110,9 +138,10 @@ impl<'b: 's, 's> FromSlice<'b, 's> for InficonState {
         Ok(true)
     }
 
-    fn get(&mut self, _rb: &[u8], (mz_segments, data_left): &Self::State) -> Result<(), EtError> {
-        self.mz_segments = mz_segments.clone();
-        self.data_left = *data_left;
+    fn get(&mut self, _rb: &[u8], params: &Self::State) -> Result<(), EtError> {
+        self.scan_info = params.scan_info;
+        self.mz_segments = params.mz_segments.clone();
+        self.data_left = params.data_left;
         Ok(())
     }
 }
@@ -121,11 +150,30 @@ impl<'b: 's, 's> FromSlice<'b, 's> for InficonState {
 #[derive(Clone, Copy, Debug, Default)]
 pub struct InficonRecord {
     time: f64,
+    scan: u32,
     mz: f64,
     intensity: f64,
+    scan_info: bool,
 }
 
-impl_record!(InficonRecord: time, mz, intensity);
+impl<'r> From<InficonRecord> for Vec<Value<'r>> {
+    fn from(record: InficonRecord) -> Self {
+        if record.scan_info {
+            vec![
+                record.time.into(),
+                record.scan.into(),
+                record.mz.into(),
+                record.intensity.into(),
+            ]
+        } else {
+            vec![
+                record.time.into(),
+                record.mz.into(),
+                record.intensity.into(),
+            ]
+        }
+    }
+}
 
 impl<'b: 's, 's> FromSlice<'b, 's> for InficonRecord {
     type State = InficonState;
@@ -143,7 +191,7 @@ impl<'b: 's, 's> FromSlice<'b, 's> for InficonRecord {
         let mut mzs_left = state.mzs_left;
         if mzs_left == 0 {
             // the first u32 is the number of the record (i.e. from 1 to r_scans)
-            let _ = extract::<u32>(rb, con, &mut Endian::Little)?;
+            state.cur_scan = extract::<u32>(rb, con, &mut Endian::Little)?;
             state.cur_time = f64::from(extract::<i32>(rb, con, &mut Endian::Little)?) / 60000.;
             // next value always seems to be 1
             let _ = extract::<u16>(rb, con, &mut Endian::Little)?;
@@ -185,8 +233,10 @@ impl<'b: 's, 's> FromSlice<'b, 's> for InficonRecord {
 
     fn get(&mut self, _rb: &[u8], state: &Self::State) -> Result<(), EtError> {
         self.time = state.cur_time;
+        self.scan = state.cur_scan;
         self.mz = state.cur_mz;
         self.intensity = state.cur_intensity;
+        self.scan_info = state.scan_info;
         Ok(())
     }
 }
@@ -196,7 +246,7 @@ impl_reader!(
     InficonRecord,
     InficonRecord,
     InficonState,
-    (Vec<Vec<f64>>, usize)
+    InficonParams
 );
 
 #[cfg(test)]
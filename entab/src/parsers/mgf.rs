@@ -0,0 +1,437 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::str;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use memchr::{memchr, memmem};
+
+use crate::parsers::FromSlice;
+use crate::record::{StateMetadata, Value};
+use crate::EtError;
+use crate::{impl_reader, impl_record};
+
+/// Lines in MGF files starting with one of these characters are comments
+fn is_comment(line: &[u8]) -> bool {
+    matches!(line.first(), Some(b'#' | b';' | b'!' | b'/'))
+}
+
+/// Split a `KEY=VALUE` line into its key and value
+fn split_param(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once('=')?;
+    if key.is_empty() || !key.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'_') {
+        return None;
+    }
+    Some((key.trim(), value.trim()))
+}
+
+/// Parse a charge like `2+`, `3-`, or `2+ and 3+` (in which case the first one is used)
+fn parse_charge(value: &str) -> Result<i32, EtError> {
+    let first = value
+        .split(|c: char| c.is_ascii_whitespace() || c == ',')
+        .next()
+        .unwrap_or("");
+    Ok(if let Some(n) = first.strip_suffix('+') {
+        n.parse()?
+    } else if let Some(n) = first.strip_suffix('-') {
+        -n.parse::<i32>()?
+    } else {
+        first.parse()?
+    })
+}
+
+/// Parse the first number in a value (e.g. the precursor m/z out of `PEPMASS=m/z intensity`)
+fn parse_first_float(value: &str) -> Result<f64, EtError> {
+    Ok(value
+        .split_ascii_whitespace()
+        .next()
+        .unwrap_or("")
+        .parse()?)
+}
+
+/// Find the next `BEGIN IONS`/`END IONS` block in `rb`, skipping any blank or comment lines in
+/// front of it.
+///
+/// Returns the offset of the `BEGIN IONS` line and the offset just past the `END IONS` line.
+fn find_block(rb: &[u8], eof: bool) -> Result<Option<(usize, usize)>, EtError> {
+    let mut start = 0;
+    loop {
+        if start >= rb.len() {
+            if eof {
+                return Ok(None);
+            }
+            return Err(EtError::new("MGF needs more data").incomplete());
+        }
+        let line_end = match memchr(b'\n', &rb[start..]) {
+            Some(p) => start + p,
+            None if eof => rb.len(),
+            None => return Err(EtError::new("Incomplete MGF line").incomplete()),
+        };
+        let line = rb[start..line_end].trim_ascii();
+        if line == b"BEGIN IONS" {
+            break;
+        }
+        if !line.is_empty() && !is_comment(line) {
+            return Err("MGF spectra must start with BEGIN IONS".into());
+        }
+        start = line_end + 1;
+    }
+    let end = match memmem::find(&rb[start..], b"END IONS") {
+        Some(p) => start + p + 8,
+        None if eof => return Err("MGF spectrum is missing END IONS".into()),
+        None => return Err(EtError::new("Incomplete MGF spectrum").incomplete()),
+    };
+    let end = match memchr(b'\n', &rb[end..]) {
+        Some(p) => end + p + 1,
+        None => rb.len(),
+    };
+    Ok(Some((start, end)))
+}
+
+/// The current state of MGF parsing
+#[derive(Clone, Debug, Default)]
+pub struct MgfState {
+    params: BTreeMap<String, String>,
+}
+
+impl StateMetadata for MgfState {
+    fn metadata(&self) -> BTreeMap<String, Value> {
+        self.params
+            .iter()
+            .map(|(k, v)| (k.clone(), v.as_str().into()))
+            .collect()
+    }
+
+    fn header(&self) -> Vec<&str> {
+        vec![
+            "title",
+            "pepmass",
+            "charge",
+            "rtinseconds",
+            "scans",
+            "mz",
+            "intensity",
+        ]
+    }
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for MgfState {
+    type State = ();
+
+    fn parse(
+        rb: &[u8],
+        eof: bool,
+        consumed: &mut usize,
+        _state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        // the global search parameters run until the first spectrum
+        let header_end = match memmem::find(rb, b"BEGIN IONS") {
+            Some(p) => p,
+            None if eof => rb.len(),
+            None => return Err(EtError::new("MGF header needs more data").incomplete()),
+        };
+        *consumed += header_end;
+        Ok(true)
+    }
+
+    fn get(&mut self, rb: &'b [u8], _state: &'s Self::State) -> Result<(), EtError> {
+        for line in rb.split(|c| *c == b'\n') {
+            let line = line.trim_ascii();
+            if line.is_empty() || is_comment(line) {
+                continue;
+            }
+            let (key, value) = split_param(str::from_utf8(line)?)
+                .ok_or_else(|| EtError::from("Invalid MGF header line"))?;
+            drop(
+                self.params
+                    .insert(key.to_ascii_lowercase(), value.to_string()),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A single spectrum (`BEGIN IONS` block) from an MGF file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MgfRecord<'r> {
+    /// The title of the spectrum
+    pub title: &'r str,
+    /// The m/z of the precursor ion
+    pub pepmass: Option<f64>,
+    /// The charge of the precursor ion
+    pub charge: Option<i32>,
+    /// The retention time of the spectrum (in seconds)
+    pub rtinseconds: Option<f64>,
+    /// The scan number(s) the spectrum came from
+    pub scans: Option<&'r str>,
+    /// The m/z of each peak in the spectrum
+    pub mz: Vec<f64>,
+    /// The intensity of each peak in the spectrum
+    pub intensity: Vec<f64>,
+}
+
+impl_record!(MgfRecord<'r>: title, pepmass, charge, rtinseconds, scans, mz, intensity);
+
+impl<'r> MgfRecord<'r> {
+    /// Read the spectrum out of a `BEGIN IONS`/`END IONS` block.
+    fn read_block(&mut self, block: &'r [u8]) -> Result<(), EtError> {
+        *self = MgfRecord::default();
+        for line in block.split(|c| *c == b'\n') {
+            let line = line.trim_ascii();
+            if line.is_empty() || is_comment(line) || line == b"BEGIN IONS" || line == b"END IONS" {
+                continue;
+            }
+            let line = str::from_utf8(line)?;
+            if let Some((key, value)) = split_param(line) {
+                match key.to_ascii_uppercase().as_str() {
+                    "TITLE" => self.title = value,
+                    "PEPMASS" => self.pepmass = Some(parse_first_float(value)?),
+                    "CHARGE" => self.charge = Some(parse_charge(value)?),
+                    "RTINSECONDS" => self.rtinseconds = Some(parse_first_float(value)?),
+                    "SCANS" => self.scans = Some(value),
+                    _ => {}
+                }
+                continue;
+            }
+            // peak lines are "m/z intensity" with an optional trailing charge
+            let mut fields = line.split_ascii_whitespace();
+            match (fields.next(), fields.next()) {
+                (Some(mz), Some(intensity)) => {
+                    self.mz.push(mz.parse()?);
+                    self.intensity.push(intensity.parse()?);
+                }
+                _ => return Err(format!("Invalid MGF peak line: {}", line).into()),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for MgfRecord<'b> {
+    type State = MgfState;
+
+    fn parse(
+        rb: &[u8],
+        eof: bool,
+        consumed: &mut usize,
+        _state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        match find_block(rb, eof)? {
+            Some((_, end)) => {
+                *consumed += end;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn get(&mut self, rb: &'b [u8], _state: &'s Self::State) -> Result<(), EtError> {
+        self.read_block(rb)
+    }
+}
+
+impl_reader!(MgfReader, MgfRecord, MgfRecord<'r>, MgfState, ());
+
+/// The current state of parsing an MGF file into one row per peak
+#[derive(Clone, Debug, Default)]
+pub struct MgfPeakState {
+    mgf_state: MgfState,
+    title: String,
+    pepmass: Option<f64>,
+    charge: Option<i32>,
+    rtinseconds: Option<f64>,
+    scans: Option<String>,
+    mz: Vec<f64>,
+    intensity: Vec<f64>,
+    cur_peak: usize,
+}
+
+impl StateMetadata for MgfPeakState {
+    fn metadata(&self) -> BTreeMap<String, Value> {
+        self.mgf_state.metadata()
+    }
+
+    fn header(&self) -> Vec<&str> {
+        self.mgf_state.header()
+    }
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for MgfPeakState {
+    type State = ();
+
+    fn parse(
+        rb: &[u8],
+        eof: bool,
+        consumed: &mut usize,
+        state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        MgfState::parse(rb, eof, consumed, state)
+    }
+
+    fn get(&mut self, rb: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
+        self.mgf_state.get(rb, state)
+    }
+}
+
+/// A single peak from an MGF file, along with the details of the spectrum it's in
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MgfPeakRecord {
+    /// The title of the spectrum
+    pub title: String,
+    /// The m/z of the precursor ion
+    pub pepmass: Option<f64>,
+    /// The charge of the precursor ion
+    pub charge: Option<i32>,
+    /// The retention time of the spectrum (in seconds)
+    pub rtinseconds: Option<f64>,
+    /// The scan number(s) the spectrum came from
+    pub scans: Option<String>,
+    /// The m/z of the peak
+    pub mz: f64,
+    /// The intensity of the peak
+    pub intensity: f64,
+}
+
+impl_record!(MgfPeakRecord: title, pepmass, charge, rtinseconds, scans, mz, intensity);
+
+impl<'b: 's, 's> FromSlice<'b, 's> for MgfPeakRecord {
+    type State = MgfPeakState;
+
+    fn parse(
+        mut rb: &[u8],
+        eof: bool,
+        consumed: &mut usize,
+        state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        // keep returning peaks from the current spectrum before reading the next one
+        state.cur_peak += 1;
+        while state.cur_peak >= state.mz.len() {
+            let (start, end) = match find_block(rb, eof)? {
+                Some(pos) => pos,
+                None => return Ok(false),
+            };
+            let mut spectrum = MgfRecord::default();
+            spectrum.read_block(&rb[start..end])?;
+            state.title = spectrum.title.to_string();
+            state.pepmass = spectrum.pepmass;
+            state.charge = spectrum.charge;
+            state.rtinseconds = spectrum.rtinseconds;
+            state.scans = spectrum.scans.map(ToString::to_string);
+            state.mz = spectrum.mz;
+            state.intensity = spectrum.intensity;
+            state.cur_peak = 0;
+            *consumed += end;
+            rb = &rb[end..];
+        }
+        Ok(true)
+    }
+
+    fn get(&mut self, _rb: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
+        self.title.clone_from(&state.title);
+        self.pepmass = state.pepmass;
+        self.charge = state.charge;
+        self.rtinseconds = state.rtinseconds;
+        self.scans.clone_from(&state.scans);
+        self.mz = state.mz[state.cur_peak];
+        self.intensity = state.intensity[state.cur_peak];
+        Ok(())
+    }
+}
+
+impl_reader!(
+    MgfPeakReader,
+    MgfPeakRecord,
+    MgfPeakRecord,
+    MgfPeakState,
+    ()
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::readers::RecordReader;
+
+    const TEST_MGF: &[u8] = b"COM=Test search\nCHARGE=2+ and 3+\n# a comment\n\nBEGIN IONS\nTITLE=scan=1\nPEPMASS=445.12 1200.5\nCHARGE=2+\nRTINSECONDS=60.5\nSCANS=1\n100.1 10\n200.2 20.5\n300.3 30 1+\nEND IONS\n\nBEGIN IONS\nTITLE=empty\nEND IONS\nBEGIN IONS\r\nTITLE=scan=3\r\nCHARGE=3-\r\n150.5 5\r\nEND IONS\r\n";
+
+    #[test]
+    fn test_mgf_reader() -> Result<(), EtError> {
+        let mut reader = MgfReader::new(TEST_MGF, None)?;
+        let metadata = reader.metadata();
+        assert_eq!(metadata["com"], "Test search".into());
+        assert_eq!(metadata["charge"], "2+ and 3+".into());
+
+        let record = reader.next()?.unwrap();
+        assert_eq!(record.title, "scan=1");
+        assert_eq!(record.pepmass, Some(445.12));
+        assert_eq!(record.charge, Some(2));
+        assert_eq!(record.rtinseconds, Some(60.5));
+        assert_eq!(record.scans, Some("1"));
+        assert_eq!(record.mz, vec![100.1, 200.2, 300.3]);
+        assert_eq!(record.intensity, vec![10., 20.5, 30.]);
+
+        let record = reader.next()?.unwrap();
+        assert_eq!(record.title, "empty");
+        assert!(record.mz.is_empty());
+
+        let record = reader.next()?.unwrap();
+        assert_eq!(record.title, "scan=3");
+        assert_eq!(record.charge, Some(-3));
+        assert_eq!(record.pepmass, None);
+        assert_eq!(record.mz, vec![150.5]);
+
+        assert!(reader.next()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_mgf_peak_reader() -> Result<(), EtError> {
+        let mut reader = MgfPeakReader::new(TEST_MGF, None)?;
+        let mut peaks = Vec::new();
+        while let Some(peak) = reader.next()? {
+            peaks.push(peak);
+        }
+        assert_eq!(peaks.len(), 4);
+        assert_eq!(peaks[0].title, "scan=1");
+        assert_eq!(peaks[0].mz, 100.1);
+        assert_eq!(peaks[2].intensity, 30.);
+        assert_eq!(peaks[3].title, "scan=3");
+        assert_eq!(peaks[3].charge, Some(-3));
+        assert_eq!(peaks[3].mz, 150.5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mgf_bad_peak() {
+        let data = b"BEGIN IONS\nTITLE=x\n100.1\nEND IONS\n";
+        let mut reader = MgfReader::new(&data[..], None).unwrap();
+        assert!(reader.next().is_err());
+    }
+
+    #[test]
+    fn test_mgf_file() -> Result<(), EtError> {
+        use crate::readers::get_reader;
+
+        let data: &[u8] = include_bytes!("../../tests/data/test.mgf");
+        let (mut reader, parser) = get_reader(data, None, None)?;
+        assert_eq!(parser, "mgf");
+        assert_eq!(reader.metadata()["search"], "MIS".into());
+        assert_eq!(reader.headers()[0], "title");
+        let record = reader.next_record()?.unwrap();
+        assert_eq!(record[0], "Spectrum 1 scans: 10".into());
+        assert_eq!(record[2], 2.into());
+        assert_eq!(record[6], vec![1205.6, 843.2, 2210.0, 987.4].into());
+        assert!(reader.next_record()?.is_some());
+        assert!(reader.next_record()?.is_none());
+
+        let mut params = BTreeMap::new();
+        drop(params.insert("long".to_string(), Value::Boolean(true)));
+        let (mut reader, _) = get_reader(data, None, Some(params))?;
+        let mut n_peaks = 0;
+        while reader.next_record()?.is_some() {
+            n_peaks += 1;
+        }
+        assert_eq!(n_peaks, 6);
+        Ok(())
+    }
+}
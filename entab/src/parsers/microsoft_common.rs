@@ -1,28 +1,58 @@
-use core::convert::TryInto;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::{TryFrom, TryInto};
+#[cfg(feature = "std")]
+use std::io::Read;
 
 use chrono::{NaiveDateTime, TimeZone, Utc};
 
+use crate::buffer::ReadBuffer;
 use crate::error::EtError;
-use crate::parsers::{extract, Endian, FromSlice};
-
+use crate::parsers::{Endian, FromSlice};
+use crate::readers::RecordReader;
+use crate::record::Value;
 
 /// Convert a "Windows" timestamp into a regular `DateTime`.
 ///
-/// Windows time is the number of "100 microsecond" chunks since January 1, 1601 so to convert to
+/// Windows time is the number of "100 nanosecond" chunks since January 1, 1601 so to convert to
 /// unix time we first need to convert into nanoseconds and then subtract the number of nanoseconds
 /// from then to Jan 1, 1970.
 pub fn from_windows_time(time: u64) -> Result<NaiveDateTime, EtError> {
-    let unix_time = time.saturating_mul(100).saturating_sub(11_644_473_600_000_000_000);
+    let unix_time = time
+        .saturating_mul(100)
+        .saturating_sub(11_644_473_600_000_000_000);
     Ok(Utc.timestamp_nanos(unix_time.try_into()?).naive_local())
 }
 
+/// Marks the last sector in a FAT chain
+const END_OF_CHAIN: u32 = 0xFFFF_FFFE;
+/// Marks an unused entry in the FAT or the DIFAT
+const FREE_SECTOR: u32 = 0xFFFF_FFFF;
+/// Marks a missing sibling or child in the directory tree
+const NO_STREAM: u32 = 0xFFFF_FFFF;
+/// Any sector numbers at or above this are special markers and not actual locations
+const MAX_SECTOR: u32 = 0xFFFF_FFFA;
+
 /// A chunk from a Microsoft "Compound File Binary" file (commonly used on Windows machines to
 /// store different data).
 ///
 /// See Microsoft documentation for more info:
 /// https://docs.microsoft.com/en-us/openspecs/windows_protocols/ms-cfb/05060311-bfce-4b12-874d-71fd4ce63aea
-#[derive(Debug, Default)]
-struct MsCfbHeader { }
+#[derive(Clone, Debug, Default)]
+struct MsCfbHeader {
+    sector_size: usize,
+    mini_sector_size: usize,
+    mini_stream_cutoff: u64,
+    first_dir_loc: u32,
+    first_minifat_loc: u32,
+    first_difat_loc: u32,
+    n_difat_sectors: u32,
+    /// The first 109 FAT sector locations (the rest are listed in DIFAT sectors)
+    difat: Vec<u32>,
+}
 
 impl<'b: 's, 's> FromSlice<'b, 's> for MsCfbHeader {
     type State = ();
@@ -30,48 +60,551 @@ impl<'b: 's, 's> FromSlice<'b, 's> for MsCfbHeader {
     fn parse(
         buffer: &[u8],
         _eof: bool,
-        _consumed: &mut usize,
+        consumed: &mut usize,
         _state: &mut Self::State,
     ) -> Result<bool, EtError> {
         const CFB_MAGIC: &[u8] = b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1";
 
         if buffer.len() < 512 {
-            return Err(EtError::new("MS CFB headers are always 512 bytes long").incomplete());
+            return Err(EtError::from("MS CFB headers are always 512 bytes long").incomplete());
         }
         if &buffer[..8] != CFB_MAGIC {
-            return Err(EtError::new("CFB header has invalid magic"));
+            return Err(EtError::from("CFB header has invalid magic"));
         }
+        if buffer[28..30] != [0xFE, 0xFF] {
+            return Err("CFB header has invalid byte order mark".into());
+        }
+        *consumed += 512;
+        Ok(true)
+    }
 
+    fn get(&mut self, buffer: &'b [u8], _state: &'s Self::State) -> Result<(), EtError> {
         // minor_version = buffer[24..26]
         // major_version = buffer[26..28]
-        // byte_order = buffer[28..30]
-        let sector_size = match buffer[30..32] {
+        self.sector_size = match buffer[30..32] {
             [0x09, 0] => 512,
             [0x0C, 0] => 4096,
             _ => return Err("Invalid sector shift specified".into()),
         };
-        // 32..44 -> ...
-    
-        let n_fat_sectors = u32::extract(&buffer[44..48], Endian::Little)?;
-        // TODO: we could maybe come up with a way to not call the `parse` side of above, but with
-        // good ergonomics? (the below is a little gross)
-        // let mut n_fat_sectors: u32 = 0;
-        // FromSlice::get(&mut n_fat_sectors, &buffer[44..48], &Endian::Little)?;
-
-        let first_dir_loc = u32::extract(&buffer[48..52], Endian::Little)?;
-        let first_minifat_loc = u32::extract(&buffer[60..64], Endian::Little)?;
-        let n_minifat_sectors = u32::extract(&buffer[64..68], Endian::Little)?;
-        let first_difat_loc = u32::extract(&buffer[68..72], Endian::Little)?;
-        let n_difat_sectors = u32::extract(&buffer[72..76], Endian::Little)?;
-        if n_difat_sectors > 0 {
-            return Err("DIFAT sectors aren't supported yet".into());
+        self.mini_sector_size = 1 << u16::extract(&buffer[32..34], &Endian::Little)?.min(15);
+        // 34..44 -> reserved and the number of directory sectors (we follow the chain instead)
+        // 44..48 -> the number of FAT sectors (we read all of them from the DIFAT instead)
+        self.first_dir_loc = u32::extract(&buffer[48..52], &Endian::Little)?;
+        // 52..56 -> transaction signature
+        self.mini_stream_cutoff = u32::extract(&buffer[56..60], &Endian::Little)?.into();
+        self.first_minifat_loc = u32::extract(&buffer[60..64], &Endian::Little)?;
+        // 64..68 -> the number of miniFAT sectors (we follow the chain instead)
+        self.first_difat_loc = u32::extract(&buffer[68..72], &Endian::Little)?;
+        self.n_difat_sectors = u32::extract(&buffer[72..76], &Endian::Little)?;
+        self.difat = buffer[76..512]
+            .chunks(4)
+            .map(|c| u32::extract(c, &Endian::Little))
+            .collect::<Result<_, _>>()?;
+        Ok(())
+    }
+}
+
+/// A stream or storage (i.e. a file or a folder) inside of a Compound File Binary file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MsCfbEntry {
+    /// The full path to the entry with each storage seperated by "/"
+    pub path: String,
+    /// True if this is a stream (i.e. it has data), false if it's a storage
+    pub is_stream: bool,
+    /// The size of the stream in bytes
+    pub size: u64,
+    /// When this storage was created (never set for streams)
+    pub created: Option<NaiveDateTime>,
+    /// When this storage was last modified (never set for streams)
+    pub modified: Option<NaiveDateTime>,
+    start_sector: u32,
+}
+
+/// The raw directory entry as stored in the file
+#[derive(Clone, Copy, Debug)]
+struct MsCfbDirEntry {
+    entry_type: u8,
+    left: u32,
+    right: u32,
+    child: u32,
+    created: u64,
+    modified: u64,
+    start_sector: u32,
+    size: u64,
+}
+
+/// A Microsoft "Compound File Binary" file (also called OLE or structured storage) which
+/// provides a filesystem-like hierarchy of named streams inside of one file.
+///
+/// The whole file is read into memory and the sector tables are decoded up front so that any of
+/// the streams inside can then be opened by name.
+#[derive(Clone, Debug)]
+pub struct MsCfbFile {
+    data: Vec<u8>,
+    header: MsCfbHeader,
+    fat: Vec<u32>,
+    minifat: Vec<u32>,
+    mini_stream: Vec<u8>,
+    entries: Vec<MsCfbEntry>,
+}
+
+impl MsCfbFile {
+    /// Read a Compound File Binary file.
+    ///
+    /// # Errors
+    /// If the file can't be read or the sector tables or directory are invalid, returns an
+    /// `EtError`.
+    pub fn new<'r, B>(data: B) -> Result<Self, EtError>
+    where
+        B: TryInto<ReadBuffer<'r>>,
+        EtError: From<<B as TryInto<ReadBuffer<'r>>>::Error>,
+    {
+        let rb: ReadBuffer<'r> = data.try_into()?;
+        #[cfg(feature = "std")]
+        let data = {
+            let mut data = Vec::new();
+            let _ = rb.into_box_read().read_to_end(&mut data)?;
+            data
+        };
+        #[cfg(not(feature = "std"))]
+        let data = rb.buffer.into_owned();
+        Self::from_vec(data)
+    }
+
+    /// Parse the sector tables and directory out of the complete contents of a file
+    fn from_vec(data: Vec<u8>) -> Result<Self, EtError> {
+        let mut header = MsCfbHeader::default();
+        let mut consumed = 0;
+        let _ = MsCfbHeader::parse(&data, true, &mut consumed, &mut ())
+            .map_err(|_| EtError::from("MS CFB file is too short or has an invalid header"))?;
+        header.get(&data, &())?;
+        let mut file = MsCfbFile {
+            data,
+            header,
+            fat: Vec::new(),
+            minifat: Vec::new(),
+            mini_stream: Vec::new(),
+            entries: Vec::new(),
+        };
+
+        // the DIFAT lists where all the sectors of the FAT are; the first 109 entries are in the
+        // header and the rest are in a chain of DIFAT sectors with the next location at the end
+        let mut fat_sectors = file.header.difat.clone();
+        let mut difat_loc = file.header.first_difat_loc;
+        let n_difat_sectors = usize::try_from(file.header.n_difat_sectors)?;
+        if n_difat_sectors > file.data.len() / file.header.sector_size {
+            return Err("MS CFB file has more DIFAT sectors than fit in it".into());
+        }
+        let mut difat_seen = Vec::with_capacity(n_difat_sectors);
+        for _ in 0..n_difat_sectors {
+            if difat_loc >= MAX_SECTOR {
+                break;
+            }
+            // each sector can only be visited once so we don't follow a loop around forever
+            if difat_seen.contains(&difat_loc) {
+                return Err("MS CFB DIFAT chain has a loop".into());
+            }
+            difat_seen.push(difat_loc);
+            let sector = file.read_u32s(file.sector(difat_loc)?)?;
+            let (next, locs) = sector
+                .split_last()
+                .ok_or_else(|| EtError::from("MS CFB DIFAT sector is empty"))?;
+            fat_sectors.extend_from_slice(locs);
+            difat_loc = *next;
+        }
+        for loc in fat_sectors {
+            if loc == FREE_SECTOR {
+                continue;
+            }
+            let sector = file.read_u32s(file.sector(loc)?)?;
+            file.fat.extend(sector);
+        }
+
+        let minifat_data = file.read_chain(file.header.first_minifat_loc, None)?;
+        file.minifat = file.read_u32s(&minifat_data)?;
+
+        let dir_data = file.read_chain(file.header.first_dir_loc, None)?;
+        let dir_entries = dir_data
+            .chunks_exact(128)
+            .map(|c| {
+                Ok(MsCfbDirEntry {
+                    entry_type: c[66],
+                    left: u32::extract(&c[68..72], &Endian::Little)?,
+                    right: u32::extract(&c[72..76], &Endian::Little)?,
+                    child: u32::extract(&c[76..80], &Endian::Little)?,
+                    created: u64::extract(&c[100..108], &Endian::Little)?,
+                    modified: u64::extract(&c[108..116], &Endian::Little)?,
+                    start_sector: u32::extract(&c[116..120], &Endian::Little)?,
+                    size: u64::extract(&c[120..128], &Endian::Little)?,
+                })
+            })
+            .collect::<Result<Vec<_>, EtError>>()?;
+        let root = match dir_entries.first() {
+            Some(e) if e.entry_type == 5 => *e,
+            _ => return Err("MS CFB file is missing its root entry".into()),
+        };
+        // the root entry holds the "mini stream" that small streams are stored inside of
+        file.mini_stream = file.read_chain(root.start_sector, Some(root.size))?;
+        file.entries = Self::walk_directory(&dir_data, &dir_entries, root.child)?;
+        Ok(file)
+    }
+
+    /// The slice for a sector in the file
+    fn sector(&self, loc: u32) -> Result<&[u8], EtError> {
+        let start = (usize::try_from(loc)? + 1) * self.header.sector_size;
+        let end = start + self.header.sector_size;
+        if loc >= MAX_SECTOR || end > self.data.len() {
+            return Err(format!("MS CFB sector {} is outside of the file", loc).into());
+        }
+        Ok(&self.data[start..end])
+    }
+
+    /// Decode a slice into a list of little-endian u32s (e.g. a sector from the FAT)
+    fn read_u32s(&self, data: &[u8]) -> Result<Vec<u32>, EtError> {
+        data.chunks_exact(4)
+            .map(|c| u32::extract(c, &Endian::Little))
+            .collect()
+    }
+
+    /// Follow the chain of sectors starting at `start` through `table` and concatenate them,
+    /// reading each sector with `read_sector`.
+    fn follow_chain<'a>(
+        table: &[u32],
+        start: u32,
+        size: Option<u64>,
+        mut read_sector: impl FnMut(u32) -> Result<&'a [u8], EtError>,
+    ) -> Result<Vec<u8>, EtError> {
+        let mut data = Vec::new();
+        let mut loc = start;
+        // each sector can only be visited once so this also protects against loops in the table
+        let mut n_sectors = 0;
+        while loc != END_OF_CHAIN {
+            if size.is_some_and(|s| data.len() as u64 >= s) {
+                break;
+            }
+            if n_sectors > table.len() {
+                return Err("MS CFB sector chain has a loop".into());
+            }
+            data.extend_from_slice(read_sector(loc)?);
+            loc = *table
+                .get(usize::try_from(loc)?)
+                .ok_or_else(|| EtError::from("MS CFB sector chain points outside of the FAT"))?;
+            n_sectors += 1;
+        }
+        if let Some(s) = size {
+            let s = usize::try_from(s)?;
+            if data.len() < s {
+                return Err("MS CFB stream is shorter than its reported size".into());
+            }
+            data.truncate(s);
+        }
+        Ok(data)
+    }
+
+    /// Read a chain of (regular) sectors starting at `start`
+    fn read_chain(&self, start: u32, size: Option<u64>) -> Result<Vec<u8>, EtError> {
+        if start == END_OF_CHAIN || start == FREE_SECTOR {
+            return Ok(Vec::new());
+        }
+        Self::follow_chain(&self.fat, start, size, |loc| self.sector(loc))
+    }
+
+    /// Read a chain of mini sectors out of the mini stream starting at `start`
+    fn read_mini_chain(&self, start: u32, size: u64) -> Result<Vec<u8>, EtError> {
+        let mini_sector_size = self.header.mini_sector_size;
+        Self::follow_chain(&self.minifat, start, Some(size), |loc| {
+            let start = usize::try_from(loc)? * mini_sector_size;
+            self.mini_stream
+                .get(start..start + mini_sector_size)
+                .ok_or_else(|| EtError::from("MS CFB mini sector is outside of the mini stream"))
+        })
+    }
+
+    /// Turn the red-black trees of directory entries into a flat list of entries with paths
+    fn walk_directory(
+        dir_data: &[u8],
+        dir_entries: &[MsCfbDirEntry],
+        first_child: u32,
+    ) -> Result<Vec<MsCfbEntry>, EtError> {
+        let mut entries = Vec::new();
+        let mut seen = vec![false; dir_entries.len()];
+        // the stack holds the entry id, the path of its parent, and if its left subtree has
+        // been visited yet so the entries are listed in the same order they're stored in
+        let mut stack: Vec<(u32, String, bool)> = vec![(first_child, String::new(), false)];
+        while let Some((id, parent, left_done)) = stack.pop() {
+            if id == NO_STREAM {
+                continue;
+            }
+            let ix = usize::try_from(id)?;
+            let entry = dir_entries
+                .get(ix)
+                .ok_or_else(|| EtError::from("MS CFB directory entry is missing"))?;
+            if !left_done {
+                // check when the entry is first reached so a loop can't keep growing the stack
+                if seen[ix] {
+                    return Err("MS CFB directory has a loop".into());
+                }
+                seen[ix] = true;
+                stack.push((id, parent.clone(), true));
+                stack.push((entry.left, parent, false));
+                continue;
+            }
+
+            let raw = &dir_data[ix * 128..ix * 128 + 64];
+            let name_len = usize::from(u16::extract(&dir_data[ix * 128 + 64..], &Endian::Little)?);
+            let name_chars: Vec<u16> = raw[..name_len.min(64)]
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .take_while(|c| *c != 0)
+                .collect();
+            let name = String::from_utf16_lossy(&name_chars);
+            let path = if parent.is_empty() {
+                name
+            } else {
+                format!("{}/{}", parent, name)
+            };
+
+            stack.push((entry.right, parent, false));
+            match entry.entry_type {
+                1 => {
+                    stack.push((entry.child, path.clone(), false));
+                    entries.push(MsCfbEntry {
+                        path,
+                        is_stream: false,
+                        size: 0,
+                        // these are only informational so skip them if they're invalid
+                        created: from_windows_time(entry.created)
+                            .ok()
+                            .filter(|_| entry.created != 0),
+                        modified: from_windows_time(entry.modified)
+                            .ok()
+                            .filter(|_| entry.modified != 0),
+                        start_sector: 0,
+                    });
+                }
+                2 => entries.push(MsCfbEntry {
+                    path,
+                    is_stream: true,
+                    size: entry.size,
+                    created: None,
+                    modified: None,
+                    start_sector: entry.start_sector,
+                }),
+                _ => {}
+            }
+        }
+        Ok(entries)
+    }
+
+    /// All of the streams and storages in the file.
+    #[must_use]
+    pub fn entries(&self) -> &[MsCfbEntry] {
+        &self.entries
+    }
+
+    /// The paths of all of the streams in the file.
+    #[must_use]
+    pub fn streams(&self) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter(|e| e.is_stream)
+            .map(|e| e.path.as_ref())
+            .collect()
+    }
+
+    /// Read the complete contents of the stream at `path`.
+    ///
+    /// # Errors
+    /// If there's no stream at `path` or it can't be read, returns an `EtError`.
+    pub fn read_stream(&self, path: &str) -> Result<Vec<u8>, EtError> {
+        let entry = self
+            .entries
+            .iter()
+            .find(|e| e.is_stream && e.path == path)
+            .ok_or_else(|| EtError::from(format!("No stream named {} in MS CFB file", path)))?;
+        // the version 3 format only stores a 32-bit size so ignore anything in the upper bits
+        let size = if self.header.sector_size == 512 {
+            entry.size & 0xFFFF_FFFF
+        } else {
+            entry.size
+        };
+        if size < self.header.mini_stream_cutoff {
+            self.read_mini_chain(entry.start_sector, size)
+        } else {
+            self.read_chain(entry.start_sector, Some(size))
         }
-        // 76..512 -> DIFAT array of u32s
+    }
 
-        Ok(false)
+    /// Open the stream at `path` as a `ReadBuffer` so it can be passed into another parser.
+    ///
+    /// # Errors
+    /// If there's no stream at `path` or it can't be read, returns an `EtError`.
+    pub fn open(&self, path: &str) -> Result<ReadBuffer<'static>, EtError> {
+        Ok(self.read_stream(path)?.into())
+    }
+}
+
+/// A reader listing all of the streams and storages in a Compound File Binary file.
+#[derive(Debug)]
+pub struct MsCfbReader {
+    file: MsCfbFile,
+    cur_entry: usize,
+}
+
+impl MsCfbReader {
+    /// Create a new instance of the reader
+    ///
+    /// # Errors
+    /// If the file can't be read or isn't a valid Compound File Binary file, returns an `EtError`.
+    pub fn new<'r, B>(data: B, _params: Option<()>) -> Result<Self, EtError>
+    where
+        B: TryInto<ReadBuffer<'r>>,
+        EtError: From<<B as TryInto<ReadBuffer<'r>>>::Error>,
+    {
+        Ok(MsCfbReader {
+            file: MsCfbFile::new(data)?,
+            cur_entry: 0,
+        })
+    }
+
+    /// The underlying file (e.g. to open one of the streams listed)
+    #[must_use]
+    pub fn file(&self) -> &MsCfbFile {
+        &self.file
+    }
+}
+
+impl RecordReader for MsCfbReader {
+    /// The next stream or storage in the file.
+    fn next_record(&mut self) -> Result<Option<Vec<Value>>, EtError> {
+        let entry = match self.file.entries.get(self.cur_entry) {
+            Some(e) => e,
+            None => return Ok(None),
+        };
+        self.cur_entry += 1;
+        Ok(Some(vec![
+            entry.path.as_str().into(),
+            if entry.is_stream { "stream" } else { "storage" }.into(),
+            entry.size.into(),
+            entry.created.into(),
+            entry.modified.into(),
+        ]))
     }
 
-    fn get(&mut self, _buffer: &'r [u8], _state: &Self::State) -> Result<(), EtError> {
+    /// The headers for this Reader.
+    fn headers(&self) -> Vec<String> {
+        ["path", "type", "size", "created", "modified"]
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    /// The metadata for this Reader.
+    fn metadata(&self) -> BTreeMap<String, Value> {
+        let mut metadata = BTreeMap::new();
+        drop(
+            metadata.insert(
+                "sector_size".to_string(),
+                u64::try_from(self.file.header.sector_size)
+                    .unwrap_or(0)
+                    .into(),
+            ),
+        );
+        metadata
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_ms_cfb_streams() -> Result<(), EtError> {
+        let data: &[u8] = include_bytes!("../../tests/data/test.cfb");
+        let file = MsCfbFile::new(data)?;
+        assert_eq!(file.streams(), ["Data/Big", "Data/Nested small", "Small"]);
+        assert_eq!(file.entries()[0].path, "Data");
+        assert!(!file.entries()[0].is_stream);
+        assert_eq!(
+            file.entries()[0].modified,
+            NaiveDate::from_ymd_opt(2021, 3, 21).and_then(|d| d.and_hms_opt(10, 11, 12))
+        );
+
+        // stored in the mini stream
+        let small = file.read_stream("Small")?;
+        assert_eq!(small.len(), 92);
+        assert!(small.starts_with(b"A short stream"));
+        let nested = file.read_stream("Data/Nested small")?;
+        assert_eq!(nested.len(), 286);
+        assert_eq!(nested[255], 255);
+        assert!(nested.ends_with(b"nested mini stream"));
+
+        // stored in regular sectors listed in a FAT sector that's only in the DIFAT
+        let big = file.read_stream("Data/Big")?;
+        assert_eq!(big.len(), 70_000);
+        assert!(big
+            .iter()
+            .enumerate()
+            .all(|(i, b)| usize::from(*b) == (i * 7) % 251));
+
+        assert!(file.read_stream("Missing").is_err());
+        assert!(file.read_stream("Data").is_err());
         Ok(())
     }
+
+    #[test]
+    fn test_ms_cfb_open() -> Result<(), EtError> {
+        let data: &[u8] = include_bytes!("../../tests/data/test.cfb");
+        let file = MsCfbFile::new(data)?;
+        let mut rb = file.open("Data/Nested small")?;
+        assert_eq!(rb.as_ref().len(), 286);
+        assert!(rb.eof);
+        assert_eq!(rb.next::<&[u8]>(&mut 4)?, Some(&[0, 1, 2, 3][..]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ms_cfb_reader() -> Result<(), EtError> {
+        let data: &[u8] = include_bytes!("../../tests/data/test.cfb");
+        let mut reader = MsCfbReader::new(data, None)?;
+        assert_eq!(
+            reader.headers(),
+            ["path", "type", "size", "created", "modified"]
+        );
+        let mut paths = Vec::new();
+        while let Some(record) = reader.next_record()? {
+            paths.push(record[0].clone().into_string()?);
+        }
+        assert_eq!(paths, ["Data", "Data/Big", "Data/Nested small", "Small"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ms_cfb_bad_data() {
+        let data: &[u8] = b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1 too short";
+        assert!(MsCfbFile::new(data).is_err());
+        let data: &[u8] = include_bytes!("../../tests/data/test_fid.ch");
+        assert!(MsCfbFile::new(data).is_err());
+
+        // make the "Small" entry its own left sibling
+        let mut data = include_bytes!("../../tests/data/test.cfb").to_vec();
+        data[2048 + 128 + 68..2048 + 128 + 72].copy_from_slice(&1u32.to_le_bytes());
+        assert!(MsCfbFile::new(&data[..]).is_err());
+
+        // make the last sector a DIFAT sector that points back to itself
+        let mut data = include_bytes!("../../tests/data/test.cfb").to_vec();
+        let last_sector = u32::try_from(data.len() / 512 - 2).unwrap();
+        data[68..72].copy_from_slice(&last_sector.to_le_bytes());
+        data[72..76].copy_from_slice(&2u32.to_le_bytes());
+        let end = data.len();
+        data[end - 4..].copy_from_slice(&last_sector.to_le_bytes());
+        let err = MsCfbFile::new(&data[..]).expect_err("DIFAT loop should fail");
+        assert!(err.to_string().contains("loop"));
+
+        // or claims to have more DIFAT sectors than there are sectors
+        let mut data = include_bytes!("../../tests/data/test.cfb").to_vec();
+        data[72..76].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(MsCfbFile::new(&data[..]).is_err());
+    }
 }
@@ -11,21 +11,45 @@ pub mod common;
 pub mod fasta;
 /// Reader for FASTQ bioinformatics format
 pub mod fastq;
+/// Reader for fixed-width text tables
+pub mod fixed_width;
 /// Reader for flow data
 pub mod flow;
+/// Reader for HDF5 files (groups, datasets, and their attributes)
+#[cfg(feature = "std")]
+pub mod hdf5;
 /// Reader for Inficon Hapsite MS formats
 pub mod inficon;
+/// Reader for Mascot Generic Format (MGF) peak lists
+pub mod mgf;
+/// Helpers for formats written by Microsoft software (e.g. Windows timestamps)
+pub mod microsoft_common;
+/// Reader for the mzML mass spectrometry format
+#[cfg(feature = "std")]
+pub mod mzml;
+/// Reader for the mzXML mass spectrometry format
+#[cfg(feature = "std")]
+pub mod mzxml;
+/// Reader for netCDF classic files (e.g. ANDI-MS and ANDI-Chrom chromatography data)
+pub mod netcdf;
 /// Reader for PNG image format
 #[cfg(feature = "std")]
 pub mod png;
 /// Reader for BAM/SAM bioinformatics formats
 pub mod sam;
+/// Regrouping of mass spectrometry readers into one record per scan
+pub mod spectrum;
 /// Readers for Thermo formats
 pub mod thermo;
+/// Reader for TIFF and OME-TIFF image formats
+#[cfg(feature = "std")]
+pub mod tiff;
 /// Readers for tab-seperated text format
 pub mod tsv;
 /// Helpers for TSV parsing
 pub mod tsv_inference;
+/// Helpers for XML-based formats (tags, attributes, and base64-encoded data)
+pub mod xml_common;
 // /// Reader for generic XML
 // pub mod xml;
 
@@ -0,0 +1,877 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use std::io::Read;
+
+use chrono::NaiveDateTime;
+use flate2::read::ZlibDecoder;
+
+use crate::impl_reader;
+use crate::parsers::xml_common::{
+    decode_base64, element_end, element_text, XmlTag, XmlTagKind, XmlTags,
+};
+use crate::parsers::FromSlice;
+use crate::record::{StateMetadata, Value};
+use crate::EtError;
+
+/// The names of the fragmentation methods for each CV term (using the same names as the
+/// Thermo RAW reader)
+const ACTIVATIONS: [(&str, &str); 7] = [
+    ("MS:1000133", "cid"),
+    ("MS:1000250", "ecd"),
+    ("MS:1000262", "irmpd"),
+    ("MS:1000422", "hcd"),
+    ("MS:1000435", "mpd"),
+    ("MS:1000598", "etd"),
+    ("MS:1000599", "pqd"),
+];
+
+/// How the numbers in a binary array are stored
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MzmlNumber {
+    Float32,
+    Float64,
+    Int32,
+    Int64,
+}
+
+/// How a binary array was compressed
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MzmlCompression {
+    None,
+    Linear,
+    Pic,
+    Slof,
+}
+
+/// Which values a binary array holds
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MzmlArray {
+    Mz,
+    Intensity,
+    Time,
+    Other,
+}
+
+/// A `cvParam` tag with its attributes pulled out
+#[derive(Clone, Debug, Default)]
+struct CvParam {
+    accession: String,
+    name: String,
+    value: String,
+    unit: String,
+}
+
+impl CvParam {
+    fn from_tag(tag: &XmlTag) -> Self {
+        let attr = |key| tag.attr(key).unwrap_or_default().into_owned();
+        CvParam {
+            accession: attr("accession"),
+            name: attr("name"),
+            value: attr("value"),
+            unit: attr("unitAccession"),
+        }
+    }
+
+    fn float(&self) -> Option<f64> {
+        self.value.trim().parse().ok()
+    }
+
+    /// The value in minutes (if the unit is seconds or minutes)
+    fn minutes(&self) -> Option<f64> {
+        self.float().map(|v| {
+            if self.unit == "UO:0000010" {
+                v / 60.
+            } else {
+                v
+            }
+        })
+    }
+}
+
+/// Split the half-bytes of MS-Numpress data back into the integers packed inside
+fn numpress_ints(data: &[u8]) -> Result<Vec<i32>, EtError> {
+    let nibble = |i: usize| (data[i / 2] >> (4 * (1 - i % 2))) & 0xF;
+    let n_nibbles = 2 * data.len();
+    let mut ints = Vec::new();
+    let mut pos = 0;
+    while pos < n_nibbles {
+        // the last half-byte may just be padding
+        if pos == n_nibbles - 1 && nibble(pos) == 0 {
+            break;
+        }
+        // the first half-byte is the number of leading zeros (or 8 + the number of leading Fs)
+        let head = nibble(pos);
+        pos += 1;
+        let (n, mut value) = if head <= 8 {
+            (usize::from(head), 0u32)
+        } else {
+            let n = usize::from(head - 8);
+            (n, (0..n).fold(0, |v, i| v | (0xF000_0000 >> (4 * i))))
+        };
+        if n_nibbles - pos < 8 - n {
+            return Err("MS-Numpress data was truncated".into());
+        }
+        for i in n..8 {
+            value |= u32::from(nibble(pos)) << (4 * (i - n));
+            pos += 1;
+        }
+        ints.push(i32::from_ne_bytes(value.to_ne_bytes()));
+    }
+    Ok(ints)
+}
+
+/// Read the fixed point at the start of MS-Numpress linear and slof data
+fn numpress_fixed_point(data: &[u8]) -> Result<f64, EtError> {
+    if data.len() < 8 {
+        return Err("MS-Numpress data is missing its fixed point".into());
+    }
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&data[..8]);
+    Ok(f64::from_be_bytes(bytes))
+}
+
+/// Decode data compressed with MS-Numpress "linear prediction" compression
+fn decode_numpress_linear(data: &[u8]) -> Result<Vec<f64>, EtError> {
+    let fixed_point = numpress_fixed_point(data)?;
+    // the first two values are stored as is and the rest are the differences from a linear
+    // extrapolation of the previous two values
+    let mut ints = [0i64; 3];
+    let mut values = Vec::new();
+    for (ix, chunk) in data[8..].chunks(4).take(2).enumerate() {
+        if chunk.len() < 4 {
+            return Err("MS-Numpress linear data was truncated".into());
+        }
+        ints[ix + 1] = i64::from(u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]));
+        #[allow(clippy::cast_precision_loss)]
+        values.push(ints[ix + 1] as f64 / fixed_point);
+    }
+    if data.len() > 16 {
+        for diff in numpress_ints(&data[16..])? {
+            let extrapolated = 2 * ints[2] - ints[1];
+            ints[1] = ints[2];
+            ints[2] = extrapolated + i64::from(diff);
+            #[allow(clippy::cast_precision_loss)]
+            values.push(ints[2] as f64 / fixed_point);
+        }
+    }
+    Ok(values)
+}
+
+/// Decode data compressed with MS-Numpress "positive integer" compression
+fn decode_numpress_pic(data: &[u8]) -> Result<Vec<f64>, EtError> {
+    Ok(numpress_ints(data)?
+        .into_iter()
+        .map(|i| f64::from(u32::from_ne_bytes(i.to_ne_bytes())))
+        .collect())
+}
+
+/// Decode data compressed with MS-Numpress "short logged float" compression
+fn decode_numpress_slof(data: &[u8]) -> Result<Vec<f64>, EtError> {
+    let fixed_point = numpress_fixed_point(data)?;
+    Ok(data[8..]
+        .chunks_exact(2)
+        .map(|c| (f64::from(u16::from_le_bytes([c[0], c[1]])) / fixed_point).exp() - 1.)
+        .collect())
+}
+
+/// Decode a `binaryDataArray` element into the kind of values it holds and the values themselves
+fn decode_binary_array(element: &[u8]) -> Result<(MzmlArray, Vec<f64>), EtError> {
+    let mut number = MzmlNumber::Float64;
+    let mut zlib = false;
+    let mut compression = MzmlCompression::None;
+    let mut kind = MzmlArray::Other;
+    let mut in_minutes = true;
+    let mut text: &[u8] = b"";
+    let mut tags = XmlTags::new(element);
+    while let Some(tag) = tags.next() {
+        match (tag.name, tag.kind) {
+            ("cvParam", _) => {
+                let param = CvParam::from_tag(&tag);
+                match param.accession.as_ref() {
+                    "MS:1000519" => number = MzmlNumber::Int32,
+                    "MS:1000521" => number = MzmlNumber::Float32,
+                    "MS:1000522" => number = MzmlNumber::Int64,
+                    "MS:1000523" => number = MzmlNumber::Float64,
+                    "MS:1000574" => zlib = true,
+                    "MS:1002312" => compression = MzmlCompression::Linear,
+                    "MS:1002313" => compression = MzmlCompression::Pic,
+                    "MS:1002314" => compression = MzmlCompression::Slof,
+                    "MS:1002746" | "MS:1002747" | "MS:1002748" => {
+                        zlib = true;
+                        compression = match param.accession.as_ref() {
+                            "MS:1002746" => MzmlCompression::Linear,
+                            "MS:1002747" => MzmlCompression::Pic,
+                            _ => MzmlCompression::Slof,
+                        };
+                    }
+                    "MS:1000514" => kind = MzmlArray::Mz,
+                    "MS:1000515" => kind = MzmlArray::Intensity,
+                    "MS:1000595" => {
+                        kind = MzmlArray::Time;
+                        in_minutes = param.unit != "UO:0000010";
+                    }
+                    _ => {}
+                }
+            }
+            ("binary", XmlTagKind::Open) => {
+                text = element_text(element, &tag)
+                    .ok_or_else(|| EtError::from("mzML binary tag was never closed"))?;
+                // skip over the base64 text
+                tags.skip_to(tag.end + text.len());
+            }
+            _ => {}
+        }
+    }
+    if kind == MzmlArray::Other {
+        return Ok((kind, Vec::new()));
+    }
+
+    let mut data = decode_base64(text)?;
+    if zlib && !data.is_empty() {
+        let mut decompressed = Vec::new();
+        let _ = ZlibDecoder::new(&data[..]).read_to_end(&mut decompressed)?;
+        data = decompressed;
+    }
+    let mut values = match compression {
+        MzmlCompression::Linear => decode_numpress_linear(&data)?,
+        MzmlCompression::Pic => decode_numpress_pic(&data)?,
+        MzmlCompression::Slof => decode_numpress_slof(&data)?,
+        MzmlCompression::None => match number {
+            MzmlNumber::Float32 => data
+                .chunks_exact(4)
+                .map(|c| f64::from(f32::from_le_bytes([c[0], c[1], c[2], c[3]])))
+                .collect(),
+            MzmlNumber::Float64 => data
+                .chunks_exact(8)
+                .map(|c| f64::from_le_bytes([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]]))
+                .collect(),
+            MzmlNumber::Int32 => data
+                .chunks_exact(4)
+                .map(|c| f64::from(i32::from_le_bytes([c[0], c[1], c[2], c[3]])))
+                .collect(),
+            #[allow(clippy::cast_precision_loss)]
+            MzmlNumber::Int64 => data
+                .chunks_exact(8)
+                .map(|c| {
+                    i64::from_le_bytes([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]]) as f64
+                })
+                .collect(),
+        },
+    };
+    if kind == MzmlArray::Time && !in_minutes {
+        values.iter_mut().for_each(|t| *t /= 60.);
+    }
+    Ok((kind, values))
+}
+
+/// Information about the mzML file and the run in it
+#[derive(Clone, Debug, Default)]
+pub struct MzmlMetadata {
+    /// The ID of the run
+    pub run_id: String,
+    /// The date the sample was run
+    pub run_date: Option<NaiveDateTime>,
+    /// The name of the file the data was converted from
+    pub source_file: String,
+    /// The model of the instrument
+    pub instrument: String,
+    /// The serial number of the instrument
+    pub instrument_serial: String,
+    /// The software used to acquire or convert the data
+    pub software: String,
+    /// The version of that software
+    pub software_version: String,
+}
+
+impl MzmlMetadata {
+    /// Read the metadata out of everything in the file before the `run` tag.
+    fn from_header(header: &[u8]) -> Self {
+        let mut metadata = MzmlMetadata::default();
+        let mut groups: BTreeMap<String, Vec<CvParam>> = BTreeMap::new();
+        let mut instrument_params: Vec<CvParam> = Vec::new();
+        let mut cur_group: Option<String> = None;
+        let mut in_software = false;
+        let mut in_instrument = false;
+        let mut seen_instrument = false;
+        let mut in_components = false;
+        for tag in XmlTags::new(header) {
+            match (tag.name, tag.kind) {
+                ("run", _) => {
+                    metadata.run_id = tag.attr("id").unwrap_or_default().into_owned();
+                    metadata.run_date = tag
+                        .attr("startTimeStamp")
+                        .and_then(|d| Value::from_iso_date(&d).ok())
+                        .and_then(|d| match d {
+                            Value::Datetime(d) => Some(d),
+                            _ => None,
+                        });
+                }
+                ("sourceFile", XmlTagKind::Open | XmlTagKind::SelfClose)
+                    if metadata.source_file.is_empty() =>
+                {
+                    metadata.source_file = tag.attr("name").unwrap_or_default().into_owned();
+                }
+                ("software", XmlTagKind::Open) => {
+                    in_software = metadata.software.is_empty();
+                    if in_software {
+                        metadata.software_version =
+                            tag.attr("version").unwrap_or_default().into_owned();
+                    }
+                }
+                ("software", XmlTagKind::Close) => in_software = false,
+                ("referenceableParamGroup", XmlTagKind::Open) => {
+                    cur_group = tag.attr("id").map(|c| c.into_owned());
+                }
+                ("referenceableParamGroup", XmlTagKind::Close) => cur_group = None,
+                ("instrumentConfiguration", XmlTagKind::Open) => {
+                    in_instrument = !seen_instrument;
+                    seen_instrument = true;
+                }
+                ("instrumentConfiguration", XmlTagKind::Close) => in_instrument = false,
+                ("componentList", XmlTagKind::Open) => in_components = true,
+                ("componentList", XmlTagKind::Close) => in_components = false,
+                ("referenceableParamGroupRef", _) if in_instrument && !in_components => {
+                    let group = tag.attr("ref").unwrap_or_default();
+                    if let Some(params) = groups.get(group.as_ref()) {
+                        instrument_params.extend(params.iter().cloned());
+                    }
+                }
+                ("cvParam", _) => {
+                    let param = CvParam::from_tag(&tag);
+                    if in_software && metadata.software.is_empty() {
+                        metadata.software.clone_from(&param.name);
+                    } else if let Some(group) = &cur_group {
+                        groups.entry(group.clone()).or_default().push(param);
+                    } else if in_instrument && !in_components {
+                        instrument_params.push(param);
+                    }
+                }
+                _ => {}
+            }
+        }
+        // the instrument model is a valueless term, but there's no way to know which terms are
+        // models without the full ontology so we take the first one
+        for param in instrument_params {
+            if param.accession == "MS:1000529" {
+                metadata.instrument_serial = param.value;
+            } else if param.value.is_empty() && metadata.instrument.is_empty() {
+                metadata.instrument = param.name;
+            }
+        }
+        metadata
+    }
+}
+
+impl<'r> From<&MzmlMetadata> for BTreeMap<String, Value<'r>> {
+    fn from(metadata: &MzmlMetadata) -> Self {
+        let mut map = BTreeMap::new();
+        drop(map.insert("run_id".to_string(), metadata.run_id.clone().into()));
+        drop(map.insert("run_date".to_string(), metadata.run_date.into()));
+        drop(map.insert(
+            "source_file".to_string(),
+            metadata.source_file.clone().into(),
+        ));
+        drop(map.insert("instrument".to_string(), metadata.instrument.clone().into()));
+        drop(map.insert(
+            "instrument_serial".to_string(),
+            metadata.instrument_serial.clone().into(),
+        ));
+        drop(map.insert("software".to_string(), metadata.software.clone().into()));
+        drop(map.insert(
+            "software_version".to_string(),
+            metadata.software_version.clone().into(),
+        ));
+        map
+    }
+}
+
+/// Parameters for the `MzmlReader`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MzmlParams {
+    scan_info: bool,
+}
+
+impl MzmlParams {
+    /// Also return the scan number and the other information about the scan (MS level, filter,
+    /// precursor, etc) with every point, and the points from any chromatograms in the file
+    #[must_use]
+    pub fn scan_info(mut self, scan_info: bool) -> Self {
+        self.scan_info = scan_info;
+        self
+    }
+}
+
+/// The current state of the mzML parser
+#[derive(Clone, Debug, Default)]
+pub struct MzmlState {
+    scan_info: bool,
+    metadata: MzmlMetadata,
+    times: Vec<f64>,
+    mzs: Vec<f64>,
+    intensities: Vec<f64>,
+    n_points: usize,
+    cur_point: usize,
+    cur_time: f64,
+    cur_scan: Option<u32>,
+    cur_ms_level: Option<u8>,
+    cur_polarity: &'static str,
+    cur_filter: Option<String>,
+    cur_precursor_mz: Option<f64>,
+    cur_charge: Option<i32>,
+    cur_activation: Option<&'static str>,
+    cur_injection_time: Option<f64>,
+    cur_centroid: bool,
+    cur_chromatogram: Option<String>,
+}
+
+impl MzmlState {
+    /// Read everything in a `spectrum` or `chromatogram` element into the state so the points in
+    /// it can be returned one by one.
+    fn read_element(&mut self, element: &[u8], tag: &XmlTag) -> Result<(), EtError> {
+        let is_chromatogram = tag.name == "chromatogram";
+        self.cur_chromatogram = if is_chromatogram {
+            Some(tag.attr("id").unwrap_or_default().into_owned())
+        } else {
+            None
+        };
+        // prefer the vendor's scan number from the native ID (e.g. "... scan=12") and fall back
+        // to the 1-based index of the spectrum
+        self.cur_scan = if is_chromatogram {
+            None
+        } else {
+            let id = tag.attr("id").unwrap_or_default();
+            id.split_whitespace()
+                .find_map(|part| part.strip_prefix("scan=")?.parse().ok())
+                .or_else(|| {
+                    let index: u32 = tag.attr("index")?.trim().parse().ok()?;
+                    index.checked_add(1)
+                })
+        };
+        self.cur_time = 0.;
+        self.cur_ms_level = None;
+        self.cur_polarity = "";
+        self.cur_filter = None;
+        self.cur_precursor_mz = None;
+        self.cur_charge = None;
+        self.cur_activation = None;
+        self.cur_injection_time = None;
+        self.cur_centroid = false;
+        self.times.clear();
+        self.mzs.clear();
+        self.intensities.clear();
+
+        let mut isolation_mz = None;
+        let mut product_mz = None;
+        let mut in_product = false;
+        let mut tags = XmlTags::new(element);
+        while let Some(t) = tags.next() {
+            match (t.name, t.kind) {
+                ("product", XmlTagKind::Open) => in_product = true,
+                ("product", XmlTagKind::Close) => in_product = false,
+                ("binaryDataArray", XmlTagKind::Open) => {
+                    let end = element_end(element, &t)
+                        .ok_or_else(|| EtError::from("mzML binaryDataArray was never closed"))?;
+                    let (kind, values) = decode_binary_array(&element[t.start..end])?;
+                    match kind {
+                        MzmlArray::Mz => self.mzs = values,
+                        MzmlArray::Intensity => self.intensities = values,
+                        MzmlArray::Time => self.times = values,
+                        MzmlArray::Other => {}
+                    }
+                    tags.skip_to(end);
+                }
+                ("cvParam", _) => {
+                    let param = CvParam::from_tag(&t);
+                    match param.accession.as_ref() {
+                        "MS:1000511" => self.cur_ms_level = param.value.trim().parse().ok(),
+                        "MS:1000129" => self.cur_polarity = "-",
+                        "MS:1000130" => self.cur_polarity = "+",
+                        "MS:1000127" => self.cur_centroid = true,
+                        "MS:1000128" => self.cur_centroid = false,
+                        "MS:1000016" => self.cur_time = param.minutes().unwrap_or_default(),
+                        "MS:1000512" => self.cur_filter = Some(param.value),
+                        "MS:1000927" => self.cur_injection_time = param.float(),
+                        "MS:1000744" => self.cur_precursor_mz = param.float(),
+                        "MS:1000041" => self.cur_charge = param.value.trim().parse().ok(),
+                        "MS:1000827" if in_product => product_mz = param.float(),
+                        "MS:1000827" => isolation_mz = param.float(),
+                        acc => {
+                            if let Some((_, name)) = ACTIVATIONS.iter().find(|(a, _)| *a == acc) {
+                                self.cur_activation = Some(name);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        // chromatograms (and some spectra) only have the isolation window and not the selected
+        // ion so fall back to that
+        if self.cur_precursor_mz.is_none() {
+            self.cur_precursor_mz = isolation_mz;
+        }
+
+        if is_chromatogram {
+            if self.times.len() != self.intensities.len() {
+                return Err("mzML time and intensity arrays have different lengths".into());
+            }
+            self.mzs = vec![product_mz.unwrap_or(f64::NAN); self.times.len()];
+        } else {
+            if self.mzs.len() != self.intensities.len() {
+                return Err("mzML m/z and intensity arrays have different lengths".into());
+            }
+            self.times = vec![self.cur_time; self.mzs.len()];
+        }
+        self.n_points = self.times.len();
+        self.cur_point = 0;
+        Ok(())
+    }
+}
+
+impl StateMetadata for MzmlState {
+    fn metadata(&self) -> BTreeMap<String, Value> {
+        (&self.metadata).into()
+    }
+
+    fn header(&self) -> Vec<&str> {
+        if !self.scan_info {
+            return vec!["time", "mz", "intensity"];
+        }
+        vec![
+            "time",
+            "scan",
+            "mz",
+            "intensity",
+            "ms_level",
+            "polarity",
+            "filter",
+            "precursor_mz",
+            "charge",
+            "activation",
+            "injection_time",
+            "centroid",
+            "chromatogram",
+        ]
+    }
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for MzmlState {
+    type State = MzmlParams;
+
+    fn parse(
+        buffer: &[u8],
+        eof: bool,
+        consumed: &mut usize,
+        _state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        // read everything up to the start of the run so the metadata is available
+        let mut is_mzml = false;
+        for tag in XmlTags::new(buffer) {
+            match tag.name {
+                "mzML" | "indexedmzML" => is_mzml = true,
+                "run" if is_mzml => {
+                    *consumed += tag.end;
+                    return Ok(true);
+                }
+                _ => {}
+            }
+        }
+        if eof {
+            return Err("mzML file has no run in it".into());
+        }
+        Err(EtError::from("Could not find the start of the mzML run").incomplete())
+    }
+
+    fn get(&mut self, buffer: &'b [u8], params: &'s Self::State) -> Result<(), EtError> {
+        self.scan_info = params.scan_info;
+        self.metadata = MzmlMetadata::from_header(buffer);
+        Ok(())
+    }
+}
+
+/// A single point from a spectrum or chromatogram in an mzML file
+#[derive(Clone, Debug, Default)]
+pub struct MzmlRecord<'r> {
+    /// The time the reading was taken at (minutes)
+    pub time: f64,
+    /// The number of the scan this was recorded in (not set for chromatograms)
+    pub scan: Option<u32>,
+    /// The mz value of the reading (for chromatograms, only set if they're for a product ion)
+    pub mz: Option<f64>,
+    /// The intensity recorded
+    pub intensity: f64,
+    /// The MS level of the scan (e.g. 1 for full scans, 2 for product ion scans)
+    pub ms_level: Option<u8>,
+    /// The polarity of the scan ("+" or "-")
+    pub polarity: &'r str,
+    /// The vendor's filter describing the scan
+    pub filter: Option<&'r str>,
+    /// The m/z of the precursor ion fragmented for this scan
+    pub precursor_mz: Option<f64>,
+    /// The charge of the precursor ion, if it could be determined
+    pub charge: Option<i32>,
+    /// How the precursor ion was fragmented (e.g. "cid" or "hcd")
+    pub activation: Option<&'r str>,
+    /// How long ions were accumulated for this scan (milliseconds)
+    pub injection_time: Option<f64>,
+    /// If the scan was recorded as centroided peaks instead of a profile
+    pub centroid: bool,
+    /// The ID of the chromatogram this point is from (if it's not from a spectrum)
+    pub chromatogram: Option<&'r str>,
+    scan_info: bool,
+}
+
+impl<'r> From<MzmlRecord<'r>> for Vec<Value<'r>> {
+    fn from(record: MzmlRecord<'r>) -> Self {
+        if !record.scan_info {
+            return vec![
+                record.time.into(),
+                record.mz.into(),
+                record.intensity.into(),
+            ];
+        }
+        vec![
+            record.time.into(),
+            record.scan.into(),
+            record.mz.into(),
+            record.intensity.into(),
+            record.ms_level.into(),
+            record.polarity.into(),
+            record.filter.into(),
+            record.precursor_mz.into(),
+            record.charge.into(),
+            record.activation.into(),
+            record.injection_time.into(),
+            record.centroid.into(),
+            record.chromatogram.into(),
+        ]
+    }
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for MzmlRecord<'s> {
+    type State = MzmlState;
+
+    fn parse(
+        buffer: &[u8],
+        eof: bool,
+        consumed: &mut usize,
+        state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        if state.cur_point < state.n_points {
+            state.cur_point += 1;
+            return Ok(true);
+        }
+        // find the next spectrum or chromatogram with data in it
+        let mut tags = XmlTags::new(buffer);
+        let mut con = 0;
+        while let Some(tag) = tags.next() {
+            match (tag.name, tag.kind) {
+                ("spectrum" | "chromatogram", XmlTagKind::Open | XmlTagKind::SelfClose) => {
+                    let end = match element_end(buffer, &tag) {
+                        Some(end) => end,
+                        None if eof => return Err("mzML file ended in the middle of a scan".into()),
+                        None => {
+                            return Err(EtError::from("Incomplete mzML scan").incomplete());
+                        }
+                    };
+                    con = end;
+                    // chromatograms don't have m/zs so they're only returned with the scan info
+                    if tag.name == "chromatogram" && !state.scan_info {
+                        tags.skip_to(end);
+                        continue;
+                    }
+                    state.read_element(&buffer[tag.start..end], &tag)?;
+                    if state.n_points > 0 {
+                        state.cur_point = 1;
+                        *consumed += con;
+                        return Ok(true);
+                    }
+                    tags.skip_to(end);
+                }
+                ("run", XmlTagKind::Close) => {
+                    *consumed += con;
+                    return Ok(false);
+                }
+                _ => {}
+            }
+        }
+        if eof {
+            *consumed += con;
+            return Ok(false);
+        }
+        Err(EtError::from("Incomplete mzML file").incomplete())
+    }
+
+    fn get(&mut self, _buffer: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
+        let ix = state.cur_point - 1;
+        self.time = state.times[ix];
+        self.scan = state.cur_scan;
+        self.mz = Some(state.mzs[ix]).filter(|mz| !mz.is_nan());
+        self.intensity = state.intensities[ix];
+        self.ms_level = state.cur_ms_level;
+        self.polarity = state.cur_polarity;
+        self.filter = state.cur_filter.as_deref();
+        self.precursor_mz = state.cur_precursor_mz;
+        self.charge = state.cur_charge;
+        self.activation = state.cur_activation;
+        self.injection_time = state.cur_injection_time;
+        self.centroid = state.cur_centroid;
+        self.chromatogram = state.cur_chromatogram.as_deref();
+        self.scan_info = state.scan_info;
+        Ok(())
+    }
+}
+
+impl_reader!(
+    MzmlReader,
+    MzmlRecord,
+    MzmlRecord<'r>,
+    MzmlState,
+    MzmlParams
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::readers::RecordReader;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_mzml_reader() -> Result<(), EtError> {
+        let data: &[u8] = include_bytes!("../../tests/data/small.mzML");
+        let params = MzmlParams::default().scan_info(true);
+        let mut reader = MzmlReader::new(data, Some(params))?;
+        let metadata = reader.metadata();
+        assert_eq!(metadata["run_id"], "small".into());
+        assert_eq!(
+            metadata["run_date"],
+            NaiveDate::from_ymd_opt(2005, 7, 20)
+                .and_then(|d| d.and_hms_opt(14, 44, 22))
+                .into()
+        );
+        assert_eq!(metadata["source_file"], "".into());
+        assert_eq!(metadata["instrument"], "LTQ FT".into());
+        assert_eq!(metadata["instrument_serial"], "SN06061F".into());
+        assert_eq!(
+            metadata["software"],
+            "custom unreleased software tool".into()
+        );
+        assert_eq!(metadata["software_version"], "".into());
+
+        // zlib-compressed 64-bit m/zs and uncompressed 32-bit intensities
+        let MzmlRecord {
+            time,
+            scan,
+            mz,
+            intensity,
+            ms_level,
+            polarity,
+            filter,
+            precursor_mz,
+            centroid,
+            chromatogram,
+            ..
+        } = reader.next()?.unwrap();
+        assert!((time - 0.004935).abs() < 1e-9);
+        assert_eq!(scan, Some(1));
+        assert_eq!(mz, Some(202.606_823_482_713_76));
+        assert!((intensity - 1_938.117_431_640_625).abs() < 1e-9);
+        assert_eq!(ms_level, Some(1));
+        assert_eq!(polarity, "+");
+        assert_eq!(filter, Some("FTMS + p ESI Full ms [200.00-2000.00]"));
+        assert_eq!(precursor_mz, None);
+        assert!(!centroid);
+        assert_eq!(chromatogram, None);
+
+        let mut n_points = 1;
+        while let Some(record) = reader.next()? {
+            n_points += 1;
+            if n_points == 121 {
+                // numpress linear m/zs and slof intensities, with the time in seconds
+                assert!((record.time - 0.007_896_666).abs() < 1e-6);
+                assert!((record.mz.unwrap() - 200.).abs() < 1e-5);
+                assert!((record.intensity - 449.05).abs() / 449.05 < 1e-3);
+            } else if n_points == 221 {
+                // numpress linear + zlib m/zs and pic intensities
+                assert!((record.mz.unwrap() - 231.388_839_7).abs() < 1e-5);
+                assert_eq!(record.scan, Some(3));
+                assert!((record.intensity - 27.).abs() < 1e-9);
+                assert_eq!(record.ms_level, Some(2));
+                assert_eq!(record.precursor_mz, Some(810.789_428_710_937_5));
+                assert_eq!(record.charge, Some(2));
+                assert_eq!(record.activation, Some("cid"));
+                assert!((record.injection_time.unwrap() - 7.993_01).abs() < 1e-9);
+                assert!(record.centroid);
+            } else if n_points == 301 {
+                // the empty spectrum is skipped and then the TIC is read
+                assert_eq!(record.chromatogram, Some("TIC"));
+                assert_eq!(record.scan, None);
+                assert_eq!(record.mz, None);
+                assert_eq!(record.ms_level, None);
+                assert!((record.time - 0.004935).abs() < 1e-9);
+                assert!((record.intensity - 276_459.1).abs() < 0.1);
+            }
+        }
+        assert_eq!(n_points, 303);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mzml_default_columns() -> Result<(), EtError> {
+        let data: &[u8] = include_bytes!("../../tests/data/small.mzML");
+        let mut reader = MzmlReader::new(data, None)?;
+        assert_eq!(reader.headers(), ["time", "mz", "intensity"]);
+        let record: Vec<Value> = reader.next()?.unwrap().into();
+        assert_eq!(record.len(), 3);
+        assert_eq!(record[1], Value::Float(202.606_823_482_713_76));
+
+        // the points from the TIC aren't returned
+        let mut n_points = 1;
+        while let Some(record) = reader.next()? {
+            assert!(record.mz.is_some());
+            n_points += 1;
+        }
+        assert_eq!(n_points, 300);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mzml_spectrum() -> Result<(), EtError> {
+        let data: &[u8] = include_bytes!("../../tests/data/small.mzML");
+        let mut params = BTreeMap::new();
+        drop(params.insert("spectrum".to_string(), Value::Boolean(true)));
+        let (mut reader, parser) = crate::readers::get_reader(data, None, Some(params))?;
+        assert_eq!(parser, "mzml");
+        let mut n_scans = 0;
+        while reader.next_record()?.is_some() {
+            n_scans += 1;
+        }
+        // the empty spectrum and the TIC aren't included
+        assert_eq!(n_scans, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_numpress() -> Result<(), EtError> {
+        // each integer is a half-byte with the number of leading 0s (or Fs) and the rest
+        let ints = numpress_ints(&[0x87, 0x1F, 0xF7, 0x18, 0xD4, 0xDE])?;
+        assert_eq!(ints, [0, 1, -1, 1, 0, -300]);
+        assert!(numpress_ints(&[0x01]).is_err());
+        assert!(decode_numpress_linear(&[0; 4]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_mzml_truncated() {
+        let data: &[u8] = include_bytes!("../../tests/data/small.mzML");
+        let mut reader = MzmlReader::new(&data[..6000], None).unwrap();
+        let mut result = reader.next();
+        while let Ok(Some(_)) = result {
+            result = reader.next();
+        }
+        assert!(result.is_err());
+        assert!(MzmlReader::new(&data[..2000], None).is_err());
+    }
+}
@@ -0,0 +1,499 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+
+use crate::impl_reader;
+use crate::parsers::xml_common::{
+    decode_base64, element_text, parse_duration, XmlTag, XmlTagKind, XmlTags,
+};
+use crate::parsers::FromSlice;
+use crate::record::{StateMetadata, Value};
+use crate::EtError;
+
+/// Convert an mzXML duration attribute (e.g. "PT12.5S") into minutes
+fn duration_minutes(tag: &XmlTag, key: &str) -> Option<f64> {
+    tag.attr(key)
+        .and_then(|d| parse_duration(&d))
+        .map(|s| s / 60.)
+}
+
+/// Decode the interleaved m/z and intensity pairs in the `peaks` element opened by `tag`
+fn decode_peaks(element: &[u8], tag: &XmlTag) -> Result<Vec<(f64, f64)>, EtError> {
+    let text = element_text(element, tag)
+        .ok_or_else(|| EtError::from("mzXML peaks tag was never closed"))?;
+    let content_type = tag
+        .attr("contentType")
+        .or_else(|| tag.attr("pairOrder"))
+        .unwrap_or_else(|| "m/z-int".into());
+    if content_type != "m/z-int" {
+        return Err(format!(
+            "mzXML peaks with contentType {} are unsupported",
+            content_type
+        )
+        .into());
+    }
+    let mut data = decode_base64(text)?;
+    if tag.attr("compressionType").as_deref() == Some("zlib") && !data.is_empty() {
+        let mut decompressed = Vec::new();
+        let _ = ZlibDecoder::new(&data[..]).read_to_end(&mut decompressed)?;
+        data = decompressed;
+    }
+    // the spec says peaks are always in "network" (big-endian) order
+    let values: Vec<f64> = match tag.attr("precision").as_deref() {
+        Some("64") => data
+            .chunks_exact(8)
+            .map(|c| f64::from_be_bytes([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]]))
+            .collect(),
+        Some("32") | None => data
+            .chunks_exact(4)
+            .map(|c| f64::from(f32::from_be_bytes([c[0], c[1], c[2], c[3]])))
+            .collect(),
+        Some(p) => return Err(format!("mzXML peaks with precision {} are unsupported", p).into()),
+    };
+    Ok(values.chunks_exact(2).map(|c| (c[0], c[1])).collect())
+}
+
+/// Information about the mzXML file and the run in it
+#[derive(Clone, Debug, Default)]
+pub struct MzxmlMetadata {
+    /// The name of the file the data was converted from
+    pub source_file: String,
+    /// The manufacturer of the instrument
+    pub manufacturer: String,
+    /// The model of the instrument
+    pub instrument: String,
+    /// The software used to acquire the data
+    pub software: String,
+    /// The version of that software
+    pub software_version: String,
+    /// The time the first scan was taken at (minutes)
+    pub start_time: Option<f64>,
+    /// The time the last scan was taken at (minutes)
+    pub end_time: Option<f64>,
+    centroided: bool,
+}
+
+impl MzxmlMetadata {
+    /// Read the metadata out of everything in the file before the first scan.
+    fn from_header(header: &[u8]) -> Self {
+        let mut metadata = MzxmlMetadata::default();
+        for tag in XmlTags::new(header) {
+            let value = |key| tag.attr(key).unwrap_or_default().into_owned();
+            match tag.name {
+                "msRun" => {
+                    metadata.start_time = duration_minutes(&tag, "startTime");
+                    metadata.end_time = duration_minutes(&tag, "endTime");
+                }
+                "parentFile" if metadata.source_file.is_empty() => {
+                    metadata.source_file = value("fileName");
+                }
+                "msManufacturer" => metadata.manufacturer = value("value"),
+                "msModel" => metadata.instrument = value("value"),
+                "software" if tag.attr("type").as_deref() == Some("acquisition") => {
+                    metadata.software = value("name");
+                    metadata.software_version = value("version");
+                }
+                "dataProcessing" => {
+                    metadata.centroided = tag.attr("centroided").as_deref() == Some("1");
+                }
+                _ => {}
+            }
+        }
+        metadata
+    }
+}
+
+impl<'r> From<&MzxmlMetadata> for BTreeMap<String, Value<'r>> {
+    fn from(metadata: &MzxmlMetadata) -> Self {
+        let mut map = BTreeMap::new();
+        drop(map.insert(
+            "source_file".to_string(),
+            metadata.source_file.clone().into(),
+        ));
+        drop(map.insert(
+            "manufacturer".to_string(),
+            metadata.manufacturer.clone().into(),
+        ));
+        drop(map.insert("instrument".to_string(), metadata.instrument.clone().into()));
+        drop(map.insert("software".to_string(), metadata.software.clone().into()));
+        drop(map.insert(
+            "software_version".to_string(),
+            metadata.software_version.clone().into(),
+        ));
+        drop(map.insert("start_time".to_string(), metadata.start_time.into()));
+        drop(map.insert("end_time".to_string(), metadata.end_time.into()));
+        map
+    }
+}
+
+/// Parameters for the `MzxmlReader`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MzxmlParams {
+    scan_info: bool,
+}
+
+impl MzxmlParams {
+    /// Also return the scan number and the other information about the scan (MS level, filter,
+    /// precursor, etc) with every point
+    #[must_use]
+    pub fn scan_info(mut self, scan_info: bool) -> Self {
+        self.scan_info = scan_info;
+        self
+    }
+}
+
+/// The current state of the mzXML parser
+#[derive(Clone, Debug, Default)]
+pub struct MzxmlState {
+    scan_info: bool,
+    metadata: MzxmlMetadata,
+    peaks: Vec<(f64, f64)>,
+    cur_point: usize,
+    cur_time: f64,
+    cur_scan: u32,
+    cur_ms_level: u8,
+    cur_polarity: String,
+    cur_filter: String,
+    cur_precursor_mz: Option<f64>,
+    cur_charge: Option<i32>,
+    cur_activation: Option<String>,
+    cur_centroid: bool,
+}
+
+impl MzxmlState {
+    /// Read the attributes and peaks of a scan.
+    ///
+    /// `element` should run from the scan's opening tag up to its closing tag or the first scan
+    /// nested inside of it (older versions of mzXML put MS2 scans inside their parent scans).
+    fn read_scan(&mut self, element: &[u8], tag: &XmlTag) -> Result<(), EtError> {
+        self.cur_time = duration_minutes(tag, "retentionTime").unwrap_or_default();
+        // fall back to numbering the scans ourselves if they're missing a `num`
+        self.cur_scan = tag
+            .attr("num")
+            .and_then(|n| n.trim().parse().ok())
+            .unwrap_or(self.cur_scan + 1);
+        self.cur_ms_level = tag
+            .attr("msLevel")
+            .and_then(|l| l.trim().parse().ok())
+            .unwrap_or_default();
+        self.cur_polarity = match tag.attr("polarity").as_deref() {
+            Some("+") => "+".to_string(),
+            Some("-") => "-".to_string(),
+            _ => String::new(),
+        };
+        self.cur_filter = tag.attr("filterLine").unwrap_or_default().into_owned();
+        self.cur_centroid = match tag.attr("centroided").as_deref() {
+            Some(c) => c == "1",
+            None => self.metadata.centroided,
+        };
+        self.cur_precursor_mz = None;
+        self.cur_charge = None;
+        self.cur_activation = None;
+        self.peaks.clear();
+
+        for t in XmlTags::new(element).skip(1) {
+            match (t.name, t.kind) {
+                ("precursorMz", XmlTagKind::Open) => {
+                    let text = element_text(element, &t)
+                        .ok_or_else(|| EtError::from("mzXML precursorMz was never closed"))?;
+                    self.cur_precursor_mz = String::from_utf8_lossy(text).trim().parse().ok();
+                    self.cur_charge = t
+                        .attr("precursorCharge")
+                        .and_then(|c| c.trim().parse().ok());
+                    self.cur_activation = t.attr("activationMethod").map(|a| a.to_lowercase());
+                }
+                ("peaks", XmlTagKind::Open) => self.peaks = decode_peaks(element, &t)?,
+                _ => {}
+            }
+        }
+        self.cur_point = 0;
+        Ok(())
+    }
+}
+
+impl StateMetadata for MzxmlState {
+    fn metadata(&self) -> BTreeMap<String, Value> {
+        (&self.metadata).into()
+    }
+
+    fn header(&self) -> Vec<&str> {
+        if !self.scan_info {
+            return vec!["time", "mz", "intensity"];
+        }
+        vec![
+            "time",
+            "scan",
+            "mz",
+            "intensity",
+            "ms_level",
+            "polarity",
+            "filter",
+            "precursor_mz",
+            "charge",
+            "activation",
+            "injection_time",
+            "centroid",
+        ]
+    }
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for MzxmlState {
+    type State = MzxmlParams;
+
+    fn parse(
+        buffer: &[u8],
+        eof: bool,
+        consumed: &mut usize,
+        _state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        // read everything up to the first scan so the metadata is available
+        let mut in_run = false;
+        for tag in XmlTags::new(buffer) {
+            match (tag.name, tag.kind) {
+                ("msRun", XmlTagKind::Open) => in_run = true,
+                ("scan", _) | ("msRun", XmlTagKind::Close) if in_run => {
+                    *consumed += tag.start;
+                    return Ok(true);
+                }
+                _ => {}
+            }
+        }
+        if eof {
+            return Err("mzXML file has no run in it".into());
+        }
+        Err(EtError::from("Could not find the start of the mzXML run").incomplete())
+    }
+
+    fn get(&mut self, buffer: &'b [u8], params: &'s Self::State) -> Result<(), EtError> {
+        self.scan_info = params.scan_info;
+        self.metadata = MzxmlMetadata::from_header(buffer);
+        Ok(())
+    }
+}
+
+/// A single point from a scan in an mzXML file
+#[derive(Clone, Debug, Default)]
+pub struct MzxmlRecord<'r> {
+    /// The time the reading was taken at (minutes)
+    pub time: f64,
+    /// The number of the scan this was recorded in
+    pub scan: u32,
+    /// The mz value of the reading
+    pub mz: f64,
+    /// The intensity recorded
+    pub intensity: f64,
+    /// The MS level of the scan (e.g. 1 for full scans, 2 for product ion scans)
+    pub ms_level: u8,
+    /// The polarity of the scan ("+" or "-")
+    pub polarity: &'r str,
+    /// The vendor's filter describing the scan
+    pub filter: &'r str,
+    /// The m/z of the precursor ion fragmented for this scan
+    pub precursor_mz: Option<f64>,
+    /// The charge of the precursor ion, if it could be determined
+    pub charge: Option<i32>,
+    /// How the precursor ion was fragmented (e.g. "cid" or "hcd")
+    pub activation: Option<&'r str>,
+    /// How long ions were accumulated for this scan (milliseconds; never present in mzXML)
+    pub injection_time: Option<f64>,
+    /// If the scan was recorded as centroided peaks instead of a profile
+    pub centroid: bool,
+    scan_info: bool,
+}
+
+impl<'r> From<MzxmlRecord<'r>> for Vec<Value<'r>> {
+    fn from(record: MzxmlRecord<'r>) -> Self {
+        if !record.scan_info {
+            return vec![
+                record.time.into(),
+                record.mz.into(),
+                record.intensity.into(),
+            ];
+        }
+        vec![
+            record.time.into(),
+            record.scan.into(),
+            record.mz.into(),
+            record.intensity.into(),
+            record.ms_level.into(),
+            record.polarity.into(),
+            record.filter.into(),
+            record.precursor_mz.into(),
+            record.charge.into(),
+            record.activation.into(),
+            record.injection_time.into(),
+            record.centroid.into(),
+        ]
+    }
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for MzxmlRecord<'s> {
+    type State = MzxmlState;
+
+    fn parse(
+        buffer: &[u8],
+        eof: bool,
+        consumed: &mut usize,
+        state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        if state.cur_point < state.peaks.len() {
+            state.cur_point += 1;
+            return Ok(true);
+        }
+        // find the next scan with peaks in it
+        let mut tags = XmlTags::new(buffer);
+        let mut con = 0;
+        while let Some(tag) = tags.next() {
+            match (tag.name, tag.kind) {
+                ("scan", XmlTagKind::Open) => {
+                    // the scan ends at its closing tag or at the next (nested) scan
+                    let mut rest = tags;
+                    let end = match rest.find(|t| t.name == "scan") {
+                        Some(t) => t.start,
+                        None if eof => {
+                            return Err("mzXML file ended in the middle of a scan".into())
+                        }
+                        None => return Err(EtError::from("Incomplete mzXML scan").incomplete()),
+                    };
+                    state.read_scan(&buffer[tag.start..end], &tag)?;
+                    con = end;
+                    if !state.peaks.is_empty() {
+                        state.cur_point = 1;
+                        *consumed += con;
+                        return Ok(true);
+                    }
+                    tags.skip_to(end);
+                }
+                ("msRun", XmlTagKind::Close) => {
+                    *consumed += con;
+                    return Ok(false);
+                }
+                _ => {}
+            }
+        }
+        if eof {
+            *consumed += con;
+            return Ok(false);
+        }
+        Err(EtError::from("Incomplete mzXML file").incomplete())
+    }
+
+    fn get(&mut self, _buffer: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
+        let (mz, intensity) = state.peaks[state.cur_point - 1];
+        self.time = state.cur_time;
+        self.scan = state.cur_scan;
+        self.mz = mz;
+        self.intensity = intensity;
+        self.ms_level = state.cur_ms_level;
+        self.polarity = &state.cur_polarity;
+        self.filter = &state.cur_filter;
+        self.precursor_mz = state.cur_precursor_mz;
+        self.charge = state.cur_charge;
+        self.activation = state.cur_activation.as_deref();
+        self.injection_time = None;
+        self.centroid = state.cur_centroid;
+        self.scan_info = state.scan_info;
+        Ok(())
+    }
+}
+
+impl_reader!(
+    MzxmlReader,
+    MzxmlRecord,
+    MzxmlRecord<'r>,
+    MzxmlState,
+    MzxmlParams
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::readers::RecordReader;
+
+    #[test]
+    fn test_mzxml_reader() -> Result<(), EtError> {
+        let data: &[u8] = include_bytes!("../../tests/data/small.mzXML");
+        let mut reader = MzxmlReader::new(data, None)?;
+        assert_eq!(reader.headers(), ["time", "mz", "intensity"]);
+        let metadata = reader.metadata();
+        assert_eq!(metadata["source_file"], "".into());
+        assert_eq!(metadata["manufacturer"], "Thermo Scientific".into());
+        assert_eq!(metadata["instrument"], "LTQ FT".into());
+        assert_eq!(metadata["software"], "Xcalibur".into());
+        assert_eq!(metadata["software_version"], "1.1 Beta 7".into());
+        assert_eq!(metadata["start_time"], Value::Float(0.2961 / 60.));
+
+        // 64-bit uncompressed peaks
+        let record = reader.next()?.unwrap();
+        assert!((record.time - 0.004935).abs() < 1e-6);
+        assert_eq!(record.scan, 1);
+        assert_eq!(record.mz, 202.606_823_482_713_76);
+        assert_eq!(record.intensity, 1_938.117_431_640_625);
+        assert_eq!(record.ms_level, 1);
+        assert_eq!(record.polarity, "+");
+        assert_eq!(record.filter, "FTMS + p ESI Full ms [200.00-2000.00]");
+        assert_eq!(record.precursor_mz, None);
+        assert!(!record.centroid);
+
+        let mut n_points = 1;
+        while let Some(record) = reader.next()? {
+            n_points += 1;
+            if n_points == 121 {
+                // 32-bit zlib-compressed peaks
+                assert!((record.time - 0.007_896).abs() < 1e-5);
+                assert_eq!(record.mz, 200.);
+                assert!((record.intensity - 449.051_727).abs() < 1e-4);
+            } else if n_points == 221 {
+                // the nested MS2 scan
+                assert!((record.mz - 231.388_84).abs() < 1e-4);
+                assert_eq!(record.scan, 3);
+                assert_eq!(record.ms_level, 2);
+                assert_eq!(record.precursor_mz, Some(810.789_428_710_937_5));
+                assert_eq!(record.charge, Some(2));
+                assert_eq!(record.activation, Some("cid"));
+                assert!(record.centroid);
+            }
+        }
+        assert_eq!(n_points, 300);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mzxml_scan_info() -> Result<(), EtError> {
+        let data: &[u8] = include_bytes!("../../tests/data/small.mzXML");
+        let params = MzxmlParams::default().scan_info(true);
+        let mut reader = MzxmlReader::new(data, Some(params))?;
+        assert_eq!(reader.headers().len(), 12);
+        let record: Vec<Value> = reader.next()?.unwrap().into();
+        assert_eq!(record[1], Value::Integer(1));
+        assert_eq!(record[4], Value::Integer(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_mzxml_spectrum() -> Result<(), EtError> {
+        let data: &[u8] = include_bytes!("../../tests/data/small.mzXML");
+        let mut params = BTreeMap::new();
+        drop(params.insert("spectrum".to_string(), Value::Boolean(true)));
+        let (mut reader, parser) = crate::readers::get_reader(data, None, Some(params))?;
+        assert_eq!(parser, "mzxml");
+        let mut n_scans = 0;
+        while reader.next_record()?.is_some() {
+            n_scans += 1;
+        }
+        assert_eq!(n_scans, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mzxml_bad_peaks() {
+        let data: &[u8] = b"<?xml version=\"1.0\"?><mzXML><msRun><scan num=\"1\" msLevel=\"1\"><peaks precision=\"16\">AAAA</peaks></scan></msRun></mzXML>";
+        let mut reader = MzxmlReader::new(data, None).unwrap();
+        assert!(reader.next().is_err());
+    }
+}
@@ -0,0 +1,749 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use chrono::NaiveDateTime;
+
+use crate::impl_reader;
+use crate::parsers::common::EndOfFile;
+use crate::parsers::{extract, Endian, FromSlice};
+use crate::record::{StateMetadata, Value};
+use crate::EtError;
+
+/// The tags that start the lists in a netCDF header
+const NC_DIMENSION: u32 = 0x0A;
+const NC_VARIABLE: u32 = 0x0B;
+const NC_ATTRIBUTE: u32 = 0x0C;
+
+/// The types of data that can be stored in a netCDF file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NetCdfType {
+    /// A signed 8-bit integer
+    Byte,
+    /// An 8-bit character (usually used for strings)
+    Char,
+    /// A signed 16-bit integer
+    Short,
+    /// A signed 32-bit integer
+    Int,
+    /// A 32-bit float
+    Float,
+    /// A 64-bit float
+    Double,
+}
+
+impl NetCdfType {
+    fn from_u32(value: u32) -> Result<Self, EtError> {
+        Ok(match value {
+            1 => NetCdfType::Byte,
+            2 => NetCdfType::Char,
+            3 => NetCdfType::Short,
+            4 => NetCdfType::Int,
+            5 => NetCdfType::Float,
+            6 => NetCdfType::Double,
+            _ => return Err("Unknown netCDF data type".into()),
+        })
+    }
+
+    /// The number of bytes one value of this type takes up
+    #[must_use]
+    pub fn size(self) -> usize {
+        match self {
+            NetCdfType::Byte | NetCdfType::Char => 1,
+            NetCdfType::Short => 2,
+            NetCdfType::Int | NetCdfType::Float => 4,
+            NetCdfType::Double => 8,
+        }
+    }
+
+    /// Read the `n` values at the start of `data` as floats
+    fn read_floats(self, data: &[u8], n: usize, values: &mut Vec<f64>) -> Result<(), EtError> {
+        let size = self.size();
+        let len = n
+            .checked_mul(size)
+            .filter(|len| *len <= data.len())
+            .ok_or_else(|| EtError::from("netCDF variable extends past the end of the file"))?;
+        for chunk in data[..len].chunks_exact(size) {
+            values.push(match self {
+                NetCdfType::Byte => f64::from(chunk[0] as i8),
+                NetCdfType::Char => return Err("netCDF character data isn't numeric".into()),
+                NetCdfType::Short => f64::from(i16::extract(chunk, &Endian::Big)?),
+                NetCdfType::Int => f64::from(i32::extract(chunk, &Endian::Big)?),
+                NetCdfType::Float => f64::from(f32::extract(chunk, &Endian::Big)?),
+                NetCdfType::Double => f64::extract(chunk, &Endian::Big)?,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Round `len` up to the next multiple of four (everything in netCDF is aligned to 4 bytes)
+fn pad4(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn read_u32(data: &[u8], con: &mut usize) -> Result<u32, EtError> {
+    extract::<u32>(data, con, &mut Endian::Big)
+}
+
+fn read_name(data: &[u8], con: &mut usize) -> Result<String, EtError> {
+    let len = usize::try_from(read_u32(data, con)?)?;
+    if data.len() < *con + pad4(len) {
+        return Err("netCDF name extends past the end of the header".into());
+    }
+    let name = String::from_utf8_lossy(&data[*con..*con + len]).into_owned();
+    *con += pad4(len);
+    Ok(name)
+}
+
+fn read_attributes(
+    data: &[u8],
+    con: &mut usize,
+) -> Result<BTreeMap<String, Value<'static>>, EtError> {
+    let mut attributes = BTreeMap::new();
+    let tag = read_u32(data, con)?;
+    let n_attributes = read_u32(data, con)?;
+    if tag == 0 && n_attributes == 0 {
+        return Ok(attributes);
+    } else if tag != NC_ATTRIBUTE {
+        return Err("Bad netCDF attribute list".into());
+    }
+    for _ in 0..n_attributes {
+        let name = read_name(data, con)?;
+        let data_type = NetCdfType::from_u32(read_u32(data, con)?)?;
+        let n_values = usize::try_from(read_u32(data, con)?)?;
+        let len = n_values * data_type.size();
+        if data.len() < *con + pad4(len) {
+            return Err("netCDF attribute extends past the end of the header".into());
+        }
+        let raw = &data[*con..*con + len];
+        *con += pad4(len);
+        let value = if data_type == NetCdfType::Char {
+            // strings are often null-terminated (or padded with nulls)
+            let text = String::from_utf8_lossy(raw);
+            text.trim_end_matches('\0').trim().to_string().into()
+        } else {
+            let mut values = Vec::with_capacity(n_values);
+            data_type.read_floats(raw, n_values, &mut values)?;
+            let mut values: Vec<Value> = values
+                .into_iter()
+                .map(|v| match data_type {
+                    #[allow(clippy::cast_possible_truncation)]
+                    NetCdfType::Byte | NetCdfType::Short | NetCdfType::Int => {
+                        Value::Integer(v as i64)
+                    }
+                    _ => Value::Float(v),
+                })
+                .collect();
+            if values.len() == 1 {
+                values.remove(0)
+            } else {
+                Value::List(values)
+            }
+        };
+        drop(attributes.insert(name, value));
+    }
+    Ok(attributes)
+}
+
+/// A variable in a netCDF file
+#[derive(Clone, Debug)]
+pub struct NetCdfVariable {
+    /// The name of the variable
+    pub name: String,
+    /// The type of the data stored in the variable
+    pub data_type: NetCdfType,
+    /// The attributes (e.g. `units`) attached to the variable
+    pub attributes: BTreeMap<String, Value<'static>>,
+    /// The indices of the dimensions of the variable
+    dimensions: Vec<usize>,
+    /// Where the data starts in the file
+    begin: usize,
+}
+
+/// The header of a netCDF classic (CDF-1) or 64-bit offset (CDF-2) file
+#[derive(Clone, Debug, Default)]
+pub struct NetCdfHeader {
+    /// The version of the format (1 for classic or 2 for 64-bit offsets)
+    pub version: u8,
+    /// The number of records for the variables that use the record dimension
+    pub n_records: usize,
+    /// The names and lengths of all the dimensions (a length of 0 denotes the record dimension)
+    pub dimensions: Vec<(String, usize)>,
+    /// The global attributes of the file
+    pub attributes: BTreeMap<String, Value<'static>>,
+    /// The variables in the file
+    pub variables: Vec<NetCdfVariable>,
+    /// How many bytes each record (one slice of every record variable) takes up
+    record_size: usize,
+}
+
+impl NetCdfHeader {
+    /// Parse the header from the start of a netCDF file
+    ///
+    /// # Errors
+    /// If the header is malformed or truncated, an error is returned.
+    pub fn from_slice(data: &[u8]) -> Result<Self, EtError> {
+        if data.len() < 8 || &data[..3] != b"CDF" {
+            return Err("Bad netCDF magic".into());
+        }
+        let version = data[3];
+        if version != 1 && version != 2 {
+            return Err("Only netCDF classic and 64-bit offset files are supported".into());
+        }
+        let con = &mut 4;
+        let n_records = read_u32(data, con)?;
+
+        let mut dimensions = Vec::new();
+        let tag = read_u32(data, con)?;
+        let n_dimensions = read_u32(data, con)?;
+        if tag != NC_DIMENSION && (tag != 0 || n_dimensions != 0) {
+            return Err("Bad netCDF dimension list".into());
+        }
+        for _ in 0..n_dimensions {
+            let name = read_name(data, con)?;
+            dimensions.push((name, usize::try_from(read_u32(data, con)?)?));
+        }
+
+        let attributes = read_attributes(data, con)?;
+
+        let mut variables = Vec::new();
+        let tag = read_u32(data, con)?;
+        let n_variables = read_u32(data, con)?;
+        if tag != NC_VARIABLE && (tag != 0 || n_variables != 0) {
+            return Err("Bad netCDF variable list".into());
+        }
+        for _ in 0..n_variables {
+            let name = read_name(data, con)?;
+            let n_var_dims = read_u32(data, con)?;
+            let mut var_dims = Vec::new();
+            for _ in 0..n_var_dims {
+                let dim = usize::try_from(read_u32(data, con)?)?;
+                if dim >= dimensions.len() {
+                    return Err("netCDF variable has an unknown dimension".into());
+                }
+                var_dims.push(dim);
+            }
+            let var_attributes = read_attributes(data, con)?;
+            let data_type = NetCdfType::from_u32(read_u32(data, con)?)?;
+            // the size here can't be trusted for very large variables so we compute it ourselves
+            let _ = read_u32(data, con)?;
+            let begin = if version == 1 {
+                usize::try_from(read_u32(data, con)?)?
+            } else {
+                usize::try_from(extract::<u64>(data, con, &mut Endian::Big)?)?
+            };
+            variables.push(NetCdfVariable {
+                name,
+                data_type,
+                attributes: var_attributes,
+                dimensions: var_dims,
+                begin,
+            });
+        }
+
+        let mut header = NetCdfHeader {
+            version,
+            n_records: 0,
+            dimensions,
+            attributes,
+            variables,
+            record_size: 0,
+        };
+        let record_sizes = header
+            .variables
+            .iter()
+            .filter(|v| header.is_record_variable(v))
+            .map(|v| header.slice_size(v))
+            .collect::<Result<Vec<usize>, EtError>>()?;
+        // the data for a lone record variable isn't padded
+        header.record_size = match &record_sizes[..] {
+            [size] => *size,
+            sizes => sizes
+                .iter()
+                .try_fold(0usize, |total, s| total.checked_add(s.checked_add(3)? & !3))
+                .ok_or_else(|| EtError::from("netCDF record size is too large"))?,
+        };
+        header.n_records = if n_records == u32::MAX {
+            // the file was streamed so the number of records wasn't known when the header was
+            // written; work it out from the file size instead
+            let records_start = header
+                .variables
+                .iter()
+                .filter(|v| header.is_record_variable(v))
+                .map(|v| v.begin)
+                .min();
+            match records_start {
+                Some(start) if header.record_size > 0 => {
+                    data.len().saturating_sub(start) / header.record_size
+                }
+                _ => 0,
+            }
+        } else {
+            usize::try_from(n_records)?
+        };
+        Ok(header)
+    }
+
+    fn is_record_variable(&self, variable: &NetCdfVariable) -> bool {
+        variable
+            .dimensions
+            .first()
+            .is_some_and(|d| self.dimensions[*d].1 == 0)
+    }
+
+    /// The number of values in one record of the variable (or in the entire variable if it's not
+    /// a record variable)
+    fn slice_len(&self, variable: &NetCdfVariable) -> Result<usize, EtError> {
+        variable
+            .dimensions
+            .iter()
+            .map(|d| self.dimensions[*d].1)
+            .filter(|len| *len != 0)
+            .try_fold(1usize, usize::checked_mul)
+            .ok_or_else(|| format!("netCDF variable {} is too large", variable.name).into())
+    }
+
+    /// The number of bytes in one record of the variable (or in the entire variable if it's not
+    /// a record variable)
+    fn slice_size(&self, variable: &NetCdfVariable) -> Result<usize, EtError> {
+        self.slice_len(variable)?
+            .checked_mul(variable.data_type.size())
+            .ok_or_else(|| format!("netCDF variable {} is too large", variable.name).into())
+    }
+
+    /// Find a variable by name
+    #[must_use]
+    pub fn variable(&self, name: &str) -> Option<&NetCdfVariable> {
+        self.variables.iter().find(|v| v.name == name)
+    }
+
+    /// Read all the values in the variable `name` out of `data` (the entire file) as floats,
+    /// applying any `scale_factor` and `add_offset` attributes on the variable.
+    ///
+    /// Returns `None` if there's no variable with that name.
+    ///
+    /// # Errors
+    /// If the variable isn't numeric or its data is truncated, an error is returned.
+    pub fn read_variable(&self, data: &[u8], name: &str) -> Result<Option<Vec<f64>>, EtError> {
+        let variable = match self.variable(name) {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let n_values = self.slice_len(variable)?;
+        let mut values = Vec::new();
+        if self.is_record_variable(variable) {
+            // make sure the file could actually hold every record before allocating space for them
+            let total_size = self
+                .slice_size(variable)?
+                .checked_mul(self.n_records)
+                .and_then(|size| size.checked_add(variable.begin));
+            if total_size.is_none_or(|size| size > data.len()) {
+                return Err("netCDF variable extends past the end of the file".into());
+            }
+            values.reserve(n_values * self.n_records);
+            for record in 0..self.n_records {
+                let start = record
+                    .checked_mul(self.record_size)
+                    .and_then(|offset| offset.checked_add(variable.begin))
+                    .ok_or_else(|| EtError::from("netCDF record offset is too large"))?;
+                let slice = data.get(start..).unwrap_or_default();
+                variable
+                    .data_type
+                    .read_floats(slice, n_values, &mut values)?;
+            }
+        } else {
+            let slice = data.get(variable.begin..).unwrap_or_default();
+            variable
+                .data_type
+                .read_floats(slice, n_values, &mut values)?;
+        }
+
+        let scale = variable.attributes.get("scale_factor").and_then(to_float);
+        let offset = variable.attributes.get("add_offset").and_then(to_float);
+        if scale.is_some() || offset.is_some() {
+            let (scale, offset) = (scale.unwrap_or(1.), offset.unwrap_or(0.));
+            for value in &mut values {
+                *value = *value * scale + offset;
+            }
+        }
+        Ok(Some(values))
+    }
+}
+
+fn to_float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Float(f) => Some(*f),
+        #[allow(clippy::cast_precision_loss)]
+        Value::Integer(i) => Some(*i as f64),
+        _ => None,
+    }
+}
+
+/// Parameters for the `NetCdfReader`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NetCdfParams {
+    scan_info: bool,
+}
+
+impl NetCdfParams {
+    /// Also return the number of the scan with every point
+    #[must_use]
+    pub fn scan_info(mut self, scan_info: bool) -> Self {
+        self.scan_info = scan_info;
+        self
+    }
+}
+
+/// The current state of the netCDF parser
+#[derive(Clone, Debug, Default)]
+pub struct NetCdfState {
+    scan_info: bool,
+    header: NetCdfHeader,
+    is_chromatogram: bool,
+    times: Vec<f64>,
+    scans: Vec<u32>,
+    mzs: Vec<f64>,
+    intensities: Vec<f64>,
+    cur_point: usize,
+}
+
+impl NetCdfState {
+    /// Read the points out of a file following the ANDI-MS conventions
+    fn read_andi_ms(&mut self, data: &[u8], mzs: Vec<f64>) -> Result<(), EtError> {
+        let intensities = self
+            .header
+            .read_variable(data, "intensity_values")?
+            .ok_or_else(|| EtError::from("ANDI-MS file has no intensity_values"))?;
+        let scan_times = self
+            .header
+            .read_variable(data, "scan_acquisition_time")?
+            .ok_or_else(|| EtError::from("ANDI-MS file has no scan_acquisition_time"))?;
+        let counts = self
+            .header
+            .read_variable(data, "point_count")?
+            .ok_or_else(|| EtError::from("ANDI-MS file has no point_count"))?;
+        if mzs.len() != intensities.len() {
+            return Err("ANDI-MS mass and intensity values have different lengths".into());
+        }
+        if scan_times.len() != counts.len() {
+            return Err("ANDI-MS scan times and point counts have different lengths".into());
+        }
+        // older files don't always have the scan index so we can also work it out from the counts
+        let starts = match self.header.read_variable(data, "scan_index")? {
+            Some(starts) => starts,
+            None => counts
+                .iter()
+                .scan(0., |start, count| {
+                    let cur = *start;
+                    *start += count;
+                    Some(cur)
+                })
+                .collect(),
+        };
+        let scan_numbers = self
+            .header
+            .read_variable(data, "actual_scan_number")?
+            .filter(|n| n.len() == counts.len());
+        for (ix, ((time, start), count)) in scan_times.iter().zip(starts).zip(counts).enumerate() {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let (start, count) = (start as usize, count as usize);
+            if start + count > mzs.len() {
+                return Err("ANDI-MS scan extends past the end of the mass values".into());
+            }
+            // scans are numbered from 1 if the file doesn't have the instrument's numbers
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let scan = match &scan_numbers {
+                Some(numbers) => numbers[ix] as u32,
+                None => u32::try_from(ix + 1)?,
+            };
+            self.times.extend(vec![time / 60.; count]);
+            self.scans.extend(vec![scan; count]);
+            self.mzs.extend_from_slice(&mzs[start..start + count]);
+            self.intensities
+                .extend_from_slice(&intensities[start..start + count]);
+        }
+        Ok(())
+    }
+
+    /// Read the points out of a file following the ANDI-Chrom conventions
+    fn read_andi_chrom(&mut self, data: &[u8], intensities: Vec<f64>) -> Result<(), EtError> {
+        self.is_chromatogram = true;
+        self.times = if let Some(times) = self.header.read_variable(data, "raw_data_retention")? {
+            if times.len() != intensities.len() {
+                return Err("ANDI-Chrom retention times and values have different lengths".into());
+            }
+            times.iter().map(|t| t / 60.).collect()
+        } else {
+            let interval = self
+                .header
+                .read_variable(data, "actual_sampling_interval")?
+                .and_then(|v| v.first().copied())
+                .ok_or_else(|| EtError::from("ANDI-Chrom file has no sampling interval"))?;
+            let delay = self
+                .header
+                .read_variable(data, "actual_delay_time")?
+                .and_then(|v| v.first().copied())
+                .unwrap_or_default();
+            #[allow(clippy::cast_precision_loss)]
+            (0..intensities.len())
+                .map(|i| (delay + interval * i as f64) / 60.)
+                .collect()
+        };
+        self.intensities = intensities;
+        Ok(())
+    }
+}
+
+impl StateMetadata for NetCdfState {
+    fn metadata(&self) -> BTreeMap<String, Value> {
+        let mut map = self.header.attributes.clone();
+        let timestamp = match map.get("injection_date_time_stamp") {
+            Some(Value::String(s)) => s
+                .get(..14)
+                .and_then(|s| NaiveDateTime::parse_from_str(s, "%Y%m%d%H%M%S").ok()),
+            _ => None,
+        };
+        if let Some(run_date) = timestamp {
+            drop(map.insert("run_date".to_string(), run_date.into()));
+        }
+        map
+    }
+
+    fn header(&self) -> Vec<&str> {
+        if self.scan_info {
+            vec!["time", "scan", "mz", "intensity"]
+        } else {
+            vec!["time", "mz", "intensity"]
+        }
+    }
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for NetCdfState {
+    type State = NetCdfParams;
+
+    fn parse(
+        buffer: &[u8],
+        eof: bool,
+        consumed: &mut usize,
+        _state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        // the data for each variable can be anywhere in the file so we need all of it
+        if !EndOfFile::parse(buffer, eof, &mut 0, &mut ())? {
+            return Ok(false);
+        }
+        *consumed += buffer.len();
+        Ok(true)
+    }
+
+    fn get(&mut self, buffer: &'b [u8], params: &'s Self::State) -> Result<(), EtError> {
+        self.scan_info = params.scan_info;
+        self.header = NetCdfHeader::from_slice(buffer)?;
+        if let Some(mzs) = self.header.read_variable(buffer, "mass_values")? {
+            self.read_andi_ms(buffer, mzs)?;
+        } else if let Some(intensities) = self.header.read_variable(buffer, "ordinate_values")? {
+            self.read_andi_chrom(buffer, intensities)?;
+        } else {
+            return Err("netCDF file doesn't follow the ANDI-MS or ANDI-Chrom conventions".into());
+        }
+        Ok(())
+    }
+}
+
+/// A single point from an ANDI-MS or ANDI-Chrom netCDF file
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NetCdfRecord {
+    /// The time the reading was taken at (minutes)
+    pub time: f64,
+    /// The number of the scan the reading was taken in (not set for chromatograms)
+    pub scan: Option<u32>,
+    /// The mz value of the reading (not set for chromatograms)
+    pub mz: Option<f64>,
+    /// The intensity recorded
+    pub intensity: f64,
+    scan_info: bool,
+}
+
+impl<'r> From<NetCdfRecord> for Vec<Value<'r>> {
+    fn from(record: NetCdfRecord) -> Self {
+        if record.scan_info {
+            vec![
+                record.time.into(),
+                record.scan.into(),
+                record.mz.into(),
+                record.intensity.into(),
+            ]
+        } else {
+            vec![
+                record.time.into(),
+                record.mz.into(),
+                record.intensity.into(),
+            ]
+        }
+    }
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for NetCdfRecord {
+    type State = NetCdfState;
+
+    fn parse(
+        _buffer: &[u8],
+        _eof: bool,
+        _consumed: &mut usize,
+        state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        // everything was already read into the state so there's nothing left to consume
+        if state.cur_point >= state.intensities.len() {
+            return Ok(false);
+        }
+        state.cur_point += 1;
+        Ok(true)
+    }
+
+    fn get(&mut self, _buffer: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
+        let ix = state.cur_point - 1;
+        self.time = state.times[ix];
+        if state.is_chromatogram {
+            self.scan = None;
+            self.mz = None;
+        } else {
+            self.scan = Some(state.scans[ix]);
+            self.mz = Some(state.mzs[ix]);
+        }
+        self.intensity = state.intensities[ix];
+        self.scan_info = state.scan_info;
+        Ok(())
+    }
+}
+
+impl_reader!(
+    NetCdfReader,
+    NetCdfRecord,
+    NetCdfRecord,
+    NetCdfState,
+    NetCdfParams
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::readers::RecordReader;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_andi_ms() -> Result<(), EtError> {
+        let rb: &[u8] = include_bytes!("../../tests/data/andi_ms.cdf");
+        let mut reader = NetCdfReader::new(rb, None)?;
+        let metadata = reader.metadata();
+        assert_eq!(metadata["experiment_title"], "small".into());
+        assert_eq!(
+            metadata["dataset_origin"],
+            "Written by hand for testing.".into()
+        );
+        assert_eq!(
+            metadata["run_date"],
+            NaiveDate::from_ymd_opt(2005, 7, 20)
+                .and_then(|d| d.and_hms_opt(14, 44, 22))
+                .unwrap()
+                .into()
+        );
+
+        assert_eq!(reader.headers(), ["time", "mz", "intensity"]);
+        let NetCdfRecord {
+            time,
+            mz,
+            intensity,
+            ..
+        } = reader.next()?.unwrap();
+        assert!((time - 0.004935).abs() < 1e-6);
+        assert!((mz.unwrap() - 202.606_82).abs() < 1e-4);
+        // the intensities are stored halved with a scale factor of 2
+        assert!((intensity - 1938.117).abs() < 1e-2);
+
+        let mut n_points = 1;
+        let mut last_time = time;
+        let mut n_scans = 1;
+        while let Some(record) = reader.next()? {
+            if record.time != last_time {
+                n_scans += 1;
+                last_time = record.time;
+            }
+            n_points += 1;
+        }
+        assert_eq!(n_points, 150);
+        assert_eq!(n_scans, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_andi_ms_scan_info() -> Result<(), EtError> {
+        let rb: &[u8] = include_bytes!("../../tests/data/andi_ms.cdf");
+        let params = NetCdfParams::default().scan_info(true);
+        let mut reader = NetCdfReader::new(rb, Some(params))?;
+        assert_eq!(reader.headers(), ["time", "scan", "mz", "intensity"]);
+        let mut last_scan = None;
+        while let Some(record) = reader.next()? {
+            assert!(record.scan >= last_scan);
+            last_scan = record.scan;
+        }
+        assert_eq!(last_scan, Some(3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_andi_chrom() -> Result<(), EtError> {
+        let rb: &[u8] = include_bytes!("../../tests/data/andi_chrom.cdf");
+        let mut reader = NetCdfReader::new(rb, None)?;
+        let metadata = reader.metadata();
+        assert_eq!(metadata["detector_name"], "FID1A".into());
+        assert_eq!(metadata["sample_name"], "alkanes".into());
+
+        let NetCdfRecord {
+            time,
+            mz,
+            intensity,
+            ..
+        } = reader.next()?.unwrap();
+        assert!((time - 0.5).abs() < 1e-6);
+        assert_eq!(mz, None);
+        assert!((intensity - 1000.).abs() < 1e-3);
+
+        let mut n_points = 1;
+        let mut max = (0., 0.);
+        while let Some(record) = reader.next()? {
+            if record.intensity > max.1 {
+                max = (record.time, record.intensity);
+            }
+            n_points += 1;
+        }
+        assert_eq!(n_points, 200);
+        // the peak's at point 100, i.e. 30 + 100 * 0.5 seconds
+        assert!((max.0 - 80. / 60.).abs() < 1e-6);
+        Ok(())
+    }
+
+    #[test]
+    fn test_netcdf_header() -> Result<(), EtError> {
+        let rb: &[u8] = include_bytes!("../../tests/data/andi_chrom.cdf");
+        let header = NetCdfHeader::from_slice(rb)?;
+        assert_eq!(header.version, 2);
+        assert_eq!(header.n_records, 200);
+        assert_eq!(header.dimensions[1], ("point_number".to_string(), 0));
+        let interval = header.read_variable(rb, "actual_sampling_interval")?;
+        assert_eq!(interval, Some(vec![0.5]));
+        assert!(header.read_variable(rb, "missing")?.is_none());
+
+        assert!(NetCdfHeader::from_slice(b"CDF\x05\x00\x00\x00\x00").is_err());
+        assert!(NetCdfHeader::from_slice(&rb[..40]).is_err());
+
+        // a huge record count shouldn't allocate anything or overflow the offsets
+        let mut data = rb.to_vec();
+        data[4..8].copy_from_slice(&0x7FFF_FFFFu32.to_be_bytes());
+        let header = NetCdfHeader::from_slice(&data)?;
+        assert!(header.read_variable(&data, "ordinate_values").is_err());
+        Ok(())
+    }
+}
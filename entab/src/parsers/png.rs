@@ -1,9 +1,13 @@
 use alloc::collections::BTreeMap;
 use core::convert::TryFrom;
 use core::marker::Copy;
+use core::str::FromStr;
 use std::io::Read;
 
+use chrono::NaiveDate;
 use flate2::read::ZlibDecoder;
+use flate2::{Crc, Decompress, FlushDecompress, Status};
+use memchr::memchr;
 
 use crate::parsers::common::Skip;
 use crate::parsers::{extract, Endian, FromSlice};
@@ -55,16 +59,37 @@ impl PngColorType {
 }
 
 /// The state of the PNG parser
-#[derive(Clone, Debug, Default)]
+///
+/// Image data is decompressed as it's read and only the current and previous scanlines are kept
+/// in memory, except for interlaced images which have to be decoded in full before the first
+/// pixel can be returned. Any chunks after the image data (e.g. text) are read in along with the
+/// last scanline.
+#[derive(Debug, Default)]
 pub struct PngState {
     color_type: PngColorType,
     bit_depth: u8,
     width: usize,
     height: usize,
+    interlaced: bool,
     cur_x: usize,
     cur_y: usize,
+    // the decompressor and where we are in the IDAT chunks
+    inflater: Option<Decompress>,
+    chunk_crc: Crc,
+    chunk_left: usize,
+    in_idat: bool,
+    stream_done: bool,
+    trailer_done: bool,
+    // the current (unfiltered) and previous scanlines
+    line: Vec<u8>,
+    prev_line: Vec<u8>,
+    line_filled: usize,
+    line_pending: bool,
+    // the entire image for interlaced images
     image_data: Vec<u8>,
     palette: Option<Vec<(u16, u16, u16)>>,
+    transparency: Option<PngTransparency>,
+    metadata: BTreeMap<String, Value<'static>>,
 }
 
 impl PngState {
@@ -73,74 +98,462 @@ impl PngState {
         1 + (self.width * self.color_type.pixel_size() * usize::from(self.bit_depth) + 7) / 8
     }
 
-    fn unfilter_line(&mut self, line_num: usize) -> Result<(), EtError> {
-        let bytes_per_pixel = (self.color_type.pixel_size() * usize::from(self.bit_depth) + 7) / 8;
-        let line_len = self.line_len();
+    fn bytes_per_pixel(&self) -> usize {
+        (self.color_type.pixel_size() * usize::from(self.bit_depth)).div_ceil(8)
+    }
 
-        for pos in line_num * line_len + 1..(line_num + 1) * line_len {
-            let left = if pos < line_num * line_len + 1 + bytes_per_pixel {
-                0
-            } else {
-                self.image_data[pos - bytes_per_pixel]
-            };
-            let above = if line_num == 0 {
-                0
-            } else {
-                self.image_data[pos - line_len]
-            };
-            self.image_data[pos] = match self.image_data.get(line_num * line_len) {
-                // no filtering; skip
-                Some(0) => self.image_data[pos],
-                // sub filtering
-                Some(1) => self.image_data[pos].wrapping_add(left),
-                // up filtering
-                Some(2) => self.image_data[pos].wrapping_add(above),
-                // average filtering
-                Some(3) => {
-                    // average left and above together
-                    let mut average = (left >> 1) + (above >> 1);
-                    if left & above & 1 == 1 {
-                        average += 1;
+    /// Decompress image data from the IDAT chunks at the start of `rb` into `out` until it's
+    /// full, returning an incomplete error if we run out of data first. Any data decompressed
+    /// is marked as consumed so this can be resumed once the buffer is refilled.
+    fn inflate(
+        &mut self,
+        rb: &[u8],
+        consumed: &mut usize,
+        out: &mut [u8],
+        filled: &mut usize,
+    ) -> Result<(), EtError> {
+        let con = &mut 0;
+        let result = self.inflate_inner(rb, con, out, filled);
+        *consumed += *con;
+        result
+    }
+
+    fn inflate_inner(
+        &mut self,
+        rb: &[u8],
+        con: &mut usize,
+        out: &mut [u8],
+        filled: &mut usize,
+    ) -> Result<(), EtError> {
+        while *filled < out.len() {
+            if self.stream_done {
+                return Err("PNG image data is too short for its dimensions".into());
+            }
+            if self.chunk_left == 0 && self.in_idat {
+                // the decompressor may still have output buffered from the last chunk
+                if self.decompress(&[], out, filled)?.1 > 0 {
+                    continue;
+                }
+            }
+            if self.chunk_left == 0 {
+                // move on to the next IDAT chunk, checking the CRC of the one we just finished
+                if rb.len() < *con + if self.in_idat { 12 } else { 8 } {
+                    return Err(EtError::from("PNG chunk header incomplete").incomplete());
+                }
+                if self.in_idat {
+                    let expected = extract::<u32>(rb, con, &mut Endian::Big)?;
+                    if self.chunk_crc.sum() != expected {
+                        return Err("PNG IDAT chunk is corrupt (CRC mismatch)".into());
                     }
-                    self.image_data[pos].wrapping_add(average)
                 }
-                // paeth filtering
-                Some(4) => {
-                    let immediate_left = if pos == line_num * line_len + 1 {
-                        0
-                    } else {
-                        self.image_data[pos - 1]
+                self.chunk_left = extract::<u32>(rb, con, &mut Endian::Big)? as usize;
+                let chunk_header = extract::<&[u8]>(rb, con, &mut 4)?;
+                if chunk_header != b"IDAT" {
+                    return Err("PNG image data is too short for its dimensions".into());
+                }
+                self.chunk_crc.reset();
+                self.chunk_crc.update(chunk_header);
+                self.in_idat = true;
+                continue;
+            }
+
+            let available = self.chunk_left.min(rb.len() - *con);
+            if available == 0 {
+                return Err(EtError::from("PNG image data incomplete").incomplete());
+            }
+            let input = &rb[*con..*con + available];
+            let (n_in, _) = self.decompress(input, out, filled)?;
+            self.chunk_crc.update(&input[..n_in]);
+            self.chunk_left -= n_in;
+            *con += n_in;
+        }
+        Ok(())
+    }
+
+    /// Feed `input` to the decompressor and return how much of it was used and how much output
+    /// was produced.
+    fn decompress(
+        &mut self,
+        input: &[u8],
+        out: &mut [u8],
+        filled: &mut usize,
+    ) -> Result<(usize, usize), EtError> {
+        let inflater = self.inflater.get_or_insert_with(|| Decompress::new(true));
+        let (start_in, start_out) = (inflater.total_in(), inflater.total_out());
+        let status = inflater
+            .decompress(input, &mut out[*filled..], FlushDecompress::None)
+            .map_err(|_| EtError::from("PNG image data could not be decompressed"))?;
+        #[allow(clippy::cast_possible_truncation)]
+        let (n_in, n_out) = (
+            (inflater.total_in() - start_in) as usize,
+            (inflater.total_out() - start_out) as usize,
+        );
+        *filled += n_out;
+        if status == Status::StreamEnd {
+            self.stream_done = true;
+        } else if !input.is_empty() && n_in == 0 && n_out == 0 {
+            return Err("PNG image data could not be decompressed".into());
+        }
+        Ok((n_in, n_out))
+    }
+
+    /// Read the chunks after the image data up to the `IEND` chunk, checking their CRCs and
+    /// adding any text or modification times in them to the metadata. Like `inflate`, this can
+    /// be resumed if it runs out of data.
+    fn read_trailer(&mut self, rb: &[u8], consumed: &mut usize) -> Result<(), EtError> {
+        let con = &mut 0;
+        let result = self.read_trailer_inner(rb, con);
+        *consumed += *con;
+        result
+    }
+
+    fn read_trailer_inner(&mut self, rb: &[u8], con: &mut usize) -> Result<(), EtError> {
+        if self.in_idat {
+            // finish checking the CRC of the last IDAT chunk (which may still have the end of
+            // the compressed stream in it)
+            let available = self.chunk_left.min(rb.len() - *con);
+            self.chunk_crc.update(&rb[*con..*con + available]);
+            self.chunk_left -= available;
+            *con += available;
+            if self.chunk_left > 0 || rb.len() < *con + 4 {
+                return Err(EtError::from("PNG image data incomplete").incomplete());
+            }
+            let expected = extract::<u32>(rb, con, &mut Endian::Big)?;
+            if self.chunk_crc.sum() != expected {
+                return Err("PNG IDAT chunk is corrupt (CRC mismatch)".into());
+            }
+            self.in_idat = false;
+        }
+        while !self.trailer_done {
+            if rb.len() < *con + 8 {
+                return Err(EtError::from("PNG chunk header incomplete").incomplete());
+            }
+            let chunk_con = &mut con.clone();
+            let mut chunk_size = extract::<u32>(rb, chunk_con, &mut Endian::Big)? as usize;
+            if rb.len() < *chunk_con + chunk_size + 8 {
+                return Err(EtError::from("PNG chunk incomplete").incomplete());
+            }
+            let chunk_start = *chunk_con;
+            let chunk_header = extract::<&[u8]>(rb, chunk_con, &mut 4)?;
+            let data = extract::<&[u8]>(rb, chunk_con, &mut chunk_size)?;
+            check_crc(rb, chunk_start, chunk_con)?;
+            match chunk_header {
+                b"tEXt" | b"zTXt" | b"iTXt" => {
+                    let (key, value) = parse_text_chunk(chunk_header, data)?;
+                    let text = self
+                        .metadata
+                        .entry("text".to_string())
+                        .or_insert_with(|| Value::Record(BTreeMap::new()));
+                    if let Value::Record(text) = text {
+                        drop(text.insert(key, value.into()));
+                    }
+                }
+                b"tIME" => {
+                    drop(
+                        self.metadata
+                            .insert("modified".to_string(), parse_time_chunk(data)?),
+                    );
+                }
+                b"IEND" => self.trailer_done = true,
+                _ => {}
+            }
+            *con = *chunk_con;
+        }
+        Ok(())
+    }
+
+    /// Read and unfilter the next scanline, keeping the previous one for reference.
+    fn next_line(&mut self, rb: &[u8], consumed: &mut usize, y: usize) -> Result<(), EtError> {
+        let start = *consumed;
+        if !self.line_pending {
+            core::mem::swap(&mut self.line, &mut self.prev_line);
+            self.line.clear();
+            self.line.resize(self.line_len(), 0);
+            self.line_filled = 0;
+            self.line_pending = true;
+        }
+        let mut line = core::mem::take(&mut self.line);
+        let mut filled = self.line_filled;
+        let result = self.inflate(rb, consumed, &mut line, &mut filled);
+        self.line = line;
+        self.line_filled = filled;
+        result?;
+        if y + 1 == self.height {
+            self.read_trailer(&rb[*consumed - start..], consumed)?;
+        }
+
+        let bytes_per_pixel = self.bytes_per_pixel();
+        let above = if y == 0 {
+            None
+        } else {
+            Some(&self.prev_line[..])
+        };
+        unfilter_line(&mut self.line, above, bytes_per_pixel)?;
+        self.line_pending = false;
+        Ok(())
+    }
+
+    /// Read the entire image (for interlaced images).
+    fn read_image(&mut self, rb: &[u8], consumed: &mut usize) -> Result<(), EtError> {
+        let bits_per_pixel = self.color_type.pixel_size() * usize::from(self.bit_depth);
+        let mut size = 0;
+        for (x_start, y_start, x_step, y_step) in ADAM7_PASSES {
+            let pass_width = (self.width + x_step - x_start - 1) / x_step;
+            let pass_height = (self.height + y_step - y_start - 1) / y_step;
+            if pass_width > 0 && pass_height > 0 {
+                size += (1 + (pass_width * bits_per_pixel).div_ceil(8)) * pass_height;
+            }
+        }
+        if self.image_data.len() != size {
+            self.image_data.resize(size, 0);
+        }
+        let start = *consumed;
+        let mut image = core::mem::take(&mut self.image_data);
+        let mut filled = self.line_filled;
+        let result = self.inflate(rb, consumed, &mut image, &mut filled);
+        self.image_data = image;
+        self.line_filled = filled;
+        result?;
+        self.read_trailer(&rb[*consumed - start..], consumed)?;
+        self.deinterlace()
+    }
+
+    /// Rearrange Adam7-interlaced image data into the normal progressive order, unfiltering each
+    /// line of each of the passes in the process.
+    fn deinterlace(&mut self) -> Result<(), EtError> {
+        let bits_per_pixel = self.color_type.pixel_size() * usize::from(self.bit_depth);
+        let bytes_per_pixel = self.bytes_per_pixel();
+        let line_len = self.line_len();
+        let mut image = vec![0; line_len * self.height];
+
+        let mut pass_start = 0;
+        for (x_start, y_start, x_step, y_step) in ADAM7_PASSES {
+            let pass_width = (self.width + x_step - x_start - 1) / x_step;
+            let pass_height = (self.height + y_step - y_start - 1) / y_step;
+            if pass_width == 0 || pass_height == 0 {
+                // empty passes don't have any data (not even filter bytes)
+                continue;
+            }
+            let pass_line_len = 1 + (pass_width * bits_per_pixel).div_ceil(8);
+            let pass_end = pass_start + pass_line_len * pass_height;
+            if pass_end > self.image_data.len() {
+                return Err("PNG image data is too short for its dimensions".into());
+            }
+            let pass = &mut self.image_data[pass_start..pass_end];
+            for row in 0..pass_height {
+                let (before, rest) = pass.split_at_mut(row * pass_line_len);
+                let above = if row == 0 {
+                    None
+                } else {
+                    Some(&before[(row - 1) * pass_line_len..])
+                };
+                let line = &mut rest[..pass_line_len];
+                unfilter_line(line, above, bytes_per_pixel)?;
+
+                let y = y_start + row * y_step;
+                let out_line = &mut image[y * line_len + 1..(y + 1) * line_len];
+                for col in 0..pass_width {
+                    let x = x_start + col * x_step;
+                    copy_pixel(&line[1..], col, out_line, x, bits_per_pixel);
+                }
+            }
+            pass_start = pass_end;
+        }
+        self.image_data = image;
+        Ok(())
+    }
+}
+
+impl PngState {
+    /// The unfiltered data for the current line (without the filter type byte).
+    fn current_line(&self) -> &[u8] {
+        if self.interlaced {
+            let line_len = self.line_len();
+            &self.image_data[self.cur_y * line_len + 1..(self.cur_y + 1) * line_len]
+        } else {
+            &self.line[1..]
+        }
+    }
+
+    /// Move to the start of the next line, reading it in. Returns false once every line has been
+    /// read.
+    fn advance_line(&mut self, rb: &[u8], consumed: &mut usize) -> Result<bool, EtError> {
+        let y = if self.cur_x == usize::MAX {
+            0
+        } else {
+            self.cur_y + 1
+        };
+        if y >= self.height || self.width == 0 {
+            return Ok(false);
+        }
+        if self.interlaced {
+            if self.cur_x == usize::MAX {
+                self.read_image(rb, consumed)?;
+            }
+        } else {
+            self.next_line(rb, consumed, y)?;
+        }
+        self.cur_x = 0;
+        self.cur_y = y;
+        Ok(true)
+    }
+
+    /// The red, green, blue and alpha values of pixel `x` in the current line.
+    fn pixel(&self, x: usize) -> Result<(u16, u16, u16, u16), EtError> {
+        let bd = usize::from(self.bit_depth);
+
+        let line = self.current_line();
+        let pos = x * self.color_type.pixel_size();
+        Ok(match self.color_type {
+            PngColorType::Indexed => {
+                let palette_pos = get_bits(line, pos, bd, false)? as usize;
+                if let Some(palette) = &self.palette {
+                    if palette_pos >= palette.len() {
+                        return Err("Color index was outside palette dimensions".into());
+                    }
+                    let (red, green, blue) = palette[palette_pos];
+                    let alpha = match &self.transparency {
+                        Some(PngTransparency::Palette(alphas)) => alphas
+                            .get(palette_pos)
+                            .map_or(u16::MAX, |a| 257 * u16::from(*a)),
+                        _ => u16::MAX,
                     };
-                    let above_left = if pos == line_num * line_len + 1 || line_num == 0 {
+                    (red, green, blue, alpha)
+                } else {
+                    return Err("No palette was provided".into());
+                }
+            }
+            PngColorType::Grayscale => {
+                let gray = get_bits(line, pos, bd, true)?;
+                let alpha = match self.transparency {
+                    Some(PngTransparency::Gray(g)) if get_bits(line, pos, bd, false)? == g => 0,
+                    _ => u16::MAX,
+                };
+                (gray, gray, gray, alpha)
+            }
+            PngColorType::AlphaGrayscale => {
+                let gray = get_bits(line, pos, bd, true)?;
+                let alpha = get_bits(line, pos + 1, bd, true)?;
+                (gray, gray, gray, alpha)
+            }
+            PngColorType::Color => {
+                let red = get_bits(line, pos, bd, true)?;
+                let green = get_bits(line, pos + 1, bd, true)?;
+                let blue = get_bits(line, pos + 2, bd, true)?;
+                let alpha = match self.transparency {
+                    Some(PngTransparency::Color(r, g, b))
+                        if (
+                            get_bits(line, pos, bd, false)?,
+                            get_bits(line, pos + 1, bd, false)?,
+                            get_bits(line, pos + 2, bd, false)?,
+                        ) == (r, g, b) =>
+                    {
                         0
-                    } else {
-                        self.image_data[pos - 1 - line_len]
-                    };
-                    let estimate =
-                        i16::from(immediate_left) + i16::from(above) - i16::from(above_left);
-                    let pred_left = (estimate - i16::from(immediate_left)).abs();
-                    let pred_above = (estimate - i16::from(above)).abs();
-                    let pred_above_left = (estimate - i16::from(above_left)).abs();
-                    let paeth = if pred_left <= pred_above && pred_left <= pred_above_left {
-                        immediate_left
-                    } else if pred_above <= pred_above_left {
-                        above
-                    } else {
-                        above_left
-                    };
-                    self.image_data[pos].wrapping_add(paeth)
+                    }
+                    _ => u16::MAX,
+                };
+                (red, green, blue, alpha)
+            }
+            PngColorType::AlphaColor => {
+                let red = get_bits(line, pos, bd, true)?;
+                let green = get_bits(line, pos + 1, bd, true)?;
+                let blue = get_bits(line, pos + 2, bd, true)?;
+                let alpha = get_bits(line, pos + 3, bd, true)?;
+                (red, green, blue, alpha)
+            }
+        })
+    }
+}
+
+/// The starting x and y and the x and y spacing of each of the seven Adam7 passes
+const ADAM7_PASSES: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// Copy the pixel at `from_pos` in `from` into `to_pos` in `to`; pixels smaller than a byte are
+/// packed from the most significant bit down.
+fn copy_pixel(from: &[u8], from_pos: usize, to: &mut [u8], to_pos: usize, bits_per_pixel: usize) {
+    if bits_per_pixel >= 8 {
+        let size = bits_per_pixel / 8;
+        to[to_pos * size..(to_pos + 1) * size]
+            .copy_from_slice(&from[from_pos * size..(from_pos + 1) * size]);
+    } else {
+        let mask = 0xFF >> (8 - bits_per_pixel);
+        let from_shift = 8 - bits_per_pixel - (from_pos * bits_per_pixel) % 8;
+        let value = (from[from_pos * bits_per_pixel / 8] >> from_shift) & mask;
+        let to_shift = 8 - bits_per_pixel - (to_pos * bits_per_pixel) % 8;
+        let byte = &mut to[to_pos * bits_per_pixel / 8];
+        *byte = (*byte & !(mask << to_shift)) | (value << to_shift);
+    }
+}
+
+/// Reverse the filtering on a single line of image data (starting with its filter type byte),
+/// given the already-unfiltered line above it.
+fn unfilter_line(
+    line: &mut [u8],
+    above: Option<&[u8]>,
+    bytes_per_pixel: usize,
+) -> Result<(), EtError> {
+    let filter = line[0];
+    for pos in 1..line.len() {
+        let left = if pos <= bytes_per_pixel {
+            0
+        } else {
+            line[pos - bytes_per_pixel]
+        };
+        let up = above.map_or(0, |a| a[pos]);
+        line[pos] = match filter {
+            // no filtering; skip
+            0 => line[pos],
+            // sub filtering
+            1 => line[pos].wrapping_add(left),
+            // up filtering
+            2 => line[pos].wrapping_add(up),
+            // average filtering
+            3 => {
+                // average left and above together
+                let mut average = (left >> 1) + (up >> 1);
+                if left & up & 1 == 1 {
+                    average += 1;
                 }
-                _ => return Err("Unknown line filter".into()),
+                line[pos].wrapping_add(average)
+            }
+            // paeth filtering
+            4 => {
+                let up_left = if pos <= bytes_per_pixel {
+                    0
+                } else {
+                    above.map_or(0, |a| a[pos - bytes_per_pixel])
+                };
+                let estimate = i16::from(left) + i16::from(up) - i16::from(up_left);
+                let pred_left = (estimate - i16::from(left)).abs();
+                let pred_up = (estimate - i16::from(up)).abs();
+                let pred_up_left = (estimate - i16::from(up_left)).abs();
+                let paeth = if pred_left <= pred_up && pred_left <= pred_up_left {
+                    left
+                } else if pred_up <= pred_up_left {
+                    up
+                } else {
+                    up_left
+                };
+                line[pos].wrapping_add(paeth)
             }
+            _ => return Err("Unknown line filter".into()),
         }
-        self.image_data[line_num * line_len] = 0;
-        Ok(())
     }
+    line[0] = 0;
+    Ok(())
 }
 
 impl StateMetadata for PngState {
     fn metadata(&self) -> BTreeMap<String, Value> {
-        let mut metadata = BTreeMap::new();
+        let mut metadata = self.metadata.clone();
         drop(metadata.insert("height".to_string(), (self.height as u64).into()));
         drop(metadata.insert("width".to_string(), (self.width as u64).into()));
         metadata
@@ -176,16 +589,22 @@ impl<'b: 's, 's> FromSlice<'b, 's> for PngState {
         if extract::<u8>(rb, con, &mut Endian::Big)? != 0 {
             return Err("PNG filtering must be type 0".into());
         }
-        if extract::<u8>(rb, con, &mut Endian::Big)? != 0 {
-            return Err("PNG interlacing not supported yet".into());
+        if extract::<u8>(rb, con, &mut Endian::Big)? > 1 {
+            return Err("PNG interlacing must be type 0 or 1 (Adam7)".into());
         }
 
+        // read up to the start of the image data; that's streamed in as records are read
         loop {
+            // skip the checksum from the previous chunk
             let _ = extract::<&[u8]>(rb, con, &mut 4)?;
+            let chunk_start = *con;
             let mut chunk_size = extract::<u32>(rb, con, &mut Endian::Big)? as usize;
             let chunk_header = extract::<&[u8]>(rb, con, &mut 4)?;
-            if &chunk_header == b"IEND" {
+            if &chunk_header == b"IDAT" {
+                *con = chunk_start;
                 break;
+            } else if &chunk_header == b"IEND" {
+                return Err("PNG has no image data".into());
             }
             let _ = extract::<&[u8]>(rb, con, &mut chunk_size)?;
         }
@@ -200,18 +619,25 @@ impl<'b: 's, 's> FromSlice<'b, 's> for PngState {
         self.height = extract::<u32>(rb, con, &mut Endian::Big)? as usize;
         self.bit_depth = extract(rb, con, &mut Endian::Big)?;
         self.color_type = PngColorType::from_byte(extract(rb, con, &mut Endian::Big)?)?;
-        *con += 3;
+        // skip the compression and filter bytes
+        *con += 2;
+        let interlaced = extract::<u8>(rb, con, &mut Endian::Big)? == 1;
 
-        // parse through the entire file beforehand; because the data is compressed into multiple
-        // chunks and those chunks have to be concatenated before decompression, this makes
-        // writing the handler a lot easier (although we should maybe do this in a streaming
-        // fashion someday).
-        let mut compressed_data = Vec::new();
+        let mut metadata = BTreeMap::new();
+        let mut text = BTreeMap::new();
+        let mut transparency = None;
+        // the IHDR chunk's type starts after the magic and its length
+        let mut chunk_start = 12;
         loop {
-            // throw away the checksum from the previous chunk
-            let _ = extract::<&[u8]>(rb, con, &mut 4)?;
+            // check the checksum from the previous chunk
+            check_crc(rb, chunk_start, con)?;
+            if *con >= rb.len() {
+                // we've reached the image data
+                break;
+            }
             // now read the header for the current chunk
             let mut chunk_size = extract::<u32>(rb, con, &mut Endian::Big)? as usize;
+            chunk_start = *con;
             let chunk_header = extract::<&[u8]>(rb, con, &mut 4)?;
             match chunk_header {
                 b"PLTE" => {
@@ -228,12 +654,61 @@ impl<'b: 's, 's> FromSlice<'b, 's> for PngState {
                     }
                     self.palette = Some(raw_palette);
                 }
-                b"IDAT" => {
-                    // append all the IDAT chunks together
-                    compressed_data.extend_from_slice(extract(rb, con, &mut chunk_size)?);
+                b"tEXt" | b"zTXt" | b"iTXt" => {
+                    let data = extract::<&[u8]>(rb, con, &mut chunk_size)?;
+                    let (key, value) = parse_text_chunk(chunk_header, data)?;
+                    drop(text.insert(key, value.into()));
+                }
+                b"pHYs" => {
+                    let x = extract::<u32>(rb, con, &mut Endian::Big)?;
+                    let y = extract::<u32>(rb, con, &mut Endian::Big)?;
+                    let unit = match extract::<u8>(rb, con, &mut Endian::Big)? {
+                        1 => "meter",
+                        _ => "unknown",
+                    };
+                    drop(metadata.insert("pixels_per_unit_x".to_string(), x.into()));
+                    drop(metadata.insert("pixels_per_unit_y".to_string(), y.into()));
+                    drop(metadata.insert("pixel_unit".to_string(), unit.into()));
                 }
-                b"IEND" => {
-                    break;
+                b"gAMA" => {
+                    let gamma = extract::<u32>(rb, con, &mut Endian::Big)?;
+                    drop(
+                        metadata.insert("gamma".to_string(), (f64::from(gamma) / 100_000.).into()),
+                    );
+                }
+                b"cHRM" => {
+                    let mut chromaticity = BTreeMap::new();
+                    for key in &[
+                        "white_x", "white_y", "red_x", "red_y", "green_x", "green_y", "blue_x",
+                        "blue_y",
+                    ] {
+                        let value = extract::<u32>(rb, con, &mut Endian::Big)?;
+                        drop(
+                            chromaticity
+                                .insert((*key).to_string(), (f64::from(value) / 100_000.).into()),
+                        );
+                    }
+                    drop(metadata.insert("chromaticity".to_string(), Value::Record(chromaticity)));
+                }
+                b"sRGB" => {
+                    let intent = match extract::<u8>(rb, con, &mut Endian::Big)? {
+                        0 => "perceptual",
+                        1 => "relative colorimetric",
+                        2 => "saturation",
+                        3 => "absolute colorimetric",
+                        _ => return Err("Unknown PNG sRGB rendering intent".into()),
+                    };
+                    drop(metadata.insert("srgb_intent".to_string(), intent.into()));
+                }
+                b"tIME" => {
+                    let data = extract::<&[u8]>(rb, con, &mut chunk_size)?;
+                    drop(metadata.insert("modified".to_string(), parse_time_chunk(data)?));
+                }
+                b"tRNS" => {
+                    let data = extract::<&[u8]>(rb, con, &mut chunk_size)?;
+                    let trns = PngTransparency::from_chunk(self.color_type, data)?;
+                    drop(metadata.insert("transparency".to_string(), trns.to_value()));
+                    transparency = Some(trns);
                 }
                 _ => {
                     // just skip any other kinds of chunks
@@ -241,7 +716,12 @@ impl<'b: 's, 's> FromSlice<'b, 's> for PngState {
                 }
             }
         }
-        let _ = ZlibDecoder::new(&compressed_data[..]).read_to_end(&mut self.image_data)?;
+        if !text.is_empty() {
+            drop(metadata.insert("text".to_string(), Value::Record(text)));
+        }
+        self.metadata = metadata;
+        self.transparency = transparency;
+        self.interlaced = interlaced;
         // initialize x to MAX to sentinel we haven't started yet
         self.cur_x = usize::MAX;
         self.cur_y = 0;
@@ -250,6 +730,123 @@ impl<'b: 's, 's> FromSlice<'b, 's> for PngState {
     }
 }
 
+/// Check the CRC at `con` against the chunk type and data starting at `chunk_start`.
+fn check_crc(rb: &[u8], chunk_start: usize, con: &mut usize) -> Result<(), EtError> {
+    let chunk = rb
+        .get(chunk_start..*con)
+        .ok_or("PNG chunk extends past end of file")?;
+    let expected = extract::<u32>(rb, con, &mut Endian::Big)?;
+    let mut crc = Crc::new();
+    crc.update(chunk);
+    if crc.sum() != expected {
+        return Err(format!(
+            "PNG {} chunk is corrupt (CRC mismatch)",
+            String::from_utf8_lossy(&chunk[..4.min(chunk.len())])
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Decode the modification time from a `tIME` chunk.
+fn parse_time_chunk(data: &[u8]) -> Result<Value<'static>, EtError> {
+    if data.len() < 7 {
+        return Err("PNG tIME chunk is too short".into());
+    }
+    let year = u16::from_be_bytes([data[0], data[1]]);
+    let [month, day, hour, minute, second] = [data[2], data[3], data[4], data[5], data[6]];
+    let time = NaiveDate::from_ymd_opt(i32::from(year), u32::from(month), u32::from(day))
+        .and_then(|d| {
+            // PNG allows leap seconds (60)
+            d.and_hms_opt(
+                u32::from(hour),
+                u32::from(minute),
+                u32::from(second.min(59)),
+            )
+        })
+        .ok_or("Invalid PNG tIME")?;
+    Ok(time.into())
+}
+
+/// Decode the key and value from a `tEXt`, `zTXt`, or `iTXt` chunk.
+fn parse_text_chunk(chunk_type: &[u8], data: &[u8]) -> Result<(String, String), EtError> {
+    let key_end = memchr(0, data).ok_or("PNG text chunk has no keyword")?;
+    // keywords and tEXt/zTXt text are Latin-1
+    let latin1 = |bytes: &[u8]| bytes.iter().map(|b| char::from(*b)).collect::<String>();
+    let key = latin1(&data[..key_end]);
+    let rest = &data[key_end + 1..];
+    let value = match chunk_type {
+        b"tEXt" => latin1(rest),
+        b"zTXt" => {
+            if rest.first() != Some(&0) {
+                return Err("Unknown PNG zTXt compression method".into());
+            }
+            let mut text = Vec::new();
+            let _ = ZlibDecoder::new(&rest[1..]).read_to_end(&mut text)?;
+            latin1(&text)
+        }
+        _ => {
+            // iTXt has a compression flag and method and then the null-terminated language and
+            // translated keyword before the UTF-8 text
+            if rest.len() < 2 {
+                return Err("PNG iTXt chunk is too short".into());
+            }
+            let compressed = rest[0] == 1;
+            let mut text = &rest[2..];
+            for _ in 0..2 {
+                let end = memchr(0, text).ok_or("PNG iTXt chunk is missing fields")?;
+                text = &text[end + 1..];
+            }
+            if compressed {
+                let mut decompressed = Vec::new();
+                let _ = ZlibDecoder::new(text).read_to_end(&mut decompressed)?;
+                String::from_utf8(decompressed)?
+            } else {
+                String::from_utf8(text.to_vec())?
+            }
+        }
+    };
+    Ok((key, value))
+}
+
+/// Transparency information from a `tRNS` chunk
+#[derive(Clone, Debug)]
+enum PngTransparency {
+    /// The (unscaled) gray value that should be fully transparent
+    Gray(u16),
+    /// The (unscaled) RGB color that should be fully transparent
+    Color(u16, u16, u16),
+    /// The alpha values for each of the entries in the palette
+    Palette(Vec<u8>),
+}
+
+impl PngTransparency {
+    fn from_chunk(color_type: PngColorType, data: &[u8]) -> Result<Self, EtError> {
+        let sample =
+            |ix: usize| -> Result<u16, EtError> { u16::extract(&data[ix * 2..], &Endian::Big) };
+        Ok(match color_type {
+            PngColorType::Grayscale if data.len() >= 2 => PngTransparency::Gray(sample(0)?),
+            PngColorType::Color if data.len() >= 6 => {
+                PngTransparency::Color(sample(0)?, sample(1)?, sample(2)?)
+            }
+            PngColorType::Indexed => PngTransparency::Palette(data.to_vec()),
+            _ => return Err("PNG tRNS chunk is invalid for this color type".into()),
+        })
+    }
+
+    fn to_value(&self) -> Value<'static> {
+        match self {
+            PngTransparency::Gray(g) => (*g).into(),
+            PngTransparency::Color(r, g, b) => {
+                Value::List(vec![(*r).into(), (*g).into(), (*b).into()])
+            }
+            PngTransparency::Palette(alphas) => {
+                Value::List(alphas.iter().map(|a| (*a).into()).collect())
+            }
+        }
+    }
+}
+
 /// A single pixel from a PNG file
 #[derive(Clone, Copy, Debug, Default)]
 pub struct PngRecord {
@@ -267,7 +864,8 @@ fn get_bits(data: &[u8], pos: usize, n_bits: usize, rescale: bool) -> Result<u16
     if n_bits == 16 {
         u16::extract(&data[pos * 2..], &Endian::Big)
     } else {
-        let shift = n_bits * (pos % (8 / n_bits));
+        // pixels are packed starting from the most significant bit
+        let shift = 8 - n_bits - (pos * n_bits) % 8;
         let mask = u8::try_from(2u16.pow(u32::try_from(n_bits)?) - 1)?;
 
         let d = data[n_bits * pos / 8];
@@ -287,87 +885,442 @@ impl<'b: 's, 's> FromSlice<'b, 's> for PngRecord {
     type State = PngState;
 
     fn parse(
-        _rb: &[u8],
+        rb: &[u8],
         _eof: bool,
-        _consumed: &mut usize,
+        consumed: &mut usize,
         state: &mut Self::State,
     ) -> Result<bool, EtError> {
-        if state.cur_x == usize::MAX {
-            state.cur_x = 0;
+        let (mut x, mut y) = if state.cur_x == usize::MAX {
+            (0, 0)
         } else {
-            state.cur_x += 1;
-        }
-        if state.cur_x == state.width {
-            state.cur_x = 0;
-            state.cur_y += 1;
+            (state.cur_x + 1, state.cur_y)
+        };
+        if x == state.width {
+            x = 0;
+            y += 1;
         }
 
         // halt if we're outside the dimensions
-        if state.cur_y >= state.height {
+        if y >= state.height || state.width == 0 {
             return Ok(false);
         }
-        // unscramble the line if we're just starting it
-        if state.cur_x == 0 {
-            state.unfilter_line(state.cur_y)?;
+        // read in the next line if we're just starting it; note that this may return an
+        // incomplete error partway through, in which case we'll pick up where we left off
+        if state.interlaced {
+            if state.cur_x == usize::MAX {
+                state.read_image(rb, consumed)?;
+            }
+        } else if x == 0 {
+            state.next_line(rb, consumed, y)?;
         }
+        state.cur_x = x;
+        state.cur_y = y;
 
         Ok(true)
     }
 
     fn get(&mut self, _rb: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
-        let bd = usize::from(state.bit_depth);
+        let (red, green, blue, alpha) = state.pixel(state.cur_x)?;
+        self.red = red;
+        self.green = green;
+        self.blue = blue;
+        self.alpha = alpha;
 
-        let line = &state.image_data
-            [state.cur_y * state.line_len() + 1..(state.cur_y + 1) * state.line_len()];
-        let pos = state.cur_x * state.color_type.pixel_size();
-        let (red, green, blue, alpha) = match state.color_type {
-            PngColorType::Indexed => {
-                let palette_pos = get_bits(line, pos, bd, false)? as usize;
-                if let Some(palette) = &state.palette {
-                    if palette_pos >= palette.len() {
-                        return Err("Color index was outside palette dimensions".into());
-                    }
-                    let (red, green, blue) = palette[palette_pos];
-                    (red, green, blue, u16::MAX)
-                } else {
-                    return Err("No palette was provided".into());
-                }
+        self.x = u32::try_from(state.cur_x)?;
+        self.y = u32::try_from(state.cur_y)?;
+        Ok(())
+    }
+}
+
+impl_reader!(PngReader, PngRecord, PngRecord, PngState, ());
+
+/// The state for reading a PNG one scanline at a time
+#[derive(Debug, Default)]
+pub struct PngLineState {
+    png: PngState,
+}
+
+impl StateMetadata for PngLineState {
+    fn metadata(&self) -> BTreeMap<String, Value> {
+        self.png.metadata()
+    }
+
+    fn header(&self) -> Vec<&str> {
+        vec!["y", "red", "green", "blue", "alpha"]
+    }
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for PngLineState {
+    type State = ();
+
+    fn parse(
+        rb: &[u8],
+        eof: bool,
+        consumed: &mut usize,
+        state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        PngState::parse(rb, eof, consumed, state)
+    }
+
+    fn get(&mut self, rb: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
+        self.png.get(rb, state)
+    }
+}
+
+/// A single scanline from a PNG file with a list of values for each channel
+#[derive(Clone, Debug, Default)]
+pub struct PngLineRecord {
+    y: u32,
+    red: Vec<u16>,
+    green: Vec<u16>,
+    blue: Vec<u16>,
+    alpha: Vec<u16>,
+}
+
+impl_record!(PngLineRecord: y, red, green, blue, alpha);
+
+impl<'b: 's, 's> FromSlice<'b, 's> for PngLineRecord {
+    type State = PngLineState;
+
+    fn parse(
+        rb: &[u8],
+        _eof: bool,
+        consumed: &mut usize,
+        state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        state.png.advance_line(rb, consumed)
+    }
+
+    fn get(&mut self, _rb: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
+        let png = &state.png;
+        self.red.clear();
+        self.green.clear();
+        self.blue.clear();
+        self.alpha.clear();
+        for x in 0..png.width {
+            let (red, green, blue, alpha) = png.pixel(x)?;
+            self.red.push(red);
+            self.green.push(green);
+            self.blue.push(blue);
+            self.alpha.push(alpha);
+        }
+        self.y = u32::try_from(png.cur_y)?;
+        Ok(())
+    }
+}
+
+impl_reader!(
+    PngLineReader,
+    PngLineRecord,
+    PngLineRecord,
+    PngLineState,
+    ()
+);
+
+/// How the pixels in each region are combined
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PngStatistic {
+    /// The average value of each channel
+    #[default]
+    Mean,
+    /// The total of each channel
+    Sum,
+    /// The largest value of each channel
+    Max,
+}
+
+impl FromStr for PngStatistic {
+    type Err = EtError;
+
+    /// Parse a statistic from its name (`mean`, `sum`, or `max`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.trim().to_ascii_lowercase().as_str() {
+            "mean" => PngStatistic::Mean,
+            "sum" => PngStatistic::Sum,
+            "max" => PngStatistic::Max,
+            _ => return Err(format!("Unknown PNG region statistic {}", s).into()),
+        })
+    }
+}
+
+/// A rectangular region of a PNG image to summarize
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PngRegion {
+    /// The name the region is reported under
+    pub name: String,
+    /// The left edge of the region
+    pub x: usize,
+    /// The top edge of the region
+    pub y: usize,
+    /// The width of the region in pixels
+    pub width: usize,
+    /// The height of the region in pixels
+    pub height: usize,
+}
+
+impl FromStr for PngRegion {
+    type Err = EtError;
+
+    /// Parse a region written as `name:x,y,width,height`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, bounds) = s
+            .split_once(':')
+            .ok_or("PNG regions must look like name:x,y,width,height")?;
+        let bounds = bounds
+            .split(',')
+            .map(|b| b.trim().parse())
+            .collect::<Result<Vec<usize>, _>>()?;
+        if let [x, y, width, height] = bounds[..] {
+            Ok(PngRegion {
+                name: name.trim().to_string(),
+                x,
+                y,
+                width,
+                height,
+            })
+        } else {
+            Err("PNG regions must look like name:x,y,width,height".into())
+        }
+    }
+}
+
+/// Parameters for summarizing regions of a PNG image
+///
+/// If no regions or grid are given, the whole image is summarized as one region.
+#[derive(Clone, Debug, Default)]
+pub struct PngRegionParams {
+    regions: Vec<PngRegion>,
+    grid: Option<(usize, usize)>,
+    statistic: PngStatistic,
+}
+
+impl PngRegionParams {
+    /// Summarize each of these regions.
+    #[must_use]
+    pub fn regions(mut self, regions: Vec<PngRegion>) -> Self {
+        self.regions = regions;
+        self
+    }
+
+    /// Split the image into an evenly-spaced grid and summarize each cell; cells are named like
+    /// the wells of a plate (e.g. A1, A2, ..., B1) and reported after any other regions.
+    #[must_use]
+    pub fn grid(mut self, rows: usize, columns: usize) -> Self {
+        self.grid = Some((rows, columns));
+        self
+    }
+
+    /// How the pixels in each region should be combined.
+    #[must_use]
+    pub fn statistic(mut self, statistic: PngStatistic) -> Self {
+        self.statistic = statistic;
+        self
+    }
+}
+
+/// The spreadsheet-style name for a (zero-indexed) grid row, e.g. A, B, ..., Z, AA, AB.
+fn row_name(mut row: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + u8::try_from(row % 26).unwrap_or_default());
+        if row < 26 {
+            break;
+        }
+        row = row / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8_lossy(&name).into_owned()
+}
+
+/// The state for summarizing regions of a PNG image
+///
+/// The image is still read one scanline at a time and the statistics for each region are updated
+/// as the lines are read, so the first region is returned once the whole image has been read.
+#[derive(Debug, Default)]
+pub struct PngRegionState {
+    png: PngState,
+    regions: Vec<PngRegion>,
+    statistic: PngStatistic,
+    // the red, green, blue and alpha statistics for each region
+    values: Vec<[f64; 4]>,
+    image_read: bool,
+    cur_region: usize,
+}
+
+impl PngRegionState {
+    /// Read all the remaining lines in the image, updating the statistics with each.
+    fn read_image(&mut self, rb: &[u8], con: &mut usize) -> Result<(), EtError> {
+        while self.png.advance_line(&rb[*con..], con)? {
+            self.add_line()?;
+        }
+        Ok(())
+    }
+
+    /// Update the statistics for every region that overlaps the current line.
+    #[allow(clippy::cast_precision_loss)]
+    fn add_line(&mut self) -> Result<(), EtError> {
+        let y = self.png.cur_y;
+        for (region, values) in self.regions.iter().zip(self.values.iter_mut()) {
+            if y < region.y || y >= region.y + region.height {
+                continue;
             }
-            PngColorType::Grayscale => {
-                let gray = get_bits(line, pos, bd, true)?;
-                (gray, gray, gray, u16::MAX)
+            for x in region.x..region.x + region.width {
+                let (red, green, blue, alpha) = self.png.pixel(x)?;
+                for (value, channel) in values.iter_mut().zip([red, green, blue, alpha]) {
+                    let channel = f64::from(channel);
+                    *value = match self.statistic {
+                        PngStatistic::Max => value.max(channel),
+                        PngStatistic::Mean | PngStatistic::Sum => *value + channel,
+                    };
+                }
             }
-            PngColorType::AlphaGrayscale => {
-                let gray = get_bits(line, pos, bd, true)?;
-                let alpha = get_bits(line, pos + 1, bd, true)?;
-                (gray, gray, gray, alpha)
+        }
+        Ok(())
+    }
+}
+
+impl StateMetadata for PngRegionState {
+    fn metadata(&self) -> BTreeMap<String, Value> {
+        self.png.metadata()
+    }
+
+    fn header(&self) -> Vec<&str> {
+        vec![
+            "region", "x", "y", "width", "height", "red", "green", "blue", "alpha",
+        ]
+    }
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for PngRegionState {
+    type State = PngRegionParams;
+
+    fn parse(
+        rb: &[u8],
+        eof: bool,
+        consumed: &mut usize,
+        _state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        PngState::parse(rb, eof, consumed, &mut ())
+    }
+
+    fn get(&mut self, rb: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
+        self.png.get(rb, &())?;
+        let (width, height) = (self.png.width, self.png.height);
+
+        let mut regions = state.regions.clone();
+        if let Some((rows, columns)) = state.grid {
+            if rows == 0 || columns == 0 || rows > height || columns > width {
+                return Err("PNG grid doesn't fit in the image".into());
             }
-            PngColorType::Color => {
-                let red = get_bits(line, pos, bd, true)?;
-                let green = get_bits(line, pos + 1, bd, true)?;
-                let blue = get_bits(line, pos + 2, bd, true)?;
-                (red, green, blue, u16::MAX)
+            for row in 0..rows {
+                for column in 0..columns {
+                    let (x, y) = (column * width / columns, row * height / rows);
+                    regions.push(PngRegion {
+                        name: format!("{}{}", row_name(row), column + 1),
+                        x,
+                        y,
+                        width: (column + 1) * width / columns - x,
+                        height: (row + 1) * height / rows - y,
+                    });
+                }
             }
-            PngColorType::AlphaColor => {
-                let red = get_bits(line, pos, bd, true)?;
-                let green = get_bits(line, pos + 1, bd, true)?;
-                let blue = get_bits(line, pos + 2, bd, true)?;
-                let alpha = get_bits(line, pos + 3, bd, true)?;
-                (red, green, blue, alpha)
+        }
+        if regions.is_empty() {
+            regions.push(PngRegion {
+                name: "image".to_string(),
+                x: 0,
+                y: 0,
+                width,
+                height,
+            });
+        }
+        for region in &regions {
+            if region.width == 0
+                || region.height == 0
+                || region.x + region.width > width
+                || region.y + region.height > height
+            {
+                return Err(format!("PNG region {} is outside the image", region.name).into());
             }
-        };
+        }
+        self.values = vec![[0.; 4]; regions.len()];
+        self.regions = regions;
+        self.statistic = state.statistic;
+        self.image_read = false;
+        self.cur_region = 0;
+        Ok(())
+    }
+}
+
+/// Statistics for each channel over one region of a PNG file
+#[derive(Clone, Debug, Default)]
+pub struct PngRegionRecord {
+    region: String,
+    x: u64,
+    y: u64,
+    width: u64,
+    height: u64,
+    red: f64,
+    green: f64,
+    blue: f64,
+    alpha: f64,
+}
+
+impl_record!(PngRegionRecord: region, x, y, width, height, red, green, blue, alpha);
+
+impl<'b: 's, 's> FromSlice<'b, 's> for PngRegionRecord {
+    type State = PngRegionState;
+
+    fn parse(
+        rb: &[u8],
+        _eof: bool,
+        consumed: &mut usize,
+        state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        if !state.image_read {
+            // keep track of everything we've read so a refill doesn't reread any lines
+            let con = &mut 0;
+            let result = state.read_image(rb, con);
+            *consumed += *con;
+            result?;
+            state.image_read = true;
+        } else {
+            state.cur_region += 1;
+        }
+        Ok(state.cur_region < state.regions.len())
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn get(&mut self, _rb: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
+        let region = &state.regions[state.cur_region];
+        let [mut red, mut green, mut blue, mut alpha] = state.values[state.cur_region];
+        if state.statistic == PngStatistic::Mean {
+            let n_pixels = (region.width * region.height) as f64;
+            red /= n_pixels;
+            green /= n_pixels;
+            blue /= n_pixels;
+            alpha /= n_pixels;
+        }
+        self.region.clone_from(&region.name);
+        self.x = region.x as u64;
+        self.y = region.y as u64;
+        self.width = region.width as u64;
+        self.height = region.height as u64;
         self.red = red;
         self.green = green;
         self.blue = blue;
         self.alpha = alpha;
-
-        self.x = u32::try_from(state.cur_x)?;
-        self.y = u32::try_from(state.cur_y)?;
         Ok(())
     }
 }
 
-impl_reader!(PngReader, PngRecord, PngRecord, PngState, ());
+impl_reader!(
+    PngRegionReader,
+    PngRegionRecord,
+    PngRegionRecord,
+    PngRegionState,
+    PngRegionParams
+);
 
 #[cfg(test)]
 mod tests {
@@ -405,17 +1358,17 @@ mod tests {
         Ok(())
     }
 
+    // data from https://en.wikipedia.org/wiki/Portable_Network_Graphics
+    const TEST_IMAGE: &[u8] = &[
+        0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44,
+        0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x02, 0x00, 0x00, 0x00, 0x90,
+        0x77, 0x53, 0xDE, 0x00, 0x00, 0x00, 0x0C, 0x49, 0x44, 0x41, 0x54, 0x08, 0xD7, 0x63, 0xF8,
+        0xCF, 0xC0, 0x00, 0x00, 0x03, 0x01, 0x01, 0x00, 0x18, 0xDD, 0x8D, 0xB0, 0x00, 0x00, 0x00,
+        0x00, 0x49, 0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82,
+    ];
+
     #[test]
     fn test_minimal_png() -> Result<(), EtError> {
-        // data from https://en.wikipedia.org/wiki/Portable_Network_Graphics
-        const TEST_IMAGE: &[u8] = &[
-            0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48,
-            0x44, 0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x02, 0x00, 0x00,
-            0x00, 0x90, 0x77, 0x53, 0xDE, 0x00, 0x00, 0x00, 0x0C, 0x49, 0x44, 0x41, 0x54, 0x08,
-            0xD7, 0x63, 0xF8, 0xCF, 0xC0, 0x00, 0x00, 0x03, 0x01, 0x01, 0x00, 0x18, 0xDD, 0x8D,
-            0xB0, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82,
-        ];
-
         let mut reader = PngReader::new(TEST_IMAGE, None)?;
         let _ = reader.metadata();
         let pixel = reader.next()?.expect("first pixel exists");
@@ -429,4 +1382,274 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_interlaced_png() -> Result<(), EtError> {
+        let rb: &[u8] = &include_bytes!("../../tests/data/adam7_rgb.png")[..];
+        let mut reader = PngReader::new(rb, None)?;
+        for y in 0..11 {
+            for x in 0..13 {
+                let pixel = reader.next()?.expect("pixel exists");
+                assert_eq!((pixel.x, pixel.y), (x, y));
+                assert_eq!(pixel.red, 257 * ((x * 19) % 256) as u16);
+                assert_eq!(pixel.green, 257 * ((y * 23) % 256) as u16);
+                assert_eq!(pixel.blue, 257 * ((x * y * 7 + x) % 256) as u16);
+            }
+        }
+        assert!(reader.next()?.is_none());
+
+        let rb: &[u8] = &include_bytes!("../../tests/data/adam7_gray2.png")[..];
+        let mut reader = PngReader::new(rb, None)?;
+        for y in 0..6 {
+            for x in 0..9 {
+                let pixel = reader.next()?.expect("pixel exists");
+                assert_eq!((pixel.x, pixel.y), (x, y));
+                assert_eq!(pixel.red, 21845 * ((x + 2 * y) % 4) as u16);
+            }
+        }
+        assert!(reader.next()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_png_ancillary_chunks() -> Result<(), EtError> {
+        let rb: &[u8] = &include_bytes!("../../tests/data/ancillary.png")[..];
+        let mut reader = PngReader::new(rb, None)?;
+        let metadata = reader.metadata();
+        assert_eq!(metadata["gamma"], 0.45455.into());
+        assert_eq!(metadata["srgb_intent"], "perceptual".into());
+        assert_eq!(metadata["pixels_per_unit_x"], 3780u32.into());
+        assert_eq!(metadata["pixel_unit"], "meter".into());
+        assert_eq!(
+            metadata["modified"],
+            NaiveDate::from_ymd_opt(2021, 3, 14)
+                .and_then(|d| d.and_hms_opt(15, 9, 26))
+                .expect("valid date")
+                .into()
+        );
+        if let Value::Record(chromaticity) = &metadata["chromaticity"] {
+            assert_eq!(chromaticity["white_x"], 0.3127.into());
+        } else {
+            panic!("Chromaticity should be a record");
+        }
+        if let Value::Record(text) = &metadata["text"] {
+            assert_eq!(text["Title"], "Gel image".into());
+            assert_eq!(text["Comment"], "Lane 1: ladder".into());
+            assert_eq!(text["Author"], "J\u{fc}rgen".into());
+        } else {
+            panic!("Text should be a record");
+        }
+
+        // the red pixel is marked as transparent by the tRNS chunk
+        let pixel = reader.next()?.expect("first pixel exists");
+        assert_eq!((pixel.red, pixel.alpha), (65535, 0));
+        let pixel = reader.next()?.expect("second pixel exists");
+        assert_eq!((pixel.blue, pixel.alpha), (65535, 65535));
+        assert!(reader.next()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_png_bad_crc() {
+        let mut data = include_bytes!("../../tests/data/bmp_24.png").to_vec();
+        // flip a bit in the IHDR width
+        data[19] ^= 1;
+        let err = PngReader::new(&data[..], None).expect_err("bad CRC should fail");
+        assert!(err.to_string().contains("CRC mismatch"));
+    }
+
+    #[test]
+    fn test_png_chunks_after_image_data() -> Result<(), EtError> {
+        // add a tEXt chunk between the IDAT and IEND chunks
+        let mut chunk = b"tEXt".to_vec();
+        chunk.extend_from_slice(b"Comment\0written after the image");
+        let mut crc = Crc::new();
+        crc.update(&chunk);
+        let mut text = u32::try_from(chunk.len() - 4)?.to_be_bytes().to_vec();
+        text.extend_from_slice(&chunk);
+        text.extend_from_slice(&crc.sum().to_be_bytes());
+        let mut data = TEST_IMAGE.to_vec();
+        let iend = data.len() - 12;
+        drop(data.splice(iend..iend, text));
+
+        let mut reader = PngReader::new(&data[..], None)?;
+        assert!(!reader.metadata().contains_key("text"));
+        let _ = reader.next()?.expect("first pixel exists");
+        if let Value::Record(text) = &reader.metadata()["text"] {
+            assert_eq!(text["Comment"], "written after the image".into());
+        } else {
+            panic!("Text should be a record");
+        }
+        assert!(reader.next()?.is_none());
+
+        // the last IDAT chunk's CRC is checked too
+        let mut data = TEST_IMAGE.to_vec();
+        data[53] ^= 1;
+        let mut reader = PngReader::new(&data[..], None)?;
+        let err = reader.next().expect_err("bad CRC should fail");
+        assert!(err.to_string().contains("IDAT chunk is corrupt"));
+
+        // as is the IEND chunk's
+        let mut data = TEST_IMAGE.to_vec();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        let mut reader = PngReader::new(&data[..], None)?;
+        let err = reader.next().expect_err("bad CRC should fail");
+        assert!(err.to_string().contains("IEND chunk is corrupt"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_png_streaming() -> Result<(), EtError> {
+        use crate::buffer::ReadBuffer;
+
+        for data in [
+            &include_bytes!("../../tests/data/bmp_24.png")[..],
+            &include_bytes!("../../tests/data/adam7_rgb.png")[..],
+        ] {
+            // a tiny buffer forces the image data to be decompressed across many refills
+            let rb = ReadBuffer::from_reader(Box::new(data), Some(32))?;
+            let mut streamed = PngReader::new(rb, None)?;
+            let mut reader = PngReader::new(data, None)?;
+            while let Some(pixel) = reader.next()? {
+                let (x, y, red, alpha) = (pixel.x, pixel.y, pixel.red, pixel.alpha);
+                let other = streamed.next()?.expect("streamed pixel exists");
+                assert_eq!(
+                    (other.x, other.y, other.red, other.alpha),
+                    (x, y, red, alpha)
+                );
+            }
+            assert!(streamed.next()?.is_none());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_png_scanlines() -> Result<(), EtError> {
+        let data = &include_bytes!("../../tests/data/adam7_rgb.png")[..];
+        let mut lines = PngLineReader::new(data, None)?;
+        assert_eq!(lines.headers(), vec!["y", "red", "green", "blue", "alpha"]);
+        let mut pixels = PngReader::new(data, None)?;
+        let mut n_lines = 0;
+        while let Some(line) = lines.next()? {
+            assert_eq!(line.y, n_lines);
+            assert_eq!(line.red.len(), 13);
+            for x in 0..13 {
+                let pixel = pixels.next()?.expect("pixel exists");
+                assert_eq!((pixel.x, pixel.y), (x, n_lines));
+                assert_eq!(pixel.red, line.red[x as usize]);
+                assert_eq!(pixel.blue, line.blue[x as usize]);
+            }
+            n_lines += 1;
+        }
+        assert_eq!(n_lines, 11);
+
+        let mut lines = PngLineReader::new(data, None)?;
+        let record = lines.next_record()?.expect("first line");
+        assert!(matches!(&record[1], Value::List(l) if l.len() == 13));
+        Ok(())
+    }
+
+    #[test]
+    fn test_png_regions() -> Result<(), EtError> {
+        use crate::buffer::ReadBuffer;
+
+        let data = &include_bytes!("../../tests/data/bmp_24.png")[..];
+        // calculate the expected values for the bottom right quarter of the image
+        let mut pixels = PngReader::new(data, None)?;
+        let (mut sum, mut max, mut n) = (0., 0u16, 0.);
+        while let Some(pixel) = pixels.next()? {
+            if pixel.x >= 100 && pixel.y >= 100 {
+                sum += f64::from(pixel.green);
+                max = max.max(pixel.green);
+                n += 1.;
+            }
+        }
+
+        let params = PngRegionParams::default().grid(2, 2);
+        let mut reader = PngRegionReader::new(data, Some(params))?;
+        let mut names = Vec::new();
+        let mut last = None;
+        while let Some(record) = reader.next()? {
+            names.push(record.region.clone());
+            last = Some(record);
+        }
+        assert_eq!(names, vec!["A1", "A2", "B1", "B2"]);
+        let last = last.expect("region exists");
+        assert_eq!(
+            (last.x, last.y, last.width, last.height),
+            (100, 100, 100, 100)
+        );
+        assert!((last.green - sum / n).abs() < 1e-9);
+
+        let region = PngRegion {
+            name: "well".to_string(),
+            x: 100,
+            y: 100,
+            width: 100,
+            height: 100,
+        };
+        for (statistic, expected) in [
+            (PngStatistic::Sum, sum),
+            (PngStatistic::Max, f64::from(max)),
+        ] {
+            let params = PngRegionParams::default()
+                .regions(vec![region.clone()])
+                .statistic(statistic);
+            // read with a tiny buffer to make sure lines aren't skipped or reread on refills
+            let rb = ReadBuffer::from_reader(Box::new(data), Some(32))?;
+            let mut reader = PngRegionReader::new(rb, Some(params))?;
+            let record = reader.next()?.expect("region exists");
+            assert_eq!(record.region, "well");
+            assert!((record.green - expected).abs() < 1e-9);
+            assert!(reader.next()?.is_none());
+        }
+
+        let params = PngRegionParams::default().regions(vec![PngRegion {
+            width: 300,
+            ..region
+        }]);
+        assert!(PngRegionReader::new(data, Some(params)).is_err());
+        assert_eq!(row_name(27), "AB");
+        Ok(())
+    }
+
+    #[test]
+    fn test_png_parser_names() -> Result<(), EtError> {
+        use crate::readers::get_reader;
+
+        let data = &include_bytes!("../../tests/data/bmp_24.png")[..];
+        let (mut reader, _) = get_reader(data, Some("png_lines"), None)?;
+        assert_eq!(reader.headers(), ["y", "red", "green", "blue", "alpha"]);
+        let mut n_lines = 0;
+        while reader.next_record()?.is_some() {
+            n_lines += 1;
+        }
+        assert_eq!(n_lines, 200);
+
+        // without any regions, the whole image is summarized
+        let (mut reader, _) = get_reader(data, Some("png_regions"), None)?;
+        let record = reader.next_record()?.expect("one region");
+        assert_eq!(
+            record[..5],
+            ["image".into(), 0.into(), 0.into(), 200.into(), 200.into()]
+        );
+        assert!(reader.next_record()?.is_none());
+
+        let mut params = BTreeMap::new();
+        drop(params.insert("regions".to_string(), "well:100,100,100,100".into()));
+        drop(params.insert("grid".to_string(), "1x2".into()));
+        drop(params.insert("statistic".to_string(), "max".into()));
+        let (mut reader, _) = get_reader(data, Some("png_regions"), Some(params))?;
+        let mut names = Vec::new();
+        while let Some(record) = reader.next_record()? {
+            names.push(record[0].clone().into_string()?);
+        }
+        assert_eq!(names, ["well", "A1", "A2"]);
+
+        let mut params = BTreeMap::new();
+        drop(params.insert("statistic".to_string(), "median".into()));
+        assert!(get_reader(data, Some("png_regions"), Some(params)).is_err());
+        Ok(())
+    }
 }
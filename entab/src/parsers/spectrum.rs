@@ -0,0 +1,252 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::readers::RecordReader;
+use crate::record::Value;
+use crate::EtError;
+
+/// The parsers that emit one `time`/`mz`/`intensity` row per peak and can be grouped into scans
+pub const SPECTRUM_PARSERS: [&str; 8] = [
+    "chemstation_ms",
+    "inficon",
+    "mzml",
+    "mzxml",
+    "netcdf",
+    "thermo_cf",
+    "thermo_dxf",
+    "thermo_raw",
+];
+
+/// Find the position of `name` in `headers`
+fn column(headers: &[String], name: &str) -> Result<usize, EtError> {
+    headers
+        .iter()
+        .position(|h| h == name)
+        .ok_or_else(|| format!("Spectrum mode needs a {} column", name).into())
+}
+
+/// Regroups the rows of a mass spectrometry reader into one record per scan.
+///
+/// Rows are grouped by the `scan` column of the underlying reader and rows without a scan number
+/// (e.g. points from chromatograms) are skipped. Every record has the scan's time, its scan
+/// number, any other per-scan columns from the underlying reader, lists of the m/z values and
+/// intensities in the scan, and the total ion current and base peak of the scan. Scans without
+/// any points in them aren't in the underlying reader so they're not returned.
+#[derive(Debug)]
+pub struct SpectrumReader<'r> {
+    reader: Box<dyn RecordReader + 'r>,
+    time_col: usize,
+    scan_col: usize,
+    mz_col: usize,
+    intensity_col: usize,
+    scan_cols: Vec<usize>,
+    scan_headers: Vec<String>,
+    next_row: Option<Vec<Value<'static>>>,
+    started: bool,
+}
+
+impl<'r> SpectrumReader<'r> {
+    /// Wrap a reader with `time`, `scan`, `mz`, and `intensity` columns (e.g. a
+    /// `ChemstationMsReader`).
+    ///
+    /// # Errors
+    /// If the reader doesn't have the columns needed, an `EtError` is returned.
+    pub fn new(reader: Box<dyn RecordReader + 'r>) -> Result<Self, EtError> {
+        let headers = reader.headers();
+        let time_col = column(&headers, "time")?;
+        let scan_col = column(&headers, "scan")?;
+        let mz_col = column(&headers, "mz")?;
+        let intensity_col = column(&headers, "intensity")?;
+        let scan_cols: Vec<usize> = (0..headers.len())
+            .filter(|ix| ![time_col, scan_col, mz_col, intensity_col].contains(ix))
+            .collect();
+        let scan_headers = scan_cols.iter().map(|ix| headers[*ix].clone()).collect();
+        Ok(SpectrumReader {
+            reader,
+            time_col,
+            scan_col,
+            mz_col,
+            intensity_col,
+            scan_cols,
+            scan_headers,
+            next_row: None,
+            started: false,
+        })
+    }
+
+    /// Read the next row that's part of a scan from the underlying reader, copied out so it can
+    /// outlive the read
+    fn read_row(&mut self) -> Result<Option<Vec<Value<'static>>>, EtError> {
+        while let Some(row) = self.reader.next_record()? {
+            if row[self.scan_col] != Value::Null {
+                return Ok(Some(row.into_iter().map(Value::into_owned).collect()));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<'r> RecordReader for SpectrumReader<'r> {
+    fn next_record(&mut self) -> Result<Option<Vec<Value>>, EtError> {
+        if !self.started {
+            self.started = true;
+            self.next_row = self.read_row()?;
+        }
+        let first = match self.next_row.take() {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+
+        let mut mzs = Vec::new();
+        let mut intensities = Vec::new();
+        let mut tic = 0.;
+        let mut base_peak: Option<(Value, f64)> = None;
+        let mut row = Some(first.clone());
+        while let Some(cur_row) = row {
+            if cur_row[self.scan_col] != first[self.scan_col] {
+                self.next_row = Some(cur_row);
+                break;
+            }
+            let mz = cur_row[self.mz_col].clone();
+            let intensity = cur_row[self.intensity_col].clone();
+            let value = match intensity {
+                Value::Float(f) => f,
+                #[allow(clippy::cast_precision_loss)]
+                Value::Integer(i) => i as f64,
+                _ => 0.,
+            };
+            tic += value;
+            match base_peak {
+                Some((_, i)) if i >= value => {}
+                _ => base_peak = Some((mz.clone(), value)),
+            }
+            mzs.push(mz);
+            intensities.push(intensity);
+            row = self.read_row()?;
+        }
+
+        let (base_peak_mz, base_peak_intensity) = match base_peak {
+            Some((mz, intensity)) => (mz, intensity.into()),
+            None => (Value::Null, Value::Null),
+        };
+        let mut record = vec![first[self.time_col].clone(), first[self.scan_col].clone()];
+        record.extend(self.scan_cols.iter().map(|ix| first[*ix].clone()));
+        record.extend([
+            mzs.into(),
+            intensities.into(),
+            tic.into(),
+            base_peak_mz,
+            base_peak_intensity,
+        ]);
+        Ok(Some(record))
+    }
+
+    fn headers(&self) -> Vec<String> {
+        let mut headers = vec!["time".to_string(), "scan".to_string()];
+        headers.extend(self.scan_headers.iter().cloned());
+        headers.extend(
+            [
+                "mz",
+                "intensity",
+                "tic",
+                "base_peak_mz",
+                "base_peak_intensity",
+            ]
+            .iter()
+            .map(ToString::to_string),
+        );
+        headers
+    }
+
+    fn metadata(&self) -> BTreeMap<String, Value> {
+        self.reader.metadata()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::readers::get_reader;
+
+    fn spectrum_params() -> Option<BTreeMap<String, Value<'static>>> {
+        let mut params = BTreeMap::new();
+        drop(params.insert("spectrum".to_string(), Value::Boolean(true)));
+        Some(params)
+    }
+
+    #[test]
+    fn test_spectrum_chemstation_ms() -> Result<(), EtError> {
+        let data: &[u8] = include_bytes!("../../tests/data/carotenoid_extract.d/MSD1.MS");
+        let (mut reader, _) = get_reader(data, None, spectrum_params())?;
+        assert_eq!(
+            reader.headers(),
+            [
+                "time",
+                "scan",
+                "mz",
+                "intensity",
+                "tic",
+                "base_peak_mz",
+                "base_peak_intensity"
+            ]
+        );
+        let record = reader.next_record()?.unwrap();
+        assert_eq!(record[1], Value::Integer(1));
+        if let (Value::List(mzs), Value::List(intensities)) = (&record[2], &record[3]) {
+            assert_eq!(mzs.len(), intensities.len());
+            assert_eq!(mzs[0], Value::Float(915.7));
+        } else {
+            panic!("m/z and intensity should be lists");
+        }
+
+        let mut n_scans = 1;
+        while let Some(record) = reader.next_record()? {
+            n_scans += 1;
+            assert_eq!(record[1], Value::Integer(n_scans));
+        }
+        assert_eq!(n_scans, 2534);
+        Ok(())
+    }
+
+    #[test]
+    fn test_spectrum_thermo_raw() -> Result<(), EtError> {
+        let data: &[u8] = include_bytes!("../../tests/data/small.RAW");
+        let (mut reader, _) = get_reader(data, None, spectrum_params())?;
+        let headers = reader.headers();
+        assert_eq!(headers[..3], ["time", "scan", "ms_level"]);
+        let mz_col = headers.iter().position(|h| h == "mz").unwrap();
+        let filter_col = headers.iter().position(|h| h == "filter").unwrap();
+        let mut n_scans = 0;
+        let mut n_ms2_scans = 0;
+        let mut n_peaks = 0;
+        while let Some(record) = reader.next_record()? {
+            n_scans += 1;
+            if record[2] == Value::Integer(2) {
+                n_ms2_scans += 1;
+            }
+            if n_scans == 3 {
+                assert_eq!(
+                    record[filter_col],
+                    "ITMS + c ESI d Full ms2 810.79@cid35.00 [210.00-1635.00]".into()
+                );
+            }
+            if let Value::List(mzs) = &record[mz_col] {
+                n_peaks += mzs.len();
+            }
+        }
+        assert_eq!(n_scans, 48);
+        assert_eq!(n_ms2_scans, 34);
+        assert_eq!(n_peaks, 242_645);
+        Ok(())
+    }
+
+    #[test]
+    fn test_spectrum_bad_parser() {
+        let data: &[u8] = b">test\nACGT\n";
+        assert!(get_reader(data, None, spectrum_params()).is_err());
+    }
+}
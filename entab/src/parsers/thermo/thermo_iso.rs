@@ -6,11 +6,11 @@ use alloc::vec::Vec;
 use core::char::{decode_utf16, REPLACEMENT_CHARACTER};
 use core::marker::Copy;
 
+use crate::impl_reader;
 use crate::parsers::common::{SeekPattern, Skip};
 use crate::parsers::{extract, extract_opt, Endian, FromSlice};
-use crate::record::StateMetadata;
+use crate::record::{StateMetadata, Value};
 use crate::EtError;
-use crate::{impl_reader, impl_record};
 
 /// A string serialized out by the MFC framework.
 #[derive(Debug, Default)]
@@ -86,14 +86,31 @@ fn mzs_from_gas(gas: &str) -> Result<Vec<f64>, EtError> {
     })
 }
 
+/// Parameters for the `ThermoDxfReader` and `ThermoCfReader`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThermoIsoParams {
+    scan_info: bool,
+}
+
+impl ThermoIsoParams {
+    /// Also return the number of the scan with every point
+    #[must_use]
+    pub fn scan_info(mut self, scan_info: bool) -> Self {
+        self.scan_info = scan_info;
+        self
+    }
+}
+
 /// The current state of the `ThermoDxfReader`
 #[derive(Clone, Debug)]
 pub struct ThermoDxfState {
+    scan_info: bool,
     first: bool,
     n_scans_left: usize,
     cur_mz_idx: usize,
     mzs: Vec<f64>,
     cur_time: f64,
+    cur_scan: u32,
     cur_mz: f64,
     cur_intensity: f64,
 }
@@ -101,11 +118,13 @@ pub struct ThermoDxfState {
 impl Default for ThermoDxfState {
     fn default() -> Self {
         ThermoDxfState {
+            scan_info: false,
             first: true,
             n_scans_left: 0,
             cur_mz_idx: 0,
             mzs: Vec::new(),
             cur_time: 0.,
+            cur_scan: 0,
             cur_mz: 0.,
             cur_intensity: 0.,
         }
@@ -114,12 +133,21 @@ impl Default for ThermoDxfState {
 
 impl StateMetadata for ThermoDxfState {
     fn header(&self) -> Vec<&str> {
-        vec!["time", "mz", "intensity"]
+        if self.scan_info {
+            vec!["time", "scan", "mz", "intensity"]
+        } else {
+            vec!["time", "mz", "intensity"]
+        }
     }
 }
 
 impl<'b: 's, 's> FromSlice<'b, 's> for ThermoDxfState {
-    type State = ();
+    type State = ThermoIsoParams;
+
+    fn get(&mut self, _buf: &'b [u8], params: &'s Self::State) -> Result<(), EtError> {
+        self.scan_info = params.scan_info;
+        Ok(())
+    }
 }
 
 /// A single data point from a Thermo DXF file
@@ -127,13 +155,33 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ThermoDxfState {
 pub struct ThermoDxfRecord {
     /// The time the reading was taken at
     pub time: f64,
+    /// The (1-based) number of the scan this was recorded in
+    pub scan: u32,
     /// The mz value of the reading
     pub mz: f64,
     /// The intensity recorded
     pub intensity: f64,
+    scan_info: bool,
 }
 
-impl_record!(ThermoDxfRecord: time, mz, intensity);
+impl<'r> From<ThermoDxfRecord> for Vec<Value<'r>> {
+    fn from(record: ThermoDxfRecord) -> Self {
+        if record.scan_info {
+            vec![
+                record.time.into(),
+                record.scan.into(),
+                record.mz.into(),
+                record.intensity.into(),
+            ]
+        } else {
+            vec![
+                record.time.into(),
+                record.mz.into(),
+                record.intensity.into(),
+            ]
+        }
+    }
+}
 
 impl<'b: 's, 's> FromSlice<'b, 's> for ThermoDxfRecord {
     type State = ThermoDxfState;
@@ -203,6 +251,7 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ThermoDxfRecord {
         state.n_scans_left -= 1;
         if state.cur_mz_idx == 0 {
             state.cur_time = f64::from(extract::<f32>(rb, con, &mut Endian::Little)?);
+            state.cur_scan += 1;
         }
 
         state.cur_mz = state.mzs[state.cur_mz_idx];
@@ -214,8 +263,10 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ThermoDxfRecord {
 
     fn get(&mut self, _buf: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
         self.time = state.cur_time / 60.;
+        self.scan = state.cur_scan;
         self.mz = state.cur_mz;
         self.intensity = state.cur_intensity;
+        self.scan_info = state.scan_info;
         Ok(())
     }
 }
@@ -225,28 +276,39 @@ impl_reader!(
     ThermoDxfRecord,
     ThermoDxfRecord,
     ThermoDxfState,
-    ()
+    ThermoIsoParams
 );
 
 /// The current state of the `ThermoCfReader`
 #[derive(Clone, Debug, Default)]
 pub struct ThermoCfState {
+    scan_info: bool,
     n_scans_left: usize,
     cur_mz_idx: usize,
     mzs: Vec<f64>,
     cur_time: f64,
+    cur_scan: u32,
     cur_mz: f64,
     cur_intensity: f64,
 }
 
 impl StateMetadata for ThermoCfState {
     fn header(&self) -> Vec<&str> {
-        vec!["time", "mz", "intensity"]
+        if self.scan_info {
+            vec!["time", "scan", "mz", "intensity"]
+        } else {
+            vec!["time", "mz", "intensity"]
+        }
     }
 }
 
 impl<'b: 's, 's> FromSlice<'b, 's> for ThermoCfState {
-    type State = ();
+    type State = ThermoIsoParams;
+
+    fn get(&mut self, _buf: &'b [u8], params: &'s Self::State) -> Result<(), EtError> {
+        self.scan_info = params.scan_info;
+        Ok(())
+    }
 }
 
 /// A single data point from a Thermo CF file
@@ -254,13 +316,33 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ThermoCfState {
 pub struct ThermoCfRecord {
     /// The time the reading was taken at
     pub time: f64,
+    /// The (1-based) number of the scan this was recorded in
+    pub scan: u32,
     /// The mz value of the reading
     pub mz: f64,
     /// The intensity recorded
     pub intensity: f64,
+    scan_info: bool,
 }
 
-impl_record!(ThermoCfRecord: time, mz, intensity);
+impl<'r> From<ThermoCfRecord> for Vec<Value<'r>> {
+    fn from(record: ThermoCfRecord) -> Self {
+        if record.scan_info {
+            vec![
+                record.time.into(),
+                record.scan.into(),
+                record.mz.into(),
+                record.intensity.into(),
+            ]
+        } else {
+            vec![
+                record.time.into(),
+                record.mz.into(),
+                record.intensity.into(),
+            ]
+        }
+    }
+}
 
 impl<'b: 's, 's> FromSlice<'b, 's> for ThermoCfRecord {
     type State = ThermoCfState;
@@ -321,6 +403,7 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ThermoCfRecord {
         state.n_scans_left -= 1;
         if state.cur_mz_idx == 0 {
             state.cur_time = f64::from(extract::<f32>(rb, con, &mut Endian::Little)?);
+            state.cur_scan += 1;
         }
 
         state.cur_mz = state.mzs[state.cur_mz_idx];
@@ -332,8 +415,10 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ThermoCfRecord {
 
     fn get(&mut self, _buf: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
         self.time = state.cur_time / 60.;
+        self.scan = state.cur_scan;
         self.mz = state.cur_mz;
         self.intensity = state.cur_intensity;
+        self.scan_info = state.scan_info;
         Ok(())
     }
 }
@@ -343,7 +428,7 @@ impl_reader!(
     ThermoCfRecord,
     ThermoCfRecord,
     ThermoCfState,
-    ()
+    ThermoIsoParams
 );
 
 #[cfg(test)]
@@ -360,6 +445,7 @@ mod tests {
             time,
             mz,
             intensity,
+            ..
         }) = reader.next()?
         {
             assert!((time - 0.03135).abs() < 0.000001);
@@ -372,6 +458,19 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_thermo_dxf_scan_info() -> Result<(), EtError> {
+        let rb: &[u8] = include_bytes!("../../../tests/data/b3_alkanes.dxf");
+        let reader = ThermoDxfReader::new(rb, None)?;
+        assert_eq!(reader.headers(), ["time", "mz", "intensity"]);
+        let params = ThermoIsoParams::default().scan_info(true);
+        let mut reader = ThermoDxfReader::new(rb, Some(params))?;
+        assert_eq!(reader.headers(), ["time", "scan", "mz", "intensity"]);
+        let record: Vec<Value> = reader.next()?.unwrap().into();
+        assert_eq!(record[1], Value::Integer(1));
+        Ok(())
+    }
+
     #[test]
     fn test_thermo_dxf_bad_fuzzes() -> Result<(), EtError> {
         let test_data = [
@@ -402,6 +501,7 @@ mod tests {
             time,
             mz,
             intensity,
+            ..
         }) = reader.next()?
         {
             assert!((time - 0.003483).abs() < 0.000001);
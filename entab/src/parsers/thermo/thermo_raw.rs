@@ -5,11 +5,14 @@ use alloc::{format, vec};
 use core::char::{decode_utf16, REPLACEMENT_CHARACTER};
 use core::convert::TryFrom;
 
+use chrono::NaiveDateTime;
+
+use crate::impl_reader;
 use crate::parsers::common::{EndOfFile, Skip};
+use crate::parsers::microsoft_common::from_windows_time;
 use crate::parsers::{extract, Endian, FromSlice};
 use crate::record::{StateMetadata, Value};
 use crate::EtError;
-use crate::{impl_reader, impl_record};
 
 /// A UTF-16 string with a u32 header describing its length
 #[derive(Debug, Default)]
@@ -25,7 +28,7 @@ impl<'b: 's, 's> FromSlice<'b, 's> for PascalString16 {
         _state: &mut Self::State,
     ) -> Result<bool, EtError> {
         let length = usize::try_from(extract::<u32>(buffer, &mut 0, &mut Endian::Little)?)?;
-        if buffer.len() < 4 * 2 * length * 2 {
+        if buffer.len() < 4 + 2 * length {
             return Err(EtError::from("PascalString ended abruptly").incomplete());
         }
         *consumed += 4 + 2 * length;
@@ -43,11 +46,22 @@ impl<'b: 's, 's> FromSlice<'b, 's> for PascalString16 {
     }
 }
 
+/// The length of the run header (i.e. the trailer) for a given version of RAW file
+fn trailer_len(version: u32) -> usize {
+    if version >= 64 {
+        592 + 6980
+    } else {
+        592 + 6816
+    }
+}
+
 /// The post-data trailer for a Thermo RAW file
 #[derive(Clone, Copy, Debug, Default)]
 pub struct ThermoRawTrailer {
     metadata_start: usize,
+    error_log_start: usize,
     coeffs_start: usize,
+    params_start: usize,
     n_scans: usize,
     min_mz: f64,
     max_mz: f64,
@@ -64,17 +78,14 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ThermoRawTrailer {
         consumed: &mut usize,
         version: &mut Self::State,
     ) -> Result<bool, EtError> {
-        if *version >= 64 && buffer.len() < 592 + 6980 {
-            return Err(EtError::from("Trailer too short for version >= 64").incomplete());
-        } else if *version >= 64 {
-            *consumed += 592 + 6980;
-        } else if *version >= 50 && buffer.len() < 592 + 6816 {
-            return Err(EtError::from("Trailer too short for version >= 50").incomplete());
-        } else if *version >= 50 {
-            *consumed += 592 + 6816;
-        } else {
+        if *version < 50 {
             return Err("Version must be >= 50".into());
         }
+        let length = trailer_len(*version);
+        if buffer.len() < length {
+            return Err(EtError::from("Trailer too short").incomplete());
+        }
+        *consumed += length;
 
         Ok(true)
     }
@@ -97,6 +108,16 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ThermoRawTrailer {
         } else {
             usize::try_from(u32::extract(&buffer[28..32], &Endian::Little)?)?
         };
+        self.error_log_start = if *version >= 64 {
+            usize::try_from(u64::extract(&buffer[7432..7440], &Endian::Little)?)?
+        } else {
+            usize::try_from(u32::extract(&buffer[40..44], &Endian::Little)?)?
+        };
+        self.params_start = if *version >= 64 {
+            usize::try_from(u64::extract(&buffer[7456..7464], &Endian::Little)?)?
+        } else {
+            usize::try_from(u32::extract(&buffer[7372..7376], &Endian::Little)?)?
+        };
 
         Ok(())
     }
@@ -141,13 +162,58 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ThermoRawScanMetadata {
     }
 }
 
-/// Coefficients and other data about a scan
+/// The names of the mass analyzers, indexed by their code in the scan event
+const ANALYZERS: [&str; 6] = ["ITMS", "TQMS", "SQMS", "TOFMS", "FTMS", "Sector"];
+/// The names of the ionization sources, indexed by their code in the scan event
+const IONIZATIONS: [&str; 10] = [
+    "EI", "CI", "FAB", "ESI", "APCI", "NSI", "TSP", "FD", "MALDI", "GD",
+];
+/// The names of the scan types, indexed by their code in the scan event
+const SCAN_TYPES: [&str; 8] = ["Full", "SIM", "Zoom", "SRM", "CRM", "", "Q1MS", "Q3MS"];
+/// The names of the activation types, indexed by their code in a scan event's reactions
+const ACTIVATIONS: [&str; 12] = [
+    "cid", "mpd", "ecd", "pqd", "etd", "hcd", "", "sa", "ptr", "netd", "nptr", "uvpd",
+];
+
+/// The length of the fixed part of a scan event (before the list of reactions)
+fn scan_event_preamble_len(version: u32) -> usize {
+    if version >= 66 {
+        136
+    } else if version >= 63 {
+        128
+    } else if version >= 62 {
+        120
+    } else {
+        80
+    }
+}
+
+/// The length of one precursor reaction in a scan event
+fn scan_event_reaction_len(version: u32) -> usize {
+    if version >= 66 {
+        56
+    } else {
+        32
+    }
+}
+
+/// Coefficients and other data about a scan (taken from the scan's "scan event")
 #[derive(Clone, Copy, Debug, Default)]
 pub struct ThermoRawScanCoeffs {
     n_coeffs: u32,
     a: f64,
     b: f64,
     c: f64,
+    polarity: u8,
+    centroid: bool,
+    ms_level: u8,
+    scan_type: u8,
+    dependent: bool,
+    ionization: u8,
+    analyzer: u8,
+    precursor_mz: Option<f64>,
+    activation: Option<u8>,
+    energy: f64,
 }
 
 impl ThermoRawScanCoeffs {
@@ -161,6 +227,66 @@ impl ThermoRawScanCoeffs {
             _ => unreachable!("Unparseable number of coefficients"),
         }
     }
+
+    /// The polarity of the scan as a "+" or "-"
+    #[must_use]
+    pub fn polarity(&self) -> &'static str {
+        match self.polarity {
+            0 => "-",
+            1 => "+",
+            _ => "",
+        }
+    }
+
+    /// The name of the method used to fragment the precursor ion (if this isn't an MS1 scan)
+    #[must_use]
+    pub fn activation(&self) -> Option<&'static str> {
+        self.activation
+            .and_then(|a| ACTIVATIONS.get(usize::from(a)))
+            .copied()
+    }
+
+    /// Build a Thermo-style scan filter (e.g. "FTMS + p ESI Full ms [200.00-2000.00]")
+    #[must_use]
+    pub fn filter(&self, low_mz: f64, high_mz: f64) -> String {
+        let mut parts: Vec<String> = vec![
+            ANALYZERS
+                .get(usize::from(self.analyzer))
+                .unwrap_or(&"")
+                .to_string(),
+            self.polarity().to_string(),
+            if self.centroid { "c" } else { "p" }.to_string(),
+            IONIZATIONS
+                .get(usize::from(self.ionization))
+                .unwrap_or(&"")
+                .to_string(),
+        ];
+        if self.dependent {
+            parts.push("d".to_string());
+        }
+        parts.push(
+            SCAN_TYPES
+                .get(usize::from(self.scan_type))
+                .unwrap_or(&"")
+                .to_string(),
+        );
+        if self.ms_level > 1 {
+            parts.push(format!("ms{}", self.ms_level));
+        } else {
+            parts.push("ms".to_string());
+        }
+        if let Some(precursor_mz) = self.precursor_mz {
+            parts.push(format!(
+                "{:.2}@{}{:.2}",
+                precursor_mz,
+                self.activation().unwrap_or(""),
+                self.energy
+            ));
+        }
+        parts.push(format!("[{:.2}-{:.2}]", low_mz, high_mz));
+        parts.retain(|p| !p.is_empty());
+        parts.join(" ")
+    }
 }
 
 impl<'b: 's, 's> FromSlice<'b, 's> for ThermoRawScanCoeffs {
@@ -172,28 +298,14 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ThermoRawScanCoeffs {
         consumed: &mut usize,
         (version, coeff_pos): &mut Self::State,
     ) -> Result<bool, EtError> {
-        let mut record_len = if *version >= 66 {
-            140
-        } else if *version >= 63 {
-            132
-        } else if *version >= 62 {
-            124
-        } else if *version >= 57 {
-            84
-        } else {
-            44
-        };
+        let mut record_len = scan_event_preamble_len(*version) + 4;
         if buffer.len() < record_len {
             return Err(EtError::from("Coefficient data incomplete").incomplete());
         }
 
         let n_reactions =
             usize::try_from(u32::extract(&buffer[record_len - 4..], &Endian::Little)?)?;
-        if *version >= 66 {
-            record_len += n_reactions * 56;
-        } else {
-            record_len += n_reactions * 32;
-        }
+        record_len += n_reactions * scan_event_reaction_len(*version);
         record_len += 24;
         if buffer.len() < record_len {
             return Err(EtError::from("Coefficient reactions incomplete").incomplete());
@@ -217,7 +329,11 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ThermoRawScanCoeffs {
         Ok(true)
     }
 
-    fn get(&mut self, buffer: &'b [u8], (_, coeff_pos): &'s Self::State) -> Result<(), EtError> {
+    fn get(
+        &mut self,
+        buffer: &'b [u8],
+        (version, coeff_pos): &'s Self::State,
+    ) -> Result<(), EtError> {
         self.n_coeffs = u32::extract(&buffer[*coeff_pos..], &Endian::Little)?;
         if self.n_coeffs == 4 {
             self.a = f64::extract(&buffer[*coeff_pos + 12..], &Endian::Little)?;
@@ -230,36 +346,269 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ThermoRawScanCoeffs {
         } else if self.n_coeffs != 0 {
             return Err("Unexpected number of coefficients".into());
         }
+
+        // the preamble is a list of single byte flags describing how the scan was taken
+        self.polarity = buffer[4];
+        self.centroid = buffer[5] == 0;
+        self.ms_level = buffer[6];
+        self.scan_type = buffer[7];
+        self.dependent = buffer[10] == 1;
+        self.ionization = buffer[11];
+        self.analyzer = buffer[40];
+
+        // the precursor of this scan is the last reaction in the list
+        let preamble_len = scan_event_preamble_len(*version);
+        let n_reactions = usize::try_from(u32::extract(&buffer[preamble_len..], &Endian::Little)?)?;
+        if n_reactions > 0 {
+            let reaction_start =
+                preamble_len + 4 + (n_reactions - 1) * scan_event_reaction_len(*version);
+            self.precursor_mz = Some(f64::extract(&buffer[reaction_start..], &Endian::Little)?);
+            self.energy = f64::extract(&buffer[reaction_start + 16..], &Endian::Little)?;
+            // the lowest bit flags if the energy is valid and the next byte is the activation type
+            let flags = u32::extract(&buffer[reaction_start + 24..], &Endian::Little)?;
+            self.activation = Some(((flags >> 1) & 0xFF) as u8);
+        } else {
+            self.precursor_mz = None;
+            self.energy = 0.;
+            self.activation = None;
+        }
         Ok(())
     }
 }
 
-/// The state of a parser that handles Thermo RAW files
+/// Where the fields we care about are inside each scan's record in the "trailer extra" section
 #[derive(Clone, Copy, Debug, Default)]
+pub struct ThermoRawParamsLayout {
+    record_len: usize,
+    charge: Option<(usize, u32)>,
+    injection_time: Option<(usize, u32)>,
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for ThermoRawParamsLayout {
+    type State = ();
+
+    fn parse(
+        buffer: &[u8],
+        eof: bool,
+        consumed: &mut usize,
+        _state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        let con = &mut 0;
+        let n_fields = extract::<u32>(buffer, con, &mut Endian::Little)?;
+        for _ in 0..n_fields {
+            let _ = extract::<Skip>(buffer, con, &mut 8)?;
+            let _ = PascalString16::parse(&buffer[*con..], eof, con, &mut ())?;
+        }
+        *consumed += *con;
+        Ok(true)
+    }
+
+    fn get(&mut self, buffer: &'b [u8], _state: &'s Self::State) -> Result<(), EtError> {
+        // this is a list of the type, length, and label of every field
+        let con = &mut 0;
+        let n_fields = extract::<u32>(buffer, con, &mut Endian::Little)?;
+        self.record_len = 0;
+        for _ in 0..n_fields {
+            let field_type = extract::<u32>(buffer, con, &mut Endian::Little)?;
+            let length = usize::try_from(extract::<u32>(buffer, con, &mut Endian::Little)?)?;
+            let label: PascalString16 = extract(buffer, con, &mut ())?;
+            match label.0.as_str() {
+                "Charge State:" => self.charge = Some((self.record_len, field_type)),
+                "Ion Injection Time (ms):" => {
+                    self.injection_time = Some((self.record_len, field_type));
+                }
+                _ => {}
+            }
+            self.record_len += match field_type {
+                0 => 0,
+                1..=5 => 1,
+                6 | 7 => 2,
+                8..=10 => 4,
+                11 => 8,
+                12 => length,
+                13 => 2 * length,
+                _ => return Err(format!("Unknown trailer field type {}", field_type).into()),
+            };
+        }
+        Ok(())
+    }
+}
+
+impl ThermoRawParamsLayout {
+    /// Read a numeric field out of a scan's "trailer extra" record
+    fn read_field(record: &[u8], field: Option<(usize, u32)>) -> Result<Option<f64>, EtError> {
+        let (pos, field_type) = match field {
+            Some(f) => f,
+            None => return Ok(None),
+        };
+        Ok(Some(match field_type {
+            1..=5 => f64::from(record[pos]),
+            6 => f64::from(i16::extract(&record[pos..], &Endian::Little)?),
+            7 => f64::from(u16::extract(&record[pos..], &Endian::Little)?),
+            8 => f64::from(i32::extract(&record[pos..], &Endian::Little)?),
+            9 => f64::from(u32::extract(&record[pos..], &Endian::Little)?),
+            10 => f64::from(f32::extract(&record[pos..], &Endian::Little)?),
+            11 => f64::extract(&record[pos..], &Endian::Little)?,
+            _ => return Ok(None),
+        }))
+    }
+}
+
+/// Information about the sample and run in a Thermo RAW file
+#[derive(Clone, Debug, Default)]
+pub struct ThermoRawMetadata {
+    /// The sample ID entered into the sequence
+    pub sample: String,
+    /// The comment entered into the sequence
+    pub comment: String,
+    /// The autosampler position the sample was run from
+    pub vial: String,
+    /// The path to the instrument method used
+    pub instrument_method: String,
+    /// The path to the processing method used
+    pub processing_method: String,
+    /// The name of the file when it was acquired
+    pub file_name: String,
+    /// The date the sample was run
+    pub run_date: Option<NaiveDateTime>,
+    /// The model of the instrument
+    pub instrument: String,
+    /// The serial number of the instrument
+    pub instrument_serial: String,
+    /// The version of the software on the instrument
+    pub software_version: String,
+    /// The time the first scan was taken at (minutes)
+    pub start_time: f64,
+    /// The time the last scan was taken at (minutes)
+    pub end_time: f64,
+    /// The lowest m/z scanned
+    pub min_mz: f64,
+    /// The highest m/z scanned
+    pub max_mz: f64,
+}
+
+impl<'r> From<&ThermoRawMetadata> for BTreeMap<String, Value<'r>> {
+    fn from(metadata: &ThermoRawMetadata) -> Self {
+        let mut map = BTreeMap::new();
+        drop(map.insert("sample".to_string(), metadata.sample.clone().into()));
+        drop(map.insert("comment".to_string(), metadata.comment.clone().into()));
+        drop(map.insert("vial".to_string(), metadata.vial.clone().into()));
+        drop(map.insert(
+            "instrument_method".to_string(),
+            metadata.instrument_method.clone().into(),
+        ));
+        drop(map.insert(
+            "processing_method".to_string(),
+            metadata.processing_method.clone().into(),
+        ));
+        drop(map.insert("file_name".to_string(), metadata.file_name.clone().into()));
+        drop(map.insert("run_date".to_string(), metadata.run_date.into()));
+        drop(map.insert("instrument".to_string(), metadata.instrument.clone().into()));
+        drop(map.insert(
+            "instrument_serial".to_string(),
+            metadata.instrument_serial.clone().into(),
+        ));
+        drop(map.insert(
+            "software_version".to_string(),
+            metadata.software_version.clone().into(),
+        ));
+        drop(map.insert("start_time".to_string(), metadata.start_time.into()));
+        drop(map.insert("end_time".to_string(), metadata.end_time.into()));
+        drop(map.insert("min_mz".to_string(), metadata.min_mz.into()));
+        drop(map.insert("max_mz".to_string(), metadata.max_mz.into()));
+        map
+    }
+}
+
+/// The state of a parser that handles Thermo RAW files
+#[derive(Clone, Debug, Default)]
 pub struct ThermoRawParams {
     version: u32,
     data_start: usize,
     trailer_start: usize,
     trailer: Option<ThermoRawTrailer>,
+    params_layout: Option<ThermoRawParamsLayout>,
+    metadata: ThermoRawMetadata,
+    scan_info: bool,
+}
+
+impl ThermoRawParams {
+    /// Also return the scan number and the other information about the scan (MS level, filter,
+    /// precursor, etc) with every point
+    #[must_use]
+    pub fn scan_info(mut self, scan_info: bool) -> Self {
+        self.scan_info = scan_info;
+        self
+    }
+}
+
+/// Read the instrument model, serial number, and software version.
+///
+/// These are stored right after the run header.
+fn read_instrument_info(
+    buffer: &[u8],
+    mut pos: usize,
+) -> Result<(String, String, String), EtError> {
+    let con = &mut pos;
+    let _ = extract::<Skip>(buffer, con, &mut 12)?;
+    let instrument = extract::<PascalString16>(buffer, con, &mut ())?.0;
+    // the instrument "name" is the same as the model in every file we've seen
+    let _ = extract::<PascalString16>(buffer, con, &mut ())?;
+    let instrument_serial = extract::<PascalString16>(buffer, con, &mut ())?.0;
+    let software_version = extract::<PascalString16>(buffer, con, &mut ())?.0;
+    Ok((instrument, instrument_serial, software_version))
+}
+
+/// Find the description of the "trailer extra" records.
+///
+/// This is stored after the error log and the list of scan events used in the method.
+fn read_params_layout(
+    buffer: &[u8],
+    mut error_log_start: usize,
+    version: u32,
+) -> Result<ThermoRawParamsLayout, EtError> {
+    let con = &mut error_log_start;
+    let n_errors = extract::<u32>(buffer, con, &mut Endian::Little)?;
+    for _ in 0..n_errors {
+        let _ = extract::<f32>(buffer, con, &mut Endian::Little)?;
+        let _ = PascalString16::parse(&buffer[*con..], true, con, &mut ())?;
+    }
+    let n_segments = extract::<u32>(buffer, con, &mut Endian::Little)?;
+    for _ in 0..n_segments {
+        let n_events = extract::<u32>(buffer, con, &mut Endian::Little)?;
+        for _ in 0..n_events {
+            let _ = ThermoRawScanCoeffs::parse(&buffer[*con..], true, con, &mut (version, 0))?;
+        }
+    }
+    extract(buffer, con, &mut ())
 }
 
 /// The state of a parser that handles Thermo RAW files
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct ThermoRawState {
     version: u32,
     metadata_pos: usize,
     coeffs_pos: usize,
+    params_pos: usize,
+    params_layout: Option<ThermoRawParamsLayout>,
     n_scans_left: usize,
     n_chunks_left: usize,
     n_points_left: usize,
     chunk_has_adjustment: bool,
     extra_bytes: usize,
     cur_time: f64,
+    cur_scan: u32,
     cur_freq: f64,
     base_freq: f64,
     freq_step: f64,
     cur_coeffs: ThermoRawScanCoeffs,
     cur_adjustment: f64,
+    cur_centroid: bool,
+    cur_filter: String,
+    cur_charge: Option<i32>,
+    cur_injection_time: Option<f64>,
+    metadata: ThermoRawMetadata,
+    scan_info: bool,
 }
 
 impl ThermoRawState {
@@ -275,19 +624,67 @@ impl ThermoRawState {
         }
         self.metadata_pos -= con;
         self.coeffs_pos -= con;
+        // the trailer extra section is optional so we only move it if it's there
+        self.params_pos = self.params_pos.saturating_sub(con);
+        Ok(())
+    }
+
+    /// Read all of the information about the next scan from the sections after the data
+    fn read_scan_info(&mut self, buffer: &[u8]) -> Result<(), EtError> {
+        let scan_metadata: ThermoRawScanMetadata =
+            extract(buffer, &mut self.metadata_pos, &mut self.version)?;
+        self.cur_time = scan_metadata.time;
+        self.cur_scan += 1;
+        self.cur_coeffs = extract(buffer, &mut self.coeffs_pos, &mut (self.version, 0))?;
+        self.cur_filter = self
+            .cur_coeffs
+            .filter(scan_metadata.low_mz, scan_metadata.high_mz);
+
+        self.cur_charge = None;
+        self.cur_injection_time = None;
+        if let Some(layout) = self.params_layout {
+            if self.params_pos + layout.record_len > buffer.len() {
+                return Err("Trailer extra section ended abruptly".into());
+            }
+            let record = &buffer[self.params_pos..self.params_pos + layout.record_len];
+            self.params_pos += layout.record_len;
+            #[allow(clippy::cast_possible_truncation)]
+            let charge =
+                ThermoRawParamsLayout::read_field(record, layout.charge)?.map(|c| c as i32);
+            // a charge of zero means it couldn't be determined
+            self.cur_charge = charge.filter(|c| *c != 0);
+            self.cur_injection_time =
+                ThermoRawParamsLayout::read_field(record, layout.injection_time)?;
+        }
         Ok(())
     }
 }
 
 impl StateMetadata for ThermoRawState {
     fn metadata(&self) -> BTreeMap<String, Value> {
-        let mut map = BTreeMap::new();
+        let mut map: BTreeMap<String, Value> = (&self.metadata).into();
         drop(map.insert("version".to_string(), self.version.into()));
         map
     }
 
     fn header(&self) -> Vec<&str> {
-        vec!["time", "mz", "intensity"]
+        if !self.scan_info {
+            return vec!["time", "mz", "intensity"];
+        }
+        vec![
+            "time",
+            "scan",
+            "mz",
+            "intensity",
+            "ms_level",
+            "polarity",
+            "filter",
+            "precursor_mz",
+            "charge",
+            "activation",
+            "injection_time",
+            "centroid",
+        ]
     }
 }
 
@@ -369,11 +766,25 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ThermoRawState {
             // instruments
             let _: Skip = extract(buffer, &mut 0, &mut state.trailer_start)?;
             let mut trailer_start = state.trailer_start;
-            state.trailer = Some(extract::<ThermoRawTrailer>(
-                buffer,
-                &mut trailer_start,
-                &mut state.version,
-            )?);
+            let trailer =
+                extract::<ThermoRawTrailer>(buffer, &mut trailer_start, &mut state.version)?;
+            // the per-scan "trailer extra" values (charge, injection time, etc) aren't needed to
+            // read the data so if we can't find their layout we just skip them
+            state.params_layout =
+                read_params_layout(buffer, trailer.error_log_start, state.version).ok();
+            // likewise, the instrument information is only used for the metadata
+            if let Ok((instrument, instrument_serial, software_version)) =
+                read_instrument_info(buffer, state.trailer_start + trailer_len(state.version))
+            {
+                state.metadata.instrument = instrument;
+                state.metadata.instrument_serial = instrument_serial;
+                state.metadata.software_version = software_version;
+            }
+            state.metadata.start_time = trailer.min_time;
+            state.metadata.end_time = trailer.max_time;
+            state.metadata.min_mz = trailer.min_mz;
+            state.metadata.max_mz = trailer.max_mz;
+            state.trailer = Some(trailer);
         }
 
         *consumed += state.data_start;
@@ -387,25 +798,94 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ThermoRawState {
             .ok_or_else(|| EtError::from("Trailer missing?"))?;
         self.metadata_pos = trailer.metadata_start - state.data_start;
         self.coeffs_pos = trailer.coeffs_start - state.data_start + 4;
+        self.params_layout = state.params_layout;
+        if let Some(params_pos) = trailer.params_start.checked_sub(state.data_start) {
+            self.params_pos = params_pos;
+        } else {
+            self.params_layout = None;
+        }
         self.n_scans_left = trailer.n_scans;
+        self.scan_info = state.scan_info;
+
+        // the sequence information is stored in a list of strings right after the file header
+        let mut metadata = state.metadata.clone();
+        metadata.run_date = Some(from_windows_time(u64::extract(
+            &buffer[40..48],
+            &Endian::Little,
+        )?)?);
+        let con = &mut 1420;
+        let mut strings = Vec::with_capacity(16);
+        for _ in 0..16 {
+            strings.push(extract::<PascalString16>(buffer, con, &mut ())?.0);
+        }
+        metadata.sample = strings[2].clone();
+        metadata.comment = strings[3].clone();
+        metadata.instrument_method = strings[9].clone();
+        metadata.processing_method = strings[10].clone();
+        metadata.file_name = strings[11].clone();
+        metadata.vial = strings[13].clone();
+        self.metadata = metadata;
         Ok(())
     }
 }
 
 /// A single data point from a Thermo RAW file
 #[derive(Clone, Copy, Debug, Default)]
-pub struct ThermoRawRecord {
+pub struct ThermoRawRecord<'r> {
     /// The time the reading was taken at
     pub time: f64,
+    /// The (1-based) number of the scan this was recorded in
+    pub scan: u32,
     /// The mz value of the reading
     pub mz: f64,
     /// The intensity recorded
     pub intensity: f32,
+    /// The MS level of the scan (e.g. 1 for full scans, 2 for product ion scans)
+    pub ms_level: u8,
+    /// The polarity of the scan ("+" or "-")
+    pub polarity: &'r str,
+    /// The Thermo scan filter describing the scan
+    pub filter: &'r str,
+    /// The m/z of the precursor ion fragmented for this scan
+    pub precursor_mz: Option<f64>,
+    /// The charge of the precursor ion, if it could be determined
+    pub charge: Option<i32>,
+    /// How the precursor ion was fragmented (e.g. "cid" or "hcd")
+    pub activation: Option<&'r str>,
+    /// How long ions were accumulated for this scan (milliseconds)
+    pub injection_time: Option<f64>,
+    /// If the scan was recorded as centroided peaks instead of a profile
+    pub centroid: bool,
+    scan_info: bool,
 }
 
-impl_record!(ThermoRawRecord: time, mz, intensity);
+impl<'r> From<ThermoRawRecord<'r>> for Vec<Value<'r>> {
+    fn from(record: ThermoRawRecord<'r>) -> Self {
+        if !record.scan_info {
+            return vec![
+                record.time.into(),
+                record.mz.into(),
+                record.intensity.into(),
+            ];
+        }
+        vec![
+            record.time.into(),
+            record.scan.into(),
+            record.mz.into(),
+            record.intensity.into(),
+            record.ms_level.into(),
+            record.polarity.into(),
+            record.filter.into(),
+            record.precursor_mz.into(),
+            record.charge.into(),
+            record.activation.into(),
+            record.injection_time.into(),
+            record.centroid.into(),
+        ]
+    }
+}
 
-impl<'b: 's, 's> FromSlice<'b, 's> for ThermoRawRecord {
+impl<'b: 's, 's> FromSlice<'b, 's> for ThermoRawRecord<'s> {
     type State = ThermoRawState;
 
     fn parse(
@@ -415,73 +895,88 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ThermoRawRecord {
         state: &mut Self::State,
     ) -> Result<bool, EtError> {
         let mut con = 0;
-        if state.n_scans_left == 0 && state.n_chunks_left == 0 && state.n_points_left == 0 {
-            return Ok(false);
-        }
-        let mut extra_bytes = state.extra_bytes;
-        let mut n_scans_left = state.n_scans_left;
-        let mut n_chunks_left = state.n_chunks_left;
         if state.n_chunks_left == 0 && state.n_points_left == 0 {
-            let mut size_data = 0;
-            while size_data == 0 {
+            // find the next scan with data in it
+            loop {
+                if state.n_scans_left == 0 {
+                    return Ok(false);
+                }
                 // skip the trailer from the last scan
-                let _ = extract::<Skip>(buffer, &mut con, &mut extra_bytes)?;
+                let _ = extract::<Skip>(buffer, &mut con, &mut state.extra_bytes)?;
 
                 // read the extra metadata from the end of the file
-                let scan_metadata: ThermoRawScanMetadata =
-                    extract(buffer, &mut state.metadata_pos, &mut state.version)?;
-                state.cur_time = scan_metadata.time;
-                state.cur_coeffs = extract(buffer, &mut state.coeffs_pos, &mut (state.version, 0))?;
+                state.read_scan_info(buffer)?;
 
                 // now read the record header itself
                 let _ = extract::<Skip>(buffer, &mut con, &mut 4)?;
-                size_data = extract::<u32>(buffer, &mut con, &mut Endian::Little)?;
-                extra_bytes =
+                let profile_size = extract::<u32>(buffer, &mut con, &mut Endian::Little)?;
+                let peak_list_size =
                     4 * usize::try_from(extract::<u32>(buffer, &mut con, &mut Endian::Little)?)?;
                 // only the second bit `01000000` is ever set here?
                 state.chunk_has_adjustment =
                     extract::<u32>(buffer, &mut con, &mut Endian::Little)? != 0;
                 // three more sections we need to skip
-                extra_bytes +=
+                let mut extra_bytes =
                     4 * usize::try_from(extract::<u32>(buffer, &mut con, &mut Endian::Little)?)?;
                 extra_bytes +=
                     4 * usize::try_from(extract::<u32>(buffer, &mut con, &mut Endian::Little)?)?;
                 extra_bytes +=
                     4 * usize::try_from(extract::<u32>(buffer, &mut con, &mut Endian::Little)?)?;
                 let _ = extract::<Skip>(buffer, &mut con, &mut 12)?;
+                state.n_scans_left -= 1;
 
-                n_scans_left -= 1;
-                if n_scans_left == 0 {
-                    state.n_scans_left = n_scans_left;
-                    return Ok(false);
+                if profile_size > 0 {
+                    state.extra_bytes = peak_list_size + extra_bytes;
+                    state.cur_centroid = false;
+                    state.base_freq = extract(buffer, &mut con, &mut Endian::Little)?;
+                    state.freq_step = extract(buffer, &mut con, &mut Endian::Little)?;
+                    state.n_chunks_left =
+                        usize::try_from(extract::<u32>(buffer, &mut con, &mut Endian::Little)?)?;
+                    let _ = extract::<Skip>(buffer, &mut con, &mut 4)?;
+                    if state.n_chunks_left > 0 {
+                        break;
+                    }
+                } else if peak_list_size > 0 {
+                    // no profile was recorded so read the centroided peaks instead
+                    state.cur_centroid = true;
+                    state.n_points_left =
+                        usize::try_from(extract::<u32>(buffer, &mut con, &mut Endian::Little)?)?;
+                    let peaks_size = 4 + 8 * state.n_points_left;
+                    if peaks_size > peak_list_size {
+                        return Err("Peak list extends past the end of the scan".into());
+                    }
+                    state.extra_bytes = peak_list_size - peaks_size + extra_bytes;
+                    if state.n_points_left > 0 {
+                        break;
+                    }
+                } else {
+                    state.extra_bytes = extra_bytes;
                 }
             }
-            state.base_freq = extract(buffer, &mut con, &mut Endian::Little)?;
-            state.freq_step = extract(buffer, &mut con, &mut Endian::Little)?;
-            n_chunks_left =
-                usize::try_from(extract::<u32>(buffer, &mut con, &mut Endian::Little)?)?;
-            let _ = extract::<Skip>(buffer, &mut con, &mut 4)?;
-        }
-        if state.n_points_left == 0 {
-            // read a chunk header
-            let freq_offset = f64::from(extract::<u32>(buffer, &mut con, &mut Endian::Little)?);
-            state.cur_freq = state.base_freq + state.freq_step * freq_offset - state.freq_step;
-            state.n_points_left =
-                usize::try_from(extract::<u32>(buffer, &mut con, &mut Endian::Little)?)?;
-            if state.chunk_has_adjustment {
-                state.cur_adjustment =
-                    f64::from(extract::<f32>(buffer, &mut con, &mut Endian::Little)?);
+        }
+        if state.cur_centroid {
+            // each peak is a m/z and an intensity
+            let mz = extract::<f32>(buffer, &mut con, &mut Endian::Little)?;
+            state.cur_freq = f64::from(mz);
+        } else {
+            if state.n_points_left == 0 {
+                // read a chunk header
+                let freq_offset = f64::from(extract::<u32>(buffer, &mut con, &mut Endian::Little)?);
+                state.cur_freq = state.base_freq + state.freq_step * freq_offset - state.freq_step;
+                state.n_points_left =
+                    usize::try_from(extract::<u32>(buffer, &mut con, &mut Endian::Little)?)?;
+                if state.chunk_has_adjustment {
+                    state.cur_adjustment =
+                        f64::from(extract::<f32>(buffer, &mut con, &mut Endian::Little)?);
+                }
+                state.n_chunks_left -= 1;
             }
-            n_chunks_left -= 1;
+            state.cur_freq += state.freq_step;
         }
         // include the point itself
         let _ = extract::<Skip>(buffer, &mut con, &mut 4)?;
-        state.cur_freq += state.freq_step;
 
-        state.n_scans_left = n_scans_left;
-        state.n_chunks_left = n_chunks_left;
         state.n_points_left -= 1;
-        state.extra_bytes = extra_bytes;
         state.data_consumed(con)?;
         *consumed += con;
         Ok(true)
@@ -489,8 +984,22 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ThermoRawRecord {
 
     fn get(&mut self, buffer: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
         self.time = state.cur_time;
-        self.mz = state.cur_coeffs.to_mz(state.cur_freq) + state.cur_adjustment;
+        self.scan = state.cur_scan;
+        self.mz = if state.cur_centroid {
+            state.cur_freq
+        } else {
+            state.cur_coeffs.to_mz(state.cur_freq) + state.cur_adjustment
+        };
         self.intensity = f32::extract(&buffer[buffer.len() - 4..], &Endian::Little)?;
+        self.ms_level = state.cur_coeffs.ms_level;
+        self.polarity = state.cur_coeffs.polarity();
+        self.filter = &state.cur_filter;
+        self.precursor_mz = state.cur_coeffs.precursor_mz;
+        self.charge = state.cur_charge;
+        self.activation = state.cur_coeffs.activation();
+        self.injection_time = state.cur_injection_time;
+        self.centroid = state.cur_centroid;
+        self.scan_info = state.scan_info;
         Ok(())
     }
 }
@@ -498,7 +1007,7 @@ impl<'b: 's, 's> FromSlice<'b, 's> for ThermoRawRecord {
 impl_reader!(
     ThermoRawReader,
     ThermoRawRecord,
-    ThermoRawRecord,
+    ThermoRawRecord<'r>,
     ThermoRawState,
     ThermoRawParams
 );
@@ -514,6 +1023,7 @@ impl_reader!(
 mod tests {
     use super::*;
     use crate::readers::RecordReader;
+    use chrono::NaiveDate;
 
     #[test]
     fn test_thermo_raw() -> Result<(), EtError> {
@@ -521,19 +1031,86 @@ mod tests {
         let mut reader = ThermoRawReader::new(rb, None)?;
         let metadata = reader.metadata();
         assert_eq!(metadata["version"], 57.into());
+        assert_eq!(metadata["sample"], "1".into());
+        assert_eq!(metadata["vial"], "1a1".into());
+        assert_eq!(metadata["file_name"], "20050720data02.RAW".into());
+        assert_eq!(
+            metadata["instrument_method"],
+            "C:\\Xcalibur\\methods\\20050720_idv_AnalyzeInfusion.meth".into()
+        );
+        assert_eq!(metadata["instrument"], "LTQ FT".into());
+        assert_eq!(metadata["instrument_serial"], "SN06061F".into());
+        assert_eq!(metadata["software_version"], "1.1 Beta 7".into());
+        assert_eq!(
+            metadata["run_date"],
+            Value::Datetime(
+                NaiveDate::from_ymd_opt(2005, 7, 20)
+                    .unwrap()
+                    .and_hms_milli_opt(14, 44, 22, 377)
+                    .unwrap()
+            )
+        );
+        assert_eq!(metadata["min_mz"], 140.0.into());
+        assert_eq!(metadata["start_time"], 0.004935.into());
+        assert_eq!(metadata["max_mz"], 2000.0.into());
+        assert_eq!(reader.headers(), ["time", "mz", "intensity"]);
         if let Some(ThermoRawRecord {
             time,
+            scan,
             mz,
             intensity,
+            ms_level,
+            polarity,
+            filter,
+            precursor_mz,
+            centroid,
+            ..
         }) = reader.next()?
         {
             assert!((time - 0.004935).abs() < 0.000001);
+            assert_eq!(scan, 1);
             assert!((mz - 202.60682348271376).abs() < 0.000001);
             assert!((intensity - 1938.1174).abs() < 0.000001);
+            assert_eq!(ms_level, 1);
+            assert_eq!(polarity, "+");
+            assert_eq!(filter, "FTMS + p ESI Full ms [200.00-2000.00]");
+            assert_eq!(precursor_mz, None);
+            assert!(!centroid);
         } else {
             panic!("Thermo Raw reader returned bad record");
         }
-        while reader.next()?.is_some() {}
+
+        let mut n_points = 1;
+        let mut n_centroid_points = 0;
+        while let Some(record) = reader.next()? {
+            n_points += 1;
+            if record.centroid {
+                if n_centroid_points == 0 {
+                    assert_eq!(record.ms_level, 2);
+                    assert!((record.precursor_mz.unwrap() - 810.789429).abs() < 0.000001);
+                    assert_eq!(record.activation, Some("cid"));
+                    assert!((record.injection_time.unwrap() - 7.993005).abs() < 0.000001);
+                    assert!((record.mz - 231.388840).abs() < 0.000001);
+                }
+                n_centroid_points += 1;
+            }
+        }
+        assert_eq!(n_points, 242_645);
+        assert_eq!(n_centroid_points, 25_344);
+        Ok(())
+    }
+
+    #[test]
+    fn test_thermo_raw_scan_info() -> Result<(), EtError> {
+        let rb: &[u8] = include_bytes!("../../../tests/data/small.RAW");
+        let params = ThermoRawParams::default().scan_info(true);
+        let mut reader = ThermoRawReader::new(rb, Some(params))?;
+        let headers = reader.headers();
+        assert_eq!(headers.len(), 12);
+        assert_eq!(headers[..4], ["time", "scan", "mz", "intensity"]);
+        let record: Vec<Value> = reader.next()?.unwrap().into();
+        assert_eq!(record.len(), 12);
+        assert_eq!(record[1], Value::Integer(1));
         Ok(())
     }
 }
@@ -0,0 +1,1037 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use std::io::Read;
+
+use chrono::NaiveDateTime;
+use flate2::read::ZlibDecoder;
+
+use crate::impl_reader;
+use crate::parsers::{extract, Endian, FromSlice};
+use crate::record::{StateMetadata, Value};
+use crate::EtError;
+
+/// Standard TIFF tags that are exposed by name in the metadata
+const TAG_NAMES: &[(u16, &str)] = &[
+    (258, "bits_per_sample"),
+    (259, "compression"),
+    (262, "photometric_interpretation"),
+    (269, "document_name"),
+    (270, "description"),
+    (271, "make"),
+    (272, "model"),
+    (274, "orientation"),
+    (277, "samples_per_pixel"),
+    (282, "x_resolution"),
+    (283, "y_resolution"),
+    (284, "planar_configuration"),
+    (285, "page_name"),
+    (296, "resolution_unit"),
+    (305, "software"),
+    (306, "date_time"),
+    (315, "artist"),
+    (316, "host_computer"),
+    (317, "predictor"),
+    (339, "sample_format"),
+    (33432, "copyright"),
+];
+
+/// Tags describing the layout of the image data that aren't useful as metadata
+const STRUCTURAL_TAGS: &[u16] = &[254, 256, 257, 273, 278, 279, 320, 322, 323, 324, 325];
+
+/// A single entry from an IFD (image file directory)
+#[derive(Debug)]
+struct TiffEntry<'a> {
+    tag: u16,
+    field_type: u16,
+    count: usize,
+    data: &'a [u8],
+}
+
+impl<'a> TiffEntry<'a> {
+    fn read(data: &'a [u8], con: &mut usize, endian: Endian) -> Result<Option<Self>, EtError> {
+        let mut endian = endian;
+        let tag = extract::<u16>(data, con, &mut endian)?;
+        let field_type = extract::<u16>(data, con, &mut endian)?;
+        let count = extract::<u32>(data, con, &mut endian)? as usize;
+        let value_pos = *con;
+        *con += 4;
+        let size = match field_type {
+            1 | 2 | 6 | 7 => 1,
+            3 | 8 => 2,
+            4 | 9 | 11 | 13 => 4,
+            5 | 10 | 12 => 8,
+            // skip over any types we don't know about
+            _ => return Ok(None),
+        };
+        let length = count.checked_mul(size).ok_or("TIFF tag is too large")?;
+        let start = if length <= 4 {
+            value_pos
+        } else {
+            extract::<u32>(data, &mut value_pos.clone(), &mut endian)? as usize
+        };
+        let data = data
+            .get(start..start + length)
+            .ok_or("TIFF tag extends past end of file")?;
+        Ok(Some(TiffEntry {
+            tag,
+            field_type,
+            count,
+            data,
+        }))
+    }
+
+    fn ints(&self, endian: Endian) -> Result<Vec<u64>, EtError> {
+        let size = match self.field_type {
+            1 => 1,
+            3 => 2,
+            4 | 13 => 4,
+            _ => return Err(format!("TIFF tag {} should be an integer", self.tag).into()),
+        };
+        Ok(self
+            .data
+            .chunks_exact(size)
+            .map(|c| read_uint(c, size, endian))
+            .collect())
+    }
+
+    fn int(&self, endian: Endian) -> Result<u64, EtError> {
+        self.ints(endian)?
+            .first()
+            .copied()
+            .ok_or_else(|| format!("TIFF tag {} has no value", self.tag).into())
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    fn to_value(&self, endian: Endian) -> Value<'static> {
+        let mut values: Vec<Value> = match self.field_type {
+            2 => {
+                let text = String::from_utf8_lossy(self.data);
+                return text.trim_end_matches('\0').to_string().into();
+            }
+            1 | 3 | 4 | 7 | 13 => {
+                let size = self.data.len() / self.count.max(1);
+                self.data
+                    .chunks_exact(size.max(1))
+                    .map(|c| Value::Integer(read_uint(c, size, endian) as i64))
+                    .collect()
+            }
+            6 | 8 | 9 => {
+                let size = self.data.len() / self.count.max(1);
+                self.data
+                    .chunks_exact(size.max(1))
+                    .map(|c| Value::Integer(read_int(c, size, endian)))
+                    .collect()
+            }
+            5 | 10 => self
+                .data
+                .chunks_exact(8)
+                .map(|c| {
+                    let (num, den) = if self.field_type == 5 {
+                        (
+                            f64::from(read_uint(c, 4, endian) as u32),
+                            f64::from(read_uint(&c[4..], 4, endian) as u32),
+                        )
+                    } else {
+                        (
+                            f64::from(read_int(c, 4, endian) as i32),
+                            f64::from(read_int(&c[4..], 4, endian) as i32),
+                        )
+                    };
+                    Value::Float(num / den)
+                })
+                .collect(),
+            11 => self
+                .data
+                .chunks_exact(4)
+                .map(|c| f64::from(f32::from_bits(read_uint(c, 4, endian) as u32)).into())
+                .collect(),
+            _ => self
+                .data
+                .chunks_exact(8)
+                .map(|c| f64::from_bits(read_uint(c, 8, endian)).into())
+                .collect(),
+        };
+        if values.len() == 1 {
+            values.remove(0)
+        } else {
+            Value::List(values)
+        }
+    }
+}
+
+/// Read an unsigned integer of `n_bytes` from the start of `data`.
+fn read_uint(data: &[u8], n_bytes: usize, endian: Endian) -> u64 {
+    let bytes = &data[..n_bytes];
+    match endian {
+        Endian::Big => bytes.iter().fold(0, |acc, b| (acc << 8) | u64::from(*b)),
+        Endian::Little => bytes
+            .iter()
+            .rev()
+            .fold(0, |acc, b| (acc << 8) | u64::from(*b)),
+    }
+}
+
+/// Read a signed integer of `n_bytes` from the start of `data`.
+#[allow(clippy::cast_possible_wrap)]
+fn read_int(data: &[u8], n_bytes: usize, endian: Endian) -> i64 {
+    let shift = 64 - 8 * n_bytes;
+    ((read_uint(data, n_bytes, endian) << shift) as i64) >> shift
+}
+
+/// Write the lower `n_bytes` of `value` to the start of `data`.
+#[allow(clippy::cast_possible_truncation)]
+fn write_uint(data: &mut [u8], n_bytes: usize, endian: Endian, value: u64) {
+    for (i, byte) in data[..n_bytes].iter_mut().enumerate() {
+        let shift = match endian {
+            Endian::Big => 8 * (n_bytes - 1 - i),
+            Endian::Little => 8 * i,
+        };
+        *byte = (value >> shift) as u8;
+    }
+}
+
+/// Decompress TIFF LZW data; this uses the MSB-first codes with the "early change" code width
+/// bump that the TIFF specification requires (the older LSB-first variant isn't supported).
+fn decompress_lzw(data: &[u8], expected: usize) -> Result<Vec<u8>, EtError> {
+    const CLEAR: usize = 256;
+    const END: usize = 257;
+    let mut out = Vec::with_capacity(expected);
+    // each table entry is a previous code plus one byte
+    let mut prefixes: Vec<usize> = (0..258).collect();
+    let mut suffixes: Vec<u8> = (0..=255).chain([0, 0]).collect();
+    let mut lengths: Vec<usize> = vec![1; 258];
+    let mut width = 9;
+    let mut prev: Option<usize> = None;
+
+    let mut bit_pos = 0;
+    while bit_pos + width <= data.len() * 8 {
+        let mut code = 0;
+        for i in bit_pos..bit_pos + width {
+            code = (code << 1) | usize::from((data[i / 8] >> (7 - i % 8)) & 1);
+        }
+        bit_pos += width;
+
+        if code == END {
+            break;
+        } else if code == CLEAR {
+            prefixes.truncate(258);
+            suffixes.truncate(258);
+            lengths.truncate(258);
+            width = 9;
+            prev = None;
+            continue;
+        }
+        let start = out.len();
+        let first = if code < prefixes.len() {
+            // write out the entry from the back
+            out.resize(start + lengths[code], 0);
+            let mut cur = code;
+            for pos in (start..out.len()).rev() {
+                out[pos] = suffixes[cur];
+                cur = prefixes[cur];
+            }
+            out[start]
+        } else if let (Some(p), true) = (prev, code == prefixes.len()) {
+            // the code that's about to be defined: the previous entry plus its own first byte
+            let prev_start = start - lengths[p];
+            out.extend_from_within(prev_start..start);
+            out.push(out[prev_start]);
+            out[prev_start]
+        } else {
+            return Err("Invalid TIFF LZW code".into());
+        };
+        if let Some(p) = prev {
+            if prefixes.len() < 4096 {
+                prefixes.push(p);
+                suffixes.push(first);
+                lengths.push(lengths[p] + 1);
+            }
+        }
+        prev = Some(code);
+        if prefixes.len() + 1 >= 1 << width && width < 12 {
+            width += 1;
+        }
+    }
+    Ok(out)
+}
+
+/// Decompress `PackBits` run-length encoded data.
+#[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+fn decompress_packbits(data: &[u8], expected: usize) -> Result<Vec<u8>, EtError> {
+    let mut out = Vec::with_capacity(expected);
+    let mut pos = 0;
+    while pos < data.len() && out.len() < expected {
+        let n = data[pos] as i8;
+        pos += 1;
+        if n >= 0 {
+            let literal = data
+                .get(pos..pos + n as usize + 1)
+                .ok_or("TIFF PackBits data ended early")?;
+            out.extend_from_slice(literal);
+            pos += literal.len();
+        } else if n != -128 {
+            let byte = *data.get(pos).ok_or("TIFF PackBits data ended early")?;
+            out.resize(out.len() + (1 - isize::from(n)) as usize, byte);
+            pos += 1;
+        }
+    }
+    Ok(out)
+}
+
+/// Undo the horizontal differencing predictor for each row of a strip or tile.
+fn undo_predictor(chunk: &mut [u8], row_len: usize, stride: usize, n_bytes: usize, endian: Endian) {
+    for row in chunk.chunks_exact_mut(row_len) {
+        for i in stride..row_len / n_bytes {
+            let prev = read_uint(&row[(i - stride) * n_bytes..], n_bytes, endian);
+            let cur = read_uint(&row[i * n_bytes..], n_bytes, endian);
+            write_uint(
+                &mut row[i * n_bytes..],
+                n_bytes,
+                endian,
+                cur.wrapping_add(prev),
+            );
+        }
+    }
+}
+
+/// The most a strip or tile can grow when decompressed with the given compression scheme (used
+/// to reject images whose header claims more data than the file could possibly hold)
+fn max_expansion(compression: u64) -> usize {
+    match compression {
+        // a two-byte run can expand into 128 bytes
+        32773 => 64,
+        // deflate tops out at about 1032:1
+        8 | 32946 => 1032,
+        // each (at most 12-bit) LZW code can expand into a string of up to 4096 bytes
+        5 => 4096,
+        _ => 1,
+    }
+}
+
+/// The layout of a single image (plane) in the file
+#[derive(Clone, Debug, Default)]
+struct TiffIfd {
+    width: usize,
+    height: usize,
+    bits_per_sample: usize,
+    samples_per_pixel: usize,
+    sample_format: u64,
+    compression: u64,
+    photometric: u64,
+    predictor: u64,
+    planar: bool,
+    tiled: bool,
+    chunk_width: usize,
+    chunk_height: usize,
+    offsets: Vec<usize>,
+    byte_counts: Vec<usize>,
+}
+
+impl TiffIfd {
+    fn from_entries(entries: &[TiffEntry], endian: Endian) -> Result<Self, EtError> {
+        let mut ifd = TiffIfd {
+            bits_per_sample: 1,
+            samples_per_pixel: 1,
+            sample_format: 1,
+            compression: 1,
+            predictor: 1,
+            chunk_height: usize::MAX,
+            ..Default::default()
+        };
+        let mut bits_per_sample = vec![1];
+        let mut sample_formats = vec![1];
+        for entry in entries {
+            match entry.tag {
+                256 => ifd.width = entry.int(endian)? as usize,
+                257 => ifd.height = entry.int(endian)? as usize,
+                258 => bits_per_sample = entry.ints(endian)?,
+                259 => ifd.compression = entry.int(endian)?,
+                262 => ifd.photometric = entry.int(endian)?,
+                273 | 324 => {
+                    ifd.offsets = entry
+                        .ints(endian)?
+                        .into_iter()
+                        .map(|i| i as usize)
+                        .collect();
+                }
+                277 => ifd.samples_per_pixel = entry.int(endian)? as usize,
+                278 | 323 => ifd.chunk_height = entry.int(endian)? as usize,
+                279 | 325 => {
+                    ifd.byte_counts = entry
+                        .ints(endian)?
+                        .into_iter()
+                        .map(|i| i as usize)
+                        .collect();
+                }
+                284 => ifd.planar = entry.int(endian)? == 2,
+                317 => ifd.predictor = entry.int(endian)?,
+                322 => {
+                    ifd.tiled = true;
+                    ifd.chunk_width = entry.int(endian)? as usize;
+                }
+                339 => sample_formats = entry.ints(endian)?,
+                _ => {}
+            }
+        }
+        let first_bits = bits_per_sample
+            .first()
+            .copied()
+            .ok_or("TIFF BitsPerSample tag has no values")?;
+        let first_format = sample_formats
+            .first()
+            .copied()
+            .ok_or("TIFF SampleFormat tag has no values")?;
+        if bits_per_sample.iter().any(|b| *b != first_bits)
+            || sample_formats.iter().any(|f| *f != first_format)
+        {
+            return Err("TIFF samples with different types are not supported".into());
+        }
+        ifd.bits_per_sample = first_bits as usize;
+        ifd.sample_format = first_format;
+        if ![8, 16, 32, 64].contains(&ifd.bits_per_sample) {
+            return Err(
+                format!("TIFF {}-bit samples are not supported", ifd.bits_per_sample).into(),
+            );
+        }
+        if ifd.sample_format == 3 && ifd.bits_per_sample < 32 {
+            return Err("TIFF floating point samples must be 32 or 64-bit".into());
+        }
+        if ifd.samples_per_pixel == 0 {
+            return Err("TIFF must have at least one sample per pixel".into());
+        }
+        if !ifd.tiled {
+            // strips are handled as tiles that are as wide as the image
+            ifd.chunk_width = ifd.width;
+            ifd.chunk_height = ifd.chunk_height.min(ifd.height);
+        }
+        if ifd.width > 0 && ifd.height > 0 && (ifd.chunk_width == 0 || ifd.chunk_height == 0) {
+            return Err("TIFF strips and tiles must not be empty".into());
+        }
+        Ok(ifd)
+    }
+
+    fn bytes_per_sample(&self) -> usize {
+        self.bits_per_sample / 8
+    }
+
+    fn decompress(&self, raw: &[u8], expected: usize) -> Result<Vec<u8>, EtError> {
+        Ok(match self.compression {
+            1 => raw.to_vec(),
+            5 => decompress_lzw(raw, expected)?,
+            8 | 32946 => {
+                let mut out = Vec::with_capacity(expected);
+                let _ = ZlibDecoder::new(raw)
+                    .read_to_end(&mut out)
+                    .map_err(|e| EtError::from(format!("Could not inflate TIFF data: {}", e)))?;
+                out
+            }
+            32773 => decompress_packbits(raw, expected)?,
+            x => return Err(format!("TIFF compression type {} is not supported", x).into()),
+        })
+    }
+
+    /// Decode all of the strips or tiles for this image into one buffer of interleaved samples
+    /// in the file's byte order.
+    fn read_image(&self, data: &[u8], endian: Endian) -> Result<Vec<u8>, EtError> {
+        let bps = self.bytes_per_sample();
+        let spp = self.samples_per_pixel;
+        let (chunk_spp, n_sample_planes) = if self.planar { (1, spp) } else { (spp, 1) };
+        let across = self.width.div_ceil(self.chunk_width);
+        let down = self.height.div_ceil(self.chunk_height);
+        let per_plane = across * down;
+        if self.offsets.len() / n_sample_planes < per_plane
+            || self.byte_counts.len() / n_sample_planes < per_plane
+        {
+            return Err("TIFF is missing strip or tile offsets".into());
+        }
+        if self.predictor != 1 && self.predictor != 2 {
+            return Err(format!("TIFF predictor {} is not supported", self.predictor).into());
+        }
+
+        // don't trust the dimensions in the header to allocate anything the file couldn't hold
+        let too_big = || EtError::from("TIFF image is larger than the file could hold");
+        let image_len = self
+            .width
+            .checked_mul(self.height)
+            .and_then(|n| n.checked_mul(spp))
+            .and_then(|n| n.checked_mul(bps))
+            .ok_or_else(too_big)?;
+        if image_len / max_expansion(self.compression) > data.len() {
+            return Err(too_big());
+        }
+        let mut image = vec![0; image_len];
+        let row_len = self
+            .chunk_width
+            .checked_mul(chunk_spp * bps)
+            .ok_or_else(too_big)?;
+        let chunks = self.offsets.iter().zip(&self.byte_counts);
+        for (i, (&offset, &count)) in chunks.take(per_plane * n_sample_planes).enumerate() {
+            let sample = i / per_plane;
+            let x0 = (i % per_plane) % across * self.chunk_width;
+            let y0 = (i % per_plane) / across * self.chunk_height;
+            // the last strip in an image can be shorter than the rest
+            let rows = if self.tiled {
+                self.chunk_height
+            } else {
+                self.chunk_height.min(self.height - y0)
+            };
+            let raw = offset
+                .checked_add(count)
+                .and_then(|end| data.get(offset..end))
+                .ok_or("TIFF strip or tile extends past end of file")?;
+            let chunk_len = row_len.checked_mul(rows).ok_or_else(too_big)?;
+            if chunk_len / max_expansion(self.compression) > raw.len() {
+                return Err("TIFF strip or tile has too little data".into());
+            }
+            let mut chunk = self.decompress(raw, chunk_len)?;
+            if chunk.len() < chunk_len {
+                return Err("TIFF strip or tile has too little data".into());
+            }
+            if self.predictor == 2 {
+                undo_predictor(&mut chunk[..chunk_len], row_len, chunk_spp, bps, endian);
+            }
+
+            let cols = self.chunk_width.min(self.width - x0);
+            for row in 0..rows.min(self.height - y0) {
+                let from = row * row_len;
+                let to = ((y0 + row) * self.width + x0) * spp * bps;
+                if self.planar {
+                    for col in 0..cols {
+                        let (f, t) = (from + col * bps, to + (col * spp + sample) * bps);
+                        image[t..t + bps].copy_from_slice(&chunk[f..f + bps]);
+                    }
+                } else {
+                    let len = cols * spp * bps;
+                    image[to..to + len].copy_from_slice(&chunk[from..from + len]);
+                }
+            }
+        }
+        Ok(image)
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    fn sample_value(&self, data: &[u8], endian: Endian) -> Value<'static> {
+        let bps = self.bytes_per_sample();
+        match (self.sample_format, bps) {
+            (3, 4) => Value::Float(f64::from(f32::from_bits(read_uint(data, 4, endian) as u32))),
+            (3, _) => Value::Float(f64::from_bits(read_uint(data, 8, endian))),
+            (2, _) => Value::Integer(read_int(data, bps, endian)),
+            _ => {
+                let mut value = read_uint(data, bps, endian);
+                if self.photometric == 0 {
+                    // WhiteIsZero images are stored inverted
+                    value = (u64::MAX >> (64 - 8 * bps)) - value;
+                }
+                Value::Integer(value as i64)
+            }
+        }
+    }
+}
+
+/// Find the attributes of the `Pixels` element in an OME-XML document.
+fn ome_pixels(xml: &str) -> BTreeMap<&str, &str> {
+    let mut attributes = BTreeMap::new();
+    let start = xml.match_indices("Pixels").map(|(i, _)| i).find(|i| {
+        (xml[..*i].ends_with('<') || xml[..*i].ends_with(':'))
+            && xml[i + 6..].starts_with(char::is_whitespace)
+    });
+    let mut rest = match start {
+        Some(i) => {
+            let tag = &xml[i + 6..];
+            &tag[..tag.find('>').unwrap_or(tag.len())]
+        }
+        None => return attributes,
+    };
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim();
+        let value = rest[eq + 1..].trim_start();
+        let quote = match value.chars().next() {
+            Some(q) if q == '"' || q == '\'' => q,
+            _ => break,
+        };
+        let end = match value[1..].find(quote) {
+            Some(e) => e + 1,
+            None => break,
+        };
+        let _ = attributes.insert(key, &value[1..end]);
+        rest = &value[end + 1..];
+    }
+    attributes
+}
+
+/// The state of the TIFF parser
+///
+/// TIFFs can store their image data anywhere in the file so the entire file is read into memory
+/// before parsing. Each image in the file is treated as a separate plane (e.g. a z-slice, a time
+/// point or a channel) and decoded when the records reach it.
+#[derive(Debug, Default)]
+pub struct TiffState {
+    endian: Endian,
+    data: Vec<u8>,
+    ifds: Vec<TiffIfd>,
+    // how planes map onto the OME dimensions from fastest to slowest changing
+    dimensions: Option<Vec<(char, usize)>>,
+    header: Vec<String>,
+    metadata: BTreeMap<String, Value<'static>>,
+    plane_data: Vec<u8>,
+    cur_plane: usize,
+    cur_x: usize,
+    cur_y: usize,
+}
+
+impl StateMetadata for TiffState {
+    fn metadata(&self) -> BTreeMap<String, Value> {
+        self.metadata.clone()
+    }
+
+    fn header(&self) -> Vec<&str> {
+        self.header.iter().map(AsRef::as_ref).collect()
+    }
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for TiffState {
+    type State = ();
+
+    fn parse(
+        rb: &[u8],
+        eof: bool,
+        consumed: &mut usize,
+        _state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        let magic = extract::<&[u8]>(rb, &mut 0, &mut 4)?;
+        match magic {
+            b"II*\0" | b"MM\0*" => {}
+            b"II+\0" | b"MM\0+" => return Err("BigTIFF files are not supported".into()),
+            _ => return Err("Invalid TIFF magic".into()),
+        }
+        if !eof {
+            return Err(EtError::from("TIFFs must be read in full").incomplete());
+        }
+        *consumed += rb.len();
+        Ok(true)
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    fn get(&mut self, rb: &'b [u8], _state: &'s Self::State) -> Result<(), EtError> {
+        let mut endian = if rb.starts_with(b"II") {
+            Endian::Little
+        } else {
+            Endian::Big
+        };
+        let mut ifd_offset = extract::<u32>(rb, &mut 4, &mut endian)? as usize;
+
+        let mut ifds = Vec::new();
+        let mut metadata = BTreeMap::new();
+        let mut seen_offsets = Vec::new();
+        while ifd_offset != 0 {
+            if seen_offsets.contains(&ifd_offset) {
+                return Err("TIFF IFDs form a loop".into());
+            }
+            seen_offsets.push(ifd_offset);
+            let con = &mut ifd_offset.clone();
+            let n_entries = extract::<u16>(rb, con, &mut endian)?;
+            let mut entries = Vec::new();
+            for _ in 0..n_entries {
+                if let Some(entry) = TiffEntry::read(rb, con, endian)? {
+                    entries.push(entry);
+                }
+            }
+            ifd_offset = extract::<u32>(rb, con, &mut endian)? as usize;
+
+            // skip thumbnails and other reduced-resolution images
+            let subfile_type = entries.iter().find(|e| e.tag == 254);
+            if let Some(entry) = subfile_type {
+                if entry.int(endian)? & 1 == 1 {
+                    continue;
+                }
+            }
+            if ifds.is_empty() {
+                let mut tags = BTreeMap::new();
+                for entry in &entries {
+                    if let Some((_, name)) = TAG_NAMES.iter().find(|(t, _)| *t == entry.tag) {
+                        drop(metadata.insert((*name).to_string(), entry.to_value(endian)));
+                    } else if !STRUCTURAL_TAGS.contains(&entry.tag)
+                        && (entry.field_type == 2 || entry.count <= 16)
+                    {
+                        drop(tags.insert(entry.tag.to_string(), entry.to_value(endian)));
+                    }
+                }
+                if !tags.is_empty() {
+                    drop(metadata.insert("tags".to_string(), Value::Record(tags)));
+                }
+            }
+            let ifd = TiffIfd::from_entries(&entries, endian)?;
+            if let Some(first) = ifds.first() {
+                let first: &TiffIfd = first;
+                if (first.samples_per_pixel, first.photometric)
+                    != (ifd.samples_per_pixel, ifd.photometric)
+                {
+                    return Err("TIFF planes have different kinds of samples".into());
+                }
+            }
+            ifds.push(ifd);
+        }
+        let first = ifds.first().ok_or("TIFF has no images")?;
+
+        if let Some(Value::String(date)) = metadata.get("date_time") {
+            if let Ok(date) = NaiveDateTime::parse_from_str(date, "%Y:%m:%d %H:%M:%S") {
+                drop(metadata.insert("date_time".to_string(), date.into()));
+            }
+        }
+
+        let mut dimensions = None;
+        let description = match metadata.get("description") {
+            Some(Value::String(d)) if d.contains("<OME") => Some(d.to_string()),
+            _ => None,
+        };
+        if let Some(xml) = description {
+            let pixels = ome_pixels(&xml);
+            let size = |key: &str| pixels.get(key).and_then(|s| s.parse::<usize>().ok());
+            for (key, name) in &[
+                ("SizeZ", "size_z"),
+                ("SizeC", "size_c"),
+                ("SizeT", "size_t"),
+            ] {
+                if let Some(s) = size(key) {
+                    drop(metadata.insert((*name).to_string(), Value::Integer(s as i64)));
+                }
+            }
+            for (key, name) in &[
+                ("PhysicalSizeX", "physical_size_x"),
+                ("PhysicalSizeY", "physical_size_y"),
+                ("PhysicalSizeZ", "physical_size_z"),
+            ] {
+                if let Some(s) = pixels.get(key).and_then(|s| s.parse::<f64>().ok()) {
+                    drop(metadata.insert((*name).to_string(), s.into()));
+                }
+            }
+            if let Some(t) = pixels.get("Type") {
+                drop(metadata.insert("pixel_type".to_string(), t.to_string().into()));
+            }
+            if let Some(order) = pixels.get("DimensionOrder") {
+                drop(metadata.insert("dimension_order".to_string(), order.to_string().into()));
+                let dims: Option<Vec<(char, usize)>> = order
+                    .chars()
+                    .skip(2)
+                    .map(|c| size(&format!("Size{}", c)).map(|s| (c, s)))
+                    .collect();
+                // only label planes if every image in the file is accounted for
+                dimensions = dims.filter(|d| {
+                    d.len() == 3
+                        && d.iter().all(|(_, s)| *s > 0)
+                        && d.iter().map(|(_, s)| s).product::<usize>() == ifds.len()
+                });
+            }
+            drop(metadata.remove("description"));
+            drop(metadata.insert("ome_xml".to_string(), xml.into()));
+        }
+        drop(metadata.insert("width".to_string(), (first.width as u64).into()));
+        drop(metadata.insert("height".to_string(), (first.height as u64).into()));
+        drop(metadata.insert("planes".to_string(), (ifds.len() as u64).into()));
+
+        let mut header = vec!["plane".to_string()];
+        if dimensions.is_some() {
+            header.extend(["z", "c", "t"].iter().map(|s| (*s).to_string()));
+        }
+        header.extend(["x", "y"].iter().map(|s| (*s).to_string()));
+        match (first.photometric, first.samples_per_pixel) {
+            (_, 1) => header.push("value".to_string()),
+            (2, 3) => header.extend(["red", "green", "blue"].iter().map(|s| (*s).to_string())),
+            (2, 4) => header.extend(
+                ["red", "green", "blue", "alpha"]
+                    .iter()
+                    .map(|s| (*s).to_string()),
+            ),
+            (_, n) => header.extend((0..n).map(|i| format!("sample_{}", i))),
+        }
+
+        self.endian = endian;
+        self.data = rb.to_vec();
+        self.ifds = ifds;
+        self.dimensions = dimensions;
+        self.header = header;
+        self.metadata = metadata;
+        // initialize x to MAX to sentinel we haven't started yet
+        self.cur_plane = 0;
+        self.cur_x = usize::MAX;
+        self.cur_y = 0;
+        Ok(())
+    }
+}
+
+/// A single pixel from a TIFF image
+#[derive(Clone, Debug, Default)]
+pub struct TiffRecord {
+    /// The index of the image in the file that this pixel is from
+    pub plane: u32,
+    /// For OME-TIFFs, the z-slice, channel and time point of the plane
+    pub dimensions: Option<(u32, u32, u32)>,
+    /// The x coordinate of the pixel
+    pub x: u32,
+    /// The y coordinate of the pixel
+    pub y: u32,
+    /// The values of each sample in the pixel; see the associated state for their names.
+    pub values: Vec<Value<'static>>,
+}
+
+impl<'b: 's, 's> FromSlice<'b, 's> for TiffRecord {
+    type State = TiffState;
+
+    fn parse(
+        _rb: &[u8],
+        _eof: bool,
+        _consumed: &mut usize,
+        state: &mut Self::State,
+    ) -> Result<bool, EtError> {
+        let (mut plane, mut x, mut y) = if state.cur_x == usize::MAX {
+            (0, 0, 0)
+        } else {
+            (state.cur_plane, state.cur_x + 1, state.cur_y)
+        };
+        loop {
+            let ifd = match state.ifds.get(plane) {
+                Some(ifd) => ifd,
+                None => return Ok(false),
+            };
+            if x >= ifd.width {
+                x = 0;
+                y += 1;
+            }
+            if y < ifd.height && ifd.width > 0 {
+                break;
+            }
+            plane += 1;
+            x = 0;
+            y = 0;
+        }
+        if plane != state.cur_plane || state.cur_x == usize::MAX {
+            state.plane_data = state.ifds[plane].read_image(&state.data, state.endian)?;
+        }
+        state.cur_plane = plane;
+        state.cur_x = x;
+        state.cur_y = y;
+        Ok(true)
+    }
+
+    fn get(&mut self, _rb: &'b [u8], state: &'s Self::State) -> Result<(), EtError> {
+        let ifd = &state.ifds[state.cur_plane];
+        let bps = ifd.bytes_per_sample();
+        let pos = (state.cur_y * ifd.width + state.cur_x) * ifd.samples_per_pixel * bps;
+        self.values = state.plane_data[pos..pos + ifd.samples_per_pixel * bps]
+            .chunks_exact(bps)
+            .map(|s| ifd.sample_value(s, state.endian))
+            .collect();
+
+        self.dimensions = if let Some(dims) = &state.dimensions {
+            let (mut z, mut c, mut t) = (0, 0, 0);
+            let mut index = state.cur_plane;
+            for (dim, size) in dims {
+                let value = u32::try_from(index % size)?;
+                index /= size;
+                match dim {
+                    'Z' => z = value,
+                    'C' => c = value,
+                    _ => t = value,
+                }
+            }
+            Some((z, c, t))
+        } else {
+            None
+        };
+        self.plane = u32::try_from(state.cur_plane)?;
+        self.x = u32::try_from(state.cur_x)?;
+        self.y = u32::try_from(state.cur_y)?;
+        Ok(())
+    }
+}
+
+impl<'r> From<TiffRecord> for Vec<Value<'r>> {
+    fn from(record: TiffRecord) -> Self {
+        let mut row = Vec::with_capacity(record.values.len() + 6);
+        row.push(record.plane.into());
+        if let Some((z, c, t)) = record.dimensions {
+            row.extend([z.into(), c.into(), t.into()]);
+        }
+        row.push(record.x.into());
+        row.push(record.y.into());
+        row.extend(record.values);
+        row
+    }
+}
+
+impl_reader!(TiffReader, TiffRecord, TiffRecord, TiffState, ());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::readers::RecordReader;
+
+    #[test]
+    fn test_ome_tiff_lzw() -> Result<(), EtError> {
+        let rb: &[u8] = &include_bytes!("../../tests/data/ome_lzw.ome.tif")[..];
+        let mut reader = TiffReader::new(rb, None)?;
+        assert_eq!(
+            reader.headers(),
+            vec!["plane", "z", "c", "t", "x", "y", "value"]
+        );
+        let metadata = reader.metadata();
+        assert_eq!(metadata["planes"], Value::Integer(2));
+        assert_eq!(metadata["size_c"], Value::Integer(2));
+        assert_eq!(metadata["dimension_order"], "XYZCT".into());
+        assert_eq!(metadata["physical_size_x"], Value::Float(0.5));
+        assert_eq!(metadata["software"], "entab test".into());
+        assert_eq!(metadata["x_resolution"], Value::Float(72.));
+        assert!(matches!(metadata["date_time"], Value::Datetime(_)));
+        assert!(matches!(&metadata["ome_xml"], Value::String(s) if s.starts_with("<?xml")));
+        assert!(!metadata.contains_key("description"));
+
+        let mut n_recs = 0;
+        while let Some(TiffRecord {
+            plane,
+            dimensions,
+            x,
+            y,
+            values,
+        }) = reader.next()?
+        {
+            assert_eq!(dimensions, Some((0, plane, 0)));
+            // there's some noise in the image so the LZW codes grow past 9 bits
+            let noise = (x * x * 31 + y * y * 17 + x * y * 7) % 97;
+            let expected = 5000 * plane + 100 * y + 7 * x + noise;
+            assert_eq!(values, vec![Value::Integer(i64::from(expected))]);
+            n_recs += 1;
+        }
+        assert_eq!(n_recs, 2 * 40 * 30);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tiled_packbits_tiff() -> Result<(), EtError> {
+        let rb: &[u8] = &include_bytes!("../../tests/data/rgb_tiled.tif")[..];
+        let mut reader = TiffReader::new(rb, None)?;
+        assert_eq!(
+            reader.headers(),
+            vec!["plane", "x", "y", "red", "green", "blue"]
+        );
+        let metadata = reader.metadata();
+        assert_eq!(metadata["make"], "Entab".into());
+        assert_eq!(metadata["width"], Value::Integer(20));
+
+        let mut n_recs = 0;
+        while let Some(TiffRecord { x, y, values, .. }) = reader.next()? {
+            let (x, y) = (i64::from(x), i64::from(y));
+            let blue = if (x + y) % 2 == 1 { 200 } else { 0 };
+            assert_eq!(
+                values,
+                vec![
+                    Value::Integer(x * 10 % 256),
+                    Value::Integer(y * 10 % 256),
+                    Value::Integer(blue)
+                ]
+            );
+            n_recs += 1;
+        }
+        assert_eq!(n_recs, 20 * 18);
+        Ok(())
+    }
+
+    #[test]
+    fn test_planar_float_tiff() -> Result<(), EtError> {
+        let rb: &[u8] = &include_bytes!("../../tests/data/float_planar.tif")[..];
+        let mut reader = TiffReader::new(rb, None)?;
+        assert_eq!(
+            reader.headers(),
+            vec!["plane", "x", "y", "sample_0", "sample_1"]
+        );
+        let _ = reader.next()?;
+        let record = reader.next_record()?.expect("second pixel");
+        assert_eq!(
+            record,
+            vec![
+                Value::Integer(0),
+                Value::Integer(1),
+                Value::Integer(0),
+                Value::Float(0.5),
+                Value::Float(-2.)
+            ]
+        );
+        let mut n_recs = 2;
+        while reader.next()?.is_some() {
+            n_recs += 1;
+        }
+        assert_eq!(n_recs, 6);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tiff_lzw_and_packbits() -> Result<(), EtError> {
+        // runs of the same byte exercise the case where a code is used as it's defined
+        let lzw = [0x80, 0x01, 0xE0, 0x50, 0x20, 0x44, 0x04];
+        assert_eq!(decompress_lzw(&lzw, 0)?, b"\x07\x07\x07\x07\x07\x08");
+        // example from Apple's PackBits documentation
+        let packbits = [
+            0xFE, 0xAA, 0x02, 0x80, 0x00, 0x2A, 0xFD, 0xAA, 0x03, 0x80, 0x00, 0x2A, 0x22, 0xF7,
+            0xAA,
+        ];
+        let unpacked = decompress_packbits(&packbits, 24)?;
+        assert_eq!(unpacked.len(), 24);
+        assert_eq!(&unpacked[..6], &[0xAA, 0xAA, 0xAA, 0x80, 0x00, 0x2A]);
+        assert_eq!(&unpacked[15..], &[0xAA; 9]);
+        assert!(TiffReader::new(&b"II*\0\0\0\0\0"[..], None).is_err());
+        Ok(())
+    }
+
+    /// Build a little-endian TIFF with one IFD of (tag, type, count, value) entries
+    fn tiff_with_entries(entries: &[(u16, u16, u32, u32)]) -> Vec<u8> {
+        let mut buf = b"II*\0\x08\0\0\0".to_vec();
+        buf.extend((entries.len() as u16).to_le_bytes());
+        for (tag, typ, count, value) in entries {
+            buf.extend(tag.to_le_bytes());
+            buf.extend(typ.to_le_bytes());
+            buf.extend(count.to_le_bytes());
+            buf.extend(value.to_le_bytes());
+        }
+        buf.extend([0; 4]);
+        buf.extend([0; 16]);
+        buf
+    }
+
+    fn read_all(buf: &[u8]) -> Result<usize, EtError> {
+        let mut reader = TiffReader::new(buf, None)?;
+        let mut n_recs = 0;
+        while reader.next()?.is_some() {
+            n_recs += 1;
+        }
+        Ok(n_recs)
+    }
+
+    #[test]
+    fn test_tiff_bad_headers() -> Result<(), EtError> {
+        let entries = |bits: (u32, u32), width: u32, height: u32| {
+            vec![
+                (256, 4, 1, width),
+                (257, 4, 1, height),
+                (258, 3, bits.0, bits.1),
+                (273, 4, 1, 8),
+                (277, 3, 1, 1),
+                (278, 4, 1, height),
+                (279, 4, 1, 1),
+            ]
+        };
+        assert_eq!(read_all(&tiff_with_entries(&entries((1, 8), 1, 1)))?, 1);
+        // an empty BitsPerSample or SampleFormat tag
+        assert!(read_all(&tiff_with_entries(&entries((0, 0), 1, 1))).is_err());
+        let mut buf = entries((1, 8), 1, 1);
+        buf.push((339, 3, 0, 0));
+        assert!(read_all(&tiff_with_entries(&buf)).is_err());
+        // dimensions that overflow or that the file is far too small to hold
+        assert!(read_all(&tiff_with_entries(&entries((1, 64), u32::MAX, u32::MAX))).is_err());
+        assert!(read_all(&tiff_with_entries(&entries((1, 8), 100_000, 100_000))).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,331 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::str::from_utf8;
+
+use memchr::memmem::find;
+use memchr::{memchr, memrchr};
+
+use crate::EtError;
+
+/// What kind of XML tag this is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum XmlTagKind {
+    /// An opening tag, e.g. <a>
+    Open,
+    /// An closing tag, e.g. </a>
+    Close,
+    /// A self-closing tag, e.g. <br />
+    SelfClose,
+}
+
+/// A tag found in an XML document.
+#[derive(Clone, Copy, Debug)]
+pub struct XmlTag<'a> {
+    /// The name of the tag (e.g. `a` for `<a href="">`)
+    pub name: &'a str,
+    /// If this tag opens, closes, or is self-closing
+    pub kind: XmlTagKind,
+    /// The position of the `<` that starts this tag
+    pub start: usize,
+    /// The position right after the `>` that ends this tag
+    pub end: usize,
+    attributes: &'a [u8],
+}
+
+impl<'a> XmlTag<'a> {
+    /// The (unescaped) value of the attribute `key`, if it's present.
+    #[must_use]
+    pub fn attr(&self, key: &str) -> Option<Cow<'a, str>> {
+        let mut rest = self.attributes;
+        loop {
+            let eq = memchr(b'=', rest)?;
+            let name = rest[..eq].trim_ascii();
+            let after = rest[eq + 1..].trim_ascii_start();
+            let quote = *after.first()?;
+            if quote != b'"' && quote != b'\'' {
+                return None;
+            }
+            let len = memchr(quote, &after[1..])?;
+            if name == key.as_bytes() {
+                return Some(xml_unescape(&after[1..=len]));
+            }
+            rest = &after[len + 2..];
+        }
+    }
+}
+
+/// An iterator over the tags in a slice of XML.
+///
+/// Comments, processing instructions, doctypes and CDATA sections are skipped over and any text
+/// between tags is ignored. Iteration stops at the first tag that isn't complete in the slice.
+#[derive(Clone, Copy, Debug)]
+pub struct XmlTags<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> XmlTags<'a> {
+    /// Iterate over the tags in `data`
+    #[must_use]
+    pub fn new(data: &'a [u8]) -> Self {
+        XmlTags { data, pos: 0 }
+    }
+
+    /// Continue iterating from `pos` (e.g. to skip over the contents of an element)
+    pub fn skip_to(&mut self, pos: usize) {
+        self.pos = pos.min(self.data.len());
+    }
+}
+
+impl<'a> Iterator for XmlTags<'a> {
+    type Item = XmlTag<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let start = self.pos + memchr(b'<', &self.data[self.pos..])?;
+            let rest = &self.data[start..];
+            let skip_to: Option<&[u8]> = if rest.starts_with(b"<!--") {
+                Some(b"-->")
+            } else if rest.starts_with(b"<![CDATA[") {
+                Some(b"]]>")
+            } else if rest.starts_with(b"<?") {
+                Some(b"?>")
+            } else if rest.starts_with(b"<!") {
+                Some(b">")
+            } else {
+                None
+            };
+            if let Some(end_marker) = skip_to {
+                self.pos = start + find(rest, end_marker)? + end_marker.len();
+                continue;
+            }
+
+            // find the end of the tag while ignoring any `>`s inside of quoted attributes
+            let mut quote = None;
+            let mut end = None;
+            for (i, c) in rest.iter().enumerate().skip(1) {
+                match (quote, c) {
+                    (None, b'>') => {
+                        end = Some(i);
+                        break;
+                    }
+                    (None, b'"' | b'\'') => quote = Some(*c),
+                    (Some(q), c) if q == *c => quote = None,
+                    _ => {}
+                }
+            }
+            let end = end?;
+            self.pos = start + end + 1;
+            let (kind, inner) = if rest.get(1) == Some(&b'/') {
+                (XmlTagKind::Close, &rest[2..end])
+            } else if rest[end - 1] == b'/' {
+                (XmlTagKind::SelfClose, &rest[1..end - 1])
+            } else {
+                (XmlTagKind::Open, &rest[1..end])
+            };
+            let name_end = inner
+                .iter()
+                .position(u8::is_ascii_whitespace)
+                .unwrap_or(inner.len());
+            return Some(XmlTag {
+                name: from_utf8(&inner[..name_end]).unwrap_or(""),
+                kind,
+                start,
+                end: self.pos,
+                attributes: &inner[name_end..],
+            });
+        }
+    }
+}
+
+/// Find the end of the element opened by `tag` inside of `data` (i.e. the position right after
+/// the matching closing tag).
+///
+/// Returns `None` if the closing tag isn't in `data` yet.
+#[must_use]
+pub fn element_end(data: &[u8], tag: &XmlTag) -> Option<usize> {
+    if tag.kind == XmlTagKind::SelfClose {
+        return Some(tag.end);
+    }
+    let mut depth = 0;
+    for t in XmlTags::new(&data[tag.end..]) {
+        if t.name != tag.name {
+            continue;
+        }
+        match t.kind {
+            XmlTagKind::Open => depth += 1,
+            XmlTagKind::Close if depth == 0 => return Some(tag.end + t.end),
+            XmlTagKind::Close => depth -= 1,
+            XmlTagKind::SelfClose => {}
+        }
+    }
+    None
+}
+
+/// The text inside of the element opened by `tag` (e.g. `b` for `<a>b</a>`).
+///
+/// Returns `None` if the closing tag isn't in `data` yet.
+#[must_use]
+pub fn element_text<'a>(data: &'a [u8], tag: &XmlTag) -> Option<&'a [u8]> {
+    if tag.kind == XmlTagKind::SelfClose {
+        return Some(b"");
+    }
+    let end = element_end(data, tag)?;
+    let close_start = memrchr(b'<', &data[..end])?;
+    Some(&data[tag.end..close_start])
+}
+
+/// Replace the predefined XML entities (e.g. `&amp;`) and any numeric character references in
+/// `text`.
+#[must_use]
+pub fn xml_unescape(text: &[u8]) -> Cow<str> {
+    let text = String::from_utf8_lossy(text);
+    if !text.contains('&') {
+        return text;
+    }
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest: &str = &text;
+    while let Some(amp) = rest.find('&') {
+        unescaped.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity_end = rest.find(';').unwrap_or(0);
+        let replacement = match &rest[1..entity_end.max(1)] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            e if e.starts_with("#x") => u32::from_str_radix(&e[2..], 16)
+                .ok()
+                .and_then(char::from_u32),
+            e if e.starts_with('#') => e[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+        if let Some(c) = replacement {
+            unescaped.push(c);
+            rest = &rest[entity_end + 1..];
+        } else {
+            unescaped.push('&');
+            rest = &rest[1..];
+        }
+    }
+    unescaped.push_str(rest);
+    Cow::Owned(unescaped)
+}
+
+/// Parse an XML duration (e.g. "PT1M30.5S") into a number of seconds.
+///
+/// Durations with years or months don't have a fixed length so they aren't supported.
+#[must_use]
+pub fn parse_duration(text: &str) -> Option<f64> {
+    let (negative, text) = match text.trim().strip_prefix('-') {
+        Some(t) => (true, t),
+        None => (false, text.trim()),
+    };
+    let mut seconds = 0.;
+    let mut in_time = false;
+    let mut number_start = 1;
+    if !text.starts_with('P') {
+        return None;
+    }
+    for (i, c) in text.char_indices().skip(1) {
+        let multiplier = match (c, in_time) {
+            ('T', false) => {
+                in_time = true;
+                number_start = i + 1;
+                continue;
+            }
+            ('D', false) => 86400.,
+            ('H', true) => 3600.,
+            ('M', true) => 60.,
+            ('S', true) => 1.,
+            (c, _) if c.is_ascii_digit() || c == '.' => continue,
+            _ => return None,
+        };
+        seconds += multiplier * text[number_start..i].parse::<f64>().ok()?;
+        number_start = i + 1;
+    }
+    if number_start != text.len() {
+        return None;
+    }
+    Some(if negative { -seconds } else { seconds })
+}
+
+/// Decode base64-encoded text (ignoring any whitespace in it).
+///
+/// # Errors
+/// If the text contains characters outside of the base64 alphabet, an error is returned.
+pub fn decode_base64(text: &[u8]) -> Result<Vec<u8>, EtError> {
+    let mut decoded = Vec::with_capacity(text.len() * 3 / 4);
+    let mut chunk: u32 = 0;
+    let mut n_bits = 0;
+    for c in text {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            c if c.is_ascii_whitespace() => continue,
+            _ => return Err("Invalid character in base64 data".into()),
+        };
+        chunk = (chunk << 6) | u32::from(value);
+        n_bits += 6;
+        if n_bits >= 8 {
+            n_bits -= 8;
+            decoded.push(((chunk >> n_bits) & 0xFF) as u8);
+        }
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xml_tags() {
+        let data: &[u8] = b"<?xml version=\"1.0\"?>\n<!-- a <comment> -->\n<a x=\"1 > 0\" y='&lt;b&gt;'>text<br/></a>";
+        let tags: Vec<XmlTag> = XmlTags::new(data).collect();
+        assert_eq!(tags.len(), 3);
+        assert_eq!(tags[0].name, "a");
+        assert_eq!(tags[0].kind, XmlTagKind::Open);
+        assert_eq!(tags[0].attr("x").unwrap(), "1 > 0");
+        assert_eq!(tags[0].attr("y").unwrap(), "<b>");
+        assert!(tags[0].attr("z").is_none());
+        assert_eq!(tags[1].name, "br");
+        assert_eq!(tags[1].kind, XmlTagKind::SelfClose);
+        assert_eq!(tags[2].kind, XmlTagKind::Close);
+        assert_eq!(element_end(data, &tags[0]), Some(data.len()));
+        assert_eq!(element_end(&data[..data.len() - 1], &tags[0]), None);
+        assert_eq!(element_text(data, &tags[0]), Some(&b"text<br/>"[..]));
+        assert_eq!(element_text(data, &tags[1]), Some(&b""[..]));
+    }
+
+    #[test]
+    fn test_xml_unescape() {
+        assert_eq!(xml_unescape(b"a &amp; b"), "a & b");
+        assert_eq!(xml_unescape(b"&#65;&#x42;&bad"), "AB&bad");
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("PT0.2961S"), Some(0.2961));
+        assert_eq!(parse_duration("PT1M30.5S"), Some(90.5));
+        assert_eq!(parse_duration("P1DT1H"), Some(90000.));
+        assert_eq!(parse_duration("-PT2S"), Some(-2.));
+        assert_eq!(parse_duration("P1Y"), None);
+        assert_eq!(parse_duration("PT5"), None);
+        assert_eq!(parse_duration("12.5"), None);
+    }
+
+    #[test]
+    fn test_decode_base64() -> Result<(), EtError> {
+        assert_eq!(decode_base64(b"aGVsbG8=")?, b"hello");
+        assert_eq!(decode_base64(b"aGVs\nbG8h")?, b"hello!");
+        assert!(decode_base64(b"a*b").is_err());
+        Ok(())
+    }
+}
@@ -32,6 +32,15 @@ where
     _get_reader(rb, parser_name, params.unwrap_or_else(BTreeMap::new))
 }
 
+/// Remove an optional boolean param (defaulting to false) from `params`.
+fn bool_param(params: &mut BTreeMap<String, Value>, name: &str) -> Result<bool, EtError> {
+    match params.remove(name) {
+        None | Some(Value::Boolean(false)) => Ok(false),
+        Some(Value::Boolean(true)) => Ok(true),
+        Some(_) => Err(format!("The {} param must be a boolean", name).into()),
+    }
+}
+
 /// Internal function to handle `get_reader` not inferring that the Reader constructors need to be
 /// created using `ReadBuffer` and not `B`.
 fn _get_reader<'n, 'p, 'r>(
@@ -39,7 +48,26 @@ fn _get_reader<'n, 'p, 'r>(
     parser_name: &'n str,
     mut params: BTreeMap<String, Value<'p>>,
 ) -> Result<(Box<dyn RecordReader + 'r>, &'n str), EtError> {
-    let reader: Box<dyn RecordReader + 'r> = match parser_name {
+    let spectrum = match params.remove("spectrum") {
+        None | Some(Value::Boolean(false)) => false,
+        Some(Value::Boolean(true)) => true,
+        Some(_) => return Err("The spectrum param must be a boolean".into()),
+    };
+    if spectrum && !parsers::spectrum::SPECTRUM_PARSERS.contains(&parser_name) {
+        return Err(format!(
+            "Spectrum mode is not available for the parser {}",
+            parser_name
+        )
+        .into());
+    }
+    let mut reader: Box<dyn RecordReader + 'r> = match parser_name {
+        #[cfg(feature = "std")]
+        "agilent_d" => Box::new(parsers::agilent::directory::AgilentDirReader::new(
+            params
+                .remove("filename")
+                .ok_or_else(|| "The agilent_d parser needs the path to a .d directory".into())
+                .and_then(Value::into_string)?,
+        )?),
         "bam" => Box::new(parsers::sam::BamReader::new(rb, None)?),
         "chemstation_dad" => Box::new(parsers::agilent::chemstation::ChemstationDadReader::new(
             rb, None,
@@ -47,12 +75,19 @@ fn _get_reader<'n, 'p, 'r>(
         "chemstation_fid" => Box::new(parsers::agilent::chemstation::ChemstationFidReader::new(
             rb, None,
         )?),
+        "chemstation_fid2" => {
+            Box::new(parsers::agilent::chemstation_new::ChemstationFid2Reader::new(rb, None)?)
+        }
         "chemstation_ms" => Box::new(parsers::agilent::chemstation::ChemstationMsReader::new(
-            rb, None,
+            rb,
+            Some(parsers::agilent::chemstation::ChemstationMsParams::default().scan_info(spectrum)),
         )?),
         "chemstation_mwd" => Box::new(parsers::agilent::chemstation::ChemstationMwdReader::new(
             rb, None,
         )?),
+        "chemstation_reg" => {
+            Box::new(parsers::agilent::chemstation_reg::ChemstationRegReader::new(rb, None)?)
+        }
         "chemstation_uv" => Box::new(parsers::agilent::chemstation_new::ChemstationUvReader::new(
             rb, None,
         )?),
@@ -62,8 +97,34 @@ fn _get_reader<'n, 'p, 'r>(
         )?),
         "fasta" => Box::new(parsers::fasta::FastaReader::new(rb, None)?),
         "fastq" => Box::new(parsers::fastq::FastqReader::new(rb, None)?),
-        "flow" => Box::new(parsers::flow::FcsReader::new(rb, None)?),
-        "inficon" => Box::new(parsers::inficon::InficonReader::new(rb, None)?),
+        "fixed_width" => Box::new(parsers::fixed_width::FixedWidthReader::new(rb, None)?),
+        "flow" => {
+            let mut fcs_params = parsers::flow::FcsParams::default()
+                .compensate(bool_param(&mut params, "compensate")?)
+                .scale(bool_param(&mut params, "scale")?);
+            if let Some(transform) = params.remove("transform") {
+                fcs_params = fcs_params.transform(transform.into_string()?.parse()?);
+            }
+            if let Some(channel_names) = params.remove("channel_names") {
+                fcs_params = fcs_params.channel_names(channel_names.into_string()?.parse()?);
+            }
+            if let Some(dataset) = params.remove("dataset") {
+                fcs_params = fcs_params.dataset(dataset.into_string()?.trim().parse()?);
+            }
+            Box::new(parsers::flow::FcsReader::new(rb, Some(fcs_params))?)
+        }
+        #[cfg(feature = "std")]
+        "hdf5" => Box::new(parsers::hdf5::Hdf5Reader::new(
+            rb,
+            params
+                .remove("dataset")
+                .map(Value::into_string)
+                .transpose()?,
+        )?),
+        "inficon" => Box::new(parsers::inficon::InficonReader::new(
+            rb,
+            Some(parsers::inficon::InficonParams::default().scan_info(spectrum)),
+        )?),
         #[cfg(feature = "std")]
         "masshunter_dad" => Box::new(parsers::agilent::masshunter::MasshunterDadReader::new(
             rb,
@@ -74,18 +135,79 @@ fn _get_reader<'n, 'p, 'r>(
                     .and_then(Value::into_string)?,
             ),
         )?),
+        "mgf" => match params.remove("long") {
+            None | Some(Value::Boolean(false)) => Box::new(parsers::mgf::MgfReader::new(rb, None)?),
+            Some(Value::Boolean(true)) => Box::new(parsers::mgf::MgfPeakReader::new(rb, None)?),
+            Some(_) => return Err("The long param must be a boolean".into()),
+        },
+        "ms_cfb" => Box::new(parsers::microsoft_common::MsCfbReader::new(rb, None)?),
+        #[cfg(feature = "std")]
+        "mzml" => Box::new(parsers::mzml::MzmlReader::new(
+            rb,
+            Some(parsers::mzml::MzmlParams::default().scan_info(spectrum)),
+        )?),
+        #[cfg(feature = "std")]
+        "mzxml" => Box::new(parsers::mzxml::MzxmlReader::new(
+            rb,
+            Some(parsers::mzxml::MzxmlParams::default().scan_info(spectrum)),
+        )?),
+        "netcdf" => Box::new(parsers::netcdf::NetCdfReader::new(
+            rb,
+            Some(parsers::netcdf::NetCdfParams::default().scan_info(spectrum)),
+        )?),
         #[cfg(feature = "std")]
         "png" => Box::new(parsers::png::PngReader::new(rb, None)?),
+        #[cfg(feature = "std")]
+        "png_lines" => Box::new(parsers::png::PngLineReader::new(rb, None)?),
+        #[cfg(feature = "std")]
+        "png_regions" => {
+            let mut region_params = parsers::png::PngRegionParams::default();
+            if let Some(regions) = params.remove("regions") {
+                region_params = region_params.regions(
+                    regions
+                        .into_string()?
+                        .split(';')
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()?,
+                );
+            }
+            if let Some(grid) = params.remove("grid") {
+                let grid = grid.into_string()?;
+                let (rows, columns) = grid
+                    .split_once('x')
+                    .ok_or("The grid param must look like ROWSxCOLUMNS")?;
+                region_params = region_params.grid(rows.trim().parse()?, columns.trim().parse()?);
+            }
+            if let Some(statistic) = params.remove("statistic") {
+                region_params = region_params.statistic(statistic.into_string()?.parse()?);
+            }
+            Box::new(parsers::png::PngRegionReader::new(rb, Some(region_params))?)
+        }
         "sam" => Box::new(parsers::sam::SamReader::new(rb, None)?),
-        "thermo_cf" => Box::new(parsers::thermo::thermo_iso::ThermoCfReader::new(rb, None)?),
-        "thermo_dxf" => Box::new(parsers::thermo::thermo_iso::ThermoDxfReader::new(rb, None)?),
-        "thermo_raw" => Box::new(parsers::thermo::thermo_raw::ThermoRawReader::new(rb, None)?),
+        "thermo_cf" => Box::new(parsers::thermo::thermo_iso::ThermoCfReader::new(
+            rb,
+            Some(parsers::thermo::thermo_iso::ThermoIsoParams::default().scan_info(spectrum)),
+        )?),
+        "thermo_dxf" => Box::new(parsers::thermo::thermo_iso::ThermoDxfReader::new(
+            rb,
+            Some(parsers::thermo::thermo_iso::ThermoIsoParams::default().scan_info(spectrum)),
+        )?),
+        "thermo_raw" => Box::new(parsers::thermo::thermo_raw::ThermoRawReader::new(
+            rb,
+            // the per-scan columns are only needed to group the points into scans
+            Some(parsers::thermo::thermo_raw::ThermoRawParams::default().scan_info(spectrum)),
+        )?),
+        #[cfg(feature = "std")]
+        "tiff" => Box::new(parsers::tiff::TiffReader::new(rb, None)?),
         "tsv" => Box::new(parsers::tsv::TsvReader::new(
             rb,
             Some(parsers::tsv::TsvParams::default().delim(b'\t')),
         )?),
         x => return Err(format!("No parser available for the parser {}", x).into()),
     };
+    if spectrum {
+        reader = Box::new(parsers::spectrum::SpectrumReader::new(reader)?);
+    }
     drop(params.remove("filename"));
     if !params.is_empty() {
         let keys: Vec<&str> = params.keys().map(AsRef::as_ref).collect();
@@ -91,6 +91,23 @@ impl<'a> Value<'a> {
         }
         Err(EtError::from("Value was not a string"))
     }
+
+    /// Copy any borrowed data so the Value no longer depends on the buffer it was read from.
+    #[must_use]
+    pub fn into_owned(self) -> Value<'static> {
+        match self {
+            Value::Null => Value::Null,
+            Value::Boolean(b) => Value::Boolean(b),
+            Value::Datetime(d) => Value::Datetime(d),
+            Value::Float(f) => Value::Float(f),
+            Value::Integer(i) => Value::Integer(i),
+            Value::String(s) => Value::String(Cow::Owned(s.into_owned())),
+            Value::List(l) => Value::List(l.into_iter().map(Value::into_owned).collect()),
+            Value::Record(r) => {
+                Value::Record(r.into_iter().map(|(k, v)| (k, v.into_owned())).collect())
+            }
+        }
+    }
 }
 
 impl<'a, T: Into<Value<'a>>> From<Option<T>> for Value<'a> {
@@ -233,6 +250,18 @@ impl<'a> From<Vec<String>> for Value<'a> {
     }
 }
 
+impl<'a> From<Vec<u16>> for Value<'a> {
+    fn from(value: Vec<u16>) -> Self {
+        Value::List(value.into_iter().map(Value::from).collect())
+    }
+}
+
+impl<'a> From<Vec<f64>> for Value<'a> {
+    fn from(value: Vec<f64>) -> Self {
+        Value::List(value.into_iter().map(Value::from).collect())
+    }
+}
+
 impl<'a> From<Vec<Value<'a>>> for Value<'a> {
     fn from(value: Vec<Value<'a>>) -> Self {
         Value::List(value)